@@ -0,0 +1,697 @@
+//! import/export intre imagini si arhive tar (ustar + extensii pax), ca sa
+//! poata fi folosite unelte standard (`tar`, `bsdtar`).
+//!
+//! pe langa campurile ustar se scriu record-uri pax pt path-uri lungi, timpi
+//! cu nanosecunde, `created_at` (`LIBARCHIVE.creationtime`) si xattr-uri
+//! (`SCHILY.xattr.*`). vfs-ul nu are symlink-uri sau device-uri, deci la import
+//! astfel de intrari sunt sarite si raportate.
+
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::{ACCESS_W, ACCESS_X, Inner, Vfs};
+
+const BLOCK: usize = 512;
+// cat citim/scriem odata din datele unui fisier
+const CHUNK: usize = 1 << 20;
+// cea mai mare valoare care incape in campul octal de 12 bytes
+const MAX_OCTAL_11: u64 = (1 << 33) - 1;
+// header-ele pax si numele lungi GNU se citesc in memorie; marimea vine din arhiva
+const MAX_META: u64 = 1 << 20;
+
+/// ce a facut `import_tar`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportReport {
+    pub files: u64,
+    pub dirs: u64,
+    /// intrari pe care vfs-ul nu le poate reprezenta (symlink-uri, hard link-uri, device-uri).
+    pub skipped: Vec<String>,
+}
+
+/// scrie `root` (fisier sau director, recursiv) ca arhiva tar. path-urile din
+/// arhiva sunt relative la `root`; un fisier apare sub numele lui.
+pub fn export_tar<P: AsRef<Path>, W: Write>(vfs: &Vfs, root: P, mut out: W) -> Result<()> {
+    let root = VfsPath::new(root)?;
+    match vfs.read_dir(root.as_str()) {
+        Ok(_) => export_dir(vfs, &root, "", &mut out)?,
+        Err(VfsError::NotADir(_)) => {
+            let name = root
+                .file_name()
+                .ok_or_else(|| VfsError::InvalidPath(root.to_string()))?;
+            export_entry(vfs, &root, name, NodeKind::File, &mut out)?;
+        }
+        Err(e) => return Err(e),
+    }
+    // finalul arhivei: doua blocuri goale
+    out.write_all(&[0u8; 2 * BLOCK])?;
+    out.flush()?;
+    Ok(())
+}
+
+fn export_dir<W: Write>(vfs: &Vfs, dir: &VfsPath, prefix: &str, out: &mut W) -> Result<()> {
+    let mut entries = vfs.read_dir(dir.as_str())?.collect::<Result<Vec<_>>>()?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    for entry in entries {
+        let path = dir.join(&entry.name)?;
+        let name = format!("{prefix}{}", entry.name);
+        export_entry(vfs, &path, &name, entry.kind, out)?;
+        if entry.kind == NodeKind::Dir {
+            export_dir(vfs, &path, &format!("{name}/"), out)?;
+        }
+    }
+    Ok(())
+}
+
+fn export_entry<W: Write>(
+    vfs: &Vfs,
+    path: &VfsPath,
+    name: &str,
+    kind: NodeKind,
+    out: &mut W,
+) -> Result<()> {
+    let meta = vfs.metadata(path.as_str())?;
+    let name = match kind {
+        NodeKind::Dir => format!("{name}/"),
+        NodeKind::File => name.to_string(),
+    };
+    let size = match kind {
+        NodeKind::Dir => 0,
+        NodeKind::File => meta.size,
+    };
+
+    let mut pax = Vec::new();
+    if name.len() > 100 || !name.is_ascii() {
+        pax_record(&mut pax, "path", name.as_bytes());
+    }
+    if size > MAX_OCTAL_11 {
+        pax_record(&mut pax, "size", size.to_string().as_bytes());
+    }
+    pax_record(&mut pax, "mtime", pax_time(meta.modified_at).as_bytes());
+    pax_record(&mut pax, "atime", pax_time(meta.accessed_at).as_bytes());
+    // la import lipsa inseamna created_at = mtime; gnu tar avertizeaza pt cheia asta
+    if meta.created_at != meta.modified_at {
+        pax_record(
+            &mut pax,
+            "LIBARCHIVE.creationtime",
+            pax_time(meta.created_at).as_bytes(),
+        );
+    }
+    for xattr in vfs.list_xattrs(path.as_str())? {
+        if let Some(value) = vfs.get_xattr(path.as_str(), &xattr)? {
+            pax_record(&mut pax, &format!("SCHILY.xattr.{xattr}"), &value);
+        }
+    }
+
+    let pax_name = format!("PaxHeaders/{}", name.trim_end_matches('/'));
+    let mut hdr = ustar_header(&pax_name, b'x', pax.len() as u64, &meta);
+    out.write_all(&hdr)?;
+    write_padded(out, &pax)?;
+
+    let typeflag = match kind {
+        NodeKind::Dir => b'5',
+        NodeKind::File => b'0',
+    };
+    hdr = ustar_header(&name, typeflag, size, &meta);
+    out.write_all(&hdr)?;
+
+    if kind == NodeKind::File {
+        let mut file = vfs.open(path.as_str())?;
+        let mut buf = vec![0u8; CHUNK];
+        let mut left = size;
+        while left > 0 {
+            let n = (left as usize).min(CHUNK);
+            file.read_exact(&mut buf[..n])?;
+            out.write_all(&buf[..n])?;
+            left -= n as u64;
+        }
+        out.write_all(&[0u8; BLOCK][..padding(size)])?;
+    }
+    Ok(())
+}
+
+/// despacheteaza arhiva sub `dest` (care trebuie sa existe). directoarele care
+/// exista deja sunt completate, fisierele existente dau `AlreadyExists`.
+///
+/// importul e un singur batch: fiecare fisier e scris direct cu metadatele
+/// finale (fara record-uri intermediare de timpi), timpii directoarelor se pun
+/// la final si imaginea se sincronizeaza o singura data, cu un checkpoint.
+/// daca importul esueaza la jumatate, intrarile deja scrise raman.
+pub fn import_tar<P: AsRef<Path>, R: Read>(
+    vfs: &mut Vfs,
+    dest: P,
+    mut input: R,
+) -> Result<ImportReport> {
+    let dest = VfsPath::new(dest)?;
+    let cwd = vfs.cwd;
+    let mut inner = vfs.inner.borrow_mut();
+    inner.ensure_writable()?;
+    let dest_id = inner.resolve(cwd, &dest)?;
+    if inner.inodes[&dest_id].kind != NodeKind::Dir {
+        return Err(VfsError::NotADir(dest.to_string()));
+    }
+    inner.check_access(dest_id, ACCESS_W | ACCESS_X, &dest)?;
+
+    let mut batch = ImportBatch {
+        dest: dest_id,
+        dest_path: dest,
+        report: ImportReport::default(),
+        dir_times: Vec::new(),
+    };
+    let mut pax = PaxOverrides::default();
+    let mut block = [0u8; BLOCK];
+
+    loop {
+        if !read_block(&mut input, &mut block)? {
+            break;
+        }
+        if block.iter().all(|&b| b == 0) {
+            break;
+        }
+        let mut entry = parse_header(&block)?;
+        let typeflag = block[156];
+
+        match typeflag {
+            // pax pt urmatoarea intrare
+            b'x' => {
+                let data = read_data(&mut input, entry.size)?;
+                pax.parse(&data)?;
+                continue;
+            }
+            // pax global si alte extensii de care nu avem nevoie
+            b'g' => {
+                skip_data(&mut input, entry.size)?;
+                continue;
+            }
+            // nume lung GNU
+            b'L' => {
+                let data = read_data(&mut input, entry.size)?;
+                let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                pax.path = Some(String::from_utf8_lossy(&data[..end]).into_owned());
+                continue;
+            }
+            _ => {}
+        }
+
+        std::mem::take(&mut pax).apply(&mut entry);
+        match typeflag {
+            b'0' | b'\0' | b'7' => {
+                inner.import_file(&mut batch, &entry, &mut input)?;
+                skip_exact(&mut input, padding(entry.size) as u64)?;
+            }
+            b'5' => {
+                inner.import_dir(&mut batch, &entry)?;
+                skip_data(&mut input, entry.size)?;
+            }
+            _ => {
+                batch.report.skipped.push(entry.path.clone());
+                skip_data(&mut input, entry.size)?;
+            }
+        }
+    }
+
+    inner.finish_import(batch)
+}
+
+struct ImportBatch {
+    dest: InodeId,
+    dest_path: VfsPath,
+    report: ImportReport,
+    /// (director, created, modified, accessed), puse dupa ce nu mai primesc copii
+    dir_times: Vec<(InodeId, Timestamp, Timestamp, Timestamp)>,
+}
+
+/// o intrare din arhiva, cu campurile pax deja aplicate.
+struct TarEntry {
+    path: String,
+    size: u64,
+    mode: u32,
+    uid: u32,
+    gid: u32,
+    modified_at: Timestamp,
+    accessed_at: Option<Timestamp>,
+    created_at: Option<Timestamp>,
+    xattrs: XattrMap,
+}
+
+#[derive(Default)]
+struct PaxOverrides {
+    path: Option<String>,
+    size: Option<u64>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    modified_at: Option<Timestamp>,
+    accessed_at: Option<Timestamp>,
+    created_at: Option<Timestamp>,
+    xattrs: XattrMap,
+}
+
+impl PaxOverrides {
+    fn parse(&mut self, mut data: &[u8]) -> Result<()> {
+        let bad = || VfsError::InvalidPath("malformed pax header".into());
+        // fiecare record: "<len> <cheie>=<valoare>\n", len include tot record-ul
+        while !data.is_empty() {
+            let space = data.iter().position(|&b| b == b' ').ok_or_else(bad)?;
+            let len: usize = std::str::from_utf8(&data[..space])
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .ok_or_else(bad)?;
+            if len <= space + 1 || len > data.len() || data[len - 1] != b'\n' {
+                return Err(bad());
+            }
+            let record = &data[space + 1..len - 1];
+            data = &data[len..];
+
+            let eq = record.iter().position(|&b| b == b'=').ok_or_else(bad)?;
+            let key = std::str::from_utf8(&record[..eq]).map_err(|_| bad())?;
+            let value = &record[eq + 1..];
+            let text = || std::str::from_utf8(value).map_err(|_| bad());
+            match key {
+                "path" => self.path = Some(text()?.to_string()),
+                "size" => self.size = Some(text()?.parse().map_err(|_| bad())?),
+                "uid" => self.uid = Some(text()?.parse().map_err(|_| bad())?),
+                "gid" => self.gid = Some(text()?.parse().map_err(|_| bad())?),
+                "mtime" => self.modified_at = Some(parse_pax_time(text()?)?),
+                "atime" => self.accessed_at = Some(parse_pax_time(text()?)?),
+                "LIBARCHIVE.creationtime" => self.created_at = Some(parse_pax_time(text()?)?),
+                _ => {
+                    if let Some(name) = key.strip_prefix("SCHILY.xattr.") {
+                        self.xattrs.insert(name.to_string(), value.to_vec());
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(self, entry: &mut TarEntry) {
+        if let Some(path) = self.path {
+            entry.path = path;
+        }
+        if let Some(size) = self.size {
+            entry.size = size;
+        }
+        if let Some(uid) = self.uid {
+            entry.uid = uid;
+        }
+        if let Some(gid) = self.gid {
+            entry.gid = gid;
+        }
+        if let Some(t) = self.modified_at {
+            entry.modified_at = t;
+        }
+        entry.accessed_at = self.accessed_at.or(entry.accessed_at);
+        entry.created_at = self.created_at.or(entry.created_at);
+        entry.xattrs = self.xattrs;
+    }
+}
+
+impl Inner {
+    /// parintele unei intrari, creand directoarele intermediare lipsa.
+    fn import_parent(&mut self, batch: &mut ImportBatch, path: &str) -> Result<(InodeId, String)> {
+        let mut parts = archive_path(path)?;
+        let name = parts
+            .pop()
+            .ok_or_else(|| VfsError::InvalidPath(format!("empty path in archive: {path}")))?;
+
+        let mut cur = batch.dest;
+        let mut cur_path = batch.dest_path.clone();
+        for part in parts {
+            let parent_path = cur_path.clone();
+            cur_path = cur_path.join(&part)?;
+            self.check_access(cur, ACCESS_X, &parent_path)?;
+            cur = match self.children.get(&(cur, part.clone())) {
+                Some(&id) if self.inodes[&id].kind == NodeKind::Dir => id,
+                Some(_) => return Err(VfsError::NotADir(cur_path.to_string())),
+                None => {
+                    // ca la `create_dir`: parintele trebuie sa poata fi scris
+                    self.check_access(cur, ACCESS_W, &parent_path)?;
+                    let now = self.options.clock.now();
+                    let metadata = self.new_metadata(now, DEFAULT_DIR_MODE);
+                    batch.report.dirs += 1;
+                    self.import_node(cur, &part, NodeKind::Dir, metadata, XattrMap::new())?
+                }
+            };
+        }
+        self.check_access(cur, ACCESS_W | ACCESS_X, &cur_path)?;
+        Ok((cur, name))
+    }
+
+    /// InodeAlloc cu metadatele finale + DirEntryAdd.
+    fn import_node(
+        &mut self,
+        parent: InodeId,
+        name: &str,
+        kind: NodeKind,
+        metadata: Metadata,
+        xattrs: XattrMap,
+    ) -> Result<InodeId> {
+        let id = self.next_inode;
+        self.next_inode = InodeId(id.0 + 1);
+        let snap = InodeSnapshot {
+            id,
+            parent: Some(parent),
+            name: name.to_string(),
+            kind,
+            metadata,
+            extents: vec![],
+            xattrs,
+        };
+        let de = DirEntry {
+            parent,
+            inode: id,
+            name: name.to_string(),
+            kind,
+        };
+        for rec in [Record::InodeAlloc(snap), Record::DirEntryAdd { entry: de }] {
+            self.log(&rec)?;
+            self.apply_record(&rec)?;
+        }
+        Ok(id)
+    }
+
+    fn entry_metadata(&self, entry: &TarEntry) -> Metadata {
+        let mut meta = self.new_metadata(self.options.clock.now(), entry.mode & 0o7777);
+        meta.modified_at = entry.modified_at;
+        meta.accessed_at = entry.accessed_at.unwrap_or(entry.modified_at);
+        meta.created_at = entry.created_at.unwrap_or(entry.modified_at);
+        // ca tar: ownership-ul din arhiva se pastreaza doar pt root
+        if self.options.user.as_ref().is_none_or(|u| u.uid == 0) {
+            meta.uid = entry.uid;
+            meta.gid = entry.gid;
+        }
+        meta
+    }
+
+    fn import_file<R: Read>(
+        &mut self,
+        batch: &mut ImportBatch,
+        entry: &TarEntry,
+        input: &mut R,
+    ) -> Result<()> {
+        let (parent, name) = self.import_parent(batch, &entry.path)?;
+        if self.children.contains_key(&(parent, name.clone())) {
+            return Err(VfsError::AlreadyExists(entry.path.clone()));
+        }
+        let metadata = self.entry_metadata(entry);
+        let id = self.import_node(
+            parent,
+            &name,
+            NodeKind::File,
+            metadata,
+            entry.xattrs.clone(),
+        )?;
+
+        let mut buf = vec![0u8; (entry.size as usize).min(CHUNK)];
+        let mut off = 0;
+        while off < entry.size {
+            let n = ((entry.size - off) as usize).min(CHUNK);
+            input.read_exact(&mut buf[..n])?;
+            self.store_data(id, off, &buf[..n])?;
+            off += n as u64;
+        }
+        batch.report.files += 1;
+        Ok(())
+    }
+
+    fn import_dir(&mut self, batch: &mut ImportBatch, entry: &TarEntry) -> Result<()> {
+        let metadata = self.entry_metadata(entry);
+        let times = (
+            metadata.created_at,
+            metadata.modified_at,
+            metadata.accessed_at,
+        );
+
+        // "./" e chiar `dest`
+        let id = if archive_path(&entry.path)?.is_empty() {
+            batch.dest
+        } else {
+            let (parent, name) = self.import_parent(batch, &entry.path)?;
+            match self.children.get(&(parent, name.clone())) {
+                Some(&id) if self.inodes[&id].kind == NodeKind::Dir => id,
+                Some(_) => return Err(VfsError::NotADir(entry.path.clone())),
+                None => {
+                    batch.report.dirs += 1;
+                    let id = self.import_node(
+                        parent,
+                        &name,
+                        NodeKind::Dir,
+                        metadata.clone(),
+                        entry.xattrs.clone(),
+                    )?;
+                    batch.dir_times.push((id, times.0, times.1, times.2));
+                    return Ok(());
+                }
+            }
+        };
+
+        // director existent (sau creat implicit mai devreme): doar metadatele,
+        // cu aceleasi drepturi ca `set_permissions` si `set_xattr`
+        let mut path = batch.dest_path.clone();
+        for part in archive_path(&entry.path)? {
+            path = path.join(&part)?;
+        }
+        self.check_owner(id, &path)?;
+        if !entry.xattrs.is_empty() {
+            self.check_access(id, ACCESS_W, &path)?;
+        }
+        let mut recs = vec![Record::SetPermissions {
+            inode: id,
+            mode: metadata.mode,
+        }];
+        recs.extend(entry.xattrs.iter().map(|(name, value)| Record::SetXattr {
+            inode: id,
+            name: name.clone(),
+            value: value.clone(),
+        }));
+        for rec in recs {
+            self.log(&rec)?;
+            self.apply_record(&rec)?;
+        }
+        batch.dir_times.push((id, times.0, times.1, times.2));
+        Ok(())
+    }
+
+    fn finish_import(&mut self, batch: ImportBatch) -> Result<ImportReport> {
+        for (inode, created, modified, accessed) in batch.dir_times {
+            let rec = Record::SetTimes {
+                inode,
+                created_at: Some(created),
+                modified_at: Some(modified),
+                accessed_at: Some(accessed),
+                changed_at: None,
+            };
+            self.log(&rec)?;
+            self.apply_record(&rec)?;
+        }
+        if batch.report.files + batch.report.dirs > 0 {
+            self.touch(batch.dest, true)?;
+        }
+        self.write_checkpoint()?;
+        self.file.sync_all()?;
+        Ok(batch.report)
+    }
+}
+
+/// componentele unui path din arhiva; `..` ar putea iesi din `dest`, deci e refuzat.
+fn archive_path(path: &str) -> Result<Vec<String>> {
+    let mut out = Vec::new();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                return Err(VfsError::InvalidPath(format!(
+                    "archive path escapes destination: {path}"
+                )));
+            }
+            _ => out.push(part.to_string()),
+        }
+    }
+    Ok(out)
+}
+
+fn parse_header(block: &[u8; BLOCK]) -> Result<TarEntry> {
+    let stored: u32 = parse_octal(&block[148..156])? as u32;
+    let sum: u32 = block
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| {
+            if (148..156).contains(&i) {
+                b' ' as u32
+            } else {
+                b as u32
+            }
+        })
+        .sum();
+    if stored != sum {
+        return Err(VfsError::InvalidPath("tar header checksum mismatch".into()));
+    }
+
+    let name = c_str(&block[0..100]);
+    let prefix = if &block[257..262] == b"ustar" {
+        c_str(&block[345..500])
+    } else {
+        String::new()
+    };
+    let path = if prefix.is_empty() {
+        name
+    } else {
+        format!("{prefix}/{name}")
+    };
+
+    Ok(TarEntry {
+        path,
+        size: parse_octal(&block[124..136])?,
+        mode: parse_octal(&block[100..108])? as u32,
+        uid: parse_octal(&block[108..116])? as u32,
+        gid: parse_octal(&block[116..124])? as u32,
+        modified_at: Timestamp(parse_octal(&block[136..148])? as i128 * 1_000_000_000),
+        accessed_at: None,
+        created_at: None,
+        xattrs: XattrMap::new(),
+    })
+}
+
+fn ustar_header(name: &str, typeflag: u8, size: u64, meta: &Metadata) -> [u8; BLOCK] {
+    let mut h = [0u8; BLOCK];
+    // numele complet e in pax daca nu incape; aici punem un prefix trunchiat
+    let bytes = name.as_bytes();
+    let n = bytes.len().min(100);
+    h[..n].copy_from_slice(&bytes[..n]);
+    put_octal(&mut h[100..108], (meta.mode & 0o7777) as u64);
+    put_octal(&mut h[108..116], meta.uid as u64);
+    put_octal(&mut h[116..124], meta.gid as u64);
+    put_octal(&mut h[124..136], size.min(MAX_OCTAL_11));
+    let secs = meta.modified_at.0.div_euclid(1_000_000_000);
+    put_octal(&mut h[136..148], secs.clamp(0, MAX_OCTAL_11 as i128) as u64);
+    h[156] = typeflag;
+    h[257..263].copy_from_slice(b"ustar\0");
+    h[263..265].copy_from_slice(b"00");
+
+    h[148..156].fill(b' ');
+    let sum: u32 = h.iter().map(|&b| b as u32).sum();
+    put_octal(&mut h[148..155], sum as u64);
+    h[155] = b' ';
+    h
+}
+
+// valoare octala pe toata latimea campului, terminata cu NUL
+fn put_octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let s = format!("{value:0digits$o}");
+    field[..digits].copy_from_slice(&s.as_bytes()[s.len() - digits..]);
+    field[digits] = 0;
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64> {
+    // extensia gnu pt numere mari: bitul de sus setat, apoi big-endian
+    if field.first().is_some_and(|&b| b & 0x80 != 0) {
+        let mut v: u64 = (field[0] & 0x7f) as u64;
+        for &b in &field[1..] {
+            v = v
+                .checked_mul(256)
+                .and_then(|v| v.checked_add(b as u64))
+                .ok_or_else(|| VfsError::InvalidPath("tar number too large".into()))?;
+        }
+        return Ok(v);
+    }
+    let s = c_str(field);
+    let s = s.trim_matches(|c: char| c == ' ' || c == '\0');
+    if s.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(s, 8).map_err(|_| VfsError::InvalidPath(format!("bad tar number {s:?}")))
+}
+
+fn c_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn pax_record(out: &mut Vec<u8>, key: &str, value: &[u8]) {
+    // lungimea include si cifrele ei
+    let body = key.len() + value.len() + 3; // ' ', '=', '\n'
+    let mut len = body + body.to_string().len();
+    if len.to_string().len() != body.to_string().len() {
+        len = body + len.to_string().len();
+    }
+    out.extend_from_slice(format!("{len} {key}=").as_bytes());
+    out.extend_from_slice(value);
+    out.push(b'\n');
+}
+
+fn pax_time(t: Timestamp) -> String {
+    let sign = if t.0 < 0 { "-" } else { "" };
+    let abs = t.0.unsigned_abs();
+    format!("{sign}{}.{:09}", abs / 1_000_000_000, abs % 1_000_000_000)
+}
+
+fn parse_pax_time(s: &str) -> Result<Timestamp> {
+    let bad = || VfsError::InvalidPath(format!("bad pax time {s:?}"));
+    let (neg, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (secs, frac) = s.split_once('.').unwrap_or((s, ""));
+    let secs: i128 = secs.parse().map_err(|_| bad())?;
+    let mut nanos: i128 = 0;
+    for (i, c) in frac.chars().take(9).enumerate() {
+        let d = c.to_digit(10).ok_or_else(bad)? as i128;
+        nanos += d * 10i128.pow(8 - i as u32);
+    }
+    let t = secs * 1_000_000_000 + nanos;
+    Ok(Timestamp(if neg { -t } else { t }))
+}
+
+fn padding(size: u64) -> usize {
+    (BLOCK - (size % BLOCK as u64) as usize) % BLOCK
+}
+
+fn write_padded<W: Write>(out: &mut W, data: &[u8]) -> Result<()> {
+    out.write_all(data)?;
+    out.write_all(&[0u8; BLOCK][..padding(data.len() as u64)])?;
+    Ok(())
+}
+
+// false la EOF curat (arhive fara cele doua blocuri goale de final)
+fn read_block<R: Read>(input: &mut R, block: &mut [u8; BLOCK]) -> Result<bool> {
+    let mut got = 0;
+    while got < BLOCK {
+        match input.read(&mut block[got..])? {
+            0 if got == 0 => return Ok(false),
+            0 => return Err(VfsError::InvalidPath("truncated tar header".into())),
+            n => got += n,
+        }
+    }
+    Ok(true)
+}
+
+// datele unui header pax sau ale unui nume lung; marimea nu e de incredere
+fn read_data<R: Read>(input: &mut R, size: u64) -> Result<Vec<u8>> {
+    if size > MAX_META {
+        return Err(VfsError::InvalidPath(format!(
+            "tar metadata entry of {size} bytes (at most {MAX_META})"
+        )));
+    }
+    let mut data = Vec::new();
+    input.take(size).read_to_end(&mut data)?;
+    if data.len() as u64 != size {
+        return Err(VfsError::InvalidPath("truncated tar entry".into()));
+    }
+    skip_exact(input, padding(size) as u64)?;
+    Ok(data)
+}
+
+// datele unei intrari, cu tot cu padding-ul pana la bloc
+fn skip_data<R: Read>(input: &mut R, size: u64) -> Result<()> {
+    skip_exact(input, size + padding(size) as u64)
+}
+
+fn skip_exact<R: Read>(input: &mut R, n: u64) -> Result<()> {
+    let copied = std::io::copy(&mut input.take(n), &mut std::io::sink())?;
+    if copied != n {
+        return Err(VfsError::InvalidPath("truncated tar entry".into()));
+    }
+    Ok(())
+}
@@ -0,0 +1,87 @@
+//! vfs-9p: serveste o imagine prin 9P2000.L.
+//!
+//!     vfs-9p <imagine> [adresa | unix:<socket>] [--read-only]
+//!
+//! adresa implicita e `127.0.0.1:5640`. dintr-un guest linux:
+//!
+//!     mount -t 9p -o trans=tcp,port=5640,version=9p2000.L <host> /mnt
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::process::ExitCode;
+
+use virtual_file_system::ninep::NinepServer;
+use virtual_file_system::{MountOptions, Vfs, VfsError};
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let read_only = match args.iter().position(|a| a == "--read-only") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let (image, addr) = match args.as_slice() {
+        [image] => (image.as_str(), "127.0.0.1:5640"),
+        [image, addr] => (image.as_str(), addr.as_str()),
+        _ => {
+            eprintln!("usage: vfs-9p <image> [addr | unix:<socket>] [--read-only]");
+            return ExitCode::from(2);
+        }
+    };
+
+    // `SOURCE_DATE_EPOCH` plafoneaza timpii scrisi, ca la celelalte unelte
+    let options = match MountOptions::from_env() {
+        Ok(env) => MountOptions { read_only, ..env },
+        Err(e) => {
+            eprintln!("vfs-9p: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let served = Vfs::mount_with(image, options).and_then(|vfs| {
+        let mut server = NinepServer::new(vfs);
+        match addr.strip_prefix("unix:") {
+            Some(socket) => serve_unix(&mut server, image, socket),
+            None => {
+                let listener = TcpListener::bind(addr)?;
+                eprintln!("serving {image} on {}", listener.local_addr()?);
+                for stream in listener.incoming() {
+                    serve_one(&mut server, stream?);
+                }
+                Ok(())
+            }
+        }
+    });
+    match served {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("vfs-9p: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// o conexiune esuata nu opreste serverul
+fn serve_one<S: Read + Write>(server: &mut NinepServer, stream: S) {
+    if let Err(e) = server.serve(stream) {
+        eprintln!("vfs-9p: connection: {e}");
+    }
+}
+
+#[cfg(unix)]
+fn serve_unix(server: &mut NinepServer, image: &str, socket: &str) -> Result<(), VfsError> {
+    let listener = std::os::unix::net::UnixListener::bind(socket)?;
+    eprintln!("serving {image} on unix:{socket}");
+    for stream in listener.incoming() {
+        serve_one(server, stream?);
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_unix(_: &mut NinepServer, _: &str, _: &str) -> Result<(), VfsError> {
+    Err(VfsError::Unsupported(
+        "unix sockets are not available on this platform".into(),
+    ))
+}
@@ -0,0 +1,171 @@
+//! vfs-shell: shell interactiv peste o imagine.
+//!
+//!     vfs-shell [--create] <imagine> [script]
+//!
+//! cu un script (`-` = stdin) comenzile se citesc de acolo, fara prompt, si codul
+//! de iesire spune daca a esuat vreo linie. o imagine lipsa se creeaza doar cu
+//! `--create`. editarea liniei si completarea cu tab
+//! au nevoie de feature-ul `readline`; fara el se citesc linii simple din stdin.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use virtual_file_system::shell::{Flow, Shell};
+use virtual_file_system::{MountOptions, Vfs};
+
+const USAGE: &str = "usage: vfs-shell [--create] <image> [script | -]";
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(args.first().map(String::as_str), Some("-h" | "--help")) {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let create = args.first().is_some_and(|a| a == "--create");
+    if create {
+        args.remove(0);
+    }
+    let (image, script) = match args.as_slice() {
+        [image] => (image, None),
+        [image, script] => (image, Some(script.as_str())),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    if image.starts_with('-') {
+        eprintln!("vfs-shell: {image:?} is not an image path\n{USAGE}");
+        return ExitCode::from(2);
+    }
+    if !create && !Path::new(image).exists() {
+        eprintln!("vfs-shell: {image} does not exist (use --create to make a new image)");
+        return ExitCode::FAILURE;
+    }
+
+    let vfs = match MountOptions::from_env().and_then(|options| Vfs::mount_with(image, options)) {
+        Ok(vfs) => vfs,
+        Err(e) => {
+            eprintln!("vfs-shell: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut shell = Shell::new(vfs, std::io::stdout());
+
+    let Some(script) = script else {
+        interactive(shell);
+        return ExitCode::SUCCESS;
+    };
+    let input: Box<dyn BufRead> = match script {
+        "-" => Box::new(std::io::stdin().lock()),
+        path => match File::open(path) {
+            Ok(f) => Box::new(BufReader::new(f)),
+            Err(e) => {
+                eprintln!("vfs-shell: {path}: {e}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+    match shell.run_script(input) {
+        Ok(0) => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("vfs-shell: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// o linie din bucla interactiva; false = iesire
+fn step(shell: &mut Shell, line: &str) -> bool {
+    let flow = shell.execute(line);
+    let _ = shell.session().out.flush();
+    match flow {
+        Ok(Flow::Continue) => true,
+        Ok(Flow::Exit) => false,
+        Err(e) => {
+            eprintln!("{e}");
+            true
+        }
+    }
+}
+
+#[cfg(not(feature = "readline"))]
+fn interactive(mut shell: Shell) {
+    let stdin = std::io::stdin();
+    loop {
+        print!("{}", shell.prompt());
+        let _ = std::io::stdout().flush();
+        let mut line = String::new();
+        match stdin.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if !step(&mut shell, &line) {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(feature = "readline")]
+fn interactive(shell: Shell) {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    use rustyline::completion::Completer;
+    use rustyline::error::ReadlineError;
+    use rustyline::highlight::Highlighter;
+    use rustyline::hint::Hinter;
+    use rustyline::history::DefaultHistory;
+    use rustyline::validate::Validator;
+    use rustyline::{Context, Editor, Helper};
+
+    // completarea citeste din acelasi shell pe care il modifica bucla
+    struct VfsHelper(Rc<RefCell<Shell>>);
+
+    impl Completer for VfsHelper {
+        type Candidate = String;
+
+        fn complete(
+            &self,
+            line: &str,
+            pos: usize,
+            _ctx: &Context<'_>,
+        ) -> rustyline::Result<(usize, Vec<String>)> {
+            Ok(self.0.borrow().complete(line, pos))
+        }
+    }
+
+    impl Hinter for VfsHelper {
+        type Hint = String;
+    }
+    impl Highlighter for VfsHelper {}
+    impl Validator for VfsHelper {}
+    impl Helper for VfsHelper {}
+
+    let shell = Rc::new(RefCell::new(shell));
+    let mut editor: Editor<VfsHelper, DefaultHistory> = match Editor::new() {
+        Ok(editor) => editor,
+        Err(e) => {
+            eprintln!("vfs-shell: {e}");
+            return;
+        }
+    };
+    editor.set_helper(Some(VfsHelper(shell.clone())));
+
+    loop {
+        let prompt = shell.borrow().prompt();
+        match editor.readline(&prompt) {
+            Ok(line) => {
+                let _ = editor.add_history_entry(line.as_str());
+                if !step(&mut shell.borrow_mut(), &line) {
+                    break;
+                }
+            }
+            Err(ReadlineError::Interrupted) => continue,
+            Err(_) => break,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+//! vfs-webdav: serveste o imagine prin WebDAV.
+//!
+//!     vfs-webdav <imagine> [adresa] [--read-only]
+//!
+//! adresa implicita e `127.0.0.1:8080`; imaginea se poate deschide apoi din file
+//! manager (`dav://127.0.0.1:8080/`) sau cu orice client WebDAV.
+
+use std::process::ExitCode;
+
+use virtual_file_system::webdav::DavServer;
+use virtual_file_system::{MountOptions, Vfs};
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let read_only = match args.iter().position(|a| a == "--read-only") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    let (image, addr) = match args.as_slice() {
+        [image] => (image.as_str(), "127.0.0.1:8080"),
+        [image, addr] => (image.as_str(), addr.as_str()),
+        _ => {
+            eprintln!("usage: vfs-webdav <image> [addr] [--read-only]");
+            return ExitCode::from(2);
+        }
+    };
+
+    // `SOURCE_DATE_EPOCH` plafoneaza timpii scrisi, ca la celelalte unelte
+    let options = match MountOptions::from_env() {
+        Ok(env) => MountOptions { read_only, ..env },
+        Err(e) => {
+            eprintln!("vfs-webdav: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let served = Vfs::mount_with(image, options).and_then(|vfs| {
+        let mut server = DavServer::bind(vfs, addr)?;
+        eprintln!("serving {image} on http://{}/", server.local_addr()?);
+        server.serve()
+    });
+    match served {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("vfs-webdav: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
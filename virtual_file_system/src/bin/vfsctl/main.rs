@@ -0,0 +1,842 @@
+//! vfsctl: inspecteaza si modifica o imagine din linia de comanda.
+//!
+//!     vfsctl [--json] [--key-file <cheie>] <comanda> <imagine> [argumente]
+//!
+//! cu `--json` fiecare comanda scrie un singur document json pe stdout, in afara
+//! de `cat` si `get`, care scriu datele fisierului, si `dump-log`, care scrie un
+//! document per record. o imagine noua se creeaza doar cu `init`; comenzile care
+//! doar citesc monteaza imaginea read-only. cu `SOURCE_DATE_EPOCH` setat, niciun
+//! timp scris nu trece de el, deci aceleasi comenzi dau aceeasi imagine.
+
+mod dump;
+mod json;
+mod replicate;
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+
+use json::{Json, obj};
+use virtual_file_system::debug::LogReader;
+use virtual_file_system::structs::{Record, Result};
+use virtual_file_system::{
+    Compression, EncryptionKey, Metadata, MountOptions, NodeKind, Vfs, VfsError, VfsPath,
+    WatchEvent,
+};
+
+const USAGE: &str = "usage: vfsctl [--json] [--key-file <key>] <command> <image> [args]
+
+commands:
+  init                     create an empty image (encrypted with --key-file);
+                           the other commands never create one
+  ls [-lR] [path...]       list directories
+  cat <path...>            write files to stdout
+  put <host file|-> <path> copy a host file (or stdin) into the image
+  get <path> [host file|-] copy a file out of the image (default stdout)
+  mkdir [-p] <path...>     create directories
+  rm [-r] <path...>        remove files and directories
+  mv <from> <to>           rename
+  stat <path...>           show metadata
+  tree [path]              show a directory tree
+  du [path...]             total file size under each path
+  checkpoint               write a checkpoint record
+  compact                  rewrite the image without dead records
+  upgrade                  rewrite an image written by an older version in
+                           the current format, so it can be written again
+  fsck                     verify records, data checksums and the tree
+  info                     header, block size and record counts
+  dump-log [--inode N] [--type kind,..] [--from OFF] [--to OFF] [--stop]
+                           print every record; corrupt spots are reported and
+                           skipped unless --stop is given
+  watch [-r] [path]        follow an image written by another process and
+                           print every change under path until interrupted
+  replicate <dst>          keep dst in sync with the image; either side may
+                           be `-` (stdin/stdout) or unix:<socket> instead";
+
+enum Failure {
+    Usage,
+    Vfs(VfsError),
+}
+
+impl From<VfsError> for Failure {
+    fn from(e: VfsError) -> Self {
+        Failure::Vfs(e)
+    }
+}
+
+impl From<std::io::Error> for Failure {
+    fn from(e: std::io::Error) -> Self {
+        Failure::Vfs(e.into())
+    }
+}
+
+// o intrare din `ls`: path, tip, metadate
+type Listed = (VfsPath, NodeKind, Metadata);
+
+struct Ctl {
+    image: String,
+    json: bool,
+    key: Option<EncryptionKey>,
+}
+
+fn main() -> ExitCode {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    if matches!(args.first().map(String::as_str), Some("-h" | "--help")) {
+        println!("{USAGE}");
+        return ExitCode::SUCCESS;
+    }
+    let json = take_flag(&mut args, "--json");
+    // `--key-file` fara valoare nu trebuie sa monteze imaginea fara cheie
+    let Some(key_file) = take_option(&mut args, "--key-file") else {
+        eprintln!("vfsctl: --key-file needs a path\n{USAGE}");
+        return ExitCode::from(2);
+    };
+    let key = match key_file.map(read_key).transpose() {
+        Ok(key) => key,
+        Err(e) => {
+            eprintln!("vfsctl: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let [cmd, image, rest @ ..] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        return ExitCode::from(2);
+    };
+    // `vfsctl mkdir -p img a` ar lua `-p` drept imagine; doar replicate accepta `-`
+    if image.starts_with('-') && !(cmd == "replicate" && image == "-") {
+        eprintln!("vfsctl: {image:?} is not an image path\n{USAGE}");
+        return ExitCode::from(2);
+    }
+
+    let ctl = Ctl {
+        image: image.clone(),
+        json,
+        key,
+    };
+    match ctl.run(cmd, rest) {
+        Ok(code) => code,
+        Err(Failure::Usage) => {
+            eprintln!("{USAGE}");
+            ExitCode::from(2)
+        }
+        Err(Failure::Vfs(e)) => {
+            eprintln!("vfsctl: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != flag);
+    args.len() != before
+}
+
+// `Some(None)` = optiunea lipseste; `None` = apare, dar fara valoare
+fn take_option(args: &mut Vec<String>, name: &str) -> Option<Option<String>> {
+    let Some(i) = args.iter().position(|a| a == name) else {
+        return Some(None);
+    };
+    args.remove(i);
+    (i < args.len()).then(|| Some(args.remove(i)))
+}
+
+fn read_key(path: String) -> Result<EncryptionKey> {
+    let bytes = std::fs::read(&path)?;
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| VfsError::WrongKey(format!("{path} must hold exactly 32 bytes")))?;
+    Ok(EncryptionKey::from_bytes(bytes))
+}
+
+// flag-urile scurte (`-lR`) de la inceputul argumentelor; restul sunt path-uri
+fn split_flags<'a>(
+    args: &'a [String],
+    allowed: &str,
+) -> std::result::Result<(String, &'a [String]), Failure> {
+    let mut flags = String::new();
+    let mut rest = args;
+    while let [first, tail @ ..] = rest {
+        let Some(f) = first.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            break;
+        };
+        if !f.chars().all(|c| allowed.contains(c)) {
+            return Err(Failure::Usage);
+        }
+        flags.push_str(f);
+        rest = tail;
+    }
+    Ok((flags, rest))
+}
+
+impl Ctl {
+    fn mount(&self, read_only: bool) -> Result<Vfs> {
+        if !Path::new(&self.image).exists() {
+            return Err(VfsError::NotFound(format!(
+                "{} (create it with `vfsctl init`)",
+                self.image
+            )));
+        }
+        Vfs::mount_with(
+            &self.image,
+            MountOptions {
+                read_only,
+                ..self.options()?
+            },
+        )
+    }
+
+    // cheia din `--key-file` si ceasul din `SOURCE_DATE_EPOCH`
+    fn options(&self) -> Result<MountOptions> {
+        Ok(MountOptions {
+            encryption: self.key.clone(),
+            ..MountOptions::from_env()?
+        })
+    }
+
+    fn print(&self, json: Json, text: impl FnOnce() -> String) {
+        if self.json {
+            println!("{json}");
+        } else {
+            print!("{}", text());
+        }
+    }
+
+    fn run(&self, cmd: &str, args: &[String]) -> std::result::Result<ExitCode, Failure> {
+        match (cmd, args) {
+            ("init", []) => {
+                if Path::new(&self.image).exists() {
+                    return Err(VfsError::AlreadyExists(self.image.clone()).into());
+                }
+                Vfs::mount_with(&self.image, self.options()?)?;
+                self.print(obj([("image", self.image.as_str().into())]), || {
+                    format!("created {}\n", self.image)
+                });
+            }
+            ("ls", args) => {
+                let (flags, paths) = split_flags(args, "lR")?;
+                self.ls(paths, flags.contains('l'), flags.contains('R'))?;
+            }
+            ("cat", paths) if !paths.is_empty() => {
+                let vfs = self.mount(true)?;
+                let mut out = std::io::stdout().lock();
+                for path in paths {
+                    std::io::copy(&mut vfs.open(path)?, &mut out)?;
+                }
+            }
+            ("put", [host, path]) => self.put(host, path)?,
+            ("get", [path]) => self.get(path, "-")?,
+            ("get", [path, host]) => self.get(path, host)?,
+            ("mkdir", args) => {
+                let (flags, paths) = split_flags(args, "p")?;
+                if paths.is_empty() {
+                    return Err(Failure::Usage);
+                }
+                self.mkdir(paths, flags.contains('p'))?;
+            }
+            ("rm", args) => {
+                let (flags, paths) = split_flags(args, "r")?;
+                if paths.is_empty() {
+                    return Err(Failure::Usage);
+                }
+                self.rm(paths, flags.contains('r'))?;
+            }
+            ("mv", [from, to]) => {
+                let mut vfs = self.mount(false)?;
+                vfs.rename(from, to)?;
+                self.print(
+                    obj([("from", from.as_str().into()), ("to", to.as_str().into())]),
+                    String::new,
+                );
+            }
+            ("stat", paths) if !paths.is_empty() => self.stat(paths)?,
+            ("tree", []) => self.tree("/")?,
+            ("tree", [path]) => self.tree(path)?,
+            ("du", []) => self.du(&["/".to_string()])?,
+            ("du", paths) => self.du(paths)?,
+            ("checkpoint", []) => {
+                let mut vfs = self.mount(false)?;
+                vfs.checkpoint()?;
+                let bytes = std::fs::metadata(&self.image)?.len();
+                self.print(obj([("image_bytes", bytes.into())]), || {
+                    format!("checkpoint written, image is {bytes} bytes\n")
+                });
+            }
+            ("compact", []) => {
+                let before = std::fs::metadata(&self.image)?.len();
+                let mut vfs = self.mount(false)?;
+                vfs.compact()?;
+                let after = std::fs::metadata(&self.image)?.len();
+                self.print(
+                    obj([
+                        ("before_bytes", before.into()),
+                        ("after_bytes", after.into()),
+                    ]),
+                    || format!("compacted {before} -> {after} bytes\n"),
+                );
+            }
+            ("upgrade", []) => {
+                let from = Vfs::upgrade(&self.image, self.options()?)?;
+                let to = virtual_file_system::no_sql::VERSION;
+                self.print(
+                    obj([("from_version", from.into()), ("version", to.into())]),
+                    || {
+                        if from == to {
+                            format!("already at format v{to}\n")
+                        } else {
+                            format!("upgraded from format v{from} to v{to}\n")
+                        }
+                    },
+                );
+            }
+            ("fsck", []) => return self.fsck(),
+            ("info", []) => self.info()?,
+            ("dump-log", args) => return self.dump_log(args),
+            ("replicate", [dst]) => {
+                let src = replicate::End::parse(&self.image);
+                let dst = replicate::End::parse(dst);
+                replicate::run(src, dst, self.options()?).ok_or(Failure::Usage)??;
+            }
+            ("watch", args) => {
+                let (flags, path) = split_flags(args, "r")?;
+                match path {
+                    [] => self.watch("/", flags.contains('r'))?,
+                    [path] => self.watch(path, flags.contains('r'))?,
+                    _ => return Err(Failure::Usage),
+                }
+            }
+            _ => return Err(Failure::Usage),
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+
+    fn ls(&self, paths: &[String], long: bool, recursive: bool) -> Result<()> {
+        let vfs = self.mount(true)?;
+        let paths = if paths.is_empty() {
+            vec![VfsPath::root()]
+        } else {
+            paths.iter().map(absolute).collect::<Result<_>>()?
+        };
+
+        // (director, intrarile lui); un fisier dat explicit apare singur
+        let mut listings: Vec<(VfsPath, Vec<Listed>)> = Vec::new();
+        let mut pending = paths;
+        pending.reverse();
+        while let Some(dir) = pending.pop() {
+            let meta = vfs.metadata(&dir)?;
+            if vfs.read_dir(&dir).is_err() {
+                listings.push((dir.clone(), vec![(dir, NodeKind::File, meta)]));
+                continue;
+            }
+            let mut entries = Vec::new();
+            for entry in sorted_dir(&vfs, &dir)? {
+                let path = dir.join(&entry.name)?;
+                let meta = vfs.metadata(&path)?;
+                entries.push((path, entry.kind, meta));
+            }
+            if recursive {
+                let subdirs = entries.iter().filter(|e| e.1 == NodeKind::Dir);
+                pending.extend(subdirs.rev().map(|e| e.0.clone()).collect::<Vec<_>>());
+            }
+            listings.push((dir, entries));
+        }
+
+        let json = Json::Arr(
+            listings
+                .iter()
+                .flat_map(|(_, entries)| entries.iter().map(|(p, k, m)| meta_json(p, *k, m)))
+                .collect(),
+        );
+        self.print(json, || {
+            let mut out = String::new();
+            let headers = recursive || listings.len() > 1;
+            for (i, (dir, entries)) in listings.iter().enumerate() {
+                if headers {
+                    if i > 0 {
+                        out.push('\n');
+                    }
+                    out.push_str(&format!("{dir}:\n"));
+                }
+                for (path, kind, meta) in entries {
+                    let name = path.file_name().unwrap_or("/");
+                    if long {
+                        out.push_str(&format!(
+                            "{} {:>5} {:>5} {:>10} {:.0} {name}\n",
+                            mode_string(*kind, meta.mode),
+                            meta.uid,
+                            meta.gid,
+                            meta.size,
+                            meta.modified_at
+                        ));
+                    } else {
+                        out.push_str(&format!("{name}\n"));
+                    }
+                }
+            }
+            out
+        });
+        Ok(())
+    }
+
+    fn put(&self, host: &str, path: &str) -> Result<()> {
+        let vfs = self.mount(false)?;
+        let mut input: Box<dyn Read> = match host {
+            "-" => Box::new(std::io::stdin().lock()),
+            file => Box::new(File::open(file)?),
+        };
+        let mut file = if vfs.exists(path) {
+            let mut f = vfs.open_rw(path)?;
+            f.set_len(0)?;
+            f
+        } else {
+            vfs.create(path)?
+        };
+        let bytes = copy_in_chunks(&mut input, &mut file)?;
+        drop(file);
+        self.print(
+            obj([("path", path.into()), ("bytes", bytes.into())]),
+            String::new,
+        );
+        Ok(())
+    }
+
+    fn get(&self, path: &str, host: &str) -> Result<()> {
+        let vfs = self.mount(true)?;
+        let mut out: Box<dyn Write> = match host {
+            "-" => Box::new(std::io::stdout().lock()),
+            file => Box::new(File::create(file)?),
+        };
+        let bytes = std::io::copy(&mut vfs.open(path)?, &mut out)?;
+        out.flush()?;
+        if host != "-" {
+            self.print(
+                obj([("path", path.into()), ("bytes", bytes.into())]),
+                String::new,
+            );
+        }
+        Ok(())
+    }
+
+    fn mkdir(&self, paths: &[String], parents: bool) -> Result<()> {
+        let mut vfs = self.mount(false)?;
+        let mut created = Vec::new();
+        for path in paths {
+            let path = absolute(path)?;
+            if !parents {
+                vfs.create_dir(&path)?;
+                created.push(path.to_string());
+                continue;
+            }
+            let mut prefix = VfsPath::root();
+            for part in path.components() {
+                prefix = prefix.join(part)?;
+                if !vfs.exists(&prefix) {
+                    vfs.create_dir(&prefix)?;
+                    created.push(prefix.to_string());
+                } else if vfs.read_dir(&prefix).is_err() {
+                    return Err(VfsError::NotADir(prefix.to_string()));
+                }
+            }
+        }
+        self.print(created.into(), String::new);
+        Ok(())
+    }
+
+    fn rm(&self, paths: &[String], recursive: bool) -> Result<()> {
+        let mut vfs = self.mount(false)?;
+        let mut removed = Vec::new();
+        for path in paths {
+            let path = absolute(path)?;
+            if vfs.read_dir(&path).is_err() {
+                vfs.remove_file(&path)?;
+                removed.push(path.to_string());
+            } else if recursive {
+                remove_tree(&mut vfs, &path, &mut removed)?;
+            } else {
+                vfs.remove_dir(&path)?;
+                removed.push(path.to_string());
+            }
+        }
+        self.print(removed.into(), String::new);
+        Ok(())
+    }
+
+    fn stat(&self, paths: &[String]) -> Result<()> {
+        let vfs = self.mount(true)?;
+        let mut docs = Vec::new();
+        let mut text = String::new();
+        for path in paths {
+            let path = absolute(path)?;
+            let meta = vfs.metadata(&path)?;
+            let kind = node_kind(&vfs, &path);
+            let xattrs = vfs.list_xattrs(&path)?;
+
+            let mut doc = meta_json(&path, kind, &meta);
+            if let Json::Obj(fields) = &mut doc {
+                fields.push(("xattrs".into(), xattrs.clone().into()));
+            }
+            docs.push(doc);
+
+            text.push_str(&format!(
+                "    path: {path}\n    kind: {}\n    size: {}\n    mode: {:04o} ({})\n   owner: uid {} gid {}\n",
+                kind_name(kind),
+                meta.size,
+                meta.mode,
+                mode_string(kind, meta.mode),
+                meta.uid,
+                meta.gid
+            ));
+            if !xattrs.is_empty() {
+                text.push_str(&format!("  xattrs: {}\n", xattrs.join(", ")));
+            }
+            text.push_str(&format!(
+                " created: {}\nmodified: {}\naccessed: {}\n changed: {}\n",
+                meta.created_at, meta.modified_at, meta.accessed_at, meta.changed_at
+            ));
+        }
+        self.print(Json::Arr(docs), || text);
+        Ok(())
+    }
+
+    fn tree(&self, path: &str) -> Result<()> {
+        let vfs = self.mount(true)?;
+        let root = absolute(path)?;
+        let json = tree_json(&vfs, &root)?;
+        let mut text = format!("{root}\n");
+        if node_kind(&vfs, &root) == NodeKind::Dir {
+            tree_text(&vfs, &root, "", &mut text)?;
+        }
+        self.print(json, || text);
+        Ok(())
+    }
+
+    fn du(&self, paths: &[String]) -> Result<()> {
+        let vfs = self.mount(true)?;
+        let mut docs = Vec::new();
+        let mut text = String::new();
+        for path in paths {
+            let path = absolute(path)?;
+            let mut usage = Usage::default();
+            usage.add(&vfs, &path)?;
+            docs.push(obj([
+                ("path", path.to_string().into()),
+                ("bytes", usage.bytes.into()),
+                ("files", usage.files.into()),
+                ("dirs", usage.dirs.into()),
+            ]));
+            text.push_str(&format!("{}\t{path}\n", usage.bytes));
+        }
+        self.print(Json::Arr(docs), || text);
+        Ok(())
+    }
+
+    fn fsck(&self) -> std::result::Result<ExitCode, Failure> {
+        let vfs = self.mount(true)?;
+        let report = vfs.fsck()?;
+        let json = obj([
+            ("clean", report.is_clean().into()),
+            ("records", report.records.into()),
+            ("log_end", report.log_end.into()),
+            ("image_bytes", report.image_bytes.into()),
+            ("problems", report.problems.clone().into()),
+        ]);
+        self.print(json, || {
+            let mut out = format!(
+                "{} records, log ends at {} of {} bytes\n",
+                report.records, report.log_end, report.image_bytes
+            );
+            for problem in &report.problems {
+                out.push_str(&format!("problem: {problem}\n"));
+            }
+            if report.is_clean() {
+                out.push_str("clean\n");
+            }
+            out
+        });
+        Ok(if report.is_clean() {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+
+    fn dump_log(&self, args: &[String]) -> std::result::Result<ExitCode, Failure> {
+        let mut filter = dump::Filter::default();
+        let mut resync = true;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if arg == "--stop" {
+                resync = false;
+                continue;
+            }
+            let value = args.next().ok_or(Failure::Usage)?;
+            let number = || value.parse::<u64>().map_err(|_| Failure::Usage);
+            match arg.as_str() {
+                "--inode" => filter.inode = Some(number()?),
+                "--type" => {
+                    // un tip scris gresit ar filtra tot, fara nicio eroare
+                    let kinds: Vec<String> = value.split(',').map(str::to_string).collect();
+                    if !kinds.iter().all(|k| Record::KINDS.contains(&k.as_str())) {
+                        return Err(Failure::Usage);
+                    }
+                    filter.kinds = kinds;
+                }
+                "--from" => filter.from = number()?,
+                "--to" => filter.to = Some(number()?),
+                _ => return Err(Failure::Usage),
+            }
+        }
+
+        let reader = LogReader::open_with(&self.image, self.key.as_ref())?.resync(resync);
+        let errors = dump::dump(reader, &filter, self.json);
+        Ok(if errors == 0 {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        })
+    }
+
+    // montare read-only + `tail` periodic; se opreste doar cu o eroare sau ^C
+    fn watch(&self, path: &str, recursive: bool) -> Result<()> {
+        let mut vfs = self.mount(true)?;
+        let events = vfs.watch(path, recursive)?;
+        loop {
+            vfs.tail()?;
+            for event in events.try_iter() {
+                let (kind, path, to) = match event {
+                    WatchEvent::Created(p) => ("created", p, None),
+                    WatchEvent::Modified(p) => ("modified", p, None),
+                    WatchEvent::Removed(p) => ("removed", p, None),
+                    WatchEvent::Renamed { from, to } => ("renamed", from, Some(to)),
+                };
+                let to_text = to.as_ref().map(|t| format!(" -> {t}")).unwrap_or_default();
+                self.print(
+                    obj([
+                        ("event", kind.into()),
+                        ("path", path.as_str().into()),
+                        ("to", to.map(|t| t.as_str().to_string()).into()),
+                    ]),
+                    || format!("{kind} {path}{to_text}\n"),
+                );
+            }
+            std::io::stdout().flush()?;
+            std::thread::sleep(std::time::Duration::from_millis(200));
+        }
+    }
+
+    fn info(&self) -> Result<()> {
+        let vfs = self.mount(true)?;
+        let info = vfs.info()?;
+        let stats = vfs.stats()?;
+        let snapshots = vfs.list_snapshots();
+        let h = &info.header;
+        let compression = match h.compression {
+            Compression::None => "none",
+            Compression::Zstd => "zstd",
+        };
+
+        let json = obj([
+            ("version", h.version.into()),
+            ("block_size", h.block_size.into()),
+            ("root_inode", h.root.0.into()),
+            ("compression", compression.into()),
+            ("encrypted", h.encryption.is_some().into()),
+            ("generation", h.generation.into()),
+            ("log_end", info.log_end.into()),
+            ("image_bytes", info.image_bytes.into()),
+            (
+                "records",
+                Json::Obj(
+                    info.records
+                        .iter()
+                        .map(|(k, n)| (k.to_string(), (*n).into()))
+                        .collect(),
+                ),
+            ),
+            (
+                "snapshots",
+                snapshots
+                    .iter()
+                    .map(|s| s.name.as_str())
+                    .collect::<Vec<_>>()
+                    .into(),
+            ),
+            ("files", stats.files.into()),
+            ("dirs", stats.dirs.into()),
+            ("logical_bytes", stats.logical_bytes.into()),
+            ("physical_bytes", stats.physical_bytes.into()),
+        ]);
+        self.print(json, || {
+            let mut out = format!(
+                "version:     {}\nblock size:  {}\nroot inode:  {}\ncompression: {compression}\nencrypted:   {}\nlog end:     {} of {} bytes\nfiles:       {}\ndirs:        {}\nlogical:     {} bytes\nphysical:    {} bytes\n",
+                h.version,
+                h.block_size,
+                h.root.0,
+                if h.encryption.is_some() { "yes" } else { "no" },
+                info.log_end,
+                info.image_bytes,
+                stats.files,
+                stats.dirs,
+                stats.logical_bytes,
+                stats.physical_bytes
+            );
+            if !snapshots.is_empty() {
+                let names: Vec<&str> = snapshots.iter().map(|s| s.name.as_str()).collect();
+                out.push_str(&format!("snapshots:   {}\n", names.join(", ")));
+            }
+            out.push_str("records:\n");
+            for (kind, n) in &info.records {
+                out.push_str(&format!("  {kind:<18}{n}\n"));
+            }
+            out
+        });
+        Ok(())
+    }
+}
+
+// cwd-ul e mereu radacina; path-urile absolute fac output-ul uniform
+fn absolute<P: AsRef<std::path::Path>>(path: P) -> Result<VfsPath> {
+    VfsPath::root().join(path)
+}
+
+fn sorted_dir(vfs: &Vfs, dir: &VfsPath) -> Result<Vec<virtual_file_system::DirEntry>> {
+    let mut entries: Vec<_> = vfs.read_dir(dir)?.collect::<Result<_>>()?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+fn node_kind(vfs: &Vfs, path: &VfsPath) -> NodeKind {
+    if vfs.read_dir(path).is_ok() {
+        NodeKind::Dir
+    } else {
+        NodeKind::File
+    }
+}
+
+// scrierile trec prin vfs in bucati de 1 MiB, un record per bucata
+fn copy_in_chunks(input: &mut dyn Read, out: &mut impl Write) -> Result<u64> {
+    let mut buf = vec![0u8; 1 << 20];
+    let mut total = 0;
+    loop {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = input.read(&mut buf[filled..])?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        if filled == 0 {
+            return Ok(total);
+        }
+        out.write_all(&buf[..filled])?;
+        total += filled as u64;
+    }
+}
+
+fn remove_tree(vfs: &mut Vfs, path: &VfsPath, removed: &mut Vec<String>) -> Result<()> {
+    for entry in sorted_dir(vfs, path)? {
+        let child = path.join(&entry.name)?;
+        match entry.kind {
+            NodeKind::Dir => remove_tree(vfs, &child, removed)?,
+            NodeKind::File => {
+                vfs.remove_file(&child)?;
+                removed.push(child.to_string());
+            }
+        }
+    }
+    vfs.remove_dir(path)?;
+    removed.push(path.to_string());
+    Ok(())
+}
+
+#[derive(Default)]
+struct Usage {
+    bytes: u64,
+    files: u64,
+    dirs: u64,
+}
+
+impl Usage {
+    fn add(&mut self, vfs: &Vfs, path: &VfsPath) -> Result<()> {
+        if node_kind(vfs, path) == NodeKind::File {
+            self.files += 1;
+            self.bytes += vfs.metadata(path)?.size;
+            return Ok(());
+        }
+        self.dirs += 1;
+        for entry in sorted_dir(vfs, path)? {
+            self.add(vfs, &path.join(&entry.name)?)?;
+        }
+        Ok(())
+    }
+}
+
+fn tree_json(vfs: &Vfs, path: &VfsPath) -> Result<Json> {
+    let meta = vfs.metadata(path)?;
+    let kind = node_kind(vfs, path);
+    let mut fields = vec![
+        ("name".to_string(), path.file_name().unwrap_or("/").into()),
+        ("kind".to_string(), kind_name(kind).into()),
+        ("size".to_string(), meta.size.into()),
+    ];
+    if kind == NodeKind::Dir {
+        let children = sorted_dir(vfs, path)?
+            .iter()
+            .map(|e| tree_json(vfs, &path.join(&e.name)?))
+            .collect::<Result<Vec<_>>>()?;
+        fields.push(("children".to_string(), Json::Arr(children)));
+    }
+    Ok(Json::Obj(fields))
+}
+
+fn tree_text(vfs: &Vfs, dir: &VfsPath, prefix: &str, out: &mut String) -> Result<()> {
+    let entries = sorted_dir(vfs, dir)?;
+    for (i, entry) in entries.iter().enumerate() {
+        let last = i + 1 == entries.len();
+        let branch = if last { "└── " } else { "├── " };
+        let suffix = if entry.kind == NodeKind::Dir { "/" } else { "" };
+        out.push_str(&format!("{prefix}{branch}{}{suffix}\n", entry.name));
+        if entry.kind == NodeKind::Dir {
+            let nested = format!("{prefix}{}", if last { "    " } else { "│   " });
+            tree_text(vfs, &dir.join(&entry.name)?, &nested, out)?;
+        }
+    }
+    Ok(())
+}
+
+fn kind_name(kind: NodeKind) -> &'static str {
+    match kind {
+        NodeKind::File => "file",
+        NodeKind::Dir => "dir",
+    }
+}
+
+fn meta_json(path: &VfsPath, kind: NodeKind, meta: &Metadata) -> Json {
+    obj([
+        ("path", path.to_string().into()),
+        ("kind", kind_name(kind).into()),
+        ("size", meta.size.into()),
+        ("mode", meta.mode.into()),
+        ("uid", meta.uid.into()),
+        ("gid", meta.gid.into()),
+        ("created_at", meta.created_at.0.into()),
+        ("modified_at", meta.modified_at.0.into()),
+        ("accessed_at", meta.accessed_at.0.into()),
+        ("changed_at", meta.changed_at.0.into()),
+    ])
+}
+
+// ca `ls -l`: drwxr-xr-x
+fn mode_string(kind: NodeKind, mode: u32) -> String {
+    let mut s = String::with_capacity(10);
+    s.push(if kind == NodeKind::Dir { 'd' } else { '-' });
+    for shift in [6, 3, 0] {
+        let bits = mode >> shift;
+        s.push(if bits & 4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 2 != 0 { 'w' } else { '-' });
+        s.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    s
+}
@@ -0,0 +1,143 @@
+//! `vfsctl replicate <src> <dst>`: tine o replica la zi cu sursa, pana la ^C
+//! (sau pana se inchide fluxul de intrare).
+//!
+//! fiecare capat e o imagine, `-` (stdin/stdout) sau `unix:<socket>`:
+//!
+//!     vfsctl replicate main.vfs copy.vfs              # in acelasi proces
+//!     vfsctl replicate main.vfs - | vfsctl replicate - copy.vfs
+//!     vfsctl replicate unix:/tmp/r.sock copy.vfs      # replica asculta
+//!     vfsctl replicate main.vfs unix:/tmp/r.sock      # sursa se conecteaza
+//!
+//! pe socket, replica trimite intai offset-ul aplicat (u64 le), ca sursa sa nu
+//! retrimita ce exista deja; pe un pipe sursa porneste de la 0 si replica sare
+//! peste ce are.
+
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+
+use virtual_file_system::structs::Result;
+use virtual_file_system::{MountOptions, Vfs, VfsError};
+
+// cat asteapta sursa intre doua treceri prin log
+const POLL: Duration = Duration::from_millis(200);
+
+pub enum End {
+    Image(String),
+    Stdio,
+    Socket(String),
+}
+
+impl End {
+    pub fn parse(arg: &str) -> End {
+        match arg {
+            "-" => End::Stdio,
+            _ => match arg.strip_prefix("unix:") {
+                Some(socket) => End::Socket(socket.to_string()),
+                None => End::Image(arg.to_string()),
+            },
+        }
+    }
+}
+
+/// `None` = combinatie fara sens (doua fluxuri, de ex.)
+pub fn run(src: End, dst: End, options: MountOptions) -> Option<Result<()>> {
+    let source = |image: &str| {
+        Vfs::mount_with(
+            image,
+            MountOptions {
+                read_only: true,
+                ..options.clone()
+            },
+        )
+    };
+    let replica = |image: &str| Vfs::mount_replica(image, options.clone());
+    Some(match (src, dst) {
+        (End::Image(src), End::Image(dst)) => {
+            source(&src).and_then(|src| replica(&dst).and_then(|dst| follow(&src, dst)))
+        }
+        (End::Image(src), End::Stdio) => {
+            source(&src).and_then(|src| ship(&src, 0, &mut std::io::stdout().lock()))
+        }
+        (End::Image(src), End::Socket(socket)) => {
+            source(&src).and_then(|src| ship_to_socket(&src, &socket))
+        }
+        (End::Stdio, End::Image(dst)) => {
+            replica(&dst).and_then(|mut dst| receive(&mut dst, std::io::stdin().lock()))
+        }
+        (End::Socket(socket), End::Image(dst)) => {
+            replica(&dst).and_then(|mut dst| receive_from_socket(&mut dst, &socket))
+        }
+        _ => return None,
+    })
+}
+
+// ambele imagini locale: replica cere singura ce ii lipseste
+fn follow(src: &Vfs, mut dst: Vfs) -> Result<()> {
+    loop {
+        for change in src.changes_since(dst.applied_offset())? {
+            dst.apply_change(&change?)?;
+        }
+        std::thread::sleep(POLL);
+    }
+}
+
+// trimite schimbarile de la `offset` si apoi pe cele noi, la nesfarsit
+fn ship<W: Write>(src: &Vfs, offset: u64, out: &mut W) -> Result<()> {
+    match ship_forever(src, offset, out) {
+        // celalalt capat s-a inchis: nu e o eroare a sursei
+        Err(VfsError::Io(e)) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        other => other,
+    }
+}
+
+fn ship_forever<W: Write>(src: &Vfs, mut offset: u64, out: &mut W) -> Result<()> {
+    loop {
+        for change in src.changes_since(offset)? {
+            let change = change?;
+            change.write_to(out)?;
+            offset = change.offset + change.bytes.len() as u64;
+        }
+        out.flush()?;
+        std::thread::sleep(POLL);
+    }
+}
+
+fn receive<R: Read>(dst: &mut Vfs, input: R) -> Result<()> {
+    let applied = dst.apply_changes(input)?;
+    eprintln!(
+        "vfsctl: applied {applied} changes, replica at offset {}",
+        dst.applied_offset()
+    );
+    Ok(())
+}
+
+#[cfg(unix)]
+fn ship_to_socket(src: &Vfs, socket: &str) -> Result<()> {
+    let mut stream = std::os::unix::net::UnixStream::connect(socket)?;
+    let mut offset = [0u8; 8];
+    stream.read_exact(&mut offset)?;
+    ship(src, u64::from_le_bytes(offset), &mut stream)
+}
+
+#[cfg(unix)]
+fn receive_from_socket(dst: &mut Vfs, socket: &str) -> Result<()> {
+    let listener = std::os::unix::net::UnixListener::bind(socket)?;
+    eprintln!("vfsctl: waiting for the source on unix:{socket}");
+    let (mut stream, _) = listener.accept()?;
+    stream.write_all(&dst.applied_offset().to_le_bytes())?;
+    receive(dst, stream)
+}
+
+#[cfg(not(unix))]
+fn ship_to_socket(_: &Vfs, _: &str) -> Result<()> {
+    Err(VfsError::Unsupported(
+        "unix sockets are not available on this platform".into(),
+    ))
+}
+
+#[cfg(not(unix))]
+fn receive_from_socket(_: &mut Vfs, _: &str) -> Result<()> {
+    Err(VfsError::Unsupported(
+        "unix sockets are not available on this platform".into(),
+    ))
+}
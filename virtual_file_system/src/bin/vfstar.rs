@@ -0,0 +1,78 @@
+//! vfstar: muta date intre o imagine si o arhiva tar.
+//!
+//!     vfstar export <imagine> <path in vfs> [arhiva.tar | -]
+//!     vfstar import <imagine> <director in vfs> [arhiva.tar | -]
+//!
+//! fara arhiva (sau cu `-`) se foloseste stdout/stdin. timpii scrisi la import
+//! respecta `SOURCE_DATE_EPOCH`, ca in `vfsctl`.
+
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use virtual_file_system::archive::{export_tar, import_tar};
+use virtual_file_system::structs::Result;
+use virtual_file_system::{MountOptions, Vfs};
+
+const USAGE: &str = "usage:
+  vfstar export <image> <vfs path> [archive.tar | -]
+  vfstar import <image> <vfs dir> [archive.tar | -]";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (cmd, image, path, archive) = match args.as_slice() {
+        [cmd, image, path] => (cmd, image, path, "-"),
+        [cmd, image, path, archive] => (cmd, image, path, archive.as_str()),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let result = match cmd.as_str() {
+        "export" => export(image, path, archive),
+        "import" => import(image, path, archive),
+        _ => {
+            eprintln!("{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("vfstar: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn export(image: &str, path: &str, archive: &str) -> Result<()> {
+    // read-only: nu cream imagini goale si nu scriem atime-uri
+    let options = MountOptions {
+        read_only: true,
+        ..MountOptions::from_env()?
+    };
+    let vfs = Vfs::mount_with(image, options)?;
+    let out: Box<dyn Write> = match archive {
+        "-" => Box::new(std::io::stdout().lock()),
+        file => Box::new(File::create(file)?),
+    };
+    export_tar(&vfs, path, BufWriter::new(out))
+}
+
+fn import(image: &str, path: &str, archive: &str) -> Result<()> {
+    let mut vfs = Vfs::mount_with(image, MountOptions::from_env()?)?;
+    let input: Box<dyn Read> = match archive {
+        "-" => Box::new(std::io::stdin().lock()),
+        file => Box::new(File::open(file)?),
+    };
+    let report = import_tar(&mut vfs, path, BufReader::new(input))?;
+    eprintln!(
+        "imported {} files, {} directories",
+        report.files, report.dirs
+    );
+    for skipped in report.skipped {
+        eprintln!("skipped {skipped}: not supported by the vfs");
+    }
+    Ok(())
+}
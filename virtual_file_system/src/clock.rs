@@ -0,0 +1,79 @@
+use crate::structs::{Result, Timestamp, VfsError};
+use std::cell::Cell;
+use std::fmt::Debug;
+use std::time::Duration;
+
+/// source of the timestamps written into the log.
+pub trait Clock: Debug {
+    fn now(&self) -> Timestamp;
+}
+
+/// ceasul sistemului, folosit implicit.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// ceas controlat manual: sta pe loc pana e mutat cu `set`/`advance`.
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Cell<Timestamp>,
+}
+
+impl ManualClock {
+    pub fn new(start: Timestamp) -> Self {
+        Self {
+            now: Cell::new(start),
+        }
+    }
+
+    pub fn set(&self, t: Timestamp) {
+        self.now.set(t);
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let t = self.now.get();
+        self.now.set(Timestamp(t.0 + by.as_nanos() as i128));
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        self.now.get()
+    }
+}
+
+/// reproducible builds: timpii mai noi decat `SOURCE_DATE_EPOCH` sunt
+/// fortati la valoarea lui, deci doua build-uri identice dau aceeasi imagine.
+#[derive(Debug, Clone, Copy)]
+pub struct SourceDateEpochClock {
+    epoch: Timestamp,
+}
+
+impl SourceDateEpochClock {
+    pub fn new(epoch: Timestamp) -> Self {
+        Self { epoch }
+    }
+
+    /// citeste `SOURCE_DATE_EPOCH` (secunde unix); `None` daca nu e setat.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Ok(raw) = std::env::var("SOURCE_DATE_EPOCH") else {
+            return Ok(None);
+        };
+        let secs: i64 = raw
+            .trim()
+            .parse()
+            .map_err(|_| VfsError::InvalidPath(format!("invalid SOURCE_DATE_EPOCH: {raw:?}")))?;
+        Ok(Some(Self::new(Timestamp(secs as i128 * 1_000_000_000))))
+    }
+}
+
+impl Clock for SourceDateEpochClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now().min(self.epoch)
+    }
+}
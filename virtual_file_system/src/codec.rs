@@ -0,0 +1,173 @@
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+
+use crate::crypt::Cipher;
+use crate::no_sql::*;
+use crate::structs::*;
+use crate::vfs::Inner;
+
+/// datele comprimate cu `algo`.
+pub(crate) fn compress(algo: Compression, level: i32, data: &[u8]) -> Result<Vec<u8>> {
+    match algo {
+        Compression::None => Ok(data.to_vec()),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(zstd::bulk::compress(data, level)?),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => {
+            let _ = level;
+            Err(missing(algo))
+        }
+    }
+}
+
+pub(crate) fn decompress(algo: Compression, stored: &[u8]) -> Result<Vec<u8>> {
+    match algo {
+        Compression::None => Ok(stored.to_vec()),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            zstd::stream::decode_all(stored).map_err(|e| VfsError::CorruptLog(format!("zstd: {e}")))
+        }
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => Err(missing(algo)),
+    }
+}
+
+/// imaginea poate fi citita de build-ul curent?
+pub(crate) fn ensure_supported(algo: Compression) -> Result<()> {
+    match algo {
+        Compression::None => Ok(()),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(()),
+        #[cfg(not(feature = "zstd"))]
+        Compression::Zstd => Err(missing(algo)),
+    }
+}
+
+#[cfg(not(feature = "zstd"))]
+fn missing(algo: Compression) -> VfsError {
+    VfsError::Unsupported(format!(
+        "{algo:?} compression (built without the `zstd` feature)"
+    ))
+}
+
+pub(crate) fn algo_id(algo: Compression) -> u8 {
+    match algo {
+        Compression::None => 0,
+        Compression::Zstd => 1,
+    }
+}
+
+pub(crate) fn algo_from_id(id: u8) -> Result<Compression> {
+    match id {
+        0 => Ok(Compression::None),
+        1 => Ok(Compression::Zstd),
+        _ => Err(VfsError::CorruptLog(format!("unknown compression {id}"))),
+    }
+}
+
+/// citeste `out.len()` bytes din extent, incepand cu `within` bytes in el.
+/// ultimul blob decodat ramane in `cache` pt citirile secventiale.
+pub(crate) fn read_extent(
+    file: &mut File,
+    cache: &mut Option<(u64, Vec<u8>)>,
+    cipher: Option<&Cipher>,
+    ex: &Extent,
+    within: u64,
+    out: &mut [u8],
+) -> Result<()> {
+    match ex.encoding {
+        Encoding::Raw if cipher.is_some() => {
+            return Err(VfsError::CorruptLog(
+                "unsealed extent in an encrypted image".into(),
+            ));
+        }
+        Encoding::Raw => {
+            file.seek(SeekFrom::Start(ex.file_offset + within))?;
+            file.read_exact(out)?;
+        }
+        Encoding::Compressed {
+            algo,
+            stored_len,
+            skip,
+        } => {
+            if cache.as_ref().map(|c| c.0) != Some(ex.file_offset) {
+                let mut stored = vec![0u8; stored_len as usize];
+                file.seek(SeekFrom::Start(ex.file_offset))?;
+                file.read_exact(&mut stored)?;
+                if let Some(cipher) = cipher {
+                    stored = cipher.open(ex.file_offset, &stored)?;
+                }
+                *cache = Some((ex.file_offset, decompress(algo, &stored)?));
+            }
+            let data = &cache.as_ref().map(|c| &c.1).expect("cache just filled");
+            let start = (skip + within) as usize;
+            let bytes = data
+                .get(start..start + out.len())
+                .ok_or_else(|| VfsError::CorruptLog("compressed extent too short".into()))?;
+            out.copy_from_slice(bytes);
+        }
+    }
+    Ok(())
+}
+
+impl Inner {
+    /// scrie datele unui write la finalul log-ului, comprimate daca politica
+    /// montarii o cere si daca merita, si intoarce extent-ul lor.
+    pub(crate) fn append_data(
+        &mut self,
+        inode: InodeId,
+        logical_offset: u64,
+        data: &[u8],
+    ) -> Result<Extent> {
+        self.ensure_writable()?;
+        self.file.seek(SeekFrom::End(0))?;
+
+        let algo = self.header.compression;
+        if let Some(policy) = self.options.compression
+            && algo != Compression::None
+            && data.len() >= policy.min_size
+        {
+            let stored = compress(algo, policy.level, data)?;
+            if stored.len() * 100 <= data.len() * policy.max_ratio_percent as usize {
+                let file_offset = write_compressed_write_record(
+                    &mut self.file,
+                    inode,
+                    logical_offset,
+                    data.len() as u64,
+                    algo,
+                    &stored,
+                    crc32(data),
+                    &mut self.scratch,
+                    self.cipher.as_ref(),
+                )?;
+                return Ok(data_extent(
+                    logical_offset,
+                    file_offset,
+                    data.len() as u64,
+                    algo,
+                    stored.len() as u64,
+                    self.cipher.is_some(),
+                ));
+            }
+        }
+
+        // incompresibil (sau compresia e oprita): date brute
+        let (_, file_offset) = write_data_write_record(
+            &mut self.file,
+            inode,
+            logical_offset,
+            data,
+            &mut self.scratch,
+            self.cipher.as_ref(),
+        )?;
+        let len = data.len() as u64;
+        Ok(data_extent(
+            logical_offset,
+            file_offset,
+            len,
+            Compression::None,
+            len,
+            self.cipher.is_some(),
+        ))
+    }
+}
@@ -0,0 +1,274 @@
+use std::ffi::OsString;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use crate::crypt::{Cipher, new_image};
+use crate::no_sql::*;
+use crate::structs::*;
+use crate::vfs::{Inner, Vfs};
+
+impl Vfs {
+    /// rescrie backing file-ul pastrand doar bytes-ii care se mai vad in
+    /// starea curenta, in snapshot-urile vii si in versiunile retinute de
+    /// `MountOptions::retention`. restul istoricului se pierde, iar offset-urile
+    /// din log se schimba. starile vechi pastrate costa cat diferentele dintre ele.
+    pub fn compact(&mut self) -> Result<()> {
+        self.inner.borrow_mut().compact()
+    }
+
+    /// rescrie in formatul curent o imagine scrisa de o versiune mai veche, ca sa
+    /// poata fi montata din nou read-write. istoricul se pastreaza ca la `compact`.
+    /// intoarce versiunea pe care o avea imaginea; una deja la zi ramane neatinsa.
+    pub fn upgrade<P: AsRef<Path>>(path: P, options: MountOptions) -> Result<u32> {
+        let options = MountOptions {
+            read_only: true,
+            ..options
+        };
+        let mut inner = Inner::open(path.as_ref(), options, u64::MAX)?;
+        let version = inner.header.version;
+        if version < VERSION {
+            inner.rewrite()?;
+        }
+        Ok(version)
+    }
+}
+
+impl Inner {
+    pub(crate) fn compact(&mut self) -> Result<()> {
+        self.ensure_writable()?;
+        self.purge_expired()?;
+        self.rewrite()
+    }
+
+    // scrie imaginea din nou, in formatul curent, si o inlocuieste atomic
+    fn rewrite(&mut self) -> Result<()> {
+        // starile de pastrat, in ordinea din log: snapshot-urile si versiunile
+        // retinute, apoi starea curenta
+        let mut points: Vec<(u64, Option<SnapshotInfo>)> = self
+            .snapshots
+            .values()
+            .map(|s| (s.offset, Some(s.clone())))
+            .collect();
+        points.extend(self.retained_versions()?.into_iter().map(|off| (off, None)));
+        points.sort_by_key(|(off, _)| *off);
+
+        let mut views: Vec<(Option<SnapshotInfo>, Inner)> = Vec::new();
+        for (offset, info) in points {
+            let mut view = Inner::open(&self.path, self.past_options(), offset)?;
+            for node in view.inodes.values_mut() {
+                node.extents = visible_extents(&node.extents, node.metadata.size);
+            }
+            views.push((info, view));
+        }
+        let mut current = self.make_checkpoint();
+        for snap in &mut current.inodes {
+            snap.extents = visible_extents(&snap.extents, snap.metadata.size);
+        }
+
+        // intervalele fizice referite, unite; un interval comun mai multor
+        // stari (sau fisiere) se copiaza o singura data
+        let mut ranges: Vec<(u64, u64)> = views
+            .iter()
+            .flat_map(|(_, view)| view.inodes.values())
+            .flat_map(|node| node.extents.iter())
+            .chain(current.inodes.iter().flat_map(|snap| snap.extents.iter()))
+            .map(Extent::stored_range)
+            .collect();
+        ranges.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(ranges.len());
+        for (lo, hi) in ranges {
+            match merged.last_mut() {
+                Some(last) if lo <= last.1 => last.1 = last.1.max(hi),
+                _ => merged.push((lo, hi)),
+            }
+        }
+
+        let tmp = compact_tmp_path(&self.path);
+        let mut out = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&tmp)?;
+        // o imagine criptata primeste un id nou, deci totul se resigileaza
+        let (cipher, encryption) = match &self.options.encryption {
+            Some(key) => {
+                let (cipher, enc) = new_image(key)?;
+                (Some(cipher), Some(enc))
+            }
+            None => (None, None),
+        };
+        // cine tine inca fisierul vechi deschis afla din generatie ca a fost inlocuit
+        let header = Header {
+            version: VERSION,
+            encryption,
+            generation: self.header.generation.wrapping_add(1),
+            ..self.header.clone()
+        };
+        write_image_header(&mut out, &header)?;
+
+        // (old_lo, old_hi, new_lo)
+        let mut moved: Vec<(u64, u64, u64)> = Vec::with_capacity(merged.len());
+        for (lo, hi) in merged {
+            let new_lo = match (&self.cipher, &cipher) {
+                // intr-o imagine criptata fiecare interval e exact datele unui record
+                (Some(old), Some(new)) => {
+                    let mut sealed = vec![0u8; (hi - lo) as usize];
+                    self.file.seek(SeekFrom::Start(lo))?;
+                    self.file.read_exact(&mut sealed)?;
+                    write_blob_bytes(&mut out, &old.open(lo, &sealed)?, Some(new))?
+                }
+                _ => write_blob_record(&mut out, &mut self.file, lo, hi - lo)?,
+            };
+            moved.push((lo, hi, new_lo));
+        }
+
+        // doar cea mai veche stare pastrata e un checkpoint intreg; fiecare
+        // urmatoare se scrie ca operatii obisnuite fata de cea dinainte, deci
+        // istoria costa cat schimbarile ei, nu cat imaginea
+        let mut views = views.into_iter();
+        if let Some((info, mut view)) = views.next() {
+            remap_inodes(&mut view, &moved)?;
+            let mut cp = view.make_checkpoint();
+            cp.next_inode = self.next_inode;
+            cp.dedup.clear();
+            write_record_with(&mut out, &Record::Checkpoint(cp), cipher.as_ref())?;
+            write_marker(&mut out, info, cipher.as_ref())?;
+            out.sync_all()?;
+
+            let options = MountOptions {
+                clock: self.options.clock.clone(),
+                encryption: self.options.encryption.clone(),
+                ..Default::default()
+            };
+            let mut image = Inner::open(&tmp, options, u64::MAX)?;
+            for (info, mut view) in views {
+                remap_inodes(&mut view, &moved)?;
+                image.converge(&view, None)?;
+                write_marker(&mut image.file, info, cipher.as_ref())?;
+            }
+        }
+
+        for snap in &mut current.inodes {
+            for ex in &mut snap.extents {
+                ex.file_offset = remap(&moved, ex.file_offset)?;
+            }
+        }
+        // chunk-urile care nu mai sunt referite ies din indexul de dedup
+        current.dedup = current
+            .dedup
+            .into_iter()
+            .filter_map(|mut e| {
+                let (lo, hi) = e.extent.stored_range();
+                let start = remap(&moved, lo).ok()?;
+                let last = remap(&moved, hi - 1).ok()?;
+                e.extent.file_offset = start;
+                (last == start + (hi - lo) - 1).then_some(e)
+            })
+            .collect();
+        write_record_with(&mut out, &Record::Checkpoint(current), cipher.as_ref())?;
+        out.sync_all()?;
+        drop(out);
+
+        // inlocuim atomic imaginea si reconstruim starea din noul log
+        std::fs::rename(&tmp, &self.path)?;
+        self.file = OpenOptions::new().read(true).write(true).open(&self.path)?;
+        self.header = header;
+        self.cipher = cipher;
+        self.replay_until(u64::MAX)
+    }
+}
+
+// extent-urile unei stari vechi, mutate la offset-urile din imaginea noua
+fn remap_inodes(view: &mut Inner, moved: &[(u64, u64, u64)]) -> Result<()> {
+    for node in view.inodes.values_mut() {
+        for ex in &mut node.extents {
+            ex.file_offset = remap(moved, ex.file_offset)?;
+        }
+    }
+    Ok(())
+}
+
+// marcajul unui snapshot, imediat dupa starea lui
+fn write_marker(out: &mut File, info: Option<SnapshotInfo>, cipher: Option<&Cipher>) -> Result<()> {
+    if let Some(info) = info {
+        write_record_with(
+            out,
+            &Record::Snapshot {
+                name: info.name,
+                created_at: info.created_at,
+            },
+            cipher,
+        )?;
+    }
+    Ok(())
+}
+
+fn compact_tmp_path(path: &Path) -> PathBuf {
+    let mut tmp: OsString = path.as_os_str().to_owned();
+    tmp.push(".compact");
+    PathBuf::from(tmp)
+}
+
+fn remap(moved: &[(u64, u64, u64)], file_offset: u64) -> Result<u64> {
+    let i = moved.partition_point(|&(lo, _, _)| lo <= file_offset);
+    match i.checked_sub(1).map(|i| moved[i]) {
+        Some((lo, hi, new_lo)) if file_offset < hi => Ok(new_lo + (file_offset - lo)),
+        _ => Err(VfsError::CorruptLog(format!(
+            "extent at {file_offset} not copied during compaction"
+        ))),
+    }
+}
+
+/// bucatile din `extents` care se mai vad la citire: cele mai noi le acopera
+/// pe cele vechi, iar tot ce e dupa `size` e ignorat. rezultatul nu se suprapune.
+pub(crate) fn visible_extents(extents: &[Extent], size: u64) -> ExtentList {
+    // intervale logice deja acoperite, sortate si disjuncte
+    let mut covered: Vec<(u64, u64)> = Vec::new();
+    let mut out = ExtentList::new();
+
+    for ex in extents.iter().rev() {
+        let lo = ex.logical_offset;
+        let hi = ex.logical_offset.saturating_add(ex.len).min(size);
+        if lo >= hi {
+            continue;
+        }
+
+        let mut piece = |a: u64, b: u64| out.push(ex.slice(a, b));
+
+        let mut cur = lo;
+        for &(c_lo, c_hi) in &covered {
+            if c_hi <= cur {
+                continue;
+            }
+            if c_lo >= hi {
+                break;
+            }
+            if c_lo > cur {
+                piece(cur, c_lo);
+            }
+            cur = c_hi;
+            if cur >= hi {
+                break;
+            }
+        }
+        if cur < hi {
+            piece(cur, hi);
+        }
+
+        covered.push((lo, hi));
+        covered.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(covered.len());
+        for (a, b) in covered {
+            match merged.last_mut() {
+                Some(last) if a <= last.1 => last.1 = last.1.max(b),
+                _ => merged.push((a, b)),
+            }
+        }
+        covered = merged;
+    }
+
+    out.sort_by_key(|ex| ex.logical_offset);
+    out
+}
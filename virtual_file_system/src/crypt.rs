@@ -0,0 +1,127 @@
+#[cfg(feature = "encryption")]
+use chacha20poly1305::aead::Aead;
+#[cfg(feature = "encryption")]
+use chacha20poly1305::{Key, KeyInit, XChaCha20Poly1305, XNonce};
+
+use crate::structs::*;
+
+/// cati bytes adauga sigilarea (tag-ul poly1305).
+pub const TAG_LEN: u64 = 16;
+
+// offset-ul folosit pt key_check; nu poate fi offset de record
+const KEY_CHECK_OFFSET: u64 = u64::MAX;
+const KEY_CHECK_PLAIN: [u8; 16] = *b"vfs key check v1";
+
+/// XChaCha20-Poly1305 cu cheia unei imagini. nonce-ul e id-ul imaginii urmat
+/// de offset-ul din log, deci fiecare pozitie se sigileaza o singura data.
+#[derive(Clone)]
+pub struct Cipher {
+    #[cfg(feature = "encryption")]
+    aead: XChaCha20Poly1305,
+    image_id: [u8; 16],
+}
+
+impl std::fmt::Debug for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cipher")
+            .field("image_id", &self.image_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Cipher {
+    #[cfg(feature = "encryption")]
+    fn new(key: &EncryptionKey, image_id: [u8; 16]) -> Result<Self> {
+        Ok(Self {
+            aead: XChaCha20Poly1305::new(Key::from_slice(key.as_bytes())),
+            image_id,
+        })
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    fn new(_key: &EncryptionKey, _image_id: [u8; 16]) -> Result<Self> {
+        Err(missing())
+    }
+
+    #[cfg(feature = "encryption")]
+    fn nonce(&self, offset: u64) -> XNonce {
+        let mut n = [0u8; 24];
+        n[..16].copy_from_slice(&self.image_id);
+        n[16..].copy_from_slice(&offset.to_le_bytes());
+        XNonce::from(n)
+    }
+
+    /// `plain` criptat si autentificat pt pozitia `offset`; are `TAG_LEN` bytes in plus.
+    #[cfg(feature = "encryption")]
+    pub fn seal(&self, offset: u64, plain: &[u8]) -> Result<Vec<u8>> {
+        self.aead
+            .encrypt(&self.nonce(offset), plain)
+            .map_err(|_| VfsError::CorruptLog(format!("cannot seal at {offset}")))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    pub fn seal(&self, _offset: u64, _plain: &[u8]) -> Result<Vec<u8>> {
+        Err(missing())
+    }
+
+    #[cfg(feature = "encryption")]
+    pub fn open(&self, offset: u64, sealed: &[u8]) -> Result<Vec<u8>> {
+        self.aead
+            .decrypt(&self.nonce(offset), sealed)
+            .map_err(|_| VfsError::CorruptLog(format!("authentication failed at {offset}")))
+    }
+
+    #[cfg(not(feature = "encryption"))]
+    pub fn open(&self, _offset: u64, _sealed: &[u8]) -> Result<Vec<u8>> {
+        Err(missing())
+    }
+}
+
+/// parametrii pt o imagine noua (sau rescrisa de `compact`) criptata cu `key`.
+pub(crate) fn new_image(key: &EncryptionKey) -> Result<(Cipher, HeaderEncryption)> {
+    let cipher = Cipher::new(key, random_id()?)?;
+    let sealed = cipher.seal(KEY_CHECK_OFFSET, &KEY_CHECK_PLAIN)?;
+    let mut key_check = [0u8; 32];
+    key_check.copy_from_slice(&sealed);
+    let enc = HeaderEncryption {
+        image_id: cipher.image_id,
+        key_check,
+    };
+    Ok((cipher, enc))
+}
+
+/// cipher-ul pt o imagine existenta; cheia trebuie sa se potriveasca cu header-ul.
+pub(crate) fn unlock(key: Option<&EncryptionKey>, header: &Header) -> Result<Option<Cipher>> {
+    match (header.encryption, key) {
+        (None, None) => Ok(None),
+        (None, Some(_)) => Err(VfsError::Unsupported(
+            "key supplied for an image that is not encrypted".into(),
+        )),
+        (Some(_), None) => Err(VfsError::WrongKey("image is encrypted".into())),
+        (Some(enc), Some(key)) => {
+            let cipher = Cipher::new(key, enc.image_id)?;
+            match cipher.open(KEY_CHECK_OFFSET, &enc.key_check) {
+                Ok(plain) if plain == KEY_CHECK_PLAIN => Ok(Some(cipher)),
+                _ => Err(VfsError::WrongKey("key does not match image".into())),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "encryption")]
+fn random_id() -> Result<[u8; 16]> {
+    let mut id = [0u8; 16];
+    getrandom::getrandom(&mut id)
+        .map_err(|e| VfsError::Io(std::io::Error::other(e.to_string())))?;
+    Ok(id)
+}
+
+#[cfg(not(feature = "encryption"))]
+fn random_id() -> Result<[u8; 16]> {
+    Err(missing())
+}
+
+#[cfg(not(feature = "encryption"))]
+fn missing() -> VfsError {
+    VfsError::Unsupported("encrypted images (built without the `encryption` feature)".into())
+}
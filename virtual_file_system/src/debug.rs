@@ -0,0 +1,180 @@
+//! unelte pt inspectarea unei imagini fara s-o montezi.
+
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::crypt::{Cipher, unlock};
+use crate::no_sql::*;
+use crate::structs::*;
+
+/// iterator peste record-urile din log-ul unei imagini, in ordinea din fisier.
+///
+/// fiecare element e `(offset, record, data_payload_offset)`. un record stricat
+/// sau o coada scrisa pe jumatate apar ca `Err(CorruptLog)` cu offset-ul in mesaj;
+/// dupa o eroare iteratorul se opreste, sau cu `resync(true)` cauta urmatorul
+/// record valid si continua de acolo.
+pub struct LogReader {
+    file: File,
+    header: Header,
+    cipher: Option<Cipher>,
+    offset: u64,
+    end: u64,
+    resync: bool,
+    done: bool,
+}
+
+impl LogReader {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_with(path, None)
+    }
+
+    /// ca `open`; o imagine criptata are nevoie de cheie ca sa poata fi citita.
+    pub fn open_with<P: AsRef<Path>>(path: P, key: Option<&EncryptionKey>) -> Result<Self> {
+        let mut file = File::open(path)?;
+        let header = read_header(&mut file)?;
+        let cipher = unlock(key, &header)?;
+        let end = file.metadata()?.len();
+        let offset = header.log_start();
+        Ok(Self {
+            file,
+            header,
+            cipher,
+            offset,
+            end,
+            resync: false,
+            done: false,
+        })
+    }
+
+    /// dupa o eroare, sare la urmatorul record valid in loc sa se opreasca.
+    pub fn resync(mut self, resync: bool) -> Self {
+        self.resync = resync;
+        self
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// offset-ul de la care se citeste urmatorul record; dupa un record, finalul lui.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    // primul offset > `from` la care incepe un record care se poate citi
+    fn find_next(&mut self, from: u64) -> Result<Option<u64>> {
+        const WINDOW: usize = 64 * 1024;
+        let mut buf = vec![0u8; WINDOW + RECORD_MAGIC.len() - 1];
+        let mut pos = from + 1;
+        while pos < self.end {
+            self.file.seek(SeekFrom::Start(pos))?;
+            let want = ((self.end - pos) as usize).min(buf.len());
+            self.file.read_exact(&mut buf[..want])?;
+            let hits: Vec<u64> = buf[..want]
+                .windows(RECORD_MAGIC.len())
+                .enumerate()
+                .filter(|(_, w)| w == RECORD_MAGIC)
+                .map(|(i, _)| pos + i as u64)
+                .collect();
+            for at in hits {
+                if let Ok(Some(_)) = read_versioned_record(
+                    &mut self.file,
+                    at,
+                    self.header.version,
+                    self.cipher.as_ref(),
+                ) {
+                    return Ok(Some(at));
+                }
+            }
+            pos += WINDOW as u64;
+        }
+        Ok(None)
+    }
+
+    fn fail(&mut self, err: VfsError) -> Option<Result<(u64, Record, Option<u64>)>> {
+        let at = self.offset;
+        let err = match err {
+            VfsError::CorruptLog(msg) => VfsError::CorruptLog(format!("at offset {at}: {msg}")),
+            other => {
+                self.done = true;
+                return Some(Err(other));
+            }
+        };
+        match self.resync.then(|| self.find_next(at)) {
+            Some(Ok(Some(next))) => self.offset = next,
+            Some(Err(e)) => return Some(Err(e)),
+            _ => self.done = true,
+        }
+        Some(Err(err))
+    }
+}
+
+impl Iterator for LogReader {
+    type Item = Result<(u64, Record, Option<u64>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let at = self.offset;
+        match read_versioned_record(
+            &mut self.file,
+            at,
+            self.header.version,
+            self.cipher.as_ref(),
+        ) {
+            Ok(Some((decoded, next))) => {
+                self.offset = next;
+                Some(Ok((at, decoded.record, decoded.data_payload_offset)))
+            }
+            Ok(None) if at < self.end => self.fail(VfsError::CorruptLog(format!(
+                "torn record, {} bytes after the last valid record",
+                self.end - at
+            ))),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => self.fail(e),
+        }
+    }
+}
+
+/// inode-urile la care se refera un record (pt filtrare).
+pub fn record_inodes(record: &Record) -> Vec<InodeId> {
+    match record {
+        Record::Header(_)
+        | Record::Snapshot { .. }
+        | Record::SnapshotDelete { .. }
+        | Record::Blob { .. } => vec![],
+        Record::Checkpoint(cp) => cp.inodes.iter().map(|s| s.id).collect(),
+        Record::InodeAlloc(snap) => snap.parent.into_iter().chain([snap.id]).collect(),
+        Record::DirEntryAdd { entry } => vec![entry.parent, entry.inode],
+        Record::DirEntryRemove { parent, inode, .. }
+        | Record::Trash {
+            parent,
+            entry: TrashEntry { inode, .. },
+            ..
+        } => {
+            vec![*parent, *inode]
+        }
+        Record::TrashRestore { inode, parent, .. } => vec![*parent, *inode],
+        Record::Rename {
+            inode,
+            old_parent,
+            new_parent,
+            ..
+        } => vec![*old_parent, *new_parent, *inode],
+        Record::DataWrite { inode, .. }
+        | Record::CompressedWrite { inode, .. }
+        | Record::DataRef { inode, .. }
+        | Record::Truncate { inode, .. }
+        | Record::SetTimes { inode, .. }
+        | Record::SetXattr { inode, .. }
+        | Record::RemoveXattr { inode, .. }
+        | Record::SetPermissions { inode, .. }
+        | Record::Chown { inode, .. }
+        | Record::TrashPurge { inode } => vec![*inode],
+    }
+}
@@ -0,0 +1,140 @@
+use crate::codec::read_extent;
+use crate::no_sql::*;
+use crate::structs::*;
+use crate::vfs::Inner;
+
+// tabela pt gear hash, generata determinist (splitmix64) ca boundary-urile sa
+// fie aceleasi de la o versiune la alta
+const GEAR: [u64; 256] = gear_table();
+
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0u64; 256];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+}
+
+/// lungimile chunk-urilor in care e impartit `data`.
+pub(crate) fn chunk_lengths(data: &[u8], chunking: Chunking) -> Vec<usize> {
+    match chunking {
+        Chunking::Fixed(size) => {
+            let size = (size as usize).max(1);
+            data.chunks(size).map(<[u8]>::len).collect()
+        }
+        Chunking::ContentDefined { avg } => {
+            let avg = (avg as usize).next_power_of_two().max(64);
+            let (min, max) = (avg / 4, avg * 4);
+            let mask = (avg - 1) as u64;
+
+            let mut out = Vec::new();
+            let mut rest = data;
+            while !rest.is_empty() {
+                let mut len = rest.len().min(max);
+                let mut hash = 0u64;
+                for (i, &b) in rest.iter().enumerate().take(len).skip(min) {
+                    hash = (hash << 1).wrapping_add(GEAR[b as usize]);
+                    if hash & mask == 0 {
+                        len = i + 1;
+                        break;
+                    }
+                }
+                out.push(len);
+                rest = &rest[len..];
+            }
+            out
+        }
+    }
+}
+
+impl Inner {
+    /// scrie `buf` la `off` chunk cu chunk; chunk-urile deja in log devin `DataRef`.
+    pub(crate) fn write_chunks(
+        &mut self,
+        inode: InodeId,
+        off: u64,
+        buf: &[u8],
+        chunking: Chunking,
+    ) -> Result<()> {
+        let mut pos = 0usize;
+        for len in chunk_lengths(buf, chunking) {
+            let chunk = &buf[pos..pos + len];
+            let logical_offset = off + pos as u64;
+            pos += len;
+
+            let checksum = crc32(chunk);
+            let extent = match self.find_chunk(checksum, chunk)? {
+                Some(stored) => {
+                    let extent = Extent {
+                        logical_offset,
+                        ..stored
+                    };
+                    self.log(&Record::DataRef { inode, extent })?;
+                    extent
+                }
+                None => {
+                    let extent = self.append_data(inode, logical_offset, chunk)?;
+                    self.index_chunk(checksum, extent);
+                    extent
+                }
+            };
+            self.push_extent(inode, extent)?;
+        }
+        Ok(())
+    }
+
+    /// tine minte unde e stocat un chunk; `logical_offset` nu conteaza.
+    pub(crate) fn index_chunk(&mut self, checksum: u32, extent: Extent) {
+        let extent = Extent {
+            logical_offset: 0,
+            ..extent
+        };
+        let stored = self.dedup.entry((checksum, extent.len)).or_default();
+        if !stored.contains(&extent) {
+            stored.push(extent);
+        }
+    }
+
+    /// un chunk identic deja scris; crc-ul poate avea coliziuni, deci comparam bytes.
+    fn find_chunk(&mut self, checksum: u32, chunk: &[u8]) -> Result<Option<Extent>> {
+        let Some(candidates) = self.dedup.get(&(checksum, chunk.len() as u64)).cloned() else {
+            return Ok(None);
+        };
+        let mut existing = vec![0u8; chunk.len()];
+        for stored in candidates {
+            read_extent(
+                &mut self.file,
+                &mut self.blob_cache,
+                self.cipher.as_ref(),
+                &stored,
+                0,
+                &mut existing,
+            )?;
+            if existing == chunk {
+                return Ok(Some(stored));
+            }
+        }
+        Ok(None)
+    }
+
+    pub(crate) fn dedup_entries(&self) -> Vec<DedupEntry> {
+        let mut out: Vec<DedupEntry> = self
+            .dedup
+            .iter()
+            .flat_map(|(&(checksum, _), stored)| {
+                stored
+                    .iter()
+                    .map(move |&extent| DedupEntry { checksum, extent })
+            })
+            .collect();
+        out.sort_by_key(|e| (e.extent.file_offset, e.extent.len));
+        out
+    }
+}
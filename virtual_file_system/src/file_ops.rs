@@ -2,35 +2,49 @@ use crate::VfsError;
 use crate::structs::InodeId;
 use crate::vfs::*;
 use std::cell::RefCell;
+use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::rc::Rc;
 
 #[derive(Debug)]
 pub struct VfsFile {
-    inner: Rc<RefCell<Inner>>,
-    inode: InodeId,
+    backing: Backing,
     cursor: u64,
     writable: bool,
 }
 
+// un fisier din imagine sau, sub o montare de host, un fisier de pe disc
+#[derive(Debug)]
+enum Backing {
+    Image {
+        inner: Rc<RefCell<Inner>>,
+        inode: InodeId,
+    },
+    Host(File),
+}
+
 impl VfsFile {
     pub(crate) fn new(inner: Rc<RefCell<Inner>>, inode: InodeId, writable: bool) -> Self {
         Self {
-            inner,
-            inode,
+            backing: Backing::Image { inner, inode },
             cursor: 0,
             writable,
         }
     }
 
-    fn vfs(&self) -> Vfs {
-        Vfs {
-            inner: self.inner.clone(),
+    pub(crate) fn host(file: File, writable: bool) -> Self {
+        Self {
+            backing: Backing::Host(file),
+            cursor: 0,
+            writable,
         }
     }
 
     pub fn len(&self) -> Result<u64, VfsError> {
-        self.vfs().len(self.inode)
+        match &self.backing {
+            Backing::Image { inner, inode } => Vfs::from_inner(inner.clone()).len(*inode),
+            Backing::Host(file) => Ok(file.metadata()?.len()),
+        }
     }
 
     pub fn is_empty(&self) -> Result<bool, VfsError> {
@@ -38,16 +52,29 @@ impl VfsFile {
     }
 
     pub fn set_len(&mut self, len: u64) -> Result<(), VfsError> {
-        self.vfs().truncate(self.inode, len)
+        if !self.writable {
+            return Err(VfsError::PermissionDenied(
+                "file not opened for writing".into(),
+            ));
+        }
+        match &mut self.backing {
+            Backing::Image { inner, inode } => Vfs::from_inner(inner.clone()).truncate(*inode, len),
+            Backing::Host(file) => Ok(file.set_len(len)?),
+        }
     }
 }
 
 impl Read for VfsFile {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-        let vfs = self.vfs();
-        let n = vfs
-            .read_at(self.inode, self.cursor, buf)
-            .map_err(|e| std::io::Error::other(format!("{e:?}")))?;
+        let n = match &mut self.backing {
+            Backing::Image { inner, inode } => Vfs::from_inner(inner.clone())
+                .read_at(*inode, self.cursor, buf)
+                .map_err(|e| std::io::Error::other(format!("{e:?}")))?,
+            Backing::Host(file) => {
+                file.seek(SeekFrom::Start(self.cursor))?;
+                file.read(buf)?
+            }
+        };
         self.cursor += n as u64;
         Ok(n)
     }
@@ -61,16 +88,24 @@ impl Write for VfsFile {
                 "file not opened for writing",
             ));
         }
-        let vfs = self.vfs();
-        let n = vfs
-            .write_at(self.inode, self.cursor, buf)
-            .map_err(|e| std::io::Error::other(format!("{e:?}")))?;
+        let n = match &mut self.backing {
+            Backing::Image { inner, inode } => Vfs::from_inner(inner.clone())
+                .write_at(*inode, self.cursor, buf)
+                .map_err(|e| std::io::Error::other(format!("{e:?}")))?,
+            Backing::Host(file) => {
+                file.seek(SeekFrom::Start(self.cursor))?;
+                file.write(buf)?
+            }
+        };
         self.cursor += n as u64;
         Ok(n)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
-        Ok(())
+        match &mut self.backing {
+            Backing::Image { .. } => Ok(()),
+            Backing::Host(file) => file.flush(),
+        }
     }
 }
 
@@ -0,0 +1,779 @@
+//! interfata comuna peste mai multe sisteme de fisiere, ca acelasi cod sa poata
+//! lucra cu o imagine, cu un director de pe host sau cu un FS doar in memorie.
+//!
+//! semantica urmeaza `std::fs`: `create` trunchiaza un fisier existent, `rename`
+//! inlocuieste o destinatie de acelasi tip, `remove` sterge un fisier sau un
+//! director gol. erorile sunt aceleasi `VfsError` pt toate implementarile.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::file_ops::VfsFile;
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::sync::{host_mode, host_time};
+use crate::vfs::Vfs;
+
+/// un fisier deschis prin [`FileSystem`].
+pub trait FsFile: Read + Write + Seek {
+    fn len(&self) -> Result<u64>;
+
+    fn set_len(&mut self, len: u64) -> Result<()>;
+
+    fn is_empty(&self) -> Result<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+/// operatiile pe care le foloseste codul scris pt `std::fs`.
+pub trait FileSystem {
+    type File: FsFile;
+
+    /// deschide un fisier existent doar pt citire.
+    fn open<P: AsRef<Path>>(&self, path: P) -> Result<Self::File>;
+
+    /// creeaza un fisier (sau il goleste daca exista) si il deschide pt scriere.
+    fn create<P: AsRef<Path>>(&self, path: P) -> Result<Self::File>;
+
+    /// intrarile unui director, sortate dupa nume.
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FsEntry>>;
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<FsMetadata>;
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()>;
+
+    /// sterge un fisier sau un director gol.
+    fn remove<P: AsRef<Path>>(&self, path: P) -> Result<()>;
+
+    /// creeaza directorul si toti parintii lipsa; nu e eroare daca exista deja.
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()>;
+
+    fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        self.metadata(path).is_ok()
+    }
+}
+
+// componentele unui path rezolvat lexical; `..` nu urca mai sus de radacina
+pub(crate) fn normalize(path: &Path) -> Result<Vec<String>> {
+    let mut parts: Vec<String> = Vec::new();
+    for name in VfsPath::new(path)?.components() {
+        if name == ".." {
+            parts.pop();
+        } else {
+            parts.push(name.to_string());
+        }
+    }
+    Ok(parts)
+}
+
+fn not_empty(path: &Path) -> VfsError {
+    VfsError::InvalidPath(format!("directory not empty: {}", path.display()))
+}
+
+// inlocuirea din `FileSystem::rename` peste un `rename` care refuza o destinatie
+// existenta: ca la MOVE in webdav, destinatia se da deoparte, revine la loc daca
+// mutarea esueaza si se sterge abia dupa ce mutarea a reusit
+pub(crate) fn replace_by_rename<F: FileSystem>(
+    fs: &F,
+    from: &Path,
+    to: &Path,
+    target: NodeKind,
+    mut rename: impl FnMut(&Path, &Path) -> Result<()>,
+) -> Result<()> {
+    if target == NodeKind::Dir && !fs.read_dir(to)?.is_empty() {
+        return Err(not_empty(to));
+    }
+    let (Some(parent), Some(name)) = (to.parent(), to.file_name()) else {
+        return Err(VfsError::InvalidPath(to.display().to_string()));
+    };
+    let mut n = 0;
+    let aside = loop {
+        let candidate = parent.join(format!(".{}.rename-{n}", name.to_string_lossy()));
+        if !fs.exists(&candidate) {
+            break candidate;
+        }
+        n += 1;
+    };
+    rename(to, &aside)?;
+    if let Err(e) = rename(from, to) {
+        rename(&aside, to)?;
+        return Err(e);
+    }
+    fs.remove(&aside)
+}
+
+// ---------- vfs ----------
+
+impl FsFile for VfsFile {
+    fn len(&self) -> Result<u64> {
+        VfsFile::len(self)
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        VfsFile::set_len(self, len)
+    }
+}
+
+impl Vfs {
+    pub(crate) fn kind_of(&self, path: &Path) -> Result<NodeKind> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.crossing(&path)? {
+            return c.mount.kind_of(&c.rest);
+        }
+        let inner = self.inner.borrow();
+        let inode = inner.resolve(self.cwd, &path)?;
+        inner
+            .inodes
+            .get(&inode)
+            .map(|n| n.kind)
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))
+    }
+}
+
+impl FileSystem for Vfs {
+    type File = VfsFile;
+
+    fn open<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        self.open_file(path)
+    }
+
+    fn create<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = path.as_ref();
+        match self.kind_of(path) {
+            Ok(NodeKind::File) => {
+                let mut file = self.open_rw(path)?;
+                file.set_len(0)?;
+                Ok(file)
+            }
+            Ok(NodeKind::Dir) => Err(VfsError::NotAFile(path.display().to_string())),
+            Err(_) => Vfs::create(self, path),
+        }
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FsEntry>> {
+        let mut entries = Vfs::read_dir(self, path)?
+            .map(|e| {
+                e.map(|e| FsEntry {
+                    name: e.name,
+                    kind: e.kind,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<FsMetadata> {
+        let path = path.as_ref();
+        Ok(FsMetadata {
+            kind: self.kind_of(path)?,
+            metadata: Vfs::metadata(self, path)?,
+        })
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let mut vfs = self.clone();
+        let source = self.kind_of(from)?;
+        // `Vfs::rename` nu suprascrie; ca in std, o destinatie compatibila e inlocuita
+        if let Ok(target) = self.kind_of(to) {
+            let cwd = self.current_dir()?;
+            let (abs_from, abs_to) = (
+                normalize(cwd.join(from)?.as_ref())?,
+                normalize(cwd.join(to)?.as_ref())?,
+            );
+            if abs_from == abs_to {
+                return Ok(());
+            }
+            // ce ar refuza `Vfs::rename` se verifica inainte sa se piarda destinatia
+            if abs_to.starts_with(&abs_from) {
+                return Err(VfsError::InvalidPath(format!(
+                    "cannot move {} inside itself",
+                    from.display()
+                )));
+            }
+            let point =
+                |p: &Path| Ok::<_, VfsError>(self.crossing(&VfsPath::new(p)?)?.map(|c| c.point));
+            if point(from)? != point(to)? {
+                return Err(VfsError::CrossesMount(format!(
+                    "{} -> {}",
+                    from.display(),
+                    to.display()
+                )));
+            }
+            match (source, target) {
+                (NodeKind::File, NodeKind::Dir) => {
+                    return Err(VfsError::NotAFile(to.display().to_string()));
+                }
+                (NodeKind::Dir, NodeKind::File) => {
+                    return Err(VfsError::NotADir(to.display().to_string()));
+                }
+                _ => {}
+            }
+            let to = PathBuf::from(format!("/{}", abs_to.join("/")));
+            return replace_by_rename(self, from, &to, target, |a, b| Vfs::rename(&mut vfs, a, b));
+        }
+        Vfs::rename(&mut vfs, from, to)
+    }
+
+    fn remove<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let mut vfs = self.clone();
+        match self.kind_of(path)? {
+            NodeKind::Dir => vfs.remove_dir(path),
+            NodeKind::File => vfs.remove_file(path),
+        }
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        let mut vfs = self.clone();
+        let mut cur = if path.is_absolute() {
+            VfsPath::root()
+        } else {
+            VfsPath::new("")?
+        };
+        for name in path.components() {
+            cur = cur.join(name)?;
+            match self.kind_of(Path::new(cur.as_str())) {
+                Ok(NodeKind::Dir) => {}
+                Ok(NodeKind::File) => return Err(VfsError::NotADir(cur.to_string())),
+                Err(_) => vfs.create_dir(&cur)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// ---------- host ----------
+
+/// director de pe host folosit ca radacina; path-urile (si cele absolute) sunt
+/// relative la el si nu pot iesi din el cu `..`.
+#[derive(Debug, Clone)]
+pub struct HostFs {
+    root: PathBuf,
+}
+
+impl HostFs {
+    pub fn new<P: AsRef<Path>>(root: P) -> Self {
+        Self {
+            root: root.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub(crate) fn host_path(&self, path: &Path) -> Result<PathBuf> {
+        let mut host = self.root.clone();
+        host.extend(normalize(path)?);
+        Ok(host)
+    }
+}
+
+// erorile host-ului traduse in variantele folosite si de vfs
+pub(crate) fn host_error(path: &Path, err: std::io::Error) -> VfsError {
+    use std::io::ErrorKind;
+
+    let p = path.display().to_string();
+    match err.kind() {
+        ErrorKind::NotFound => VfsError::NotFound(p),
+        ErrorKind::AlreadyExists => VfsError::AlreadyExists(p),
+        ErrorKind::PermissionDenied => VfsError::PermissionDenied(p),
+        ErrorKind::NotADirectory => VfsError::NotADir(p),
+        ErrorKind::IsADirectory => VfsError::NotAFile(p),
+        ErrorKind::DirectoryNotEmpty => not_empty(path),
+        ErrorKind::ReadOnlyFilesystem => VfsError::ReadOnly(p),
+        _ => VfsError::Io(err),
+    }
+}
+
+fn host_kind(path: &Path, meta: &fs::Metadata) -> Result<NodeKind> {
+    if meta.is_dir() {
+        Ok(NodeKind::Dir)
+    } else if meta.is_file() {
+        Ok(NodeKind::File)
+    } else {
+        Err(VfsError::Unsupported(format!(
+            "not a regular file or directory: {}",
+            path.display()
+        )))
+    }
+}
+
+#[cfg(unix)]
+fn host_owner(meta: &fs::Metadata) -> (u32, u32, Timestamp) {
+    use std::os::unix::fs::MetadataExt;
+    let ctime = Timestamp(i128::from(meta.ctime()) * 1_000_000_000 + i128::from(meta.ctime_nsec()));
+    (meta.uid(), meta.gid(), ctime)
+}
+
+#[cfg(not(unix))]
+fn host_owner(meta: &fs::Metadata) -> (u32, u32, Timestamp) {
+    (0, 0, host_time(meta.modified()))
+}
+
+impl FsFile for fs::File {
+    fn len(&self) -> Result<u64> {
+        Ok(self.metadata()?.len())
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        Ok(fs::File::set_len(self, len)?)
+    }
+}
+
+impl FileSystem for HostFs {
+    type File = fs::File;
+
+    fn open<P: AsRef<Path>>(&self, path: P) -> Result<fs::File> {
+        let path = path.as_ref();
+        let host = self.host_path(path)?;
+        // pe unix si un director se poate deschide; aici nu
+        if self.metadata(path)?.is_dir() {
+            return Err(VfsError::NotAFile(path.display().to_string()));
+        }
+        fs::File::open(host).map_err(|e| host_error(path, e))
+    }
+
+    fn create<P: AsRef<Path>>(&self, path: P) -> Result<fs::File> {
+        let path = path.as_ref();
+        fs::File::create(self.host_path(path)?).map_err(|e| host_error(path, e))
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FsEntry>> {
+        let path = path.as_ref();
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(self.host_path(path)?).map_err(|e| host_error(path, e))? {
+            let entry = entry?;
+            let name = entry.file_name().into_string().map_err(|name| {
+                VfsError::InvalidPath(format!("non utf-8 name: {}", name.display()))
+            })?;
+            // symlink-urile spre fisiere/directoare apar ca tinta lor, restul se sar
+            let Ok(kind) = fs::metadata(entry.path())
+                .map_err(VfsError::from)
+                .and_then(|m| host_kind(&entry.path(), &m))
+            else {
+                continue;
+            };
+            entries.push(FsEntry { name, kind });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<FsMetadata> {
+        let path = path.as_ref();
+        let meta = fs::metadata(self.host_path(path)?).map_err(|e| host_error(path, e))?;
+        let modified_at = host_time(meta.modified());
+        let (uid, gid, changed_at) = host_owner(&meta);
+        Ok(FsMetadata {
+            kind: host_kind(path, &meta)?,
+            metadata: Metadata {
+                size: if meta.is_file() { meta.len() } else { 0 },
+                created_at: meta.created().map_or(modified_at, Timestamp::from),
+                modified_at,
+                accessed_at: host_time(meta.accessed()),
+                changed_at,
+                mode: host_mode(&meta),
+                uid,
+                gid,
+            },
+        })
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        // host-ul ar da EINVAL (sau ar muta chiar radacina); eroarea e cea din vfs
+        let (parts_from, parts_to) = (normalize(from)?, normalize(to)?);
+        if parts_from.is_empty() {
+            return Err(VfsError::InvalidPath("cannot move the root".into()));
+        }
+        if parts_to.len() > parts_from.len() && parts_to.starts_with(&parts_from) {
+            return Err(VfsError::InvalidPath(format!(
+                "cannot move {} inside itself",
+                from.display()
+            )));
+        }
+        fs::rename(self.host_path(from)?, self.host_path(to)?).map_err(|e| host_error(from, e))
+    }
+
+    fn remove<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if normalize(path)?.is_empty() {
+            return Err(VfsError::InvalidPath("cannot remove the root".into()));
+        }
+        let host = self.host_path(path)?;
+        let meta = fs::symlink_metadata(&host).map_err(|e| host_error(path, e))?;
+        let removed = if meta.is_dir() {
+            fs::remove_dir(host)
+        } else {
+            fs::remove_file(host)
+        };
+        removed.map_err(|e| host_error(path, e))
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        // `AlreadyExists` aici inseamna ca pe drum e un fisier
+        fs::create_dir_all(self.host_path(path)?).map_err(|e| match host_error(path, e) {
+            VfsError::AlreadyExists(p) => VfsError::NotADir(p),
+            other => other,
+        })
+    }
+}
+
+// ---------- memorie ----------
+
+/// FS doar in memorie, pt teste; `clone` da acelasi arbore.
+#[derive(Clone)]
+pub struct MemFs {
+    tree: Rc<RefCell<BTreeMap<String, MemNode>>>,
+}
+
+enum MemNode {
+    Dir(Metadata),
+    File(Rc<RefCell<MemData>>),
+}
+
+struct MemData {
+    bytes: Vec<u8>,
+    metadata: Metadata,
+}
+
+impl MemNode {
+    fn kind(&self) -> NodeKind {
+        match self {
+            MemNode::Dir(_) => NodeKind::Dir,
+            MemNode::File(_) => NodeKind::File,
+        }
+    }
+}
+
+fn mem_metadata(mode: u32) -> Metadata {
+    let now = Timestamp::now();
+    Metadata {
+        size: 0,
+        created_at: now,
+        modified_at: now,
+        accessed_at: now,
+        changed_at: now,
+        mode,
+        uid: 0,
+        gid: 0,
+    }
+}
+
+// cheia din arbore: `/`, `/a`, `/a/b`
+fn mem_key(path: &Path) -> Result<String> {
+    Ok(format!("/{}", normalize(path)?.join("/")))
+}
+
+fn mem_parent(key: &str) -> Option<&str> {
+    match key.rfind('/')? {
+        _ if key == "/" => None,
+        0 => Some("/"),
+        i => Some(&key[..i]),
+    }
+}
+
+// prefixul cheilor de sub un director
+fn mem_prefix(key: &str) -> String {
+    if key == "/" {
+        key.to_string()
+    } else {
+        format!("{key}/")
+    }
+}
+
+impl MemFs {
+    pub fn new() -> Self {
+        let mut tree = BTreeMap::new();
+        tree.insert(
+            "/".to_string(),
+            MemNode::Dir(mem_metadata(DEFAULT_DIR_MODE)),
+        );
+        Self {
+            tree: Rc::new(RefCell::new(tree)),
+        }
+    }
+
+    // cheia lui `path`, dupa ce verifica ca parintele exista si e director
+    fn child_key(&self, path: &Path) -> Result<String> {
+        let key = mem_key(path)?;
+        let parent = mem_parent(&key)
+            .ok_or_else(|| VfsError::InvalidPath(format!("path has no parent: {key}")))?;
+        match self.tree.borrow().get(parent) {
+            Some(MemNode::Dir(_)) => Ok(key),
+            Some(MemNode::File(_)) => Err(VfsError::NotADir(key)),
+            None => Err(VfsError::NotFound(key)),
+        }
+    }
+
+    fn has_children(&self, key: &str) -> bool {
+        let prefix = mem_prefix(key);
+        self.tree
+            .borrow()
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|(k, _)| k.starts_with(&prefix))
+    }
+}
+
+impl Default for MemFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// fisier deschis dintr-un [`MemFs`].
+pub struct MemFile {
+    data: Rc<RefCell<MemData>>,
+    pos: u64,
+    writable: bool,
+}
+
+impl MemFile {
+    fn check_writable(&self) -> std::io::Result<()> {
+        if self.writable {
+            return Ok(());
+        }
+        Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "file not opened for writing",
+        ))
+    }
+}
+
+impl Read for MemFile {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let data = self.data.borrow();
+        let start = (self.pos as usize).min(data.bytes.len());
+        let n = buf.len().min(data.bytes.len() - start);
+        buf[..n].copy_from_slice(&data.bytes[start..start + n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Write for MemFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.check_writable()?;
+        let mut data = self.data.borrow_mut();
+        let start = self.pos as usize;
+        let end = start + buf.len();
+        if data.bytes.len() < end {
+            data.bytes.resize(end, 0);
+        }
+        data.bytes[start..end].copy_from_slice(buf);
+        let now = Timestamp::now();
+        data.metadata.size = data.bytes.len() as u64;
+        data.metadata.modified_at = now;
+        data.metadata.changed_at = now;
+        self.pos = end as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemFile {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let len = self.data.borrow().bytes.len() as i128;
+        let target = match pos {
+            SeekFrom::Start(off) => i128::from(off),
+            SeekFrom::End(delta) => len + i128::from(delta),
+            SeekFrom::Current(delta) => i128::from(self.pos) + i128::from(delta),
+        };
+        if target < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek before start of file",
+            ));
+        }
+        self.pos = target as u64;
+        Ok(self.pos)
+    }
+}
+
+impl FsFile for MemFile {
+    fn len(&self) -> Result<u64> {
+        Ok(self.data.borrow().bytes.len() as u64)
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<()> {
+        self.check_writable()?;
+        let mut data = self.data.borrow_mut();
+        data.bytes.resize(len as usize, 0);
+        let now = Timestamp::now();
+        data.metadata.size = len;
+        data.metadata.modified_at = now;
+        data.metadata.changed_at = now;
+        Ok(())
+    }
+}
+
+impl FileSystem for MemFs {
+    type File = MemFile;
+
+    fn open<P: AsRef<Path>>(&self, path: P) -> Result<MemFile> {
+        let key = mem_key(path.as_ref())?;
+        match self.tree.borrow().get(&key) {
+            Some(MemNode::File(data)) => Ok(MemFile {
+                data: data.clone(),
+                pos: 0,
+                writable: false,
+            }),
+            Some(MemNode::Dir(_)) => Err(VfsError::NotAFile(key)),
+            None => Err(VfsError::NotFound(key)),
+        }
+    }
+
+    fn create<P: AsRef<Path>>(&self, path: P) -> Result<MemFile> {
+        let key = self.child_key(path.as_ref())?;
+        let mut tree = self.tree.borrow_mut();
+        let data = match tree.get(&key) {
+            Some(MemNode::Dir(_)) => return Err(VfsError::NotAFile(key)),
+            Some(MemNode::File(data)) => data.clone(),
+            None => {
+                let data = Rc::new(RefCell::new(MemData {
+                    bytes: Vec::new(),
+                    metadata: mem_metadata(DEFAULT_FILE_MODE),
+                }));
+                tree.insert(key, MemNode::File(data.clone()));
+                data
+            }
+        };
+        drop(tree);
+        let mut file = MemFile {
+            data,
+            pos: 0,
+            writable: true,
+        };
+        file.set_len(0)?;
+        Ok(file)
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FsEntry>> {
+        let key = mem_key(path.as_ref())?;
+        let tree = self.tree.borrow();
+        match tree.get(&key) {
+            Some(MemNode::Dir(_)) => {}
+            Some(MemNode::File(_)) => return Err(VfsError::NotADir(key)),
+            None => return Err(VfsError::NotFound(key)),
+        }
+        let prefix = mem_prefix(&key);
+        Ok(tree
+            .range(prefix.clone()..)
+            .take_while(|(k, _)| k.starts_with(&prefix))
+            .filter(|(k, _)| !k[prefix.len()..].contains('/'))
+            .map(|(k, node)| FsEntry {
+                name: k[prefix.len()..].to_string(),
+                kind: node.kind(),
+            })
+            .collect())
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<FsMetadata> {
+        let key = mem_key(path.as_ref())?;
+        match self.tree.borrow().get(&key) {
+            Some(MemNode::Dir(metadata)) => Ok(FsMetadata {
+                kind: NodeKind::Dir,
+                metadata: metadata.clone(),
+            }),
+            Some(MemNode::File(data)) => Ok(FsMetadata {
+                kind: NodeKind::File,
+                metadata: data.borrow().metadata.clone(),
+            }),
+            None => Err(VfsError::NotFound(key)),
+        }
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        let from = mem_key(from.as_ref())?;
+        let to = self.child_key(to.as_ref())?;
+        let source = self
+            .tree
+            .borrow()
+            .get(&from)
+            .map(MemNode::kind)
+            .ok_or_else(|| VfsError::NotFound(from.clone()))?;
+        if from == "/" {
+            return Err(VfsError::InvalidPath("cannot move the root".into()));
+        }
+        if from == to {
+            return Ok(());
+        }
+        if to.starts_with(&mem_prefix(&from)) {
+            return Err(VfsError::InvalidPath(format!(
+                "cannot move {from} inside itself"
+            )));
+        }
+        let target = self.tree.borrow().get(&to).map(MemNode::kind);
+        match (source, target) {
+            (_, None) | (NodeKind::File, Some(NodeKind::File)) => {}
+            (NodeKind::Dir, Some(NodeKind::Dir)) if !self.has_children(&to) => {}
+            (NodeKind::Dir, Some(NodeKind::Dir)) => return Err(not_empty(Path::new(&to))),
+            (NodeKind::File, Some(NodeKind::Dir)) => return Err(VfsError::NotAFile(to)),
+            (NodeKind::Dir, Some(NodeKind::File)) => return Err(VfsError::NotADir(to)),
+        }
+
+        let mut tree = self.tree.borrow_mut();
+        tree.remove(&to);
+        let prefix = mem_prefix(&from);
+        // `/a` si `/a/...`, dar nu `/a-b`, care se sorteaza intre ele
+        let moved: Vec<String> = std::iter::once(from.clone())
+            .chain(
+                tree.range(prefix.clone()..)
+                    .map(|(k, _)| k.clone())
+                    .take_while(|k| k.starts_with(&prefix)),
+            )
+            .collect();
+        for old in moved {
+            let node = tree.remove(&old).unwrap();
+            tree.insert(format!("{to}{}", &old[from.len()..]), node);
+        }
+        Ok(())
+    }
+
+    fn remove<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let key = mem_key(path.as_ref())?;
+        if key == "/" {
+            return Err(VfsError::InvalidPath("cannot remove the root".into()));
+        }
+        let kind = self.tree.borrow().get(&key).map(MemNode::kind);
+        match kind {
+            None => return Err(VfsError::NotFound(key)),
+            Some(NodeKind::Dir) if self.has_children(&key) => {
+                return Err(not_empty(Path::new(&key)));
+            }
+            Some(_) => {}
+        }
+        self.tree.borrow_mut().remove(&key);
+        Ok(())
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut key = String::new();
+        let mut tree = self.tree.borrow_mut();
+        for name in normalize(path.as_ref())? {
+            key.push('/');
+            key.push_str(&name);
+            match tree.get(&key) {
+                Some(MemNode::Dir(_)) => {}
+                Some(MemNode::File(_)) => return Err(VfsError::NotADir(key)),
+                None => {
+                    tree.insert(key.clone(), MemNode::Dir(mem_metadata(DEFAULT_DIR_MODE)));
+                }
+            }
+        }
+        Ok(())
+    }
+}
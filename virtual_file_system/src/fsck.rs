@@ -0,0 +1,215 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::codec::read_extent;
+use crate::no_sql::*;
+use crate::structs::*;
+use crate::vfs::{Inner, Vfs};
+
+// datele se verifica in bucati, ca un blob mare de la compactare sa nu fie citit dintr-o data
+const CHECK_CHUNK: u64 = 1 << 20;
+
+impl Vfs {
+    /// verifica imaginea montata: framing-ul si crc-urile log-ului, crc-urile
+    /// datelor si consistenta arborelui reconstruit. nu modifica nimic.
+    pub fn fsck(&self) -> Result<FsckReport> {
+        self.inner.borrow_mut().fsck()
+    }
+
+    /// header-ul imaginii si cate record-uri de fiecare tip are log-ul.
+    pub fn info(&self) -> Result<ImageInfo> {
+        let mut inner = self.inner.borrow_mut();
+        let mut records = BTreeMap::new();
+        let (log_end, _) = inner.scan_log(|_, decoded| {
+            *records.entry(decoded.record.kind()).or_insert(0) += 1;
+            Ok(())
+        })?;
+        Ok(ImageInfo {
+            header: inner.header.clone(),
+            log_end,
+            image_bytes: inner.file.metadata()?.len(),
+            records,
+        })
+    }
+}
+
+impl Inner {
+    // parcurge record-urile valide; intoarce finalul lor si eroarea care a oprit parcurgerea
+    fn scan_log<F>(&mut self, mut f: F) -> Result<(u64, Option<VfsError>)>
+    where
+        F: FnMut(&mut Inner, &DecodedRecord) -> Result<()>,
+    {
+        let mut off = self.header.log_start();
+        loop {
+            match self.read_record(off) {
+                Ok(Some((decoded, next))) => {
+                    f(self, &decoded)?;
+                    off = next;
+                }
+                Ok(None) => return Ok((off, None)),
+                Err(e @ (VfsError::CorruptLog(_) | VfsError::WrongKey(_))) => {
+                    return Ok((off, Some(e)));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn fsck(&mut self) -> Result<FsckReport> {
+        let mut report = FsckReport {
+            image_bytes: self.file.metadata()?.len(),
+            ..Default::default()
+        };
+
+        let mut problems = Vec::new();
+        let mut records = 0;
+        let (log_end, stop) = self.scan_log(|inner, decoded| {
+            records += 1;
+            if let Some(problem) = inner.check_data(decoded)? {
+                problems.push(problem);
+            }
+            Ok(())
+        })?;
+        report.records = records;
+        report.log_end = log_end;
+        report.problems = problems;
+
+        if let Some(e) = stop {
+            report
+                .problems
+                .push(format!("log stops at offset {log_end}: {e}"));
+        } else if log_end < report.image_bytes {
+            report.problems.push(format!(
+                "torn tail at offset {log_end}: {} bytes after the last valid record",
+                report.image_bytes - log_end
+            ));
+        }
+
+        self.check_tree(log_end, &mut report.problems);
+        Ok(report)
+    }
+
+    // crc-ul datelor unui record cu date; `None` = ok sau record fara date
+    pub(crate) fn check_data(&mut self, decoded: &DecodedRecord) -> Result<Option<String>> {
+        let (extent, checksum) = match (&decoded.record, decoded.data_payload_offset) {
+            (
+                Record::DataWrite { checksum, .. } | Record::CompressedWrite { checksum, .. },
+                Some(_),
+            ) => (decoded.extent(), *checksum),
+            // blob-urile au crc-ul peste bytes stocati (eventual comprimati)
+            (Record::Blob { len, checksum }, Some(off)) => (
+                Some(data_extent(
+                    0,
+                    off,
+                    *len,
+                    Compression::None,
+                    *len,
+                    decoded.sealed,
+                )),
+                *checksum,
+            ),
+            _ => return Ok(None),
+        };
+        let Some(extent) = extent else {
+            return Ok(None);
+        };
+
+        let mut hasher = crc32fast::Hasher::new();
+        let mut cache = None;
+        let mut buf = vec![0u8; CHECK_CHUNK.min(extent.len) as usize];
+        let mut within = 0;
+        while within < extent.len {
+            let n = CHECK_CHUNK.min(extent.len - within) as usize;
+            let out = &mut buf[..n];
+            match read_extent(
+                &mut self.file,
+                &mut cache,
+                self.cipher.as_ref(),
+                &extent,
+                within,
+                out,
+            ) {
+                Ok(()) => hasher.update(out),
+                Err(e @ (VfsError::CorruptLog(_) | VfsError::WrongKey(_))) => {
+                    return Ok(Some(format!(
+                        "{} data at offset {}: {e}",
+                        decoded.record.kind(),
+                        extent.file_offset
+                    )));
+                }
+                Err(e) => return Err(e),
+            }
+            within += n as u64;
+        }
+        if hasher.finalize() != checksum {
+            return Ok(Some(format!(
+                "{} data at offset {}: crc mismatch",
+                decoded.record.kind(),
+                extent.file_offset
+            )));
+        }
+        Ok(None)
+    }
+
+    // legaturile parinte <-> copil, inode-urile orfane si extent-urile din afara log-ului
+    fn check_tree(&self, log_end: u64, problems: &mut Vec<String>) {
+        let root = self.header.root;
+        let mut linked = HashSet::from([root]);
+
+        let mut entries: Vec<(&(InodeId, String), &InodeId)> = self.children.iter().collect();
+        entries.sort_by_key(|((parent, name), _)| (parent.0, name.as_str()));
+        for ((parent, name), child) in entries {
+            match self.inodes.get(parent) {
+                Some(p) if p.kind == NodeKind::Dir => {}
+                Some(_) => problems.push(format!(
+                    "entry {name:?} lives in inode {}, which is not a directory",
+                    parent.0
+                )),
+                None => problems.push(format!(
+                    "entry {name:?} lives in missing inode {}",
+                    parent.0
+                )),
+            }
+            match self.inodes.get(child) {
+                Some(node) if node.parent != Some(*parent) || node.name != *name => {
+                    problems.push(format!(
+                        "entry {name:?} in inode {} points to inode {}, which thinks it is {:?} in {:?}",
+                        parent.0,
+                        child.0,
+                        node.name,
+                        node.parent.map(|p| p.0)
+                    ))
+                }
+                Some(_) => {}
+                None => problems.push(format!(
+                    "entry {name:?} in inode {} points to missing inode {}",
+                    parent.0, child.0
+                )),
+            }
+            linked.insert(*child);
+        }
+
+        let mut ids: Vec<&InodeId> = self.inodes.keys().collect();
+        ids.sort_by_key(|id| id.0);
+        for id in ids {
+            let node = &self.inodes[id];
+            if !linked.contains(id) && !self.trash.contains_key(id) {
+                problems.push(format!(
+                    "inode {} ({:?}) is not linked anywhere",
+                    id.0, node.name
+                ));
+            }
+            if node.kind == NodeKind::Dir && !node.extents.is_empty() {
+                problems.push(format!("directory inode {} has data extents", id.0));
+            }
+            for ex in &node.extents {
+                let (lo, hi) = ex.stored_range();
+                if lo < self.header.log_start() || hi > log_end {
+                    problems.push(format!(
+                        "inode {} has an extent at [{lo}, {hi}) outside the log",
+                        id.0
+                    ));
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,41 @@
+pub mod archive;
+pub mod clock;
+mod codec;
+mod compact;
+mod crypt;
+pub mod debug;
+mod dedup;
 pub mod file_ops;
+pub mod fs;
+mod fsck;
+mod mounts;
+pub mod ninep;
 pub mod no_sql;
+mod overlay;
+pub mod path;
+mod reflink;
+mod replicate;
+pub mod shell;
+mod snapshot;
 pub mod structs;
+mod sync;
+mod time_travel;
+mod trash;
+mod versions;
 pub mod vfs;
+mod watch;
+#[cfg(feature = "webdav")]
+pub mod webdav;
 
-pub use structs::{DirEntry, Metadata, NodeKind, Timestamp, VfsError};
+pub use clock::{Clock, ManualClock, SourceDateEpochClock, SystemClock};
+pub use fs::{FileSystem, FsFile, HostFs, MemFs};
+pub use overlay::OverlayVfs;
+pub use path::VfsPath;
+pub use replicate::Changes;
+pub use structs::{
+    AtimePolicy, Change, Chunking, Compression, CompressionPolicy, Credentials, DirEntry,
+    EncryptionKey, FileTimes, FileVersion, FsEntry, FsMetadata, FsStats, FsckReport, ImageInfo,
+    Metadata, MountOptions, NodeKind, Retention, SnapshotInfo, SyncOptions, SyncReport, Timestamp,
+    TrashEntry, TrashPolicy, Until, VfsError, WatchEvent,
+};
 pub use vfs::{ReadDir, Vfs};
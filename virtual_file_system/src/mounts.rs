@@ -0,0 +1,402 @@
+//! tabela de montari: o alta imagine sau un director de pe host atasat peste un
+//! director din vfs, ca `/deps` sau `/host`.
+//!
+//! montarile traiesc doar in handle-ul curent (nu ajung in log) si ascund
+//! continutul directorului peste care sunt puse. `exists`, `metadata`, `read_dir`,
+//! `open*`, `create*`, `remove_*`, `rename`, `clone_file`, permisiunile, timpii,
+//! owner-ul si xattr-urile trec granita transparent; `..` nu iese dintr-o montare.
+//! un rename sau un `clone_file` intre montari diferite da `CrossesMount`, la fel
+//! ca `set_current_dir` intr-un director de sub o montare (punctul de montare
+//! insusi merge). un handle `read_only` nu scrie nici prin montarile lui.
+//! o montare al carei director e sters de alt proces dispare odata cu el.
+//! restul operatiilor (versiuni, istoric, cos...) raman pe imaginea proprie,
+//! deci sub o montare dau `NotFound`.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::file_ops::VfsFile;
+use crate::fs::{FileSystem, HostFs, host_error};
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::{Inner, ReadDir, Vfs};
+
+#[derive(Clone)]
+pub(crate) enum Mount {
+    Image(Vfs),
+    Host(HostFs),
+}
+
+impl fmt::Debug for Mount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mount::Image(vfs) => write!(f, "Image({})", vfs.inner.borrow().path.display()),
+            Mount::Host(host) => write!(f, "Host({})", host.root().display()),
+        }
+    }
+}
+
+/// montarea traversata de un path: directorul peste care e pusa si restul path-ului,
+/// relativ la radacina montarii.
+pub(crate) struct Crossing {
+    pub(crate) point: InodeId,
+    pub(crate) mount: Mount,
+    pub(crate) rest: VfsPath,
+}
+
+impl Inner {
+    /// prima montare de pe drumul lui `path`, daca e vreuna; un path care nu se
+    /// rezolva ramane local, ca eroarea sa vina din imaginea proprie.
+    pub(crate) fn crossing(&self, cwd: InodeId, path: &VfsPath) -> Result<Option<Crossing>> {
+        if self.mounts.is_empty() {
+            return Ok(None);
+        }
+        let components: Vec<&str> = path.components().collect();
+        let mut cur = if path.is_absolute() {
+            self.header.root
+        } else {
+            cwd
+        };
+        for i in 0..=components.len() {
+            if let Some(mount) = self.mounts.get(&cur) {
+                return Ok(Some(Crossing {
+                    point: cur,
+                    mount: mount.clone(),
+                    rest: VfsPath::new(format!("/{}", components[i..].join("/")))?,
+                }));
+            }
+            let Some(name) = components.get(i) else {
+                break;
+            };
+            match self.resolve(cur, &VfsPath::new(name)?) {
+                Ok(next) => cur = next,
+                Err(_) => return Ok(None),
+            }
+        }
+        Ok(None)
+    }
+
+    /// uita montarile al caror director a fost sters de alt proces (vazut prin
+    /// `tail` sau prin replicare); altfel `attachments` ar esua de atunci incolo.
+    pub(crate) fn drop_unlinked_mounts(&mut self) {
+        let gone: Vec<InodeId> = self
+            .mounts
+            .keys()
+            .copied()
+            .filter(|&inode| !self.linked(inode))
+            .collect();
+        for inode in gone {
+            self.mounts.remove(&inode);
+        }
+    }
+}
+
+impl Vfs {
+    pub(crate) fn crossing(&self, path: &VfsPath) -> Result<Option<Crossing>> {
+        self.inner.borrow().crossing(self.cwd, path)
+    }
+
+    /// `crossing` pentru operatiile care scriu: pe un handle read-only o montare
+    /// da `ReadOnly`, altfel o imagine sau un director de pe host ar fi scrise.
+    pub(crate) fn writable_crossing(&self, path: &VfsPath) -> Result<Option<Crossing>> {
+        let c = self.crossing(path)?;
+        if c.is_some() {
+            self.inner.borrow().ensure_writable()?;
+        }
+        Ok(c)
+    }
+
+    /// ataseaza imaginea `image` peste directorul `at`.
+    pub fn attach<P: AsRef<Path>>(&mut self, at: P, image: Vfs) -> Result<()> {
+        if Rc::ptr_eq(&self.inner, &image.inner) {
+            return Err(VfsError::InvalidPath(
+                "cannot attach an image inside itself".into(),
+            ));
+        }
+        self.add_mount(at.as_ref(), Mount::Image(image))
+    }
+
+    /// ataseaza directorul de pe host `dir` peste `at`; scrierile ajung direct pe disc.
+    pub fn attach_host<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, at: P, dir: Q) -> Result<()> {
+        let dir = dir.as_ref();
+        let meta = fs::metadata(dir).map_err(|e| host_error(dir, e))?;
+        if !meta.is_dir() {
+            return Err(VfsError::NotADir(dir.display().to_string()));
+        }
+        self.add_mount(at.as_ref(), Mount::Host(HostFs::new(dir)))
+    }
+
+    fn add_mount(&mut self, at: &Path, mount: Mount) -> Result<()> {
+        let path = VfsPath::new(at)?;
+        // sub o alta montare: o imagine isi tine propria tabela
+        if let Some(c) = self.crossing(&path)? {
+            if c.rest.is_root() {
+                return Err(VfsError::AlreadyExists(format!("already attached: {path}")));
+            }
+            return match (c.mount, mount) {
+                (Mount::Image(mut vfs), Mount::Image(image)) => vfs.attach(&c.rest, image),
+                (Mount::Image(mut vfs), Mount::Host(host)) => vfs.attach_host(&c.rest, host.root()),
+                (Mount::Host(_), _) => Err(VfsError::Unsupported(format!(
+                    "cannot attach inside a host directory: {path}"
+                ))),
+            };
+        }
+        let mut inner = self.inner.borrow_mut();
+        let inode = inner.resolve(self.cwd, &path)?;
+        if inode == inner.header.root {
+            return Err(VfsError::InvalidPath("cannot attach over the root".into()));
+        }
+        if inner.inodes.get(&inode).map(|n| n.kind) != Some(NodeKind::Dir) {
+            return Err(VfsError::NotADir(path.to_string()));
+        }
+        if inner.mounts.contains_key(&inode) {
+            return Err(VfsError::AlreadyExists(format!("already attached: {path}")));
+        }
+        inner.mounts.insert(inode, mount);
+        Ok(())
+    }
+
+    /// scoate montarea de la `at`; continutul vechi al directorului reapare.
+    pub fn detach<P: AsRef<Path>>(&mut self, at: P) -> Result<()> {
+        let path = VfsPath::new(at)?;
+        if let Some(c) = self.crossing(&path)? {
+            match c.mount {
+                _ if c.rest.is_root() => {}
+                Mount::Image(mut vfs) => return vfs.detach(&c.rest),
+                Mount::Host(_) => return Err(VfsError::NotFound(format!("not attached: {path}"))),
+            }
+        }
+        let mut inner = self.inner.borrow_mut();
+        let inode = inner.resolve(self.cwd, &path)?;
+        inner
+            .mounts
+            .remove(&inode)
+            .map(drop)
+            .ok_or_else(|| VfsError::NotFound(format!("not attached: {path}")))
+    }
+
+    /// path-urile absolute peste care e atasat ceva in acest handle, sortate.
+    pub fn attachments(&self) -> Result<Vec<VfsPath>> {
+        let inner = self.inner.borrow();
+        let mut paths = inner
+            .mounts
+            .keys()
+            .map(|&inode| inner.inode_path(inode))
+            .collect::<Result<Vec<_>>>()?;
+        paths.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Ok(paths)
+    }
+}
+
+// operatiile care trec granita, pe path-ul de sub radacina montarii
+impl Mount {
+    pub(crate) fn exists(&self, path: &VfsPath) -> bool {
+        match self {
+            Mount::Image(vfs) => vfs.exists(path),
+            Mount::Host(host) => host.exists(path),
+        }
+    }
+
+    pub(crate) fn kind_of(&self, path: &VfsPath) -> Result<NodeKind> {
+        match self {
+            Mount::Image(vfs) => vfs.kind_of(path.as_ref()),
+            Mount::Host(host) => Ok(host.metadata(path)?.kind),
+        }
+    }
+
+    pub(crate) fn metadata(&self, path: &VfsPath) -> Result<Metadata> {
+        match self {
+            Mount::Image(vfs) => vfs.metadata(path),
+            Mount::Host(host) => Ok(host.metadata(path)?.metadata),
+        }
+    }
+
+    /// intrarile de pe host nu au inode-uri; `parent` si `inode` raman 0.
+    pub(crate) fn read_dir(&self, path: &VfsPath) -> Result<ReadDir> {
+        match self {
+            Mount::Image(vfs) => vfs.read_dir(path),
+            Mount::Host(host) => Ok(ReadDir::new(
+                host.read_dir(path)?
+                    .into_iter()
+                    .map(|e| DirEntry {
+                        parent: InodeId(0),
+                        inode: InodeId(0),
+                        name: e.name,
+                        kind: e.kind,
+                    })
+                    .collect(),
+            )),
+        }
+    }
+
+    pub(crate) fn open(&self, path: &VfsPath, writable: bool) -> Result<VfsFile> {
+        match self {
+            Mount::Image(vfs) if writable => vfs.open_rw(path),
+            Mount::Image(vfs) => vfs.open_file(path),
+            Mount::Host(host) => {
+                if host.metadata(path)?.is_dir() {
+                    return Err(VfsError::NotAFile(path.to_string()));
+                }
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(writable)
+                    .open(host.host_path(path.as_ref())?)
+                    .map_err(|e| host_error(path.as_ref(), e))?;
+                Ok(VfsFile::host(file, writable))
+            }
+        }
+    }
+
+    pub(crate) fn create(&self, path: &VfsPath) -> Result<VfsFile> {
+        match self {
+            Mount::Image(vfs) => vfs.create(path),
+            Mount::Host(host) => {
+                // ca in imagine: un fisier existent nu se trunchiaza
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(host.host_path(path.as_ref())?)
+                    .map_err(|e| host_error(path.as_ref(), e))?;
+                Ok(VfsFile::host(file, true))
+            }
+        }
+    }
+
+    pub(crate) fn create_dir(&self, path: &VfsPath) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().create_dir(path),
+            Mount::Host(host) => fs::create_dir(host.host_path(path.as_ref())?)
+                .map_err(|e| host_error(path.as_ref(), e)),
+        }
+    }
+
+    pub(crate) fn remove(&self, path: &VfsPath, kind: NodeKind) -> Result<()> {
+        match self {
+            Mount::Image(vfs) if kind == NodeKind::Dir => vfs.clone().remove_dir(path),
+            Mount::Image(vfs) => vfs.clone().remove_file(path),
+            Mount::Host(host) => {
+                if path.is_root() {
+                    return Err(VfsError::InvalidPath("cannot remove the root".into()));
+                }
+                match (host.metadata(path)?.kind, kind) {
+                    (NodeKind::Dir, NodeKind::File) => Err(VfsError::NotAFile(path.to_string())),
+                    (NodeKind::File, NodeKind::Dir) => Err(VfsError::NotADir(path.to_string())),
+                    _ => host.remove(path),
+                }
+            }
+        }
+    }
+
+    pub(crate) fn clone_file(&self, src: &VfsPath, dst: &VfsPath) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().clone_file(src, dst),
+            // pe host nu exista extent-uri de impartit
+            Mount::Host(_) => Err(VfsError::Unsupported(format!(
+                "clone_file in a host directory: {dst}"
+            ))),
+        }
+    }
+
+    pub(crate) fn set_times(&self, path: &VfsPath, times: FileTimes) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().set_times(path, times),
+            Mount::Host(host) => {
+                // host-ul nu poate seta momentul crearii
+                let mut t = fs::FileTimes::new();
+                if let Some(m) = times.modified_at {
+                    t = t.set_modified(m.into());
+                }
+                if let Some(a) = times.accessed_at {
+                    t = t.set_accessed(a.into());
+                }
+                fs::File::open(host.host_path(path.as_ref())?)
+                    .and_then(|f| f.set_times(t))
+                    .map_err(|e| host_error(path.as_ref(), e))
+            }
+        }
+    }
+
+    pub(crate) fn set_permissions(&self, path: &VfsPath, mode: u32) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().set_permissions(path, mode),
+            #[cfg(unix)]
+            Mount::Host(host) => {
+                use std::os::unix::fs::PermissionsExt;
+                if mode & !0o7777 != 0 {
+                    return Err(VfsError::InvalidPath(format!("invalid mode {mode:o}")));
+                }
+                fs::set_permissions(
+                    host.host_path(path.as_ref())?,
+                    fs::Permissions::from_mode(mode),
+                )
+                .map_err(|e| host_error(path.as_ref(), e))
+            }
+            #[cfg(not(unix))]
+            Mount::Host(_) => Err(VfsError::Unsupported(format!("chmod on the host: {path}"))),
+        }
+    }
+
+    pub(crate) fn chown(&self, path: &VfsPath, uid: Option<u32>, gid: Option<u32>) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().chown(path, uid, gid),
+            #[cfg(unix)]
+            Mount::Host(host) => std::os::unix::fs::chown(host.host_path(path.as_ref())?, uid, gid)
+                .map_err(|e| host_error(path.as_ref(), e)),
+            #[cfg(not(unix))]
+            Mount::Host(_) => Err(VfsError::Unsupported(format!("chown on the host: {path}"))),
+        }
+    }
+
+    /// fisierele de pe host nu au xattr-uri vizibile prin vfs: lista e goala,
+    /// iar scrierea lor e `Unsupported`.
+    pub(crate) fn get_xattr(&self, path: &VfsPath, name: &str) -> Result<Option<Vec<u8>>> {
+        match self {
+            Mount::Image(vfs) => vfs.get_xattr(path, name),
+            Mount::Host(host) => host.metadata(path).map(|_| None),
+        }
+    }
+
+    pub(crate) fn list_xattrs(&self, path: &VfsPath) -> Result<Vec<String>> {
+        match self {
+            Mount::Image(vfs) => vfs.list_xattrs(path),
+            Mount::Host(host) => host.metadata(path).map(|_| Vec::new()),
+        }
+    }
+
+    pub(crate) fn set_xattr(&self, path: &VfsPath, name: &str, value: &[u8]) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().set_xattr(path, name, value),
+            Mount::Host(_) => Err(host_xattrs(path)),
+        }
+    }
+
+    pub(crate) fn remove_xattr(&self, path: &VfsPath, name: &str) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().remove_xattr(path, name),
+            Mount::Host(_) => Err(host_xattrs(path)),
+        }
+    }
+
+    pub(crate) fn rename(&self, from: &VfsPath, to: &VfsPath) -> Result<()> {
+        match self {
+            Mount::Image(vfs) => vfs.clone().rename(from, to),
+            Mount::Host(host) => {
+                if from.is_root() || to.is_root() {
+                    return Err(VfsError::InvalidPath("cannot move the root".into()));
+                }
+                // std::fs::rename ar inlocui destinatia; vfs-ul refuza
+                if host.exists(to) {
+                    return Err(VfsError::AlreadyExists(to.to_string()));
+                }
+                host.rename(from, to)
+            }
+        }
+    }
+}
+
+fn host_xattrs(path: &VfsPath) -> VfsError {
+    VfsError::Unsupported(format!("xattrs in a host directory: {path}"))
+}
@@ -0,0 +1,1591 @@
+//! server 9P2000.L peste un `Vfs`, pt VM-uri si containere
+//! (`mount -t 9p -o trans=tcp,version=9p2000.L ...`).
+//!
+//! fid-urile tin minte inode-ul, nu path-ul, deci supravietuiesc unui rename
+//! facut prin alt fid. Twalk urmeaza `resolve` pas cu pas, Tread/Twrite merg
+//! direct prin `read_at`/`write_at`; operatiile pe nume (creare, stergere,
+//! rename, atribute) reconstruiesc path-ul inode-ului si trec prin API-ul
+//! obisnuit al `Vfs`, cu aceleasi verificari de permisiuni.
+//!
+//! modulul contine si codec-ul mesajelor (`Tmsg`/`Rmsg`) si un client minimal.
+
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::Vfs;
+
+pub const VERSION: &str = "9P2000.L";
+/// fid-ul "niciunul" (ex. `afid` la attach fara autentificare).
+pub const NOFID: u32 = !0;
+/// tag-ul folosit de Tversion.
+pub const NOTAG: u16 = !0;
+
+// cel mai mare mesaj acceptat; clientul poate negocia mai putin
+const MAX_MSIZE: u32 = (1 << 20) + IOHDRSZ;
+// size[4] type[1] tag[2] fid[4] offset[8] count[4]
+const IOHDRSZ: u32 = 24;
+
+const QTDIR: u8 = 0x80;
+const QTFILE: u8 = 0;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
+const O_ACCMODE: u32 = 3;
+const O_TRUNC: u32 = 0o1000;
+const AT_REMOVEDIR: u32 = 0x200;
+const V9FS_MAGIC: u32 = 0x0102_1997;
+
+// errno-uri linux
+const EPERM: u32 = 1;
+const ENOENT: u32 = 2;
+const EIO: u32 = 5;
+const EBADF: u32 = 9;
+const EACCES: u32 = 13;
+const EEXIST: u32 = 17;
+const EXDEV: u32 = 18;
+const ENOTDIR: u32 = 20;
+const EISDIR: u32 = 21;
+const EINVAL: u32 = 22;
+const ENOSPC: u32 = 28;
+const EROFS: u32 = 30;
+const ENOTEMPTY: u32 = 39;
+const EOPNOTSUPP: u32 = 95;
+
+/// bitii `valid` din Tsetattr.
+pub mod setattr {
+    pub const MODE: u32 = 0x1;
+    pub const UID: u32 = 0x2;
+    pub const GID: u32 = 0x4;
+    pub const SIZE: u32 = 0x8;
+    pub const ATIME: u32 = 0x10;
+    pub const MTIME: u32 = 0x20;
+    pub const CTIME: u32 = 0x40;
+    pub const ATIME_SET: u32 = 0x80;
+    pub const MTIME_SET: u32 = 0x100;
+}
+
+/// bitii `valid` din Rgetattr pe care ii completeaza serverul (`P9_GETATTR_BASIC`).
+pub const GETATTR_BASIC: u64 = 0x7ff;
+
+/// identitatea unui fisier pe server: tipul, o versiune si inode-ul.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Qid {
+    pub kind: u8,
+    pub version: u32,
+    pub path: u64,
+}
+
+impl Qid {
+    pub fn is_dir(&self) -> bool {
+        self.kind & QTDIR != 0
+    }
+}
+
+/// raspunsul la Tgetattr; timpii sunt `(secunde, nanosecunde)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Attr {
+    pub valid: u64,
+    pub qid: Qid,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub nlink: u64,
+    pub rdev: u64,
+    pub size: u64,
+    pub blksize: u64,
+    pub blocks: u64,
+    pub atime: (u64, u64),
+    pub mtime: (u64, u64),
+    pub ctime: (u64, u64),
+    pub btime: (u64, u64),
+    pub generation: u64,
+    pub data_version: u64,
+}
+
+/// campurile din Tsetattr; se aplica doar cele marcate in `valid` (vezi [`setattr`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SetAttr {
+    pub valid: u32,
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub size: u64,
+    pub atime: (u64, u64),
+    pub mtime: (u64, u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatFs {
+    pub kind: u32,
+    pub bsize: u32,
+    pub blocks: u64,
+    pub bfree: u64,
+    pub bavail: u64,
+    pub files: u64,
+    pub ffree: u64,
+    pub fsid: u64,
+    pub namelen: u32,
+}
+
+/// o intrare din Rreaddir; `offset` e cel de la care continua urmatorul Treaddir.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dirent {
+    pub qid: Qid,
+    pub offset: u64,
+    pub kind: u8,
+    pub name: String,
+}
+
+impl Dirent {
+    fn encoded_len(&self) -> usize {
+        13 + 8 + 1 + 2 + self.name.len()
+    }
+
+    /// intrarile din datele unui Rreaddir.
+    pub fn decode_all(data: &[u8]) -> Result<Vec<Dirent>> {
+        let mut cur = Cursor::new(data);
+        let mut out = Vec::new();
+        while !cur.is_empty() {
+            out.push(Dirent {
+                qid: cur.qid()?,
+                offset: cur.u64()?,
+                kind: cur.u8()?,
+                name: cur.str()?,
+            });
+        }
+        Ok(out)
+    }
+}
+
+/// mesajele trimise de client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Tmsg {
+    Version {
+        msize: u32,
+        version: String,
+    },
+    Auth {
+        afid: u32,
+        uname: String,
+        aname: String,
+        n_uname: u32,
+    },
+    Attach {
+        fid: u32,
+        afid: u32,
+        uname: String,
+        aname: String,
+        n_uname: u32,
+    },
+    Flush {
+        oldtag: u16,
+    },
+    Walk {
+        fid: u32,
+        newfid: u32,
+        names: Vec<String>,
+    },
+    Lopen {
+        fid: u32,
+        flags: u32,
+    },
+    Lcreate {
+        fid: u32,
+        name: String,
+        flags: u32,
+        mode: u32,
+        gid: u32,
+    },
+    Read {
+        fid: u32,
+        offset: u64,
+        count: u32,
+    },
+    Write {
+        fid: u32,
+        offset: u64,
+        data: Vec<u8>,
+    },
+    Clunk {
+        fid: u32,
+    },
+    Remove {
+        fid: u32,
+    },
+    Getattr {
+        fid: u32,
+        mask: u64,
+    },
+    Setattr {
+        fid: u32,
+        attr: SetAttr,
+    },
+    Readdir {
+        fid: u32,
+        offset: u64,
+        count: u32,
+    },
+    Mkdir {
+        dfid: u32,
+        name: String,
+        mode: u32,
+        gid: u32,
+    },
+    Unlinkat {
+        dfid: u32,
+        name: String,
+        flags: u32,
+    },
+    Renameat {
+        olddfid: u32,
+        oldname: String,
+        newdfid: u32,
+        newname: String,
+    },
+    Rename {
+        fid: u32,
+        dfid: u32,
+        name: String,
+    },
+    Statfs {
+        fid: u32,
+    },
+    Fsync {
+        fid: u32,
+        datasync: u32,
+    },
+    Xattrwalk {
+        fid: u32,
+        newfid: u32,
+        name: String,
+    },
+}
+
+/// raspunsurile serverului.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rmsg {
+    Lerror { ecode: u32 },
+    Version { msize: u32, version: String },
+    Attach { qid: Qid },
+    Flush,
+    Walk { qids: Vec<Qid> },
+    Lopen { qid: Qid, iounit: u32 },
+    Lcreate { qid: Qid, iounit: u32 },
+    Read { data: Vec<u8> },
+    Write { count: u32 },
+    Clunk,
+    Remove,
+    Getattr(Attr),
+    Setattr,
+    Readdir { data: Vec<u8> },
+    Mkdir { qid: Qid },
+    Unlinkat,
+    Renameat,
+    Rename,
+    Statfs(StatFs),
+    Fsync,
+}
+
+// ---------- codec ----------
+
+fn protocol_error(msg: impl Into<String>) -> VfsError {
+    VfsError::Io(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        msg.into(),
+    ))
+}
+
+struct Cursor<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Cursor<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(protocol_error("message too short"));
+        }
+        let (head, rest) = self.buf.split_at(n);
+        self.buf = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_le_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    fn str(&mut self) -> Result<String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| protocol_error("non utf-8 string"))
+    }
+
+    fn data(&mut self) -> Result<Vec<u8>> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.to_vec())
+    }
+
+    fn qid(&mut self) -> Result<Qid> {
+        Ok(Qid {
+            kind: self.u8()?,
+            version: self.u32()?,
+            path: self.u64()?,
+        })
+    }
+
+    fn time(&mut self) -> Result<(u64, u64)> {
+        Ok((self.u64()?, self.u64()?))
+    }
+
+    fn end(&self) -> Result<()> {
+        if !self.buf.is_empty() {
+            return Err(protocol_error("trailing bytes in message"));
+        }
+        Ok(())
+    }
+}
+
+// un mesaj in constructie; `size` se completeaza la final
+struct Frame(Vec<u8>);
+
+impl Frame {
+    fn new(kind: u8, tag: u16) -> Self {
+        let mut buf = vec![0; 4];
+        buf.push(kind);
+        buf.extend_from_slice(&tag.to_le_bytes());
+        Self(buf)
+    }
+
+    fn u8(&mut self, v: u8) -> &mut Self {
+        self.0.push(v);
+        self
+    }
+
+    fn u16(&mut self, v: u16) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u32(&mut self, v: u32) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn u64(&mut self, v: u64) -> &mut Self {
+        self.0.extend_from_slice(&v.to_le_bytes());
+        self
+    }
+
+    fn str(&mut self, s: &str) -> &mut Self {
+        self.u16(s.len() as u16);
+        self.0.extend_from_slice(s.as_bytes());
+        self
+    }
+
+    fn data(&mut self, d: &[u8]) -> &mut Self {
+        self.u32(d.len() as u32);
+        self.0.extend_from_slice(d);
+        self
+    }
+
+    fn qid(&mut self, q: &Qid) -> &mut Self {
+        self.u8(q.kind).u32(q.version).u64(q.path)
+    }
+
+    fn time(&mut self, (sec, nsec): (u64, u64)) -> &mut Self {
+        self.u64(sec).u64(nsec)
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let size = self.0.len() as u32;
+        self.0[..4].copy_from_slice(&size.to_le_bytes());
+        self.0
+    }
+}
+
+fn encode_dirents(entries: &[Dirent]) -> Vec<u8> {
+    let mut f = Frame(Vec::new());
+    for e in entries {
+        f.qid(&e.qid).u64(e.offset).u8(e.kind).str(&e.name);
+    }
+    f.0
+}
+
+/// citeste un mesaj intreg (cu `size` inclus); `None` la EOF intre mesaje.
+pub fn read_frame<R: Read>(reader: &mut R, max: u32) -> Result<Option<Vec<u8>>> {
+    let mut size = [0u8; 4];
+    // EOF curat doar inainte de primul byte; o marime taiata e o eroare
+    loop {
+        match reader.read(&mut size[..1]) {
+            Ok(0) => return Ok(None),
+            Ok(_) => break,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    reader.read_exact(&mut size[1..])?;
+    let size = u32::from_le_bytes(size);
+    if !(7..=max).contains(&size) {
+        return Err(protocol_error(format!("bad message size {size}")));
+    }
+    let mut frame = size.to_le_bytes().to_vec();
+    frame.resize(size as usize, 0);
+    reader.read_exact(&mut frame[4..])?;
+    Ok(Some(frame))
+}
+
+// tipul si tag-ul unui mesaj citit cu `read_frame`
+fn frame_header(frame: &[u8]) -> Result<(u8, u16, Cursor<'_>)> {
+    let mut cur = Cursor::new(frame.get(4..).unwrap_or_default());
+    Ok((cur.u8()?, cur.u16()?, cur))
+}
+
+impl Tmsg {
+    pub fn encode(&self, tag: u16) -> Vec<u8> {
+        let mut f;
+        match self {
+            Tmsg::Version { msize, version } => {
+                f = Frame::new(100, tag);
+                f.u32(*msize).str(version);
+            }
+            Tmsg::Auth {
+                afid,
+                uname,
+                aname,
+                n_uname,
+            } => {
+                f = Frame::new(102, tag);
+                f.u32(*afid).str(uname).str(aname).u32(*n_uname);
+            }
+            Tmsg::Attach {
+                fid,
+                afid,
+                uname,
+                aname,
+                n_uname,
+            } => {
+                f = Frame::new(104, tag);
+                f.u32(*fid).u32(*afid).str(uname).str(aname).u32(*n_uname);
+            }
+            Tmsg::Flush { oldtag } => {
+                f = Frame::new(108, tag);
+                f.u16(*oldtag);
+            }
+            Tmsg::Walk { fid, newfid, names } => {
+                f = Frame::new(110, tag);
+                f.u32(*fid).u32(*newfid).u16(names.len() as u16);
+                for name in names {
+                    f.str(name);
+                }
+            }
+            Tmsg::Lopen { fid, flags } => {
+                f = Frame::new(12, tag);
+                f.u32(*fid).u32(*flags);
+            }
+            Tmsg::Lcreate {
+                fid,
+                name,
+                flags,
+                mode,
+                gid,
+            } => {
+                f = Frame::new(14, tag);
+                f.u32(*fid).str(name).u32(*flags).u32(*mode).u32(*gid);
+            }
+            Tmsg::Read { fid, offset, count } => {
+                f = Frame::new(116, tag);
+                f.u32(*fid).u64(*offset).u32(*count);
+            }
+            Tmsg::Write { fid, offset, data } => {
+                f = Frame::new(118, tag);
+                f.u32(*fid).u64(*offset).data(data);
+            }
+            Tmsg::Clunk { fid } => {
+                f = Frame::new(120, tag);
+                f.u32(*fid);
+            }
+            Tmsg::Remove { fid } => {
+                f = Frame::new(122, tag);
+                f.u32(*fid);
+            }
+            Tmsg::Getattr { fid, mask } => {
+                f = Frame::new(24, tag);
+                f.u32(*fid).u64(*mask);
+            }
+            Tmsg::Setattr { fid, attr } => {
+                f = Frame::new(26, tag);
+                f.u32(*fid)
+                    .u32(attr.valid)
+                    .u32(attr.mode)
+                    .u32(attr.uid)
+                    .u32(attr.gid)
+                    .u64(attr.size)
+                    .time(attr.atime)
+                    .time(attr.mtime);
+            }
+            Tmsg::Readdir { fid, offset, count } => {
+                f = Frame::new(40, tag);
+                f.u32(*fid).u64(*offset).u32(*count);
+            }
+            Tmsg::Mkdir {
+                dfid,
+                name,
+                mode,
+                gid,
+            } => {
+                f = Frame::new(72, tag);
+                f.u32(*dfid).str(name).u32(*mode).u32(*gid);
+            }
+            Tmsg::Unlinkat { dfid, name, flags } => {
+                f = Frame::new(76, tag);
+                f.u32(*dfid).str(name).u32(*flags);
+            }
+            Tmsg::Renameat {
+                olddfid,
+                oldname,
+                newdfid,
+                newname,
+            } => {
+                f = Frame::new(74, tag);
+                f.u32(*olddfid).str(oldname).u32(*newdfid).str(newname);
+            }
+            Tmsg::Rename { fid, dfid, name } => {
+                f = Frame::new(20, tag);
+                f.u32(*fid).u32(*dfid).str(name);
+            }
+            Tmsg::Statfs { fid } => {
+                f = Frame::new(8, tag);
+                f.u32(*fid);
+            }
+            Tmsg::Fsync { fid, datasync } => {
+                f = Frame::new(50, tag);
+                f.u32(*fid).u32(*datasync);
+            }
+            Tmsg::Xattrwalk { fid, newfid, name } => {
+                f = Frame::new(30, tag);
+                f.u32(*fid).u32(*newfid).str(name);
+            }
+        }
+        f.finish()
+    }
+
+    /// decodeaza un mesaj intreg; intoarce si tag-ul lui.
+    pub fn decode(frame: &[u8]) -> Result<(u16, Tmsg)> {
+        let (kind, tag, mut c) = frame_header(frame)?;
+        let msg = match kind {
+            100 => Tmsg::Version {
+                msize: c.u32()?,
+                version: c.str()?,
+            },
+            102 => Tmsg::Auth {
+                afid: c.u32()?,
+                uname: c.str()?,
+                aname: c.str()?,
+                n_uname: c.u32()?,
+            },
+            104 => Tmsg::Attach {
+                fid: c.u32()?,
+                afid: c.u32()?,
+                uname: c.str()?,
+                aname: c.str()?,
+                n_uname: c.u32()?,
+            },
+            108 => Tmsg::Flush { oldtag: c.u16()? },
+            110 => {
+                let fid = c.u32()?;
+                let newfid = c.u32()?;
+                let n = c.u16()?;
+                let names = (0..n).map(|_| c.str()).collect::<Result<_>>()?;
+                Tmsg::Walk { fid, newfid, names }
+            }
+            12 => Tmsg::Lopen {
+                fid: c.u32()?,
+                flags: c.u32()?,
+            },
+            14 => Tmsg::Lcreate {
+                fid: c.u32()?,
+                name: c.str()?,
+                flags: c.u32()?,
+                mode: c.u32()?,
+                gid: c.u32()?,
+            },
+            116 => Tmsg::Read {
+                fid: c.u32()?,
+                offset: c.u64()?,
+                count: c.u32()?,
+            },
+            118 => Tmsg::Write {
+                fid: c.u32()?,
+                offset: c.u64()?,
+                data: c.data()?,
+            },
+            120 => Tmsg::Clunk { fid: c.u32()? },
+            122 => Tmsg::Remove { fid: c.u32()? },
+            24 => Tmsg::Getattr {
+                fid: c.u32()?,
+                mask: c.u64()?,
+            },
+            26 => Tmsg::Setattr {
+                fid: c.u32()?,
+                attr: SetAttr {
+                    valid: c.u32()?,
+                    mode: c.u32()?,
+                    uid: c.u32()?,
+                    gid: c.u32()?,
+                    size: c.u64()?,
+                    atime: c.time()?,
+                    mtime: c.time()?,
+                },
+            },
+            40 => Tmsg::Readdir {
+                fid: c.u32()?,
+                offset: c.u64()?,
+                count: c.u32()?,
+            },
+            72 => Tmsg::Mkdir {
+                dfid: c.u32()?,
+                name: c.str()?,
+                mode: c.u32()?,
+                gid: c.u32()?,
+            },
+            76 => Tmsg::Unlinkat {
+                dfid: c.u32()?,
+                name: c.str()?,
+                flags: c.u32()?,
+            },
+            74 => Tmsg::Renameat {
+                olddfid: c.u32()?,
+                oldname: c.str()?,
+                newdfid: c.u32()?,
+                newname: c.str()?,
+            },
+            20 => Tmsg::Rename {
+                fid: c.u32()?,
+                dfid: c.u32()?,
+                name: c.str()?,
+            },
+            8 => Tmsg::Statfs { fid: c.u32()? },
+            50 => Tmsg::Fsync {
+                fid: c.u32()?,
+                datasync: c.u32()?,
+            },
+            30 => Tmsg::Xattrwalk {
+                fid: c.u32()?,
+                newfid: c.u32()?,
+                name: c.str()?,
+            },
+            other => {
+                return Err(VfsError::Unsupported(format!("9p message type {other}")));
+            }
+        };
+        c.end()?;
+        Ok((tag, msg))
+    }
+}
+
+impl Rmsg {
+    pub fn encode(&self, tag: u16) -> Vec<u8> {
+        let mut f;
+        match self {
+            Rmsg::Lerror { ecode } => {
+                f = Frame::new(7, tag);
+                f.u32(*ecode);
+            }
+            Rmsg::Version { msize, version } => {
+                f = Frame::new(101, tag);
+                f.u32(*msize).str(version);
+            }
+            Rmsg::Attach { qid } => {
+                f = Frame::new(105, tag);
+                f.qid(qid);
+            }
+            Rmsg::Flush => f = Frame::new(109, tag),
+            Rmsg::Walk { qids } => {
+                f = Frame::new(111, tag);
+                f.u16(qids.len() as u16);
+                for qid in qids {
+                    f.qid(qid);
+                }
+            }
+            Rmsg::Lopen { qid, iounit } => {
+                f = Frame::new(13, tag);
+                f.qid(qid).u32(*iounit);
+            }
+            Rmsg::Lcreate { qid, iounit } => {
+                f = Frame::new(15, tag);
+                f.qid(qid).u32(*iounit);
+            }
+            Rmsg::Read { data } => {
+                f = Frame::new(117, tag);
+                f.data(data);
+            }
+            Rmsg::Write { count } => {
+                f = Frame::new(119, tag);
+                f.u32(*count);
+            }
+            Rmsg::Clunk => f = Frame::new(121, tag),
+            Rmsg::Remove => f = Frame::new(123, tag),
+            Rmsg::Getattr(a) => {
+                f = Frame::new(25, tag);
+                f.u64(a.valid)
+                    .qid(&a.qid)
+                    .u32(a.mode)
+                    .u32(a.uid)
+                    .u32(a.gid)
+                    .u64(a.nlink)
+                    .u64(a.rdev)
+                    .u64(a.size)
+                    .u64(a.blksize)
+                    .u64(a.blocks)
+                    .time(a.atime)
+                    .time(a.mtime)
+                    .time(a.ctime)
+                    .time(a.btime)
+                    .u64(a.generation)
+                    .u64(a.data_version);
+            }
+            Rmsg::Setattr => f = Frame::new(27, tag),
+            Rmsg::Readdir { data } => {
+                f = Frame::new(41, tag);
+                f.data(data);
+            }
+            Rmsg::Mkdir { qid } => {
+                f = Frame::new(73, tag);
+                f.qid(qid);
+            }
+            Rmsg::Unlinkat => f = Frame::new(77, tag),
+            Rmsg::Renameat => f = Frame::new(75, tag),
+            Rmsg::Rename => f = Frame::new(21, tag),
+            Rmsg::Statfs(s) => {
+                f = Frame::new(9, tag);
+                f.u32(s.kind)
+                    .u32(s.bsize)
+                    .u64(s.blocks)
+                    .u64(s.bfree)
+                    .u64(s.bavail)
+                    .u64(s.files)
+                    .u64(s.ffree)
+                    .u64(s.fsid)
+                    .u32(s.namelen);
+            }
+            Rmsg::Fsync => f = Frame::new(51, tag),
+        }
+        f.finish()
+    }
+
+    pub fn decode(frame: &[u8]) -> Result<(u16, Rmsg)> {
+        let (kind, tag, mut c) = frame_header(frame)?;
+        let msg = match kind {
+            7 => Rmsg::Lerror { ecode: c.u32()? },
+            101 => Rmsg::Version {
+                msize: c.u32()?,
+                version: c.str()?,
+            },
+            105 => Rmsg::Attach { qid: c.qid()? },
+            109 => Rmsg::Flush,
+            111 => {
+                let n = c.u16()?;
+                let qids = (0..n).map(|_| c.qid()).collect::<Result<_>>()?;
+                Rmsg::Walk { qids }
+            }
+            13 => Rmsg::Lopen {
+                qid: c.qid()?,
+                iounit: c.u32()?,
+            },
+            15 => Rmsg::Lcreate {
+                qid: c.qid()?,
+                iounit: c.u32()?,
+            },
+            117 => Rmsg::Read { data: c.data()? },
+            119 => Rmsg::Write { count: c.u32()? },
+            121 => Rmsg::Clunk,
+            123 => Rmsg::Remove,
+            25 => Rmsg::Getattr(Attr {
+                valid: c.u64()?,
+                qid: c.qid()?,
+                mode: c.u32()?,
+                uid: c.u32()?,
+                gid: c.u32()?,
+                nlink: c.u64()?,
+                rdev: c.u64()?,
+                size: c.u64()?,
+                blksize: c.u64()?,
+                blocks: c.u64()?,
+                atime: c.time()?,
+                mtime: c.time()?,
+                ctime: c.time()?,
+                btime: c.time()?,
+                generation: c.u64()?,
+                data_version: c.u64()?,
+            }),
+            27 => Rmsg::Setattr,
+            41 => Rmsg::Readdir { data: c.data()? },
+            73 => Rmsg::Mkdir { qid: c.qid()? },
+            77 => Rmsg::Unlinkat,
+            75 => Rmsg::Renameat,
+            21 => Rmsg::Rename,
+            9 => Rmsg::Statfs(StatFs {
+                kind: c.u32()?,
+                bsize: c.u32()?,
+                blocks: c.u64()?,
+                bfree: c.u64()?,
+                bavail: c.u64()?,
+                files: c.u64()?,
+                ffree: c.u64()?,
+                fsid: c.u64()?,
+                namelen: c.u32()?,
+            }),
+            51 => Rmsg::Fsync,
+            other => {
+                return Err(VfsError::Unsupported(format!("9p message type {other}")));
+            }
+        };
+        c.end()?;
+        Ok((tag, msg))
+    }
+}
+
+// ---------- server ----------
+
+struct Fid {
+    inode: InodeId,
+    open: Option<OpenFid>,
+}
+
+struct OpenFid {
+    writable: bool,
+    /// intrarile directorului, fixate la primul Treaddir
+    listing: Option<Vec<Dirent>>,
+}
+
+/// server 9P2000.L; fid-urile sunt per conexiune, conexiunile se servesc pe rand.
+pub struct NinepServer {
+    vfs: Vfs,
+    fids: HashMap<u32, Fid>,
+    msize: u32,
+}
+
+impl NinepServer {
+    pub fn new(vfs: Vfs) -> Self {
+        Self {
+            vfs,
+            fids: HashMap::new(),
+            msize: MAX_MSIZE,
+        }
+    }
+
+    pub fn vfs(&self) -> &Vfs {
+        &self.vfs
+    }
+
+    pub fn into_vfs(self) -> Vfs {
+        self.vfs
+    }
+
+    /// serveste o conexiune (TCP, socket unix, pipe) pana clientul o inchide.
+    pub fn serve<S: Read + Write>(&mut self, mut stream: S) -> Result<()> {
+        self.fids.clear();
+        self.msize = MAX_MSIZE;
+        while let Some(frame) = read_frame(&mut stream, self.msize)? {
+            let reply = self.handle(&frame);
+            stream.write_all(&reply)?;
+            stream.flush()?;
+        }
+        self.fids.clear();
+        Ok(())
+    }
+
+    /// raspunsul la un singur mesaj; util si fara transport.
+    pub fn handle(&mut self, frame: &[u8]) -> Vec<u8> {
+        let (tag, reply) = match Tmsg::decode(frame) {
+            Ok((tag, msg)) => (tag, self.dispatch(msg)),
+            Err(e) => {
+                let tag = frame_header(frame).map_or(NOTAG, |(_, tag, _)| tag);
+                (tag, Err(e))
+            }
+        };
+        reply
+            .unwrap_or_else(|e| Rmsg::Lerror { ecode: errno(&e) })
+            .encode(tag)
+    }
+
+    fn dispatch(&mut self, msg: Tmsg) -> Result<Rmsg> {
+        match msg {
+            Tmsg::Version { msize, version } => {
+                // o versiune noua reseteaza sesiunea
+                self.fids.clear();
+                self.msize = msize.clamp(IOHDRSZ + 1, MAX_MSIZE);
+                let version = if version.starts_with(VERSION) {
+                    VERSION
+                } else {
+                    "unknown"
+                };
+                Ok(Rmsg::Version {
+                    msize: self.msize,
+                    version: version.into(),
+                })
+            }
+            Tmsg::Auth { .. } => Err(VfsError::Unsupported("9p authentication".into())),
+            Tmsg::Attach { fid, .. } => {
+                let root = self.vfs.inner.borrow().header.root;
+                self.insert_fid(fid, root)?;
+                Ok(Rmsg::Attach {
+                    qid: self.qid(root)?,
+                })
+            }
+            Tmsg::Flush { .. } => Ok(Rmsg::Flush),
+            Tmsg::Walk { fid, newfid, names } => self.walk(fid, newfid, &names),
+            Tmsg::Lopen { fid, flags } => self.lopen(fid, flags),
+            Tmsg::Lcreate {
+                fid,
+                name,
+                flags,
+                mode,
+                ..
+            } => self.lcreate(fid, &name, flags, mode),
+            Tmsg::Read { fid, offset, count } => {
+                let inode = self.open_fid(fid, false)?;
+                let max = self.msize - 11;
+                let mut buf = vec![0u8; count.min(max) as usize];
+                let n = self.vfs.read_at(inode, offset, &mut buf)?;
+                buf.truncate(n);
+                Ok(Rmsg::Read { data: buf })
+            }
+            Tmsg::Write { fid, offset, data } => {
+                let inode = self.open_fid(fid, true)?;
+                let n = self.vfs.write_at(inode, offset, &data)?;
+                Ok(Rmsg::Write { count: n as u32 })
+            }
+            Tmsg::Clunk { fid } => self
+                .fids
+                .remove(&fid)
+                .ok_or_else(|| bad_fid(fid))
+                .map(|_| Rmsg::Clunk),
+            Tmsg::Remove { fid } => {
+                // fid-ul dispare si daca stergerea esueaza
+                let inode = self.fids.remove(&fid).ok_or_else(|| bad_fid(fid))?.inode;
+                let path = self.path(inode)?;
+                match self.kind(inode)? {
+                    NodeKind::Dir => self.vfs.remove_dir(&path)?,
+                    NodeKind::File => self.vfs.remove_file(&path)?,
+                }
+                Ok(Rmsg::Remove)
+            }
+            Tmsg::Getattr { fid, .. } => Ok(Rmsg::Getattr(self.getattr(self.fid(fid)?)?)),
+            Tmsg::Setattr { fid, attr } => {
+                self.setattr(self.fid(fid)?, &attr)?;
+                Ok(Rmsg::Setattr)
+            }
+            Tmsg::Readdir { fid, offset, count } => self.readdir(fid, offset, count),
+            Tmsg::Mkdir {
+                dfid, name, mode, ..
+            } => {
+                let path = self.child_path(dfid, &name)?;
+                self.vfs.create_dir(&path)?;
+                self.vfs.set_permissions(&path, mode & 0o7777)?;
+                let inode = self.resolve(&path)?;
+                Ok(Rmsg::Mkdir {
+                    qid: self.qid(inode)?,
+                })
+            }
+            Tmsg::Unlinkat { dfid, name, flags } => {
+                let path = self.child_path(dfid, &name)?;
+                if flags & AT_REMOVEDIR != 0 {
+                    self.vfs.remove_dir(&path)?;
+                } else {
+                    self.vfs.remove_file(&path)?;
+                }
+                Ok(Rmsg::Unlinkat)
+            }
+            Tmsg::Renameat {
+                olddfid,
+                oldname,
+                newdfid,
+                newname,
+            } => {
+                let from = self.child_path(olddfid, &oldname)?;
+                let to = self.child_path(newdfid, &newname)?;
+                self.rename(&from, &to)?;
+                Ok(Rmsg::Renameat)
+            }
+            Tmsg::Rename { fid, dfid, name } => {
+                let from = self.path(self.fid(fid)?)?;
+                let to = self.child_path(dfid, &name)?;
+                self.rename(&from, &to)?;
+                Ok(Rmsg::Rename)
+            }
+            Tmsg::Statfs { fid } => {
+                self.fid(fid)?;
+                self.statfs()
+            }
+            Tmsg::Fsync { fid, .. } => {
+                self.fid(fid)?;
+                self.vfs.inner.borrow().file.sync_data()?;
+                Ok(Rmsg::Fsync)
+            }
+            Tmsg::Xattrwalk { .. } => Err(VfsError::Unsupported("9p xattrs".into())),
+        }
+    }
+
+    fn insert_fid(&mut self, fid: u32, inode: InodeId) -> Result<()> {
+        if fid == NOFID || self.fids.contains_key(&fid) {
+            return Err(bad_fid(fid));
+        }
+        self.fids.insert(fid, Fid { inode, open: None });
+        Ok(())
+    }
+
+    fn fid(&self, fid: u32) -> Result<InodeId> {
+        self.fids
+            .get(&fid)
+            .map(|f| f.inode)
+            .ok_or_else(|| bad_fid(fid))
+    }
+
+    // inode-ul unui fid deschis (cu Tlopen/Tlcreate), pt citire sau scriere
+    fn open_fid(&self, fid: u32, write: bool) -> Result<InodeId> {
+        let f = self.fids.get(&fid).ok_or_else(|| bad_fid(fid))?;
+        match &f.open {
+            Some(open) if !write || open.writable => Ok(f.inode),
+            _ => Err(bad_fid(fid)),
+        }
+    }
+
+    fn path(&self, inode: InodeId) -> Result<VfsPath> {
+        self.vfs.inner.borrow().inode_path(inode)
+    }
+
+    fn resolve(&self, path: &VfsPath) -> Result<InodeId> {
+        let inner = self.vfs.inner.borrow();
+        inner.resolve(inner.header.root, path)
+    }
+
+    // path-ul lui `name` in directorul `dfid`; numele e o singura componenta
+    fn child_path(&self, dfid: u32, name: &str) -> Result<VfsPath> {
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(VfsError::InvalidPath(format!("bad 9p name: {name:?}")));
+        }
+        self.path(self.fid(dfid)?)?.join(name)
+    }
+
+    fn kind(&self, inode: InodeId) -> Result<NodeKind> {
+        let inner = self.vfs.inner.borrow();
+        inner
+            .inodes
+            .get(&inode)
+            .map(|n| n.kind)
+            .ok_or_else(|| VfsError::NotFound(format!("{inode:?}")))
+    }
+
+    fn qid(&self, inode: InodeId) -> Result<Qid> {
+        let inner = self.vfs.inner.borrow();
+        let node = inner
+            .inodes
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(format!("{inode:?}")))?;
+        Ok(qid_of(node))
+    }
+
+    fn walk(&mut self, fid: u32, newfid: u32, names: &[String]) -> Result<Rmsg> {
+        let start = self.fid(fid)?;
+        if newfid != fid && self.fids.contains_key(&newfid) {
+            return Err(bad_fid(newfid));
+        }
+        let mut cur = start;
+        let mut qids = Vec::with_capacity(names.len());
+        for name in names {
+            let step = {
+                let inner = self.vfs.inner.borrow();
+                // ca la Tlcreate: `/etc` sau `hosts/` ar trece drept o componenta
+                if name.is_empty() || name.contains('/') {
+                    Err(VfsError::InvalidPath(format!("bad 9p name: {name:?}")))
+                } else {
+                    VfsPath::new(name).and_then(|p| inner.resolve(cur, &p))
+                }
+                .map(|id| (id, qid_of(&inner.inodes[&id])))
+            };
+            match step {
+                Ok((id, qid)) => {
+                    cur = id;
+                    qids.push(qid);
+                }
+                // doar primul pas esuat e o eroare; altfel se intorc qid-urile de pana atunci
+                Err(e) if qids.is_empty() => return Err(e),
+                Err(_) => return Ok(Rmsg::Walk { qids }),
+            }
+        }
+        self.fids.insert(
+            newfid,
+            Fid {
+                inode: cur,
+                open: None,
+            },
+        );
+        Ok(Rmsg::Walk { qids })
+    }
+
+    fn lopen(&mut self, fid: u32, flags: u32) -> Result<Rmsg> {
+        let inode = self.fid(fid)?;
+        if self.fids[&fid].open.is_some() {
+            return Err(bad_fid(fid));
+        }
+        let path = self.path(inode)?;
+        let writable = flags & O_ACCMODE != 0;
+        match self.kind(inode)? {
+            NodeKind::Dir if writable => return Err(VfsError::NotAFile(path.to_string())),
+            NodeKind::Dir => drop(self.vfs.read_dir(&path)?),
+            NodeKind::File if writable => {
+                let mut file = self.vfs.open_rw(&path)?;
+                if flags & O_TRUNC != 0 {
+                    file.set_len(0)?;
+                }
+            }
+            NodeKind::File => drop(self.vfs.open_file(&path)?),
+        }
+        self.fids.get_mut(&fid).unwrap().open = Some(OpenFid {
+            writable,
+            listing: None,
+        });
+        Ok(Rmsg::Lopen {
+            qid: self.qid(inode)?,
+            iounit: self.msize - IOHDRSZ,
+        })
+    }
+
+    fn lcreate(&mut self, fid: u32, name: &str, flags: u32, mode: u32) -> Result<Rmsg> {
+        if self.fids.get(&fid).is_some_and(|f| f.open.is_some()) {
+            return Err(bad_fid(fid));
+        }
+        let path = self.child_path(fid, name)?;
+        drop(self.vfs.create(&path)?);
+        self.vfs.set_permissions(&path, mode & 0o7777)?;
+        let inode = self.resolve(&path)?;
+        // fid-ul directorului devine fid-ul fisierului nou, deschis
+        self.fids.insert(
+            fid,
+            Fid {
+                inode,
+                open: Some(OpenFid {
+                    writable: flags & O_ACCMODE != 0,
+                    listing: None,
+                }),
+            },
+        );
+        Ok(Rmsg::Lcreate {
+            qid: self.qid(inode)?,
+            iounit: self.msize - IOHDRSZ,
+        })
+    }
+
+    fn readdir(&mut self, fid: u32, offset: u64, count: u32) -> Result<Rmsg> {
+        let inode = self.open_fid(fid, false)?;
+        if self.kind(inode)? != NodeKind::Dir {
+            return Err(VfsError::NotADir(self.path(inode)?.to_string()));
+        }
+        // lista se reface la offset 0 (rewinddir); altfel ramane cea de la inceput
+        let cached = self.fids[&fid]
+            .open
+            .as_ref()
+            .and_then(|o| o.listing.as_ref())
+            .is_some();
+        if offset == 0 || !cached {
+            let listing = self.listing(inode)?;
+            if let Some(open) = self.fids.get_mut(&fid).and_then(|f| f.open.as_mut()) {
+                open.listing = Some(listing);
+            }
+        }
+        let listing = self.fids[&fid]
+            .open
+            .as_ref()
+            .and_then(|o| o.listing.as_ref())
+            .unwrap();
+
+        let budget = count.min(self.msize - 11) as usize;
+        let mut used = 0;
+        let mut page = Vec::new();
+        for entry in listing.iter().skip(offset as usize) {
+            if used + entry.encoded_len() > budget {
+                break;
+            }
+            used += entry.encoded_len();
+            page.push(entry.clone());
+        }
+        Ok(Rmsg::Readdir {
+            data: encode_dirents(&page),
+        })
+    }
+
+    // `.`, `..` si intrarile sortate dupa nume; offset-ul fiecareia e pozitia urmatoarei
+    fn listing(&self, dir: InodeId) -> Result<Vec<Dirent>> {
+        let path = self.path(dir)?;
+        let mut entries: Vec<DirEntry> = self.vfs.read_dir(&path)?.collect::<Result<_>>()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let inner = self.vfs.inner.borrow();
+        let parent = inner.inodes[&dir].parent.unwrap_or(dir);
+        let dot = [(".".to_string(), dir), ("..".to_string(), parent)];
+        let all = dot
+            .into_iter()
+            .chain(entries.into_iter().map(|e| (e.name, e.inode)));
+        Ok(all
+            .enumerate()
+            .map(|(i, (name, id))| {
+                let qid = qid_of(&inner.inodes[&id]);
+                Dirent {
+                    qid,
+                    offset: i as u64 + 1,
+                    kind: if qid.is_dir() { DT_DIR } else { DT_REG },
+                    name,
+                }
+            })
+            .collect())
+    }
+
+    fn getattr(&self, inode: InodeId) -> Result<Attr> {
+        let inner = self.vfs.inner.borrow();
+        let node = inner
+            .inodes
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(format!("{inode:?}")))?;
+        let meta = &node.metadata;
+        let (ftype, nlink) = match node.kind {
+            NodeKind::Dir => (S_IFDIR, 2),
+            NodeKind::File => (S_IFREG, 1),
+        };
+        Ok(Attr {
+            valid: GETATTR_BASIC,
+            qid: qid_of(node),
+            mode: ftype | (meta.mode & 0o7777),
+            uid: meta.uid,
+            gid: meta.gid,
+            nlink,
+            rdev: 0,
+            size: meta.size,
+            blksize: u64::from(inner.header.block_size),
+            blocks: meta.size.div_ceil(512),
+            atime: split_time(meta.accessed_at),
+            mtime: split_time(meta.modified_at),
+            ctime: split_time(meta.changed_at),
+            btime: split_time(meta.created_at),
+            generation: 0,
+            data_version: 0,
+        })
+    }
+
+    fn setattr(&mut self, inode: InodeId, attr: &SetAttr) -> Result<()> {
+        let path = self.path(inode)?;
+        if attr.valid & setattr::MODE != 0 {
+            self.vfs.set_permissions(&path, attr.mode & 0o7777)?;
+        }
+        let uid = (attr.valid & setattr::UID != 0).then_some(attr.uid);
+        let gid = (attr.valid & setattr::GID != 0).then_some(attr.gid);
+        if uid.is_some() || gid.is_some() {
+            self.vfs.chown(&path, uid, gid)?;
+        }
+        if attr.valid & setattr::SIZE != 0 {
+            self.vfs.open_rw(&path)?.set_len(attr.size)?;
+        }
+        // fara *_SET timpul devine "acum", dupa ceasul montarii
+        let now = self.vfs.inner.borrow().options.clock.now();
+        let time = |bit, set, value| {
+            (attr.valid & bit != 0).then(|| {
+                if attr.valid & set != 0 {
+                    join_time(value)
+                } else {
+                    now
+                }
+            })
+        };
+        let times = FileTimes {
+            created_at: None,
+            accessed_at: time(setattr::ATIME, setattr::ATIME_SET, attr.atime),
+            modified_at: time(setattr::MTIME, setattr::MTIME_SET, attr.mtime),
+        };
+        if times.accessed_at.is_some() || times.modified_at.is_some() {
+            self.vfs.set_times(&path, times)?;
+        }
+        Ok(())
+    }
+
+    // ca rename(2): o destinatie existenta de acelasi tip e inlocuita
+    fn rename(&mut self, from: &VfsPath, to: &VfsPath) -> Result<()> {
+        let source = self.kind(self.resolve(from)?)?;
+        if let Ok(target) = self.resolve(to) {
+            if self.resolve(from)? == target {
+                return Ok(());
+            }
+            match (source, self.kind(target)?) {
+                (NodeKind::File, NodeKind::File) => self.vfs.remove_file(to)?,
+                (NodeKind::Dir, NodeKind::Dir) => self.vfs.remove_dir(to)?,
+                (NodeKind::File, NodeKind::Dir) => {
+                    return Err(VfsError::NotAFile(to.to_string()));
+                }
+                (NodeKind::Dir, NodeKind::File) => {
+                    return Err(VfsError::NotADir(to.to_string()));
+                }
+            }
+        }
+        self.vfs.rename(from, to)
+    }
+
+    fn statfs(&self) -> Result<Rmsg> {
+        let stats = self.vfs.stats()?;
+        let bsize = self.vfs.inner.borrow().header.block_size;
+        let blocks = stats.image_bytes.div_ceil(u64::from(bsize));
+        // imaginea creste dupa nevoie; spatiul liber e cat mai are discul gazda,
+        // pe care nu-l stim, asa ca raportam doar ce e ocupat
+        Ok(Rmsg::Statfs(StatFs {
+            kind: V9FS_MAGIC,
+            bsize,
+            blocks,
+            bfree: 0,
+            bavail: 0,
+            files: stats.files + stats.dirs,
+            ffree: 0,
+            fsid: 0,
+            namelen: 255,
+        }))
+    }
+}
+
+// fid necunoscut, deja folosit sau nedeschis
+fn bad_fid(_fid: u32) -> VfsError {
+    VfsError::Io(std::io::Error::from_raw_os_error(EBADF as i32))
+}
+
+fn qid_of(node: &Inode) -> Qid {
+    Qid {
+        kind: match node.kind {
+            NodeKind::Dir => QTDIR,
+            NodeKind::File => QTFILE,
+        },
+        // se schimba la fiecare modificare de continut
+        version: node.metadata.modified_at.0 as u32,
+        path: node.id.0,
+    }
+}
+
+fn split_time(t: Timestamp) -> (u64, u64) {
+    (
+        t.0.div_euclid(1_000_000_000).max(0) as u64,
+        t.0.rem_euclid(1_000_000_000) as u64,
+    )
+}
+
+fn join_time((sec, nsec): (u64, u64)) -> Timestamp {
+    Timestamp(i128::from(sec) * 1_000_000_000 + i128::from(nsec))
+}
+
+/// errno-ul linux pt o eroare din vfs.
+fn errno(err: &VfsError) -> u32 {
+    match err {
+        VfsError::NotFound(_) => ENOENT,
+        VfsError::AlreadyExists(_) => EEXIST,
+        VfsError::NotAFile(_) => EISDIR,
+        VfsError::NotADir(_) => ENOTDIR,
+        VfsError::InvalidPath(m) if m.contains("not empty") => ENOTEMPTY,
+        VfsError::InvalidPath(m) if m.contains("root") => EPERM,
+        VfsError::InvalidPath(_) => EINVAL,
+        VfsError::NoSpace(_) => ENOSPC,
+        VfsError::PermissionDenied(_) => EACCES,
+        VfsError::ReadOnly(_) => EROFS,
+        VfsError::Unsupported(_) => EOPNOTSUPP,
+        VfsError::CrossesMount(_) => EXDEV,
+        VfsError::Io(e) if e.kind() == std::io::ErrorKind::InvalidData => EINVAL,
+        VfsError::Io(e) => e.raw_os_error().map_or(EIO, |c| c as u32),
+        _ => EIO,
+    }
+}
+
+// ---------- client ----------
+
+/// client 9P2000.L minimal, sincron: un mesaj pe rand.
+pub struct Client<S: Read + Write> {
+    stream: S,
+    msize: u32,
+    tag: u16,
+}
+
+impl<S: Read + Write> Client<S> {
+    /// negociaza versiunea (Tversion) pe un transport deja conectat.
+    pub fn connect(stream: S) -> Result<Self> {
+        let mut client = Self {
+            stream,
+            msize: MAX_MSIZE,
+            tag: 0,
+        };
+        let reply = client.send(
+            NOTAG,
+            &Tmsg::Version {
+                msize: MAX_MSIZE,
+                version: VERSION.into(),
+            },
+        )?;
+        match reply {
+            Rmsg::Version { msize, version } if version == VERSION => client.msize = msize,
+            other => return Err(unexpected(&other)),
+        }
+        Ok(client)
+    }
+
+    pub fn msize(&self) -> u32 {
+        self.msize
+    }
+
+    /// trimite un mesaj si asteapta raspunsul; un Rlerror devine `VfsError::Io`
+    /// cu errno-ul primit.
+    pub fn call(&mut self, msg: &Tmsg) -> Result<Rmsg> {
+        self.tag = self.tag.wrapping_add(1) % NOTAG;
+        self.send(self.tag, msg)
+    }
+
+    fn send(&mut self, tag: u16, msg: &Tmsg) -> Result<Rmsg> {
+        self.stream.write_all(&msg.encode(tag))?;
+        self.stream.flush()?;
+        let frame = read_frame(&mut self.stream, self.msize)?
+            .ok_or_else(|| protocol_error("connection closed by the server"))?;
+        let (reply_tag, reply) = Rmsg::decode(&frame)?;
+        if reply_tag != tag {
+            return Err(protocol_error(format!(
+                "reply tag {reply_tag}, expected {tag}"
+            )));
+        }
+        match reply {
+            Rmsg::Lerror { ecode } => Err(VfsError::Io(std::io::Error::from_raw_os_error(
+                ecode as i32,
+            ))),
+            reply => Ok(reply),
+        }
+    }
+
+    pub fn attach(&mut self, fid: u32) -> Result<Qid> {
+        let msg = Tmsg::Attach {
+            fid,
+            afid: NOFID,
+            uname: String::new(),
+            aname: String::new(),
+            n_uname: NOFID,
+        };
+        match self.call(&msg)? {
+            Rmsg::Attach { qid } => Ok(qid),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    /// `names` relativ la `fid`; reuseste doar daca s-a ajuns pana la capat.
+    pub fn walk(&mut self, fid: u32, newfid: u32, names: &[&str]) -> Result<Vec<Qid>> {
+        let msg = Tmsg::Walk {
+            fid,
+            newfid,
+            names: names.iter().map(|n| n.to_string()).collect(),
+        };
+        match self.call(&msg)? {
+            Rmsg::Walk { qids } if qids.len() == names.len() => Ok(qids),
+            Rmsg::Walk { .. } => Err(VfsError::NotFound(names.join("/"))),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    pub fn lopen(&mut self, fid: u32, flags: u32) -> Result<Qid> {
+        match self.call(&Tmsg::Lopen { fid, flags })? {
+            Rmsg::Lopen { qid, .. } => Ok(qid),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    pub fn lcreate(&mut self, fid: u32, name: &str, flags: u32, mode: u32) -> Result<Qid> {
+        let msg = Tmsg::Lcreate {
+            fid,
+            name: name.into(),
+            flags,
+            mode,
+            gid: 0,
+        };
+        match self.call(&msg)? {
+            Rmsg::Lcreate { qid, .. } => Ok(qid),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    pub fn read(&mut self, fid: u32, offset: u64, count: u32) -> Result<Vec<u8>> {
+        match self.call(&Tmsg::Read { fid, offset, count })? {
+            Rmsg::Read { data } => Ok(data),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    pub fn write(&mut self, fid: u32, offset: u64, data: &[u8]) -> Result<u32> {
+        let msg = Tmsg::Write {
+            fid,
+            offset,
+            data: data.to_vec(),
+        };
+        match self.call(&msg)? {
+            Rmsg::Write { count } => Ok(count),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    /// tot directorul, cu atatea Treaddir cate trebuie.
+    pub fn readdir_all(&mut self, fid: u32) -> Result<Vec<Dirent>> {
+        let mut out: Vec<Dirent> = Vec::new();
+        loop {
+            let offset = out.last().map_or(0, |e| e.offset);
+            let msg = Tmsg::Readdir {
+                fid,
+                offset,
+                count: self.msize - IOHDRSZ,
+            };
+            let page = match self.call(&msg)? {
+                Rmsg::Readdir { data } => Dirent::decode_all(&data)?,
+                other => return Err(unexpected(&other)),
+            };
+            if page.is_empty() {
+                return Ok(out);
+            }
+            out.extend(page);
+        }
+    }
+
+    pub fn getattr(&mut self, fid: u32) -> Result<Attr> {
+        match self.call(&Tmsg::Getattr {
+            fid,
+            mask: GETATTR_BASIC,
+        })? {
+            Rmsg::Getattr(attr) => Ok(attr),
+            other => Err(unexpected(&other)),
+        }
+    }
+
+    pub fn clunk(&mut self, fid: u32) -> Result<()> {
+        match self.call(&Tmsg::Clunk { fid })? {
+            Rmsg::Clunk => Ok(()),
+            other => Err(unexpected(&other)),
+        }
+    }
+}
+
+fn unexpected(reply: &Rmsg) -> VfsError {
+    protocol_error(format!("unexpected 9p reply: {reply:?}"))
+}
@@ -1,13 +1,44 @@
 use crate::VfsError;
+use crate::codec::{algo_from_id, algo_id};
+use crate::crypt::{Cipher, TAG_LEN};
+use crate::path::VfsPath;
 use crate::structs::*;
 use crc32fast::Hasher;
 use std::fs::File;
 use std::io::{Read, Seek, SeekFrom, Write};
 
-const RECORD_MAGIC: &[u8; 4] = b"VFSR";
+pub(crate) const RECORD_MAGIC: &[u8; 4] = b"VFSR";
 const HEADER_MAGIC: &[u8; 8] = &[67u8, 67u8, 67u8, 67u8, 67u8, 67u8, 67u8, 67u8];
-const VERSION: u32 = 1;
-const HEADER_LEN: u64 = 24; //aproape cum aveam pt superblock 8 magic 4 version 4 bsize 8 root
+pub const VERSION: u32 = 8;
+// 8 magic 4 version 4 bsize 8 root 1 compresie 1 criptare 2 rezervat 4 generatie
+// 16 id imagine 32 key check; generatia a fost initial rezervata, deci e 0 in v8 vechi
+pub const HEADER_LEN: u64 = 80;
+
+// ce a adus fiecare versiune; imaginile mai vechi se citesc cu valori implicite
+// pt campurile lipsa, dar se scriu doar dupa `Vfs::upgrade`
+const V_XATTRS: u32 = 2;
+const V_PERMISSIONS: u32 = 3;
+const V_ATIME: u32 = 4;
+const V_TRASH: u32 = 5;
+const V_DEDUP: u32 = 6;
+const V_COMPRESSION: u32 = 7;
+const V_ENCRYPTION: u32 = 8;
+
+/// lungimea header-ului (si offset-ul primului record) pt o versiune a formatului.
+pub fn header_len(version: u32) -> u64 {
+    match version {
+        V_ENCRYPTION.. => HEADER_LEN,
+        V_COMPRESSION => 32,
+        _ => 24,
+    }
+}
+
+impl Header {
+    /// offset-ul primului record din log.
+    pub fn log_start(&self) -> u64 {
+        header_len(self.version)
+    }
+}
 
 pub struct Encoder {
     buf: Vec<u8>,
@@ -128,11 +159,36 @@ pub fn crc32(data: &[u8]) -> u32 {
 }
 
 pub fn write_header(file: &mut File, block_size: u32, root: InodeId) -> Result<()> {
+    write_image_header(
+        file,
+        &Header {
+            magic: *HEADER_MAGIC,
+            version: VERSION,
+            block_size,
+            root,
+            compression: Compression::None,
+            encryption: None,
+            generation: 0,
+        },
+    )
+}
+
+pub fn write_image_header(file: &mut File, header: &Header) -> Result<()> {
     let mut e = Encoder::new();
     e.buf.extend_from_slice(HEADER_MAGIC);
     e.put_u32(VERSION);
-    e.put_u32(block_size);
-    e.put_u64(root.0);
+    e.put_u32(header.block_size);
+    e.put_u64(header.root.0);
+    e.put_u8(algo_id(header.compression));
+    e.put_u8(header.encryption.is_some() as u8);
+    e.buf.extend_from_slice(&[0u8; 2]);
+    e.put_u32(header.generation);
+    let enc = header.encryption.unwrap_or(HeaderEncryption {
+        image_id: [0; 16],
+        key_check: [0; 32],
+    });
+    e.buf.extend_from_slice(&enc.image_id);
+    e.buf.extend_from_slice(&enc.key_check);
 
     let bytes = e.into_inner();
     file.seek(SeekFrom::Start(0))?;
@@ -143,25 +199,54 @@ pub fn write_header(file: &mut File, block_size: u32, root: InodeId) -> Result<(
 
 pub fn read_header(file: &mut File) -> Result<Header> {
     file.seek(SeekFrom::Start(0))?;
-    let mut buf = vec![0u8; HEADER_LEN as usize];
+    let mut buf = vec![0u8; 12];
     file.read_exact(&mut buf)?;
 
     if &buf[0..8] != HEADER_MAGIC {
         return Err(VfsError::CorruptLog("invalid header magic".into()));
     }
-    let mut d = Decoder::new(&buf[8..]);
-    let version = d.get_u32()?;
-    if version != VERSION {
+    let version = u32::from_le_bytes([buf[8], buf[9], buf[10], buf[11]]);
+    if version == 0 || version > VERSION {
         return Err(VfsError::UnsupportedVersion(version));
     }
+    buf.resize(header_len(version) as usize, 0);
+    file.read_exact(&mut buf[12..])?;
+
+    let mut d = Decoder::new(&buf[12..]);
     let block_size = d.get_u32()?;
     let root = InodeId(d.get_u64()?);
+    let compression = if version >= V_COMPRESSION {
+        algo_from_id(d.get_u8()?)?
+    } else {
+        Compression::None
+    };
+    let (encrypted, generation) = if version >= V_ENCRYPTION {
+        (buf[25], u32::from_le_bytes(buf[28..32].try_into().unwrap()))
+    } else {
+        (0, 0)
+    };
+    let encryption = match encrypted {
+        0 => None,
+        1 => {
+            let mut enc = HeaderEncryption {
+                image_id: [0; 16],
+                key_check: [0; 32],
+            };
+            enc.image_id.copy_from_slice(&buf[32..48]);
+            enc.key_check.copy_from_slice(&buf[48..80]);
+            Some(enc)
+        }
+        n => return Err(VfsError::CorruptLog(format!("unknown encryption {n}"))),
+    };
 
     Ok(Header {
         magic: *HEADER_MAGIC,
         version,
         block_size,
         root,
+        compression,
+        encryption,
+        generation,
     })
 }
 
@@ -169,13 +254,42 @@ fn encode_extent(e: &mut Encoder, ex: &Extent) {
     e.put_u64(ex.logical_offset);
     e.put_u64(ex.file_offset);
     e.put_u64(ex.len);
+    match ex.encoding {
+        Encoding::Raw => e.put_u8(0),
+        Encoding::Compressed {
+            algo,
+            stored_len,
+            skip,
+        } => {
+            e.put_u8(1);
+            e.put_u8(algo_id(algo));
+            e.put_u64(stored_len);
+            e.put_u64(skip);
+        }
+    }
 }
 
-fn decode_extent(d: &mut Decoder<'_>) -> Result<Extent> {
+fn decode_extent(d: &mut Decoder<'_>, version: u32) -> Result<Extent> {
+    let logical_offset = d.get_u64()?;
+    let file_offset = d.get_u64()?;
+    let len = d.get_u64()?;
+    if version < V_COMPRESSION {
+        return Ok(Extent::raw(logical_offset, file_offset, len));
+    }
+    let encoding = match d.get_u8()? {
+        0 => Encoding::Raw,
+        1 => Encoding::Compressed {
+            algo: algo_from_id(d.get_u8()?)?,
+            stored_len: d.get_u64()?,
+            skip: d.get_u64()?,
+        },
+        _ => return Err(VfsError::CorruptLog("bad extent encoding".into())),
+    };
     Ok(Extent {
-        logical_offset: d.get_u64()?,
-        file_offset: d.get_u64()?,
-        len: d.get_u64()?,
+        logical_offset,
+        file_offset,
+        len,
+        encoding,
     })
 }
 
@@ -203,15 +317,27 @@ fn encode_inode_snapshot(e: &mut Encoder, snap: &InodeSnapshot) {
     e.put_u64(snap.metadata.size);
     e.put_i128(snap.metadata.created_at.0);
     e.put_i128(snap.metadata.modified_at.0);
+    e.put_i128(snap.metadata.accessed_at.0);
+    e.put_i128(snap.metadata.changed_at.0);
+    e.put_u32(snap.metadata.mode);
+    e.put_u32(snap.metadata.uid);
+    e.put_u32(snap.metadata.gid);
 
     // extents
     e.put_u64(snap.extents.len() as u64);
     for ex in &snap.extents {
         encode_extent(e, ex);
     }
+
+    // xattrs
+    e.put_u64(snap.xattrs.len() as u64);
+    for (name, value) in &snap.xattrs {
+        e.put_string(name);
+        e.put_bytes(value);
+    }
 }
 
-fn decode_inode_snapshot(d: &mut Decoder<'_>) -> Result<InodeSnapshot> {
+fn decode_inode_snapshot(d: &mut Decoder<'_>, version: u32) -> Result<InodeSnapshot> {
     let id = InodeId(d.get_u64()?);
 
     let parent = match d.get_u8()? {
@@ -231,11 +357,39 @@ fn decode_inode_snapshot(d: &mut Decoder<'_>) -> Result<InodeSnapshot> {
     let size = d.get_u64()?;
     let created_at = Timestamp(d.get_i128()?);
     let modified_at = Timestamp(d.get_i128()?);
+    // inainte de atime/ctime, ambele sunt ultima modificare
+    let (accessed_at, changed_at) = if version >= V_ATIME {
+        (Timestamp(d.get_i128()?), Timestamp(d.get_i128()?))
+    } else {
+        (modified_at, modified_at)
+    };
+    // inainte de permisiuni totul era al lui root, cu modurile implicite
+    let (mode, uid, gid) = if version >= V_PERMISSIONS {
+        (d.get_u32()?, d.get_u32()?, d.get_u32()?)
+    } else {
+        let mode = match kind {
+            NodeKind::File => DEFAULT_FILE_MODE,
+            NodeKind::Dir => DEFAULT_DIR_MODE,
+        };
+        (mode, 0, 0)
+    };
 
     let extent_count = d.get_u64()? as usize;
     let mut extents: ExtentList = Vec::with_capacity(extent_count);
     for _ in 0..extent_count {
-        extents.push(decode_extent(d)?);
+        extents.push(decode_extent(d, version)?);
+    }
+
+    let xattr_count = if version >= V_XATTRS {
+        d.get_u64()? as usize
+    } else {
+        0
+    };
+    let mut xattrs = XattrMap::new();
+    for _ in 0..xattr_count {
+        let name = d.get_string()?;
+        let value = d.get_bytes()?.to_vec();
+        xattrs.insert(name, value);
     }
 
     Ok(InodeSnapshot {
@@ -247,8 +401,14 @@ fn decode_inode_snapshot(d: &mut Decoder<'_>) -> Result<InodeSnapshot> {
             size,
             created_at,
             modified_at,
+            accessed_at,
+            changed_at,
+            mode,
+            uid,
+            gid,
         },
         extents,
+        xattrs,
     })
 }
 
@@ -279,7 +439,7 @@ fn decode_dir_entry(d: &mut Decoder<'_>) -> Result<DirEntry> {
     })
 }
 
-pub fn write_record(file: &mut File, record: &Record) -> Result<u64> {
+fn encode_record(record: &Record) -> Result<Vec<u8>> {
     let mut e = Encoder::new();
 
     match record {
@@ -299,9 +459,15 @@ pub fn write_record(file: &mut File, record: &Record) -> Result<u64> {
             inode,
             created_at,
             modified_at,
+            accessed_at,
+            changed_at,
         } => {
             e.put_u8(5);
-            encode_set_times(&mut e, *inode, created_at, modified_at);
+            e.put_u64(inode.0);
+            encode_opt_timestamp(&mut e, created_at);
+            encode_opt_timestamp(&mut e, modified_at);
+            encode_opt_timestamp(&mut e, accessed_at);
+            encode_opt_timestamp(&mut e, changed_at);
         }
         Record::DirEntryRemove {
             parent,
@@ -325,6 +491,63 @@ pub fn write_record(file: &mut File, record: &Record) -> Result<u64> {
             e.put_u8(8);
             encode_checkpoint(&mut e, cp);
         }
+        Record::SetXattr { inode, name, value } => {
+            e.put_u8(9);
+            encode_set_xattr(&mut e, *inode, name, value);
+        }
+        Record::RemoveXattr { inode, name } => {
+            e.put_u8(10);
+            encode_remove_xattr(&mut e, *inode, name);
+        }
+        Record::SetPermissions { inode, mode } => {
+            e.put_u8(11);
+            e.put_u64(inode.0);
+            e.put_u32(*mode);
+        }
+        Record::Chown { inode, uid, gid } => {
+            e.put_u8(12);
+            e.put_u64(inode.0);
+            e.put_u32(*uid);
+            e.put_u32(*gid);
+        }
+        Record::Snapshot { name, created_at } => {
+            e.put_u8(13);
+            e.put_string(name);
+            e.put_i128(created_at.0);
+        }
+        Record::SnapshotDelete { name } => {
+            e.put_u8(15);
+            e.put_string(name);
+        }
+        Record::Trash {
+            parent,
+            name,
+            entry,
+        } => {
+            e.put_u8(16);
+            e.put_u64(parent.0);
+            e.put_string(name);
+            encode_trash_entry(&mut e, entry);
+        }
+        Record::TrashRestore {
+            inode,
+            parent,
+            name,
+        } => {
+            e.put_u8(17);
+            e.put_u64(inode.0);
+            e.put_u64(parent.0);
+            e.put_string(name);
+        }
+        Record::TrashPurge { inode } => {
+            e.put_u8(18);
+            e.put_u64(inode.0);
+        }
+        Record::DataRef { inode, extent } => {
+            e.put_u8(19);
+            e.put_u64(inode.0);
+            encode_extent(&mut e, extent);
+        }
         _ => {
             return Err(VfsError::CorruptLog(
                 "write_record: record not implemented".into(),
@@ -332,7 +555,24 @@ pub fn write_record(file: &mut File, record: &Record) -> Result<u64> {
         }
     }
 
-    let payload = e.into_inner();
+    Ok(e.into_inner())
+}
+
+pub fn write_record(file: &mut File, record: &Record) -> Result<u64> {
+    write_record_with(file, record, None)
+}
+
+/// scrie record-ul la finalul fisierului; cu `cipher`, payload-ul e sigilat
+/// cu offset-ul record-ului. intoarce offset-ul record-ului.
+pub fn write_record_with(file: &mut File, record: &Record, cipher: Option<&Cipher>) -> Result<u64> {
+    let off = file.seek(SeekFrom::End(0))?;
+    let mut payload = encode_record(record)?;
+    if let Some(cipher) = cipher {
+        let sealed = cipher.seal(off, &payload)?;
+        payload.clear();
+        payload.push(TAG_SEALED);
+        payload.extend_from_slice(&sealed);
+    }
     let payload_len = payload.len() as u64;
 
     let mut scratch = Vec::with_capacity(12 + payload.len());
@@ -342,7 +582,6 @@ pub fn write_record(file: &mut File, record: &Record) -> Result<u64> {
 
     let header_crc = crc32(&scratch);
 
-    let off = file.seek(SeekFrom::End(0))?;
     file.write_all(&scratch)?;
     file.write_all(&header_crc.to_le_bytes())?;
     file.flush()?;
@@ -350,7 +589,124 @@ pub fn write_record(file: &mut File, record: &Record) -> Result<u64> {
     Ok(off)
 }
 
+fn decode_record(payload: &[u8], version: u32) -> Result<Record> {
+    let mut d = Decoder::new(payload);
+    let tag = d.get_u8()?;
+    let record = match tag {
+        1 => Record::InodeAlloc(decode_inode_snapshot(&mut d, version)?),
+        2 => Record::DirEntryAdd {
+            entry: decode_dir_entry(&mut d)?,
+        },
+        4 => {
+            let (inode, len) = decode_truncate(&mut d)?;
+            Record::Truncate { inode, len }
+        }
+        5 if version < V_ATIME => Record::SetTimes {
+            inode: InodeId(d.get_u64()?),
+            created_at: decode_opt_timestamp(&mut d)?,
+            modified_at: decode_opt_timestamp(&mut d)?,
+            accessed_at: None,
+            changed_at: None,
+        },
+        5 => Record::SetTimes {
+            inode: InodeId(d.get_u64()?),
+            created_at: decode_opt_timestamp(&mut d)?,
+            modified_at: decode_opt_timestamp(&mut d)?,
+            accessed_at: decode_opt_timestamp(&mut d)?,
+            changed_at: decode_opt_timestamp(&mut d)?,
+        },
+        6 => {
+            let (parent, name, inode) = decode_dir_entry_remove(&mut d)?;
+            Record::DirEntryRemove {
+                parent,
+                name,
+                inode,
+            }
+        }
+        7 => {
+            let (inode, old_parent, new_parent, old_name, new_name) = decode_rename(&mut d)?;
+            Record::Rename {
+                inode,
+                old_parent,
+                new_parent,
+                old_name,
+                new_name,
+            }
+        }
+        8 => {
+            let cp = decode_checkpoint(&mut d, version)?;
+            Record::Checkpoint(cp)
+        }
+        9 => {
+            let (inode, name, value) = decode_set_xattr(&mut d)?;
+            Record::SetXattr { inode, name, value }
+        }
+        10 => {
+            let (inode, name) = decode_remove_xattr(&mut d)?;
+            Record::RemoveXattr { inode, name }
+        }
+        11 => Record::SetPermissions {
+            inode: InodeId(d.get_u64()?),
+            mode: d.get_u32()?,
+        },
+        12 => Record::Chown {
+            inode: InodeId(d.get_u64()?),
+            uid: d.get_u32()?,
+            gid: d.get_u32()?,
+        },
+        13 => Record::Snapshot {
+            name: d.get_string()?,
+            created_at: Timestamp(d.get_i128()?),
+        },
+        15 => Record::SnapshotDelete {
+            name: d.get_string()?,
+        },
+        16 => Record::Trash {
+            parent: InodeId(d.get_u64()?),
+            name: d.get_string()?,
+            entry: decode_trash_entry(&mut d)?,
+        },
+        17 => Record::TrashRestore {
+            inode: InodeId(d.get_u64()?),
+            parent: InodeId(d.get_u64()?),
+            name: d.get_string()?,
+        },
+        18 => Record::TrashPurge {
+            inode: InodeId(d.get_u64()?),
+        },
+        19 => Record::DataRef {
+            inode: InodeId(d.get_u64()?),
+            extent: decode_extent(&mut d, version)?,
+        },
+        _ => return Err(VfsError::CorruptLog("unexpected tag".into())),
+    };
+    if !d.is_eof() {
+        return Err(VfsError::CorruptLog("trailing bytes".into()));
+    }
+    Ok(record)
+}
+
 pub fn read_next_record(file: &mut File, offset: u64) -> Result<Option<(DecodedRecord, u64)>> {
+    read_next_record_with(file, offset, None)
+}
+
+/// citeste record-ul de la `offset`. o imagine criptata (`cipher`) contine
+/// doar record-uri sigilate. `Ok(None)` = coada log-ului (EOF sau record scris pe jumatate).
+pub fn read_next_record_with(
+    file: &mut File,
+    offset: u64,
+    cipher: Option<&Cipher>,
+) -> Result<Option<(DecodedRecord, u64)>> {
+    read_versioned_record(file, offset, VERSION, cipher)
+}
+
+/// ca `read_next_record_with`, pt log-ul unei imagini cu formatul `version`.
+pub fn read_versioned_record(
+    file: &mut File,
+    offset: u64,
+    version: u32,
+    cipher: Option<&Cipher>,
+) -> Result<Option<(DecodedRecord, u64)>> {
     file.seek(SeekFrom::Start(offset))?;
 
     let mut magic = [0u8; 4];
@@ -362,7 +718,9 @@ pub fn read_next_record(file: &mut File, offset: u64) -> Result<Option<(DecodedR
     }
 
     let mut len_buf = [0u8; 8];
-    file.read_exact(&mut len_buf)?;
+    if file.read_exact(&mut len_buf).is_err() {
+        return Ok(None);
+    }
     let rec_len = u64::from_le_bytes(len_buf);
 
     // poziția imediat după rec_len
@@ -375,8 +733,17 @@ pub fn read_next_record(file: &mut File, offset: u64) -> Result<Option<(DecodedR
     }
     let tag = tag_buf[0];
 
+    let sealed = matches!(tag, TAG_SEALED | TAG_SEALED_DATA);
+    if sealed != cipher.is_some() {
+        return Err(VfsError::CorruptLog(if sealed {
+            "sealed record in a plain image".into()
+        } else {
+            "plain record in an encrypted image".into()
+        }));
+    }
+
     match tag {
-        1 | 2 | 4 | 5 | 6 | 7 | 8 => {
+        1 | 2 | 4 | 5 | 6 | 7 | 8 | 9 | 10 | 11 | 12 | 13 | 15 | 16 | 17 | 18 | 19 | TAG_SEALED => {
             // Pentru record-uri “mici”: citim tot body-ul rămas în memorie
             // Am consumat deja 1 byte pt tag deci mai rămân rec_len - 1 bytes
             let remaining = (rec_len as usize)
@@ -411,129 +778,71 @@ pub fn read_next_record(file: &mut File, offset: u64) -> Result<Option<(DecodedR
                 return Err(VfsError::CorruptLog("crc mismatch".into()));
             }
 
-            let mut d = Decoder::new(&payload);
-            let tag2 = d.get_u8()?;
-            let record = match tag2 {
-                1 => Record::InodeAlloc(decode_inode_snapshot(&mut d)?),
-                2 => Record::DirEntryAdd {
-                    entry: decode_dir_entry(&mut d)?,
-                },
-                4 => {
-                    let (inode, len) = decode_truncate(&mut d)?;
-                    Record::Truncate { inode, len }
-                }
-                5 => {
-                    let (inode, created_at, modified_at) = decode_set_times(&mut d)?;
-                    Record::SetTimes {
-                        inode,
-                        created_at,
-                        modified_at,
-                    }
-                }
-                6 => {
-                    let (parent, name, inode) = decode_dir_entry_remove(&mut d)?;
-                    Record::DirEntryRemove {
-                        parent,
-                        name,
-                        inode,
-                    }
-                }
-                7 => {
-                    let (inode, old_parent, new_parent, old_name, new_name) =
-                        decode_rename(&mut d)?;
-                    Record::Rename {
-                        inode,
-                        old_parent,
-                        new_parent,
-                        old_name,
-                        new_name,
-                    }
-                }
-                8 => {
-                    let cp = decode_checkpoint(&mut d)?;
-                    Record::Checkpoint(cp)
-                }
-                _ => return Err(VfsError::CorruptLog("unexpected tag".into())),
+            let record = match cipher {
+                Some(cipher) => decode_record(&cipher.open(offset, &payload[1..])?, version)?,
+                None => decode_record(&payload, version)?,
             };
-            if !d.is_eof() {
-                return Err(VfsError::CorruptLog("trailing bytes".into()));
-            }
 
             let next_offset = record_body_start + rec_len + 4;
             Ok(Some((
                 DecodedRecord {
                     record,
                     data_payload_offset: None,
+                    sealed,
                 },
                 next_offset,
             )))
         }
 
-        3 => {
-            // DataWrite: body = [tag][inode u64][logical u64][len u64][data_crc u32][header_crc u32][data bytes]
-
-            let mut hdr = [0u8; 28];
-            if file.read_exact(&mut hdr).is_err() {
+        TAG_DATA_WRITE | TAG_COMPRESSED_WRITE | TAG_BLOB | TAG_SEALED_DATA => {
+            // body = [tag][campuri][header_crc u32][data bytes]; sigilat:
+            // [TAG_SEALED_DATA][len u32][tag + campuri sigilate][header_crc u32][data sigilate]
+            let mut hdr = vec![tag];
+            if tag == TAG_SEALED_DATA {
+                let mut n = [0u8; 4];
+                if file.read_exact(&mut n).is_err() {
+                    return Ok(None);
+                }
+                hdr.extend_from_slice(&n);
+                hdr.resize(5 + u32::from_le_bytes(n) as usize, 0);
+            } else {
+                hdr.resize(1 + data_fields_len(tag), 0);
+            }
+            let start = if sealed { 5 } else { 1 };
+            if file.read_exact(&mut hdr[start..]).is_err() {
                 return Ok(None);
             }
 
-            let inode = InodeId(u64::from_le_bytes(match hdr[0..8].try_into() {
-                Ok(b) => b,
-                Err(_) => return Err(VfsError::CorruptLog("invalid inode bytes".into())),
-            }));
-            let logical_offset = u64::from_le_bytes(match hdr[8..16].try_into() {
-                Ok(b) => b,
-                Err(_) => return Err(VfsError::CorruptLog("invalid logical offset bytes".into())),
-            });
-            let len = u64::from_le_bytes(match hdr[16..24].try_into() {
-                Ok(b) => b,
-                Err(_) => return Err(VfsError::CorruptLog("invalid len bytes".into())),
-            });
-            let data_crc = u32::from_le_bytes(match hdr[24..28].try_into() {
-                Ok(b) => b,
-                Err(_) => return Err(VfsError::CorruptLog("invalid data crc bytes".into())),
-            });
-
-            // header_crc (4 bytes)
             let mut crc_buf = [0u8; 4];
             if file.read_exact(&mut crc_buf).is_err() {
                 return Ok(None);
             }
-            let expected_header_crc = u32::from_le_bytes(crc_buf);
-
-            // Reconstituim payloadu (tag + inode + logical + len + data_crc) și verificăm CRC
-            let mut scratch = Vec::with_capacity(1 + 28);
-            scratch.push(3);
-            scratch.extend_from_slice(&hdr);
-
-            let got_header_crc = crc32(&scratch);
-            if got_header_crc != expected_header_crc {
+            if crc32(&hdr) != u32::from_le_bytes(crc_buf) {
+                // header scris pe jumatate
                 return Ok(None);
             }
 
-            let data_payload_offset = file.stream_position().map_err(VfsError::Io)?;
+            let fields = match cipher {
+                Some(cipher) => cipher.open(offset, &hdr[5..])?,
+                None => hdr,
+            };
+            let (record, mut stored_len) = decode_data_fields(&fields)?;
+            if sealed {
+                stored_len += TAG_LEN;
+            }
 
+            let data_payload_offset = file.stream_position()?;
             let end = file.seek(SeekFrom::End(0))?;
-            let need_end = data_payload_offset.saturating_add(len);
-            if need_end > end {
+            if data_payload_offset.saturating_add(stored_len) > end {
                 return Ok(None);
             }
 
-            // sărim peste data bytes (fără să le citim)
-            file.seek(SeekFrom::Start(need_end))?;
-
-            let record = Record::DataWrite {
-                inode,
-                logical_offset,
-                len,
-                checksum: data_crc,
-            };
-
             let next_offset = record_body_start + rec_len;
             Ok(Some((
                 DecodedRecord {
                     record,
                     data_payload_offset: Some(data_payload_offset),
+                    sealed,
                 },
                 next_offset,
             )))
@@ -542,9 +851,276 @@ pub fn read_next_record(file: &mut File, offset: u64) -> Result<Option<(DecodedR
     }
 }
 
+const TAG_DATA_WRITE: u8 = 3;
+const TAG_BLOB: u8 = 14;
+const TAG_COMPRESSED_WRITE: u8 = 20;
+/// record mic cu payload-ul sigilat.
+const TAG_SEALED: u8 = 21;
+/// record cu date, cu header-ul si datele sigilate separat.
+const TAG_SEALED_DATA: u8 = 22;
+
+// lungimea campurilor de dupa tag, pt record-urile cu date
+fn data_fields_len(tag: u8) -> usize {
+    match tag {
+        TAG_DATA_WRITE => 28,       // inode, logical, len, data_crc
+        TAG_COMPRESSED_WRITE => 37, // inode, logical, len, algo, stored_len, data_crc
+        _ => 12,                    // Blob: len, data_crc
+    }
+}
+
+// (record, bytes stocati dupa header) din tag + campuri
+fn decode_data_fields(fields: &[u8]) -> Result<(Record, u64)> {
+    let mut d = Decoder::new(fields);
+    let tag = d.get_u8()?;
+    let out = match tag {
+        TAG_DATA_WRITE => {
+            let inode = InodeId(d.get_u64()?);
+            let logical_offset = d.get_u64()?;
+            let len = d.get_u64()?;
+            let checksum = d.get_u32()?;
+            let record = Record::DataWrite {
+                inode,
+                logical_offset,
+                len,
+                checksum,
+            };
+            (record, len)
+        }
+        TAG_COMPRESSED_WRITE => {
+            let inode = InodeId(d.get_u64()?);
+            let logical_offset = d.get_u64()?;
+            let len = d.get_u64()?;
+            let algo = algo_from_id(d.get_u8()?)?;
+            let stored_len = d.get_u64()?;
+            let checksum = d.get_u32()?;
+            let record = Record::CompressedWrite {
+                inode,
+                logical_offset,
+                len,
+                algo,
+                stored_len,
+                checksum,
+            };
+            (record, stored_len)
+        }
+        TAG_BLOB => {
+            let len = d.get_u64()?;
+            let checksum = d.get_u32()?;
+            (Record::Blob { len, checksum }, len)
+        }
+        _ => return Err(VfsError::CorruptLog("unexpected data tag".into())),
+    };
+    if !d.is_eof() {
+        return Err(VfsError::CorruptLog("trailing bytes".into()));
+    }
+    Ok(out)
+}
+
+/// scrie un record cu date la pozitia curenta din `w`: `fields` (tag + campuri),
+/// crc-ul lor, apoi `data`. intoarce offset-ul datelor.
+fn write_data_frame<W: Write + Seek>(
+    w: &mut W,
+    fields: &[u8],
+    data: &[u8],
+    cipher: Option<&Cipher>,
+) -> Result<u64> {
+    let Some(cipher) = cipher else {
+        let header_crc = crc32(fields);
+        // rec_len = campuri + header_crc(4) + data bytes
+        let rec_len = fields.len() as u64 + 4 + data.len() as u64;
+
+        w.write_all(RECORD_MAGIC)?;
+        w.write_all(&rec_len.to_le_bytes())?;
+        w.write_all(fields)?;
+        w.write_all(&header_crc.to_le_bytes())?;
+
+        let data_payload_offset = w.stream_position()?;
+        w.write_all(data)?;
+        return Ok(data_payload_offset);
+    };
+
+    let off = w.stream_position()?;
+    let sealed_fields = cipher.seal(off, fields)?;
+    let mut hdr = Vec::with_capacity(5 + sealed_fields.len());
+    hdr.push(TAG_SEALED_DATA);
+    hdr.extend_from_slice(&(sealed_fields.len() as u32).to_le_bytes());
+    hdr.extend_from_slice(&sealed_fields);
+
+    let data_payload_offset = off + 4 + 8 + hdr.len() as u64 + 4;
+    let sealed_data = cipher.seal(data_payload_offset, data)?;
+    let rec_len = hdr.len() as u64 + 4 + sealed_data.len() as u64;
+
+    w.write_all(RECORD_MAGIC)?;
+    w.write_all(&rec_len.to_le_bytes())?;
+    w.write_all(&hdr)?;
+    w.write_all(&crc32(&hdr).to_le_bytes())?;
+    w.write_all(&sealed_data)?;
+    Ok(data_payload_offset)
+}
+
+/// ca `write_data_write_record`, dar scrie `stored` (datele deja comprimate cu
+/// `algo`); `data_crc` e crc-ul datelor necomprimate. intoarce offset-ul datelor.
+#[allow(clippy::too_many_arguments)]
+pub fn write_compressed_write_record<W: Write + Seek>(
+    w: &mut W,
+    inode: InodeId,
+    logical_offset: u64,
+    len: u64,
+    algo: Compression,
+    stored: &[u8],
+    data_crc: u32,
+    scratch: &mut Vec<u8>,
+    cipher: Option<&Cipher>,
+) -> Result<u64> {
+    scratch.clear();
+    scratch.push(TAG_COMPRESSED_WRITE);
+    scratch.extend_from_slice(&inode.0.to_le_bytes());
+    scratch.extend_from_slice(&logical_offset.to_le_bytes());
+    scratch.extend_from_slice(&len.to_le_bytes());
+    scratch.push(algo_id(algo));
+    scratch.extend_from_slice(&(stored.len() as u64).to_le_bytes());
+    scratch.extend_from_slice(&data_crc.to_le_bytes());
+
+    write_data_frame(w, scratch, stored, cipher)
+}
+
+/// copiaza `len` bytes din `src` (de la `src_off`) ca un record Blob la finalul lui `w`.
+/// intoarce offset-ul la care incep datele in `w`.
+pub fn write_blob_record(w: &mut File, src: &mut File, src_off: u64, len: u64) -> Result<u64> {
+    const CHUNK: u64 = 1 << 20;
+    let mut buf = vec![0u8; CHUNK.min(len) as usize];
+
+    // prima trecere: crc peste date, ca sa il punem in header
+    let mut hasher = Hasher::new();
+    src.seek(SeekFrom::Start(src_off))?;
+    let mut left = len;
+    while left > 0 {
+        let n = CHUNK.min(left) as usize;
+        src.read_exact(&mut buf[..n])?;
+        hasher.update(&buf[..n]);
+        left -= n as u64;
+    }
+    let data_crc = hasher.finalize();
+
+    let mut payload = Vec::with_capacity(13);
+    payload.push(TAG_BLOB);
+    payload.extend_from_slice(&len.to_le_bytes());
+    payload.extend_from_slice(&data_crc.to_le_bytes());
+    let header_crc = crc32(&payload);
+    let rec_len = payload.len() as u64 + 4 + len;
+
+    w.seek(SeekFrom::End(0))?;
+    w.write_all(RECORD_MAGIC)?;
+    w.write_all(&rec_len.to_le_bytes())?;
+    w.write_all(&payload)?;
+    w.write_all(&header_crc.to_le_bytes())?;
+    let data_payload_offset = w.stream_position()?;
+
+    // a doua trecere: copiem efectiv
+    src.seek(SeekFrom::Start(src_off))?;
+    let mut left = len;
+    while left > 0 {
+        let n = CHUNK.min(left) as usize;
+        src.read_exact(&mut buf[..n])?;
+        w.write_all(&buf[..n])?;
+        left -= n as u64;
+    }
+
+    Ok(data_payload_offset)
+}
+
+/// `data` ca un record Blob la finalul lui `w`; folosit cand datele trebuie
+/// resigilate (compactarea unei imagini criptate). intoarce offset-ul datelor.
+pub fn write_blob_bytes(w: &mut File, data: &[u8], cipher: Option<&Cipher>) -> Result<u64> {
+    let mut fields = Vec::with_capacity(13);
+    fields.push(TAG_BLOB);
+    fields.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    fields.extend_from_slice(&crc32(data).to_le_bytes());
+
+    w.seek(SeekFrom::End(0))?;
+    write_data_frame(w, &fields, data, cipher)
+}
+
 pub struct DecodedRecord {
     pub record: crate::structs::Record,
     pub data_payload_offset: Option<u64>,
+    /// record dintr-o imagine criptata; datele au `TAG_LEN` bytes in plus.
+    pub sealed: bool,
+}
+
+impl DecodedRecord {
+    /// unde sunt datele unui DataWrite sau CompressedWrite in backing file.
+    pub fn extent(&self) -> Option<Extent> {
+        let file_offset = self.data_payload_offset?;
+        match self.record {
+            Record::DataWrite {
+                logical_offset,
+                len,
+                ..
+            } => Some(data_extent(
+                logical_offset,
+                file_offset,
+                len,
+                Compression::None,
+                len,
+                self.sealed,
+            )),
+            Record::CompressedWrite {
+                logical_offset,
+                len,
+                algo,
+                stored_len,
+                ..
+            } => Some(data_extent(
+                logical_offset,
+                file_offset,
+                len,
+                algo,
+                stored_len,
+                self.sealed,
+            )),
+            _ => None,
+        }
+    }
+
+    /// cati bytes de date ocupa record-ul in backing file, dupa header.
+    pub fn stored_len(&self) -> u64 {
+        let len = match self.record {
+            Record::DataWrite { len, .. } | Record::Blob { len, .. } => len,
+            Record::CompressedWrite { stored_len, .. } => stored_len,
+            _ => return 0,
+        };
+        if self.sealed { len + TAG_LEN } else { len }
+    }
+}
+
+/// extent-ul pt `len` bytes scrisi la `file_offset`: `stored_len` bytes
+/// comprimati cu `algo` si, in imaginile criptate, sigilati.
+pub fn data_extent(
+    logical_offset: u64,
+    file_offset: u64,
+    len: u64,
+    algo: Compression,
+    stored_len: u64,
+    sealed: bool,
+) -> Extent {
+    if algo == Compression::None && !sealed {
+        return Extent::raw(logical_offset, file_offset, len);
+    }
+    Extent {
+        logical_offset,
+        file_offset,
+        len,
+        encoding: Encoding::Compressed {
+            algo,
+            stored_len: if sealed {
+                stored_len + TAG_LEN
+            } else {
+                stored_len
+            },
+            skip: 0,
+        },
+    }
 }
 
 pub fn write_data_write_record<W: Write + Seek>(
@@ -553,9 +1129,8 @@ pub fn write_data_write_record<W: Write + Seek>(
     logical_offset: u64,
     data: &[u8],
     scratch: &mut Vec<u8>,
+    cipher: Option<&Cipher>,
 ) -> Result<(u32, u64)> {
-    const TAG_DATA_WRITE: u8 = 3;
-
     // payload mic (fără data bytes)
     scratch.clear();
     scratch.push(TAG_DATA_WRITE);
@@ -566,24 +1141,7 @@ pub fn write_data_write_record<W: Write + Seek>(
     let data_crc = crc32(data);
     scratch.extend_from_slice(&data_crc.to_le_bytes());
 
-    // CRC peste payload mic
-    let header_crc = crc32(scratch);
-
-    // rec_len = payload_mic + header_crc(4) + data_bytes
-    let rec_len = scratch.len() as u64 + 4 + data.len() as u64;
-
-    // scriem framing + payload + header_crc
-    w.write_all(RECORD_MAGIC).map_err(VfsError::Io)?;
-    w.write_all(&rec_len.to_le_bytes()).map_err(VfsError::Io)?;
-    w.write_all(scratch).map_err(VfsError::Io)?;
-    w.write_all(&header_crc.to_le_bytes())
-        .map_err(VfsError::Io)?;
-
-    // NOW get the offset before writing data
-    let data_payload_offset = w.stream_position()?;
-
-    w.write_all(data).map_err(VfsError::Io)?;
-
+    let data_payload_offset = write_data_frame(w, scratch, data, cipher)?;
     Ok((data_crc, data_payload_offset))
 }
 
@@ -616,26 +1174,6 @@ fn decode_opt_timestamp(d: &mut Decoder<'_>) -> Result<Option<Timestamp>> {
     }
 }
 
-fn encode_set_times(
-    e: &mut Encoder,
-    inode: InodeId,
-    created: &Option<Timestamp>,
-    modified: &Option<Timestamp>,
-) {
-    e.put_u64(inode.0);
-    encode_opt_timestamp(e, created);
-    encode_opt_timestamp(e, modified);
-}
-
-fn decode_set_times(
-    d: &mut Decoder<'_>,
-) -> Result<(InodeId, Option<Timestamp>, Option<Timestamp>)> {
-    let inode = InodeId(d.get_u64()?);
-    let created = decode_opt_timestamp(d)?;
-    let modified = decode_opt_timestamp(d)?;
-    Ok((inode, created, modified))
-}
-
 fn encode_dir_entry_remove(
     e: &mut Encoder,
     parent: crate::structs::InodeId,
@@ -679,6 +1217,31 @@ fn decode_rename(d: &mut Decoder<'_>) -> Result<(InodeId, InodeId, InodeId, Stri
     let new_name = d.get_string()?;
     Ok((inode, old_parent, new_parent, old_name, new_name))
 }
+
+fn encode_set_xattr(e: &mut Encoder, inode: InodeId, name: &str, value: &[u8]) {
+    e.put_u64(inode.0);
+    e.put_string(name);
+    e.put_bytes(value);
+}
+
+fn decode_set_xattr(d: &mut Decoder<'_>) -> Result<(InodeId, String, Vec<u8>)> {
+    let inode = InodeId(d.get_u64()?);
+    let name = d.get_string()?;
+    let value = d.get_bytes()?.to_vec();
+    Ok((inode, name, value))
+}
+
+fn encode_remove_xattr(e: &mut Encoder, inode: InodeId, name: &str) {
+    e.put_u64(inode.0);
+    e.put_string(name);
+}
+
+fn decode_remove_xattr(d: &mut Decoder<'_>) -> Result<(InodeId, String)> {
+    let inode = InodeId(d.get_u64()?);
+    let name = d.get_string()?;
+    Ok((inode, name))
+}
+
 fn encode_checkpoint(e: &mut Encoder, cp: &Checkpoint) {
     e.put_u64(cp.next_inode.0);
 
@@ -693,26 +1256,92 @@ fn encode_checkpoint(e: &mut Encoder, cp: &Checkpoint) {
     for ino in &cp.inodes {
         encode_inode_snapshot(e, ino);
     }
+
+    // trash
+    e.put_u64(cp.trash.len() as u64);
+    for entry in &cp.trash {
+        encode_trash_entry(e, entry);
+    }
+
+    // dedup index
+    e.put_u64(cp.dedup.len() as u64);
+    for entry in &cp.dedup {
+        e.put_u32(entry.checksum);
+        encode_extent(e, &entry.extent);
+    }
 }
 
-fn decode_checkpoint(d: &mut Decoder<'_>) -> Result<Checkpoint> {
+fn decode_checkpoint(d: &mut Decoder<'_>, version: u32) -> Result<Checkpoint> {
     let next_inode = InodeId(d.get_u64()?);
 
     let free_n = d.get_u64()? as usize;
     let mut free_extents = Vec::with_capacity(free_n);
     for _ in 0..free_n {
-        free_extents.push(decode_extent(d)?);
+        free_extents.push(decode_extent(d, version)?);
     }
 
     let inode_n = d.get_u64()? as usize;
     let mut inodes = Vec::with_capacity(inode_n);
     for _ in 0..inode_n {
-        inodes.push(decode_inode_snapshot(d)?);
+        inodes.push(decode_inode_snapshot(d, version)?);
+    }
+
+    let mut trash = Vec::new();
+    if version >= V_TRASH {
+        let trash_n = d.get_u64()? as usize;
+        for _ in 0..trash_n {
+            trash.push(decode_trash_entry(d)?);
+        }
+    }
+
+    let mut dedup = Vec::new();
+    if version >= V_DEDUP {
+        let dedup_n = d.get_u64()? as usize;
+        for _ in 0..dedup_n {
+            let checksum = d.get_u32()?;
+            // in v6 intrarea era (len, file_offset), fara encoding
+            let extent = if version < V_COMPRESSION {
+                let len = d.get_u64()?;
+                Extent::raw(0, d.get_u64()?, len)
+            } else {
+                decode_extent(d, version)?
+            };
+            dedup.push(DedupEntry { checksum, extent });
+        }
     }
 
     Ok(Checkpoint {
         next_inode,
         free_extents,
         inodes,
+        trash,
+        dedup,
+    })
+}
+
+fn encode_trash_entry(e: &mut Encoder, entry: &TrashEntry) {
+    e.put_u64(entry.inode.0);
+    e.put_u8(match entry.kind {
+        NodeKind::File => 1,
+        NodeKind::Dir => 2,
+    });
+    e.put_string(entry.original_path.as_str());
+    e.put_i128(entry.deleted_at.0);
+}
+
+fn decode_trash_entry(d: &mut Decoder<'_>) -> Result<TrashEntry> {
+    let inode = InodeId(d.get_u64()?);
+    let kind = match d.get_u8()? {
+        1 => NodeKind::File,
+        2 => NodeKind::Dir,
+        _ => return Err(VfsError::CorruptLog("bad trash entry kind".into())),
+    };
+    let original_path = VfsPath::new(d.get_string()?)
+        .map_err(|_| VfsError::CorruptLog("bad trash entry path".into()))?;
+    Ok(TrashEntry {
+        inode,
+        kind,
+        original_path,
+        deleted_at: Timestamp(d.get_i128()?),
     })
 }
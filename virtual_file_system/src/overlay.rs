@@ -0,0 +1,532 @@
+//! overlay (union mount) din doua imagini: un `lower` care nu se modifica
+//! niciodata si un `upper` in care ajung toate scrierile.
+//!
+//! conventiile sunt cele din overlayfs: un fisier din lower e copiat in upper la
+//! prima modificare (copy-up); o stergere a ceva ce exista in lower lasa in upper
+//! un fisier gol marcat cu xattr-ul `trusted.overlay.whiteout`; un director din
+//! upper marcat `trusted.overlay.opaque` ascunde tot ce are lower sub el.
+
+use std::collections::{BTreeMap, HashSet};
+use std::io::{Read, Write};
+use std::path::Path;
+
+use crate::file_ops::VfsFile;
+use crate::fs::{FileSystem, FsFile, normalize, replace_by_rename};
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::Vfs;
+
+const WHITEOUT: &str = "trusted.overlay.whiteout";
+const OPAQUE: &str = "trusted.overlay.opaque";
+const COPY_BUF: usize = 1 << 20;
+
+/// vederea combinata a doua imagini; vezi documentatia modulului.
+///
+/// `lower` e doar citit (poate fi montat `read_only`), `upper` primeste copy-up-urile,
+/// fisierele noi si whiteout-urile.
+#[derive(Clone)]
+pub struct OverlayVfs {
+    lower: Vfs,
+    upper: Vfs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Lower,
+    Upper,
+}
+
+enum UpperState {
+    Missing,
+    Whiteout,
+    Node(NodeKind),
+}
+
+// path absolut, cu `..` rezolvat lexical
+fn absolute<P: AsRef<Path>>(path: P) -> Result<VfsPath> {
+    VfsPath::new(format!("/{}", normalize(path.as_ref())?.join("/")))
+}
+
+fn parent_of(path: &VfsPath) -> Result<VfsPath> {
+    path.parent()
+        .ok_or_else(|| VfsError::InvalidPath(format!("path has no parent: {path}")))
+}
+
+fn is_overlay_xattr(name: &str) -> bool {
+    name == WHITEOUT || name == OPAQUE
+}
+
+impl OverlayVfs {
+    pub fn new(lower: Vfs, upper: Vfs) -> Self {
+        Self { lower, upper }
+    }
+
+    pub fn lower(&self) -> &Vfs {
+        &self.lower
+    }
+
+    pub fn upper(&self) -> &Vfs {
+        &self.upper
+    }
+
+    pub fn into_parts(self) -> (Vfs, Vfs) {
+        (self.lower, self.upper)
+    }
+
+    fn layer(&self, layer: Layer) -> &Vfs {
+        match layer {
+            Layer::Lower => &self.lower,
+            Layer::Upper => &self.upper,
+        }
+    }
+
+    fn upper_state(&self, path: &VfsPath) -> Result<UpperState> {
+        match self.upper.kind_of(path.as_ref()) {
+            Err(VfsError::NotFound(_) | VfsError::NotADir(_)) => Ok(UpperState::Missing),
+            Err(e) => Err(e),
+            Ok(NodeKind::File) if self.upper.get_xattr(path, WHITEOUT)?.is_some() => {
+                Ok(UpperState::Whiteout)
+            }
+            Ok(kind) => Ok(UpperState::Node(kind)),
+        }
+    }
+
+    fn is_opaque(&self, path: &VfsPath) -> Result<bool> {
+        Ok(self.upper.get_xattr(path, OPAQUE)?.is_some())
+    }
+
+    // ce spun stramosii lui `path`: `None` = ascuns de un whiteout (sau de un
+    // fisier din upper), altfel daca lower mai e vizibil pana aici
+    fn ancestors(&self, path: &VfsPath) -> Result<Option<bool>> {
+        let mut lower_visible = true;
+        let mut cur = VfsPath::root();
+        for name in path.components() {
+            match self.upper_state(&cur)? {
+                UpperState::Whiteout | UpperState::Node(NodeKind::File) => return Ok(None),
+                UpperState::Node(NodeKind::Dir) if self.is_opaque(&cur)? => lower_visible = false,
+                _ => {}
+            }
+            cur = cur.join(name)?;
+        }
+        Ok(Some(lower_visible))
+    }
+
+    // stratul din care se vede `path`, sau `None` daca nu exista in vederea combinata
+    fn lookup(&self, path: &VfsPath) -> Result<Option<(Layer, NodeKind)>> {
+        let Some(lower_visible) = self.ancestors(path)? else {
+            return Ok(None);
+        };
+        match self.upper_state(path)? {
+            UpperState::Node(kind) => Ok(Some((Layer::Upper, kind))),
+            UpperState::Whiteout => Ok(None),
+            UpperState::Missing if !lower_visible => Ok(None),
+            UpperState::Missing => match self.lower.kind_of(path.as_ref()) {
+                Ok(kind) => Ok(Some((Layer::Lower, kind))),
+                Err(VfsError::NotFound(_) | VfsError::NotADir(_)) => Ok(None),
+                Err(e) => Err(e),
+            },
+        }
+    }
+
+    fn node(&self, path: &VfsPath) -> Result<(Layer, NodeKind)> {
+        self.lookup(path)?
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))
+    }
+
+    // parintele unei intrari noi trebuie sa fie un director vizibil
+    fn check_parent(&self, path: &VfsPath) -> Result<()> {
+        if path.is_root() {
+            return Err(VfsError::InvalidPath("the root has no parent".into()));
+        }
+        match self.lookup(&parent_of(path)?)? {
+            Some((_, NodeKind::Dir)) => Ok(()),
+            Some((_, NodeKind::File)) => Err(VfsError::NotADir(path.to_string())),
+            None => Err(VfsError::NotFound(path.to_string())),
+        }
+    }
+
+    /// copiaza `path` (si directoarele de deasupra) din lower in upper, daca nu e deja acolo.
+    fn copy_up(&self, path: &VfsPath) -> Result<()> {
+        if path.is_root() {
+            return Ok(());
+        }
+        let (layer, kind) = self.node(path)?;
+        if layer == Layer::Upper {
+            return Ok(());
+        }
+        self.copy_up(&parent_of(path)?)?;
+        let mut upper = self.upper.clone();
+        match kind {
+            NodeKind::Dir => upper.create_dir(path)?,
+            NodeKind::File => {
+                let mut src = self.lower.open_file(path)?;
+                let mut dst = upper.create(path)?;
+                copy_data(&mut src, &mut dst)?;
+            }
+        }
+        copy_metadata(&self.lower, &mut upper, path, path)
+    }
+
+    // tot subarborele vizibil ajunge in upper
+    fn copy_up_tree(&self, path: &VfsPath) -> Result<()> {
+        self.copy_up(path)?;
+        if self.node(path)?.1 == NodeKind::Dir {
+            for entry in self.read_dir(path)? {
+                self.copy_up_tree(&path.join(&entry.name)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn whiteout(&self, path: &VfsPath) -> Result<()> {
+        self.copy_up(&parent_of(path)?)?;
+        let mut upper = self.upper.clone();
+        drop(upper.create(path)?);
+        upper.set_xattr(path, WHITEOUT, b"y")
+    }
+
+    // sterge un whiteout de la `path`; true daca era unul
+    fn clear_whiteout(&self, path: &VfsPath) -> Result<bool> {
+        if !matches!(self.upper_state(path)?, UpperState::Whiteout) {
+            return Ok(false);
+        }
+        self.upper.clone().remove_file(path)?;
+        Ok(true)
+    }
+
+    // pregateste locul pt o intrare noua; true daca inlocuieste un whiteout
+    fn prepare_new(&self, path: &VfsPath) -> Result<bool> {
+        if self.lookup(path)?.is_some() {
+            return Err(VfsError::AlreadyExists(path.to_string()));
+        }
+        self.check_parent(path)?;
+        self.copy_up(&parent_of(path)?)?;
+        self.clear_whiteout(path)
+    }
+
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        absolute(path).is_ok_and(|p| matches!(self.lookup(&p), Ok(Some(_))))
+    }
+
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<FsMetadata> {
+        let path = absolute(path)?;
+        let (layer, kind) = self.node(&path)?;
+        Ok(FsMetadata {
+            kind,
+            metadata: self.layer(layer).metadata(&path)?,
+        })
+    }
+
+    /// intrarile din ambele straturi, fara whiteout-uri; upper castiga la nume egale.
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FsEntry>> {
+        let path = absolute(path)?;
+        if self.node(&path)?.1 != NodeKind::Dir {
+            return Err(VfsError::NotADir(path.to_string()));
+        }
+        let mut entries = BTreeMap::new();
+        let mut hidden = HashSet::new();
+        let mut lower_visible = self.ancestors(&path)? == Some(true);
+
+        if let UpperState::Node(NodeKind::Dir) = self.upper_state(&path)? {
+            lower_visible &= !self.is_opaque(&path)?;
+            for entry in self.upper.read_dir(&path)? {
+                let entry = entry?;
+                let child = path.join(&entry.name)?;
+                if matches!(self.upper_state(&child)?, UpperState::Whiteout) {
+                    hidden.insert(entry.name);
+                } else {
+                    entries.insert(entry.name, entry.kind);
+                }
+            }
+        }
+        if lower_visible && self.lower.kind_of(path.as_ref()).ok() == Some(NodeKind::Dir) {
+            for entry in self.lower.read_dir(&path)? {
+                let entry = entry?;
+                if !hidden.contains(&entry.name) {
+                    entries.entry(entry.name).or_insert(entry.kind);
+                }
+            }
+        }
+        Ok(entries
+            .into_iter()
+            .map(|(name, kind)| FsEntry { name, kind })
+            .collect())
+    }
+
+    /// deschide pt citire din stratul in care se vede fisierul.
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = absolute(path)?;
+        let (layer, _) = self.node(&path)?;
+        self.layer(layer).open_file(&path)
+    }
+
+    /// deschide pt scriere; un fisier din lower e copiat intai in upper.
+    pub fn open_rw<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = absolute(path)?;
+        if self.node(&path)?.1 == NodeKind::Dir {
+            return Err(VfsError::NotAFile(path.to_string()));
+        }
+        self.copy_up(&path)?;
+        self.upper.open_rw(&path)
+    }
+
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = absolute(path)?;
+        self.prepare_new(&path)?;
+        self.upper.create(&path)
+    }
+
+    pub fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = absolute(path)?;
+        let replaced = self.prepare_new(&path)?;
+        self.upper.create_dir(&path)?;
+        // in locul unui director sters din lower: continutul vechi nu trebuie sa reapara
+        if replaced {
+            self.upper.set_xattr(&path, OPAQUE, b"y")?;
+        }
+        Ok(())
+    }
+
+    pub fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = absolute(path)?;
+        match self.node(&path)? {
+            (_, NodeKind::Dir) => return Err(VfsError::NotAFile(path.to_string())),
+            (Layer::Upper, _) => self.upper.remove_file(&path)?,
+            (Layer::Lower, _) => {}
+        }
+        if self.lookup(&path)?.is_some() {
+            self.whiteout(&path)?;
+        }
+        Ok(())
+    }
+
+    pub fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = absolute(path)?;
+        if path.is_root() {
+            return Err(VfsError::InvalidPath("cannot remove the root".into()));
+        }
+        let (layer, kind) = self.node(&path)?;
+        if kind != NodeKind::Dir {
+            return Err(VfsError::NotADir(path.to_string()));
+        }
+        if !self.read_dir(&path)?.is_empty() {
+            return Err(VfsError::InvalidPath("directory not empty".into()));
+        }
+        if layer == Layer::Upper {
+            // ce a ramas in upper sunt doar whiteout-uri
+            let whiteouts: Vec<DirEntry> = self.upper.read_dir(&path)?.collect::<Result<_>>()?;
+            for entry in whiteouts {
+                self.upper.remove_file(path.join(&entry.name)?)?;
+            }
+            self.upper.remove_dir(&path)?;
+        }
+        if self.lookup(&path)?.is_some() {
+            self.whiteout(&path)?;
+        }
+        Ok(())
+    }
+
+    /// muta un fisier sau un director; un director e copiat intreg in upper inainte.
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, from: P, to: Q) -> Result<()> {
+        let (from, to) = (absolute(from)?, absolute(to)?);
+        if from.is_root() || to.is_root() {
+            return Err(VfsError::InvalidPath("cannot move the root".into()));
+        }
+        let (_, kind) = self.node(&from)?;
+        if self.lookup(&to)?.is_some() {
+            return Err(VfsError::AlreadyExists(to.to_string()));
+        }
+        self.check_parent(&to)?;
+        if to.as_str().starts_with(&format!("{}/", from.as_str())) {
+            return Err(VfsError::InvalidPath(format!(
+                "cannot move {from} inside itself"
+            )));
+        }
+
+        self.copy_up_tree(&from)?;
+        self.copy_up(&parent_of(&to)?)?;
+        // un rename refuzat de upper nu trebuie sa scoata la iveala ce era ascuns
+        let cleared = self.clear_whiteout(&to)?;
+        if let Err(e) = self.upper.rename(&from, &to) {
+            if cleared {
+                self.whiteout(&to)?;
+            }
+            return Err(e);
+        }
+        if kind == NodeKind::Dir {
+            // tot continutul e acum in upper; lower de la destinatie nu mai conteaza
+            self.upper.set_xattr(&to, OPAQUE, b"y")?;
+        }
+        if self.lookup(&from)?.is_some() {
+            self.whiteout(&from)?;
+        }
+        Ok(())
+    }
+
+    pub fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let path = absolute(path)?;
+        self.copy_up(&path)?;
+        self.upper.set_permissions(&path, mode)
+    }
+
+    pub fn set_times<P: AsRef<Path>>(&mut self, path: P, times: FileTimes) -> Result<()> {
+        let path = absolute(path)?;
+        self.copy_up(&path)?;
+        self.upper.set_times(&path, times)
+    }
+
+    /// scrie vederea combinata intr-o imagine noua, care poate deveni urmatorul `lower`.
+    ///
+    /// imaginea nu trebuie sa existe; whiteout-urile si marcajele overlay nu ajung in ea.
+    pub fn commit<P: AsRef<Path>>(&self, image: P) -> Result<Vfs> {
+        let image = image.as_ref();
+        if image.exists() {
+            return Err(VfsError::AlreadyExists(image.display().to_string()));
+        }
+        let mut base = Vfs::mount(image)?;
+        let root = VfsPath::root();
+        self.commit_tree(&root, &mut base)?;
+        let (layer, _) = self.node(&root)?;
+        copy_metadata(self.layer(layer), &mut base, &root, &root)?;
+        base.checkpoint()?;
+        Ok(base)
+    }
+
+    fn commit_tree(&self, dir: &VfsPath, base: &mut Vfs) -> Result<()> {
+        for entry in self.read_dir(dir)? {
+            let path = dir.join(&entry.name)?;
+            let (layer, _) = self.node(&path)?;
+            match entry.kind {
+                NodeKind::Dir => {
+                    base.create_dir(&path)?;
+                    self.commit_tree(&path, base)?;
+                }
+                NodeKind::File => {
+                    let mut src = self.layer(layer).open_file(&path)?;
+                    copy_data(&mut src, &mut base.create(&path)?)?;
+                }
+            }
+            // dupa continut, ca timpii directorului sa nu fie atinsi de copiii creati
+            copy_metadata(self.layer(layer), base, &path, &path)?;
+        }
+        Ok(())
+    }
+}
+
+fn copy_data(src: &mut VfsFile, dst: &mut VfsFile) -> Result<()> {
+    let mut buf = vec![0u8; COPY_BUF];
+    loop {
+        let n = src.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        dst.write_all(&buf[..n])?;
+    }
+}
+
+// permisiuni, timpi si xattr-uri (fara cele ale overlay-ului)
+fn copy_metadata(src: &Vfs, dst: &mut Vfs, from: &VfsPath, to: &VfsPath) -> Result<()> {
+    for name in src.list_xattrs(from)? {
+        if is_overlay_xattr(&name) {
+            continue;
+        }
+        if let Some(value) = src.get_xattr(from, &name)? {
+            dst.set_xattr(to, &name, &value)?;
+        }
+    }
+    let meta = src.metadata(from)?;
+    dst.set_permissions(to, meta.mode)?;
+    dst.set_times(
+        to,
+        FileTimes {
+            created_at: Some(meta.created_at),
+            modified_at: Some(meta.modified_at),
+            accessed_at: Some(meta.accessed_at),
+        },
+    )
+}
+
+impl FileSystem for OverlayVfs {
+    type File = VfsFile;
+
+    fn open<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        OverlayVfs::open(self, path)
+    }
+
+    fn create<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = absolute(path)?;
+        match self.lookup(&path)? {
+            Some((_, NodeKind::File)) => {
+                let mut file = self.open_rw(&path)?;
+                FsFile::set_len(&mut file, 0)?;
+                Ok(file)
+            }
+            Some((_, NodeKind::Dir)) => Err(VfsError::NotAFile(path.to_string())),
+            None => OverlayVfs::create(self, &path),
+        }
+    }
+
+    fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FsEntry>> {
+        OverlayVfs::read_dir(self, path)
+    }
+
+    fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<FsMetadata> {
+        OverlayVfs::metadata(self, path)
+    }
+
+    fn rename<P: AsRef<Path>, Q: AsRef<Path>>(&self, from: P, to: Q) -> Result<()> {
+        let (from, to) = (absolute(from)?, absolute(to)?);
+        let mut overlay = self.clone();
+        let (_, source) = self.node(&from)?;
+        // ca std::fs::rename, o destinatie de acelasi tip e inlocuita
+        if let Some((_, target)) = self.lookup(&to)? {
+            if from == to {
+                return Ok(());
+            }
+            // refuzul din `rename` ar veni abia dupa ce destinatia a disparut
+            if from.is_root() || to.as_str().starts_with(&format!("{}/", from.as_str())) {
+                return Err(VfsError::InvalidPath(format!(
+                    "cannot move {from} inside itself"
+                )));
+            }
+            match (source, target) {
+                (NodeKind::File, NodeKind::Dir) => {
+                    return Err(VfsError::NotAFile(to.to_string()));
+                }
+                (NodeKind::Dir, NodeKind::File) => {
+                    return Err(VfsError::NotADir(to.to_string()));
+                }
+                _ => {}
+            }
+            // o eroare de mai tarziu (permisiuni, copy-up) aduce destinatia inapoi
+            return replace_by_rename(self, from.as_ref(), to.as_ref(), target, |a, b| {
+                OverlayVfs::rename(&mut overlay, a, b)
+            });
+        }
+        OverlayVfs::rename(&mut overlay, &from, &to)
+    }
+
+    fn remove<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = absolute(path)?;
+        let mut overlay = self.clone();
+        match self.node(&path)?.1 {
+            NodeKind::Dir => overlay.remove_dir(&path),
+            NodeKind::File => overlay.remove_file(&path),
+        }
+    }
+
+    fn create_dir_all<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = absolute(path)?;
+        let mut overlay = self.clone();
+        let mut cur = VfsPath::root();
+        for name in path.components() {
+            cur = cur.join(name)?;
+            match self.lookup(&cur)? {
+                Some((_, NodeKind::Dir)) => {}
+                Some((_, NodeKind::File)) => return Err(VfsError::NotADir(cur.to_string())),
+                None => overlay.create_dir(&cur)?,
+            }
+        }
+        Ok(())
+    }
+}
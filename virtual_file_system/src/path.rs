@@ -0,0 +1,124 @@
+use crate::structs::{Result, VfsError};
+use std::fmt;
+use std::path::{Component, Path};
+
+/// normalized path inside the vfs.
+///
+/// absolute paths (`/a/b`) start at the root, relative ones (`a/b`) at the
+/// current directory of the handle. `.` and empty components are dropped,
+/// `..` is kept and resolved against the real parent inode at lookup time.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VfsPath {
+    repr: String,
+}
+
+impl VfsPath {
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let mut absolute = false;
+        let mut parts: Vec<&str> = Vec::new();
+
+        for comp in path.components() {
+            match comp {
+                Component::RootDir => absolute = true,
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    // `/..` e tot root, nu are rost sa il pastram
+                    if !(absolute && parts.is_empty()) {
+                        parts.push("..");
+                    }
+                }
+                Component::Normal(name) => {
+                    let name = name.to_str().ok_or_else(|| {
+                        VfsError::InvalidPath(format!("non utf-8 path: {}", path.display()))
+                    })?;
+                    parts.push(name);
+                }
+                Component::Prefix(_) => {
+                    return Err(VfsError::InvalidPath(format!(
+                        "path prefixes are not supported: {}",
+                        path.display()
+                    )));
+                }
+            }
+        }
+
+        let mut repr = String::new();
+        if absolute {
+            repr.push('/');
+        }
+        repr.push_str(&parts.join("/"));
+        Ok(Self { repr })
+    }
+
+    pub fn root() -> Self {
+        Self {
+            repr: "/".to_string(),
+        }
+    }
+
+    pub fn is_absolute(&self) -> bool {
+        self.repr.starts_with('/')
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.repr == "/"
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.repr
+    }
+
+    /// componentele numite, fara root (`..` inclus).
+    pub fn components(&self) -> impl Iterator<Item = &str> {
+        self.repr.split('/').filter(|p| !p.is_empty())
+    }
+
+    /// ultima componenta, daca e un nume (nu `..`).
+    pub fn file_name(&self) -> Option<&str> {
+        match self.components().last() {
+            Some("..") | None => None,
+            Some(name) => Some(name),
+        }
+    }
+
+    /// path-ul fara ultima componenta. `None` pentru root si pentru path-ul gol.
+    pub fn parent(&self) -> Option<VfsPath> {
+        let mut parts: Vec<&str> = self.components().collect();
+        parts.pop()?;
+        let mut repr = String::new();
+        if self.is_absolute() {
+            repr.push('/');
+        }
+        repr.push_str(&parts.join("/"));
+        Some(Self { repr })
+    }
+
+    /// lipeste `other` la final; un `other` absolut il inlocuieste complet.
+    pub fn join<P: AsRef<Path>>(&self, other: P) -> Result<VfsPath> {
+        let other = VfsPath::new(other)?;
+        if other.is_absolute() || self.repr.is_empty() {
+            return Ok(other);
+        }
+        if other.repr.is_empty() {
+            return Ok(self.clone());
+        }
+        VfsPath::new(format!("{}/{}", self.repr, other.repr))
+    }
+}
+
+impl fmt::Display for VfsPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.repr.is_empty() {
+            write!(f, ".")
+        } else {
+            write!(f, "{}", self.repr)
+        }
+    }
+}
+
+impl AsRef<Path> for VfsPath {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.repr)
+    }
+}
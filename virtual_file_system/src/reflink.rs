@@ -0,0 +1,145 @@
+use std::path::Path;
+
+use crate::compact::visible_extents;
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::{ACCESS_R, Inner, Vfs};
+
+impl Vfs {
+    /// copie copy-on-write: `dst` primeste un inode nou care refera aceleasi
+    /// extent-uri ca `src`, fara sa copieze date. scrierile ulterioare in oricare
+    /// dintre ele adauga doar extent-uri proprii.
+    pub fn clone_file<P: AsRef<Path>, Q: AsRef<Path>>(&mut self, src: P, dst: Q) -> Result<()> {
+        let src = VfsPath::new(src)?;
+        let dst = VfsPath::new(dst)?;
+        // extent-urile nu pot fi impartite intre doua imagini
+        match (self.writable_crossing(&src)?, self.writable_crossing(&dst)?) {
+            (None, None) => {}
+            (Some(a), Some(b)) if a.point == b.point => {
+                return a.mount.clone_file(&a.rest, &b.rest);
+            }
+            _ => return Err(VfsError::CrossesMount(format!("{src} -> {dst}"))),
+        }
+        self.inner.borrow_mut().clone_file(self.cwd, &src, &dst)
+    }
+
+    /// cat spatiu ocupa imaginea si cat din el e folosit de fisiere.
+    pub fn stats(&self) -> Result<FsStats> {
+        self.inner.borrow_mut().stats()
+    }
+}
+
+impl Inner {
+    fn clone_file(&mut self, cwd: InodeId, src: &VfsPath, dst: &VfsPath) -> Result<()> {
+        let src_id = self.resolve(cwd, src)?;
+        let source = self
+            .inodes
+            .get(&src_id)
+            .ok_or_else(|| VfsError::NotFound(src.to_string()))?;
+        if source.kind != NodeKind::File {
+            return Err(VfsError::NotAFile(src.to_string()));
+        }
+        self.check_access(src_id, ACCESS_R, src)?;
+
+        let (parent, name) = self.resolve_parent(cwd, dst)?;
+        if self.children.contains_key(&(parent, name.clone())) {
+            return Err(VfsError::AlreadyExists(dst.to_string()));
+        }
+
+        // doar bucatile vizibile; restul sunt date suprascrise ale sursei
+        let source = &self.inodes[&src_id];
+        let extents = visible_extents(&source.extents, source.metadata.size);
+        let mut metadata = self.new_metadata(self.options.clock.now(), source.metadata.mode);
+        metadata.size = source.metadata.size;
+
+        let new_id = self.next_inode;
+        self.next_inode = InodeId(new_id.0 + 1);
+
+        let snap = InodeSnapshot {
+            id: new_id,
+            parent: Some(parent),
+            name: name.clone(),
+            kind: NodeKind::File,
+            metadata,
+            extents,
+            xattrs: XattrMap::new(),
+        };
+        let de = DirEntry {
+            parent,
+            inode: new_id,
+            name,
+            kind: NodeKind::File,
+        };
+        for rec in [Record::InodeAlloc(snap), Record::DirEntryAdd { entry: de }] {
+            self.log(&rec)?;
+            self.apply_record(&rec)?;
+        }
+        self.touch(parent, true)
+    }
+
+    fn stats(&mut self) -> Result<FsStats> {
+        let mut stats = FsStats {
+            image_bytes: self.file.metadata()?.len(),
+            ..Default::default()
+        };
+
+        // +1/-1 la capetele fiecarui interval fizic; in cadrul unui inode
+        // bucatile vizibile nu se suprapun, deci acoperirea = nr. de fisiere
+        let mut events: Vec<(u64, i64)> = Vec::new();
+        for node in self.inodes.values() {
+            let trashed = self.trash.contains_key(&node.id);
+            match node.kind {
+                NodeKind::Dir if !trashed => stats.dirs += 1,
+                NodeKind::File if !trashed => {
+                    stats.files += 1;
+                    stats.logical_bytes += node.metadata.size;
+                }
+                _ => {}
+            }
+            // mai multe bucati din acelasi blob comprimat conteaza o data per fisier
+            let mut ranges: Vec<(u64, u64)> = visible_extents(&node.extents, node.metadata.size)
+                .iter()
+                .map(Extent::stored_range)
+                .collect();
+            ranges.sort_unstable();
+            let mut last_hi = 0;
+            for (lo, hi) in ranges {
+                let lo = lo.max(last_hi);
+                if lo < hi {
+                    events.push((lo, 1));
+                    events.push((hi, -1));
+                    last_hi = hi;
+                }
+            }
+        }
+        events.sort_unstable();
+
+        let mut depth = 0i64;
+        let mut prev = 0u64;
+        for (pos, delta) in events {
+            let span = pos - prev;
+            if depth >= 1 {
+                stats.referenced_bytes += span;
+            }
+            if depth >= 2 {
+                stats.shared_bytes += span;
+            }
+            depth += delta;
+            prev = pos;
+        }
+
+        // datele efectiv scrise in log, inclusiv cele care nu mai sunt vizibile
+        let mut off = self.header.log_start();
+        loop {
+            let (decoded, next) = match self.read_record(off) {
+                Ok(Some(r)) => r,
+                Ok(None) | Err(VfsError::CorruptLog(_)) => break,
+                Err(e) => return Err(e),
+            };
+            stats.physical_bytes += decoded.stored_len();
+            off = next;
+        }
+
+        Ok(stats)
+    }
+}
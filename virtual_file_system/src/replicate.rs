@@ -0,0 +1,371 @@
+//! replicare prin log shipping: log-ul imaginii e deja fluxul de schimbari.
+//!
+//! sursa da record-urile brute de la un offset (`Vfs::changes_since`), replica
+//! le scrie byte cu byte la finalul propriului fisier (`Vfs::apply_changes`),
+//! deci ramane un prefix identic al sursei: offset-urile datelor si nonce-urile
+//! imaginilor criptate raman valabile. fiecare schimbare e verificata de trei ori:
+//! crc-ul cadrului, crc-ul record-ului si crc-ul datelor lui.
+//!
+//! replica e montata read-only (nu poate diverge) si isi tine offset-ul aplicat.
+//! un `compact` pe sursa muta toate offset-urile si creste generatia din header;
+//! fiecare schimbare o poarta, iar replica refuza una din alta generatie: dupa
+//! compactare replica trebuie creata din nou.
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::crypt::unlock;
+use crate::no_sql::*;
+use crate::structs::*;
+use crate::vfs::{Inner, Vfs};
+
+const FRAME_MAGIC: &[u8; 4] = b"VCHG";
+
+/// schimbarile unei imagini de la un offset, in ordinea din log.
+///
+/// se opreste la finalul log-ului (sau la un record inca incomplet); un iterator
+/// nou de la ultimul offset continua de unde a ramas.
+pub struct Changes {
+    inner: Rc<RefCell<Inner>>,
+    generation: u32,
+    offset: u64,
+    done: bool,
+}
+
+impl Iterator for Changes {
+    type Item = Result<Change>;
+
+    fn next(&mut self) -> Option<Result<Change>> {
+        if self.done {
+            return None;
+        }
+        let mut inner = self.inner.borrow_mut();
+        if inner.header.generation != self.generation {
+            self.done = true;
+            return Some(Err(VfsError::CorruptLog(format!(
+                "{} was compacted while its changes were read",
+                inner.path.display()
+            ))));
+        }
+        // de la 0: header-ul intai, ca o replica noua sa stie formatul si cheia
+        let next = if self.offset == 0 {
+            inner.header.log_start()
+        } else {
+            match inner.read_record(self.offset) {
+                Ok(Some((_, next))) => next,
+                Ok(None) | Err(VfsError::CorruptLog(_)) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        };
+        let change =
+            read_raw(&mut inner.file, self.offset, next - self.offset).map(|bytes| Change {
+                generation: self.generation,
+                offset: self.offset,
+                bytes,
+            });
+        self.offset = next;
+        self.done = change.is_err();
+        Some(change)
+    }
+}
+
+fn read_raw(file: &mut File, offset: u64, len: u64) -> Result<Vec<u8>> {
+    let mut bytes = vec![0u8; len as usize];
+    file.seek(SeekFrom::Start(offset))?;
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+impl Change {
+    /// scrie schimbarea ca un cadru: magic, generatie, offset, lungime, bytes, crc32.
+    pub fn write_to<W: Write>(&self, out: &mut W) -> Result<()> {
+        let mut frame = Vec::with_capacity(28 + self.bytes.len());
+        frame.extend_from_slice(FRAME_MAGIC);
+        frame.extend_from_slice(&self.generation.to_le_bytes());
+        frame.extend_from_slice(&self.offset.to_le_bytes());
+        frame.extend_from_slice(&(self.bytes.len() as u64).to_le_bytes());
+        frame.extend_from_slice(&self.bytes);
+        frame.extend_from_slice(&crc32(&frame).to_le_bytes());
+        out.write_all(&frame)?;
+        Ok(())
+    }
+
+    /// urmatorul cadru din flux; `None` la EOF curat, intre cadre.
+    pub fn read_from<R: Read>(input: &mut R) -> Result<Option<Change>> {
+        let mut head = [0u8; 24];
+        // EOF curat doar inainte de primul byte; un cadru taiat oriunde altundeva e o eroare
+        loop {
+            match input.read(&mut head[..1]) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let cut = |e: std::io::Error| match e.kind() {
+            ErrorKind::UnexpectedEof => VfsError::CorruptLog("change frame cut short".into()),
+            _ => e.into(),
+        };
+        input.read_exact(&mut head[1..4]).map_err(cut)?;
+        if &head[..4] != FRAME_MAGIC {
+            return Err(VfsError::CorruptLog("bad change frame magic".into()));
+        }
+        input.read_exact(&mut head[4..]).map_err(cut)?;
+        let generation = u32::from_le_bytes(head[4..8].try_into().unwrap());
+        let offset = u64::from_le_bytes(head[8..16].try_into().unwrap());
+        let len = u64::from_le_bytes(head[16..24].try_into().unwrap());
+
+        let mut bytes = Vec::new();
+        input.by_ref().take(len).read_to_end(&mut bytes)?;
+        let mut crc = [0u8; 4];
+        if bytes.len() as u64 != len || input.read_exact(&mut crc).is_err() {
+            return Err(VfsError::CorruptLog(format!(
+                "change frame at offset {offset} cut short"
+            )));
+        }
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&head);
+        hasher.update(&bytes);
+        if hasher.finalize() != u32::from_le_bytes(crc) {
+            return Err(VfsError::CorruptLog(format!(
+                "change frame at offset {offset}: crc mismatch"
+            )));
+        }
+        Ok(Some(Change {
+            generation,
+            offset,
+            bytes,
+        }))
+    }
+
+    fn end(&self) -> u64 {
+        self.offset + self.bytes.len() as u64
+    }
+}
+
+impl Inner {
+    // replica inca fara header: nici radacina nu exista pana la primele schimbari
+    fn unseeded(file: File, path: &Path, options: MountOptions) -> Self {
+        Inner {
+            file,
+            path: path.to_path_buf(),
+            header: Header {
+                magic: *b"CCCCCCCC",
+                version: VERSION,
+                block_size: DEFAULT_BLOCK_SIZE,
+                root: InodeId(1),
+                compression: Compression::None,
+                encryption: None,
+                generation: 0,
+            },
+            next_inode: InodeId(1),
+            inodes: HashMap::new(),
+            children: HashMap::new(),
+            snapshots: BTreeMap::new(),
+            trash: HashMap::new(),
+            dedup: HashMap::new(),
+            blob_cache: None,
+            cipher: None,
+            scratch: Vec::new(),
+            options,
+            mounts: HashMap::new(),
+            watchers: Vec::new(),
+            log_end: 0,
+        }
+    }
+
+    fn append_change(&mut self, change: &Change) -> Result<bool> {
+        // dupa un `compact` pe sursa offset-urile ei nu mai corespund cu ale replicii
+        if self.log_end > 0 && change.generation != self.header.generation {
+            return Err(VfsError::CorruptLog(format!(
+                "change from generation {} of the source, but the replica is at generation {}: \
+                 the source was compacted, create the replica again",
+                change.generation, self.header.generation
+            )));
+        }
+        // retrimisa dupa o reconectare: deja aplicata
+        if change.end() <= self.log_end {
+            return Ok(false);
+        }
+        if change.offset != self.log_end {
+            return Err(VfsError::CorruptLog(format!(
+                "replication gap: change at offset {} but the replica is at {}",
+                change.offset, self.log_end
+            )));
+        }
+
+        // replica e read-only; doar aici se scrie, la finalul log-ului
+        let mut out = OpenOptions::new().write(true).open(&self.path)?;
+        out.set_len(self.log_end)?; // o coada rupta de la o oprire brusca
+        out.seek(SeekFrom::Start(self.log_end))?;
+        out.write_all(&change.bytes)?;
+        out.flush()?;
+
+        let verified = if self.log_end == 0 {
+            self.seed(change.end())
+        } else {
+            self.verify_records(change.end())
+        };
+        match verified {
+            Ok(records) => {
+                for (decoded, next) in records {
+                    self.follow(decoded, next)?;
+                }
+                self.log_end = change.end();
+                Ok(true)
+            }
+            Err(e) => {
+                out.set_len(self.log_end)?;
+                Err(e)
+            }
+        }
+    }
+
+    // primul cadru e header-ul sursei
+    fn seed(&mut self, end: u64) -> Result<Vec<(DecodedRecord, u64)>> {
+        let header = read_header(&mut self.file)?;
+        if end != header.log_start() {
+            return Err(VfsError::CorruptLog(
+                "the first change of a replica must be the image header".into(),
+            ));
+        }
+        crate::codec::ensure_supported(header.compression)?;
+        self.cipher = unlock(self.options.encryption.as_ref(), &header)?;
+        self.header = header;
+        Ok(Vec::new())
+    }
+
+    // record-urile dintre `log_end` si `end`, cu crc-urile lor si ale datelor;
+    // nimic nu se aplica daca unul e stricat
+    fn verify_records(&mut self, end: u64) -> Result<Vec<(DecodedRecord, u64)>> {
+        let mut records = Vec::new();
+        let mut off = self.log_end;
+        while off < end {
+            let Some((decoded, next)) = self.read_record(off)? else {
+                return Err(VfsError::CorruptLog(format!(
+                    "incomplete record in change at offset {off}"
+                )));
+            };
+            if let Some(problem) = self.check_data(&decoded)? {
+                return Err(VfsError::CorruptLog(problem));
+            }
+            records.push((decoded, next));
+            off = next;
+        }
+        if off != end {
+            return Err(VfsError::CorruptLog(format!(
+                "change ends at {end} but its last record ends at {off}"
+            )));
+        }
+        Ok(records)
+    }
+}
+
+impl Vfs {
+    /// schimbarile de la `offset` (0 = de la inceput, cu header-ul), pt o replica.
+    ///
+    /// o montare facuta inainte ca alt proces sa compacteze imaginea da
+    /// `CorruptLog`: fisierul ei nu mai e cel de pe disc.
+    pub fn changes_since(&self, offset: u64) -> Result<Changes> {
+        let mut inner = self.inner.borrow_mut();
+        let image_bytes = inner.file.metadata()?.len();
+        if (offset > 0 && offset < inner.header.log_start()) || offset > image_bytes {
+            return Err(VfsError::InvalidPath(format!(
+                "offset {offset} is not in the log (image has {image_bytes} bytes)"
+            )));
+        }
+        if image_bytes > 0 {
+            let on_disk = read_header(&mut File::open(&inner.path)?)?;
+            if on_disk.generation != inner.header.generation {
+                return Err(VfsError::CorruptLog(format!(
+                    "{} was compacted after it was mounted; mount it again",
+                    inner.path.display()
+                )));
+            }
+        }
+        // de ex. offset-ul unei replici facute inainte de un `compact`
+        if offset > 0
+            && offset < image_bytes
+            && matches!(inner.read_record(offset), Err(VfsError::CorruptLog(_)))
+        {
+            return Err(VfsError::InvalidPath(format!(
+                "offset {offset} is not the start of a record"
+            )));
+        }
+        Ok(Changes {
+            inner: self.inner.clone(),
+            generation: inner.header.generation,
+            offset,
+            done: false,
+        })
+    }
+
+    /// monteaza read-only o replica; daca imaginea nu exista e creata goala si
+    /// primeste totul din primele `apply_changes`.
+    pub fn mount_replica<P: AsRef<Path>>(path: P, options: MountOptions) -> Result<Vfs> {
+        let path = path.as_ref();
+        let options = MountOptions {
+            read_only: true,
+            ..options
+        };
+        let empty = std::fs::metadata(path).map_or(true, |m| m.len() == 0);
+        let inner = if empty {
+            drop(
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(false)
+                    .open(path)?,
+            );
+            Inner::unseeded(File::open(path)?, path, options)
+        } else {
+            Inner::open(path, options, u64::MAX)?
+        };
+        Ok(Vfs::from_inner(Rc::new(RefCell::new(inner))))
+    }
+
+    /// aplica pe replica toate cadrele din `stream`, pana la EOF. intoarce cate
+    /// schimbari noi au fost aplicate; cele deja aplicate se sar.
+    pub fn apply_changes<R: Read>(&mut self, mut stream: R) -> Result<usize> {
+        let mut applied = 0;
+        while let Some(change) = Change::read_from(&mut stream)? {
+            if self.apply_change(&change)? {
+                applied += 1;
+            }
+        }
+        Ok(applied)
+    }
+
+    /// aplica o singura schimbare; `false` daca era deja aplicata.
+    pub fn apply_change(&mut self, change: &Change) -> Result<bool> {
+        if !self.inner.borrow().options.read_only {
+            return Err(VfsError::Unsupported(
+                "changes go to a replica mounted with Vfs::mount_replica".into(),
+            ));
+        }
+        let mut inner = self.inner.borrow_mut();
+        let applied = inner.append_change(change)?;
+        inner.drop_unlinked_mounts();
+        // dupa header, radacina replicii e cea a sursei
+        if change.offset == 0 {
+            self.cwd = inner.header.root;
+        }
+        Ok(applied)
+    }
+
+    /// offset-ul pana la care replica e identica cu sursa; de aici se cere
+    /// urmatorul `changes_since`.
+    pub fn applied_offset(&self) -> u64 {
+        self.inner.borrow().log_end
+    }
+}
@@ -0,0 +1,643 @@
+//! shell-ul din `vfs-shell`: comenzi inregistrate intr-un `Shell`, ca in lab06,
+//! dar peste un `Vfs` adevarat, cu director curent si completare de path-uri.
+
+use std::io::{BufRead, Seek, SeekFrom, Write};
+
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::Vfs;
+
+/// eroarea unei linii de comanda.
+#[derive(Debug)]
+pub enum ShellError {
+    /// argumente gresite; contine modul de folosire al comenzii.
+    Usage(String),
+    UnknownCommand(String),
+    Vfs(VfsError),
+}
+
+impl std::fmt::Display for ShellError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShellError::Usage(u) => write!(f, "usage: {u}"),
+            ShellError::UnknownCommand(c) => write!(f, "unknown command: {c}"),
+            ShellError::Vfs(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl From<VfsError> for ShellError {
+    fn from(e: VfsError) -> Self {
+        ShellError::Vfs(e)
+    }
+}
+
+impl From<std::io::Error> for ShellError {
+    fn from(e: std::io::Error) -> Self {
+        ShellError::Vfs(e.into())
+    }
+}
+
+pub type ShellResult<T> = std::result::Result<T, ShellError>;
+
+/// starea pe care o vad comenzile: imaginea montata, output-ul si istoricul.
+pub struct Session {
+    pub vfs: Vfs,
+    pub out: Box<dyn Write>,
+    history: Vec<String>,
+}
+
+impl Session {
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+}
+
+/// o comanda a shell-ului.
+pub trait Command {
+    fn name(&self) -> &'static str;
+    /// argumentele, pt `help` si erorile de folosire (ex. `"cat <path>..."`).
+    fn usage(&self) -> &'static str;
+    fn exec(&mut self, session: &mut Session, args: &[String]) -> ShellResult<()>;
+
+    fn usage_error(&self) -> ShellError {
+        ShellError::Usage(format!("{} {}", self.name(), self.usage()))
+    }
+}
+
+/// ce face bucla dupa o linie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flow {
+    Continue,
+    Exit,
+}
+
+pub struct Shell {
+    commands: Vec<Box<dyn Command>>,
+    session: Session,
+}
+
+impl Shell {
+    /// shell cu toate comenzile standard; output-ul comenzilor merge in `out`.
+    pub fn new<W: Write + 'static>(vfs: Vfs, out: W) -> Self {
+        let mut shell = Shell {
+            commands: Vec::new(),
+            session: Session {
+                vfs,
+                out: Box::new(out),
+                history: Vec::new(),
+            },
+        };
+        shell.register(Box::new(Cd));
+        shell.register(Box::new(Pwd));
+        shell.register(Box::new(Ls));
+        shell.register(Box::new(Cat));
+        shell.register(Box::new(WriteCmd { append: false }));
+        shell.register(Box::new(WriteCmd { append: true }));
+        shell.register(Box::new(Mv));
+        shell.register(Box::new(Rm));
+        shell.register(Box::new(Mkdir));
+        shell.register(Box::new(Stat));
+        shell.register(Box::new(History));
+        shell.register(Box::new(Checkpoint));
+        shell
+    }
+
+    /// adauga o comanda; una cu acelasi nume o inlocuieste pe cea veche.
+    pub fn register(&mut self, command: Box<dyn Command>) {
+        self.commands.retain(|c| c.name() != command.name());
+        self.commands.push(command);
+    }
+
+    pub fn session(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_vfs(self) -> Vfs {
+        self.session.vfs
+    }
+
+    /// `"/dir> "`, cu directorul curent.
+    pub fn prompt(&self) -> String {
+        match self.session.vfs.current_dir() {
+            Ok(dir) => format!("{dir}> "),
+            Err(_) => "?> ".to_string(),
+        }
+    }
+
+    /// ruleaza o linie. `help`, `exit` si `quit` sunt ale shell-ului, nu comenzi.
+    pub fn execute(&mut self, line: &str) -> ShellResult<Flow> {
+        let words = split_words(line)?;
+        let Some((name, args)) = words.split_first() else {
+            return Ok(Flow::Continue);
+        };
+        self.session.history.push(line.trim().to_string());
+
+        match name.as_str() {
+            "exit" | "quit" => return Ok(Flow::Exit),
+            "help" => {
+                let mut names: Vec<&dyn Command> = self.commands.iter().map(|c| &**c).collect();
+                names.sort_by_key(|c| c.name());
+                for c in names {
+                    writeln!(self.session.out, "{} {}", c.name(), c.usage())?;
+                }
+                writeln!(self.session.out, "help\nexit")?;
+                return Ok(Flow::Continue);
+            }
+            _ => {}
+        }
+        let command = self
+            .commands
+            .iter_mut()
+            .find(|c| c.name() == name)
+            .ok_or_else(|| ShellError::UnknownCommand(name.clone()))?;
+        command.exec(&mut self.session, args)?;
+        Ok(Flow::Continue)
+    }
+
+    /// ruleaza un script, o comanda pe linie; `#` incepe un comentariu. erorile
+    /// se scriu in output cu numarul liniei si scriptul continua.
+    /// intoarce cate linii au esuat.
+    pub fn run_script<R: BufRead>(&mut self, script: R) -> Result<usize> {
+        let mut failed = 0;
+        for (i, line) in script.lines().enumerate() {
+            let line = line?;
+            if line.trim_start().starts_with('#') {
+                continue;
+            }
+            match self.execute(&line) {
+                Ok(Flow::Continue) => {}
+                Ok(Flow::Exit) => break,
+                Err(e) => {
+                    failed += 1;
+                    writeln!(self.session.out, "line {}: {e}", i + 1)?;
+                }
+            }
+        }
+        self.session.out.flush()?;
+        Ok(failed)
+    }
+
+    /// completarea cuvantului care se termina la `pos`: intoarce unde incepe
+    /// cuvantul si variantele cu care poate fi inlocuit. primul cuvant e o
+    /// comanda, restul sunt path-uri din vfs (directoarele primesc `/`).
+    pub fn complete(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let word = &before[start..];
+
+        if before[..start].trim().is_empty() {
+            let mut names: Vec<String> = self
+                .commands
+                .iter()
+                .map(|c| c.name())
+                .chain(["help", "exit", "quit"])
+                .filter(|n| n.starts_with(word))
+                .map(str::to_string)
+                .collect();
+            names.sort();
+            return (start, names);
+        }
+
+        let (dir, prefix) = match word.rfind('/') {
+            Some(i) => (&word[..=i], &word[i + 1..]),
+            None => ("", word),
+        };
+        let Ok(entries) = self
+            .session
+            .vfs
+            .read_dir(if dir.is_empty() { "." } else { dir })
+        else {
+            return (start, Vec::new());
+        };
+        let mut out: Vec<String> = entries
+            .filter_map(|e| e.ok())
+            .filter(|e| e.name.starts_with(prefix))
+            .map(|e| {
+                let slash = if e.kind == NodeKind::Dir { "/" } else { "" };
+                format!("{dir}{}{slash}", e.name)
+            })
+            .collect();
+        out.sort();
+        (start, out)
+    }
+}
+
+/// imparte o linie in cuvinte; `'...'` si `"..."` pastreaza spatiile, `\` scapa
+/// urmatorul caracter.
+pub fn split_words(line: &str) -> ShellResult<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote: Option<char> = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                if let Some(next) = chars.next() {
+                    word.push(next);
+                }
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if quote.is_some() {
+        return Err(ShellError::Usage("unterminated quote".into()));
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// flag-urile scurte de la inceput (`-lR`); restul sunt argumente
+fn split_flags<'a>(
+    cmd: &dyn Command,
+    args: &'a [String],
+    allowed: &str,
+) -> ShellResult<(String, &'a [String])> {
+    let mut flags = String::new();
+    let mut rest = args;
+    while let [first, tail @ ..] = rest {
+        let Some(f) = first.strip_prefix('-').filter(|f| !f.is_empty()) else {
+            break;
+        };
+        if !f.chars().all(|c| allowed.contains(c)) {
+            return Err(cmd.usage_error());
+        }
+        flags.push_str(f);
+        rest = tail;
+    }
+    Ok((flags, rest))
+}
+
+fn is_dir(vfs: &Vfs, path: &str) -> bool {
+    vfs.read_dir(path).is_ok()
+}
+
+struct Cd;
+
+impl Command for Cd {
+    fn name(&self) -> &'static str {
+        "cd"
+    }
+    fn usage(&self) -> &'static str {
+        "[dir]"
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        match args {
+            [] => s.vfs.set_current_dir("/")?,
+            [dir] => s.vfs.set_current_dir(dir)?,
+            _ => return Err(self.usage_error()),
+        }
+        Ok(())
+    }
+}
+
+struct Pwd;
+
+impl Command for Pwd {
+    fn name(&self) -> &'static str {
+        "pwd"
+    }
+    fn usage(&self) -> &'static str {
+        ""
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        if !args.is_empty() {
+            return Err(self.usage_error());
+        }
+        writeln!(s.out, "{}", s.vfs.current_dir()?)?;
+        Ok(())
+    }
+}
+
+struct Ls;
+
+impl Command for Ls {
+    fn name(&self) -> &'static str {
+        "ls"
+    }
+    fn usage(&self) -> &'static str {
+        "[-l] [path]..."
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        let (flags, paths) = split_flags(self, args, "l")?;
+        let long = flags.contains('l');
+        let paths = if paths.is_empty() {
+            vec![".".to_string()]
+        } else {
+            paths.to_vec()
+        };
+        for (i, path) in paths.iter().enumerate() {
+            if paths.len() > 1 {
+                if i > 0 {
+                    writeln!(s.out)?;
+                }
+                writeln!(s.out, "{path}:")?;
+            }
+            if !is_dir(&s.vfs, path) {
+                let meta = s.vfs.metadata(path)?;
+                write_entry(s, path, NodeKind::File, &meta, long)?;
+                continue;
+            }
+            let mut entries: Vec<DirEntry> = s.vfs.read_dir(path)?.collect::<Result<_>>()?;
+            entries.sort_by(|a, b| a.name.cmp(&b.name));
+            for e in entries {
+                let meta = s.vfs.metadata(VfsPath::new(path)?.join(&e.name)?)?;
+                write_entry(s, &e.name, e.kind, &meta, long)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_entry(
+    s: &mut Session,
+    name: &str,
+    kind: NodeKind,
+    meta: &Metadata,
+    long: bool,
+) -> ShellResult<()> {
+    let slash = if kind == NodeKind::Dir { "/" } else { "" };
+    if long {
+        writeln!(
+            s.out,
+            "{} {:>5} {:>5} {:>10} {name}{slash}",
+            mode_string(kind, meta.mode),
+            meta.uid,
+            meta.gid,
+            meta.size
+        )?;
+    } else {
+        writeln!(s.out, "{name}{slash}")?;
+    }
+    Ok(())
+}
+
+fn mode_string(kind: NodeKind, mode: u32) -> String {
+    let mut out = String::with_capacity(10);
+    out.push(if kind == NodeKind::Dir { 'd' } else { '-' });
+    for shift in [6, 3, 0] {
+        let bits = mode >> shift;
+        out.push(if bits & 4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 2 != 0 { 'w' } else { '-' });
+        out.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    out
+}
+
+struct Cat;
+
+impl Command for Cat {
+    fn name(&self) -> &'static str {
+        "cat"
+    }
+    fn usage(&self) -> &'static str {
+        "<path>..."
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        if args.is_empty() {
+            return Err(self.usage_error());
+        }
+        for path in args {
+            std::io::copy(&mut s.vfs.open(path)?, &mut s.out)?;
+        }
+        Ok(())
+    }
+}
+
+/// `write` inlocuieste continutul, `append` adauga la final; textul e restul
+/// argumentelor, unite cu spatii, plus un newline.
+struct WriteCmd {
+    append: bool,
+}
+
+impl Command for WriteCmd {
+    fn name(&self) -> &'static str {
+        if self.append { "append" } else { "write" }
+    }
+    fn usage(&self) -> &'static str {
+        "<path> <text>..."
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        let [path, words @ ..] = args else {
+            return Err(self.usage_error());
+        };
+        let mut file = if s.vfs.exists(path) {
+            let mut f = s.vfs.open_rw(path)?;
+            if self.append {
+                f.seek(SeekFrom::End(0))?;
+            } else {
+                f.set_len(0)?;
+            }
+            f
+        } else {
+            s.vfs.create(path)?
+        };
+        file.write_all(format!("{}\n", words.join(" ")).as_bytes())?;
+        Ok(())
+    }
+}
+
+struct Mv;
+
+impl Command for Mv {
+    fn name(&self) -> &'static str {
+        "mv"
+    }
+    fn usage(&self) -> &'static str {
+        "<from> <to>"
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        let [from, to] = args else {
+            return Err(self.usage_error());
+        };
+        // ca mv: mutarea intr-un director existent pastreaza numele
+        let to = match VfsPath::new(from)?.file_name() {
+            Some(name) if is_dir(&s.vfs, to) => VfsPath::new(to)?.join(name)?,
+            _ => VfsPath::new(to)?,
+        };
+        s.vfs.rename(from, to)?;
+        Ok(())
+    }
+}
+
+struct Rm;
+
+impl Command for Rm {
+    fn name(&self) -> &'static str {
+        "rm"
+    }
+    fn usage(&self) -> &'static str {
+        "[-r] <path>..."
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        let (flags, paths) = split_flags(self, args, "r")?;
+        if paths.is_empty() {
+            return Err(self.usage_error());
+        }
+        for path in paths {
+            let path = VfsPath::new(path)?;
+            // ca rm: `/`, `.` si `..` nu se sterg; `-r` le-ar goli inainte sa esueze
+            if path.file_name().is_none() {
+                return Err(VfsError::InvalidPath(format!("refusing to remove {path}")).into());
+            }
+            if !is_dir(&s.vfs, path.as_str()) {
+                s.vfs.remove_file(&path)?;
+            } else if flags.contains('r') {
+                remove_tree(&mut s.vfs, &path)?;
+            } else {
+                s.vfs.remove_dir(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn remove_tree(vfs: &mut Vfs, path: &VfsPath) -> Result<()> {
+    let entries: Vec<DirEntry> = vfs.read_dir(path)?.collect::<Result<_>>()?;
+    for e in entries {
+        let child = path.join(&e.name)?;
+        match e.kind {
+            NodeKind::Dir => remove_tree(vfs, &child)?,
+            NodeKind::File => vfs.remove_file(&child)?,
+        }
+    }
+    vfs.remove_dir(path)
+}
+
+struct Mkdir;
+
+impl Command for Mkdir {
+    fn name(&self) -> &'static str {
+        "mkdir"
+    }
+    fn usage(&self) -> &'static str {
+        "[-p] <dir>..."
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        let (flags, paths) = split_flags(self, args, "p")?;
+        if paths.is_empty() {
+            return Err(self.usage_error());
+        }
+        for path in paths {
+            if !flags.contains('p') {
+                s.vfs.create_dir(path)?;
+                continue;
+            }
+            let path = VfsPath::new(path)?;
+            let mut prefix = if path.is_absolute() {
+                VfsPath::root()
+            } else {
+                VfsPath::new(".")?
+            };
+            for part in path.components() {
+                prefix = prefix.join(part)?;
+                if !s.vfs.exists(&prefix) {
+                    s.vfs.create_dir(&prefix)?;
+                } else if !is_dir(&s.vfs, prefix.as_str()) {
+                    return Err(VfsError::NotADir(prefix.to_string()).into());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Stat;
+
+impl Command for Stat {
+    fn name(&self) -> &'static str {
+        "stat"
+    }
+    fn usage(&self) -> &'static str {
+        "<path>..."
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        if args.is_empty() {
+            return Err(self.usage_error());
+        }
+        for path in args {
+            let meta = s.vfs.metadata(path)?;
+            let kind = if is_dir(&s.vfs, path) {
+                NodeKind::Dir
+            } else {
+                NodeKind::File
+            };
+            writeln!(s.out, "  path: {path}")?;
+            writeln!(s.out, "  size: {}", meta.size)?;
+            writeln!(
+                s.out,
+                "  mode: {:04o} ({})",
+                meta.mode,
+                mode_string(kind, meta.mode)
+            )?;
+            writeln!(s.out, " owner: uid {} gid {}", meta.uid, meta.gid)?;
+            for (label, t) in [
+                ("created", meta.created_at),
+                ("modified", meta.modified_at),
+                ("accessed", meta.accessed_at),
+                ("changed", meta.changed_at),
+            ] {
+                writeln!(s.out, "{label:>8}: {t}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+struct History;
+
+impl Command for History {
+    fn name(&self) -> &'static str {
+        "history"
+    }
+    fn usage(&self) -> &'static str {
+        ""
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        if !args.is_empty() {
+            return Err(self.usage_error());
+        }
+        for (i, line) in s.history.iter().enumerate() {
+            writeln!(s.out, "{:>4}  {line}", i + 1)?;
+        }
+        Ok(())
+    }
+}
+
+struct Checkpoint;
+
+impl Command for Checkpoint {
+    fn name(&self) -> &'static str {
+        "checkpoint"
+    }
+    fn usage(&self) -> &'static str {
+        ""
+    }
+    fn exec(&mut self, s: &mut Session, args: &[String]) -> ShellResult<()> {
+        if !args.is_empty() {
+            return Err(self.usage_error());
+        }
+        s.vfs.checkpoint()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,95 @@
+use std::cell::RefCell;
+use std::io::{Seek, SeekFrom};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::structs::*;
+use crate::vfs::{Inner, Vfs};
+
+impl Vfs {
+    /// marcheaza starea curenta sub un nume. marker-ul e durabil (sync pe disk)
+    /// si poate fi montat mai tarziu cu [`Vfs::mount_snapshot`].
+    pub fn snapshot(&mut self, name: &str) -> Result<SnapshotInfo> {
+        self.inner.borrow_mut().snapshot(name)
+    }
+
+    /// snapshot-urile vii, in ordinea din log.
+    pub fn list_snapshots(&self) -> Vec<SnapshotInfo> {
+        let mut out: Vec<SnapshotInfo> = self.inner.borrow().snapshots.values().cloned().collect();
+        out.sort_by_key(|s| s.offset);
+        out
+    }
+
+    /// sterge marker-ul; datele lui pot fi eliberate la urmatorul `compact`.
+    pub fn delete_snapshot(&mut self, name: &str) -> Result<()> {
+        self.inner.borrow_mut().delete_snapshot(name)
+    }
+
+    /// monteaza read-only starea din momentul snapshot-ului `name`.
+    pub fn mount_snapshot<P: AsRef<Path>>(path: P, name: &str) -> Result<Vfs> {
+        Self::mount_snapshot_with(path, name, MountOptions::default())
+    }
+
+    /// ca `mount_snapshot`, cu optiuni (de ex. cheia unei imagini criptate);
+    /// montarea e mereu read-only.
+    pub fn mount_snapshot_with<P: AsRef<Path>>(
+        path: P,
+        name: &str,
+        options: MountOptions,
+    ) -> Result<Vfs> {
+        let options = MountOptions {
+            read_only: true,
+            ..options
+        };
+        let mut inner = Inner::open(path.as_ref(), options, u64::MAX)?;
+        let info = inner
+            .snapshots
+            .get(name)
+            .cloned()
+            .ok_or_else(|| VfsError::NotFound(format!("snapshot {name}")))?;
+        inner.replay_until(info.offset)?;
+        Ok(Vfs::from_inner(Rc::new(RefCell::new(inner))))
+    }
+}
+
+impl Inner {
+    fn snapshot(&mut self, name: &str) -> Result<SnapshotInfo> {
+        if name.is_empty() {
+            return Err(VfsError::InvalidPath("empty snapshot name".into()));
+        }
+        if self.snapshots.contains_key(name) {
+            return Err(VfsError::AlreadyExists(format!("snapshot {name}")));
+        }
+
+        // checkpoint chiar inainte de marker, ca montarea snapshot-ului sa nu
+        // aiba nevoie de replay de la inceput
+        self.write_checkpoint()?;
+
+        let created_at = self.options.clock.now();
+        self.log(&Record::Snapshot {
+            name: name.to_string(),
+            created_at,
+        })?;
+        let offset = self.file.seek(SeekFrom::End(0))?;
+        self.file.sync_all()?;
+
+        let info = SnapshotInfo {
+            name: name.to_string(),
+            offset,
+            created_at,
+        };
+        self.snapshots.insert(name.to_string(), info.clone());
+        Ok(info)
+    }
+
+    fn delete_snapshot(&mut self, name: &str) -> Result<()> {
+        if !self.snapshots.contains_key(name) {
+            return Err(VfsError::NotFound(format!("snapshot {name}")));
+        }
+        self.log(&Record::SnapshotDelete {
+            name: name.to_string(),
+        })?;
+        self.snapshots.remove(name);
+        Ok(())
+    }
+}
@@ -1,7 +1,21 @@
+use crate::clock::{Clock, SourceDateEpochClock, SystemClock};
+use crate::path::VfsPath;
+use std::collections::BTreeMap;
+use std::rc::Rc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 pub const DEFAULT_BLOCK_SIZE: u32 = 4096;
 
+/// permisiunile implicite pentru fisiere noi.
+pub const DEFAULT_FILE_MODE: u32 = 0o644;
+/// permisiunile implicite pentru directoare noi.
+pub const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// limita pentru un nume de xattr, in bytes.
+pub const XATTR_NAME_MAX: usize = 255;
+/// limita totala (nume + valori) pentru xattr-urile unui inode.
+pub const XATTR_INODE_MAX: usize = 64 * 1024;
+
 /// normalized timestamp representation stored as UNIX nanoseconds.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Timestamp(pub i128);
@@ -34,6 +48,70 @@ impl From<Timestamp> for SystemTime {
     }
 }
 
+/// componentele UTC ale unui `Timestamp`.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct UtcTime {
+    pub year: i128,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    pub nanos: u32,
+    /// 0 = duminica
+    #[cfg(feature = "webdav")]
+    pub weekday: u32,
+}
+
+impl Timestamp {
+    pub(crate) fn utc(self) -> UtcTime {
+        let secs = self.0.div_euclid(1_000_000_000);
+        let days = secs.div_euclid(86_400);
+        let rem = secs.rem_euclid(86_400) as u32;
+
+        // zile de la epoch -> data civila (Howard Hinnant)
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+        UtcTime {
+            year: yoe + era * 400 + i128::from(month <= 2),
+            month: month as u32,
+            day: day as u32,
+            hour: rem / 3600,
+            minute: rem % 3600 / 60,
+            second: rem % 60,
+            nanos: self.0.rem_euclid(1_000_000_000) as u32,
+            // 1970-01-01 a fost joi
+            #[cfg(feature = "webdav")]
+            weekday: (days + 4).rem_euclid(7) as u32,
+        }
+    }
+}
+
+/// UTC, `2024-01-31 23:59:59.123456789`; precizia (`{:.3}`) alege cate zecimale.
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let t = self.utc();
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            t.year, t.month, t.day, t.hour, t.minute, t.second
+        )?;
+        let digits = f.precision().unwrap_or(9).min(9);
+        if digits > 0 {
+            let frac = format!("{:09}", t.nanos);
+            write!(f, ".{}", &frac[..digits])?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct InodeId(pub u64);
 
@@ -48,6 +126,165 @@ pub struct Metadata {
     pub size: u64,
     pub created_at: Timestamp,
     pub modified_at: Timestamp,
+    /// last read of the contents, updated according to the mount's [`AtimePolicy`].
+    pub accessed_at: Timestamp,
+    /// last change of the inode itself (contents, name, permissions, xattrs).
+    pub changed_at: Timestamp,
+    /// permission bits (`0o7777`), same layout as unix `st_mode`.
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+}
+
+impl Metadata {
+    /// true daca macar un bit de executie e setat.
+    pub fn is_executable(&self) -> bool {
+        self.mode & 0o111 != 0
+    }
+}
+
+/// identity used for access checks when a vfs is mounted "as" a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub uid: u32,
+    pub gid: u32,
+    /// supplementary groups.
+    pub groups: Vec<u32>,
+}
+
+impl Credentials {
+    pub fn new(uid: u32, gid: u32) -> Self {
+        Self {
+            uid,
+            gid,
+            groups: Vec::new(),
+        }
+    }
+
+    pub fn in_group(&self, gid: u32) -> bool {
+        self.gid == gid || self.groups.contains(&gid)
+    }
+}
+
+/// timestamps accepted by `Vfs::set_times`; `None` leaves the field unchanged.
+///
+/// `changed_at` can't be set explicitly, it always becomes the time of the call.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FileTimes {
+    pub created_at: Option<Timestamp>,
+    pub modified_at: Option<Timestamp>,
+    pub accessed_at: Option<Timestamp>,
+}
+
+/// when reads update `accessed_at`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AtimePolicy {
+    /// reads never touch the access time.
+    NoAtime,
+    /// like linux `relatime`: update only if the access time is older than the
+    /// last modification/change, or older than a day.
+    #[default]
+    Relatime,
+    /// every read updates the access time (one extra record per read).
+    Strict,
+}
+
+/// options applied when mounting an image.
+#[derive(Debug, Clone)]
+pub struct MountOptions {
+    /// daca e setat, toate operatiile sunt verificate contra permisiunilor
+    /// acestui user si inode-urile noi ii apartin.
+    pub user: Option<Credentials>,
+    pub atime: AtimePolicy,
+    /// de unde vin toti timpii scrisi in log (`InodeAlloc`, `SetTimes`).
+    pub clock: Rc<dyn Clock>,
+    /// backing file-ul e deschis doar pentru citire; orice modificare da `ReadOnly`,
+    /// inclusiv cele sub montarile atasate la handle.
+    pub read_only: bool,
+    /// ce versiuni vechi ale fisierelor supravietuiesc unui `compact`.
+    pub retention: Retention,
+    /// daca `remove_file`/`remove_dir` muta intrarile in trash.
+    pub trash: TrashPolicy,
+    /// daca e setat, scrierile sunt impartite in chunk-uri si un chunk deja
+    /// prezent in log e referit in loc sa fie scris din nou.
+    pub dedup: Option<Chunking>,
+    /// daca e setat, datele scrise sunt comprimate (unde merita).
+    pub compression: Option<CompressionPolicy>,
+    /// key for encrypted images; creating an image with a key encrypts it.
+    pub encryption: Option<EncryptionKey>,
+}
+
+impl Default for MountOptions {
+    fn default() -> Self {
+        Self {
+            user: None,
+            atime: AtimePolicy::default(),
+            clock: Rc::new(SystemClock),
+            read_only: false,
+            retention: Retention::default(),
+            trash: TrashPolicy::default(),
+            dedup: None,
+            compression: None,
+            encryption: None,
+        }
+    }
+}
+
+impl MountOptions {
+    /// optiunile implicite, cu ceasul din `SOURCE_DATE_EPOCH` daca e setat;
+    /// cu ele monteaza uneltele din linia de comanda.
+    pub fn from_env() -> Result<Self> {
+        let mut options = Self::default();
+        if let Some(clock) = SourceDateEpochClock::from_env()? {
+            options.clock = Rc::new(clock);
+        }
+        Ok(options)
+    }
+}
+
+/// 256-bit key of an encrypted image. needs the `encryption` cargo feature.
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey([u8; 32]);
+
+impl EncryptionKey {
+    pub fn from_bytes(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("EncryptionKey(..)")
+    }
+}
+
+/// compression algorithm of an image, fixed when the image is created.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// needs the `zstd` cargo feature to read or write compressed data.
+    Zstd,
+}
+
+/// how the bytes of an extent are stored in the backing file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Raw,
+    /// `stored_len` bytes at `file_offset` decompress to a buffer in which
+    /// this extent starts `skip` bytes in. in encrypted images every extent is
+    /// stored this way (with `algo` `None` if it isn't compressed), since the
+    /// bytes can only be decrypted as a whole.
+    Compressed {
+        algo: Compression,
+        stored_len: u64,
+        skip: u64,
+    },
 }
 
 /// logical range pointing to bytes inside the backing file.
@@ -58,11 +295,63 @@ pub struct Extent {
     /// offset inside the backing store where this extent begins.
     pub file_offset: u64,
     pub len: u64,
+    pub encoding: Encoding,
+}
+
+impl Extent {
+    /// extent necomprimat.
+    pub fn raw(logical_offset: u64, file_offset: u64, len: u64) -> Self {
+        Self {
+            logical_offset,
+            file_offset,
+            len,
+            encoding: Encoding::Raw,
+        }
+    }
+
+    /// bucata `[from, to)` (offset-uri logice) din extent.
+    pub fn slice(&self, from: u64, to: u64) -> Self {
+        let delta = from - self.logical_offset;
+        let (file_offset, encoding) = match self.encoding {
+            Encoding::Raw => (self.file_offset + delta, Encoding::Raw),
+            Encoding::Compressed {
+                algo,
+                stored_len,
+                skip,
+            } => (
+                self.file_offset,
+                Encoding::Compressed {
+                    algo,
+                    stored_len,
+                    skip: skip + delta,
+                },
+            ),
+        };
+        Self {
+            logical_offset: from,
+            file_offset,
+            len: to - from,
+            encoding,
+        }
+    }
+
+    /// intervalul din backing file de care depinde extent-ul.
+    pub fn stored_range(&self) -> (u64, u64) {
+        match self.encoding {
+            Encoding::Raw => (self.file_offset, self.file_offset + self.len),
+            Encoding::Compressed { stored_len, .. } => {
+                (self.file_offset, self.file_offset + stored_len)
+            }
+        }
+    }
 }
 
 /// file data is stored as a set of extents.
 pub type ExtentList = Vec<Extent>;
 
+/// extended attributes of an inode, ordered by name.
+pub type XattrMap = BTreeMap<String, Vec<u8>>;
+
 /// on-disk directory entry metadata.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DirEntry {
@@ -81,6 +370,230 @@ pub struct Inode {
     pub kind: NodeKind,
     pub metadata: Metadata,
     pub extents: ExtentList,
+    pub xattrs: XattrMap,
+}
+
+/// named, durable marker of a past state of the log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub name: String,
+    /// log offset right after the marker; replaying up to here gives the snapshot state.
+    pub offset: u64,
+    pub created_at: Timestamp,
+}
+
+/// one historical state of a file, as listed by `Vfs::versions`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileVersion {
+    /// log offset right after the write that produced this version.
+    pub offset: u64,
+    pub modified_at: Timestamp,
+    pub size: u64,
+}
+
+/// which old file versions are kept when the image is compacted.
+///
+/// a version is kept if either rule keeps it; the current contents are always
+/// kept. the default keeps nothing but the current contents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Retention {
+    /// keep the last N versions, counting the current one.
+    pub keep_last: Option<usize>,
+    /// keep versions modified less than this long ago.
+    pub keep_for: Option<Duration>,
+}
+
+/// what `remove_file`/`remove_dir` do with the removed entry.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TrashPolicy {
+    /// move removed entries to the trash instead of dropping them.
+    pub enabled: bool,
+    /// entries trashed longer than this are purged by `compact`;
+    /// `None` keeps them until `empty_trash`.
+    pub purge_after: Option<Duration>,
+}
+
+/// a removed file or directory waiting in the trash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashEntry {
+    pub inode: InodeId,
+    pub kind: NodeKind,
+    /// absolute path the entry had when it was removed.
+    pub original_path: VfsPath,
+    pub deleted_at: Timestamp,
+}
+
+/// how written data is split into chunks when dedup is on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunking {
+    /// chunks of exactly this many bytes (the last one may be shorter).
+    Fixed(u32),
+    /// boundaries picked by a rolling hash over the data, around this average
+    /// size; an insertion only changes the chunks next to it.
+    ContentDefined { avg: u32 },
+}
+
+/// one chunk in the dedup index persisted by checkpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DedupEntry {
+    /// crc32 of the chunk bytes.
+    pub checksum: u32,
+    /// where the chunk is stored; `logical_offset` is unused.
+    pub extent: Extent,
+}
+
+/// when writes are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionPolicy {
+    /// algorithm for new images; an existing image keeps the one in its header.
+    pub algo: Compression,
+    pub level: i32,
+    /// smaller writes are stored raw.
+    pub min_size: usize,
+    /// data that doesn't shrink below this percentage is stored raw.
+    pub max_ratio_percent: u8,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self {
+            algo: Compression::Zstd,
+            level: 3,
+            min_size: 512,
+            max_ratio_percent: 90,
+        }
+    }
+}
+
+/// space accounting returned by `Vfs::stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FsStats {
+    pub files: u64,
+    pub dirs: u64,
+    /// sum of file sizes, as seen by readers.
+    pub logical_bytes: u64,
+    /// backing-file bytes still referenced by files (trash included),
+    /// counted once even when shared by clones.
+    pub referenced_bytes: u64,
+    /// part of `referenced_bytes` shared by more than one file.
+    pub shared_bytes: u64,
+    /// file data stored in the backing file, live or not.
+    pub physical_bytes: u64,
+    /// size of the backing file.
+    pub image_bytes: u64,
+}
+
+/// options for `Vfs::import_dir_with` and `Vfs::export_dir_with`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncOptions {
+    /// like rsync: skip files whose size and modification time already match.
+    pub incremental: bool,
+    /// remove entries of the destination that don't exist in the source.
+    pub delete: bool,
+}
+
+/// what a directory sync did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    /// files written to the destination.
+    pub copied: u64,
+    /// files left alone because they were already up to date.
+    pub unchanged: u64,
+    pub dirs_created: u64,
+    /// files and directories removed because of `SyncOptions::delete`.
+    pub deleted: u64,
+    /// file data written, in bytes.
+    pub bytes: u64,
+    /// host entries that the vfs can't represent (symlinks, devices, sockets).
+    pub skipped: Vec<std::path::PathBuf>,
+}
+
+/// result of `Vfs::fsck`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FsckReport {
+    /// records read before the end of the valid log.
+    pub records: u64,
+    /// offset right after the last valid record.
+    pub log_end: u64,
+    /// size of the backing file; more than `log_end` means a torn or corrupt tail.
+    pub image_bytes: u64,
+    /// one human-readable line per problem found.
+    pub problems: Vec<String>,
+}
+
+impl FsckReport {
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// header and log summary returned by `Vfs::info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageInfo {
+    pub header: Header,
+    pub log_end: u64,
+    pub image_bytes: u64,
+    /// number of valid records per `Record::kind`.
+    pub records: std::collections::BTreeMap<&'static str, u64>,
+}
+
+/// bytes din log de la `offset`, asa cum ajung la o replica.
+///
+/// prima schimbare a unei imagini (offset 0) e header-ul; celelalte sunt cate un
+/// record cu datele lui, exact ca in imaginea sursa.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    /// `Header::generation` al sursei; o replica refuza alta generatie, pt ca
+    /// `compact` a mutat toate offset-urile.
+    pub generation: u32,
+    pub offset: u64,
+    pub bytes: Vec<u8>,
+}
+
+/// o schimbare trimisa celor care urmaresc un path cu `Vfs::watch`.
+///
+/// path-urile sunt absolute. scrierile, trunchierile si atributele (permisiuni,
+/// owner, xattr-uri) sunt `Modified`; timpii singuri nu se anunta.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Created(VfsPath),
+    Modified(VfsPath),
+    Removed(VfsPath),
+    Renamed { from: VfsPath, to: VfsPath },
+}
+
+/// what `FileSystem::metadata` reports, for any backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsMetadata {
+    pub kind: NodeKind,
+    /// size, times and permission bits; backends without owners report uid/gid 0.
+    pub metadata: Metadata,
+}
+
+impl FsMetadata {
+    pub fn is_dir(&self) -> bool {
+        self.kind == NodeKind::Dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == NodeKind::File
+    }
+}
+
+/// one entry of `FileSystem::read_dir`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FsEntry {
+    pub name: String,
+    pub kind: NodeKind,
+}
+
+/// how far to replay the log when reconstructing a past state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    /// every record that ends at or before this log offset.
+    Offset(u64),
+    /// every operation whose timestamp is at or before this moment.
+    Time(Timestamp),
 }
 
 /// header persisted at the start of the backing file.
@@ -90,6 +603,20 @@ pub struct Header {
     pub version: u32,
     pub block_size: u32,
     pub root: InodeId,
+    pub compression: Compression,
+    pub encryption: Option<HeaderEncryption>,
+    /// bumped whenever `compact` or `upgrade` rewrites the file; a reader still
+    /// holding the old file sees a different value on disk.
+    pub generation: u32,
+}
+
+/// encryption parameters stored in the header of an encrypted image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderEncryption {
+    /// random, replaced by `compact`; nonces are this id plus a log offset.
+    pub image_id: [u8; 16],
+    /// known plaintext sealed with the key, to tell a wrong key from corruption.
+    pub key_check: [u8; 32],
 }
 
 /// snapshot of the free list and inode table used to accelerate mounts.
@@ -98,6 +625,9 @@ pub struct Checkpoint {
     pub next_inode: InodeId,
     pub free_extents: ExtentList,
     pub inodes: Vec<InodeSnapshot>,
+    /// trashed inodes are in `inodes` too, but not linked in their parent.
+    pub trash: Vec<TrashEntry>,
+    pub dedup: Vec<DedupEntry>,
 }
 
 /// inode snapshot persisted in checkpoints.
@@ -109,6 +639,7 @@ pub struct InodeSnapshot {
     pub kind: NodeKind,
     pub metadata: Metadata,
     pub extents: ExtentList,
+    pub xattrs: XattrMap,
 }
 
 ///operations persisted in the log
@@ -139,6 +670,8 @@ pub enum Record {
         inode: InodeId,
         created_at: Option<Timestamp>,
         modified_at: Option<Timestamp>,
+        accessed_at: Option<Timestamp>,
+        changed_at: Option<Timestamp>,
     },
     Rename {
         inode: InodeId,
@@ -147,6 +680,119 @@ pub enum Record {
         old_name: String,
         new_name: String,
     },
+    SetXattr {
+        inode: InodeId,
+        name: String,
+        value: Vec<u8>,
+    },
+    RemoveXattr {
+        inode: InodeId,
+        name: String,
+    },
+    SetPermissions {
+        inode: InodeId,
+        mode: u32,
+    },
+    Chown {
+        inode: InodeId,
+        uid: u32,
+        gid: u32,
+    },
+    Snapshot {
+        name: String,
+        created_at: Timestamp,
+    },
+    SnapshotDelete {
+        name: String,
+    },
+    /// unlink `name` from `parent` and keep the inode in the trash.
+    Trash {
+        parent: InodeId,
+        name: String,
+        entry: TrashEntry,
+    },
+    TrashRestore {
+        inode: InodeId,
+        parent: InodeId,
+        name: String,
+    },
+    TrashPurge {
+        inode: InodeId,
+    },
+    /// like `DataWrite`, but the bytes are already in the log at `file_offset`.
+    DataRef {
+        inode: InodeId,
+        extent: Extent,
+    },
+    /// like `DataWrite`, with the data compressed; `len` is the uncompressed
+    /// length and `checksum` its crc32.
+    CompressedWrite {
+        inode: InodeId,
+        logical_offset: u64,
+        len: u64,
+        algo: Compression,
+        stored_len: u64,
+        checksum: u32,
+    },
+    /// raw bytes referenced by extents of a checkpoint (written by compaction).
+    Blob {
+        len: u64,
+        checksum: u32,
+    },
+}
+
+impl Record {
+    /// every name `kind` can return, in declaration order.
+    pub const KINDS: &'static [&'static str] = &[
+        "header",
+        "checkpoint",
+        "inode_alloc",
+        "dir_entry_add",
+        "dir_entry_remove",
+        "data_write",
+        "truncate",
+        "set_times",
+        "rename",
+        "set_xattr",
+        "remove_xattr",
+        "set_permissions",
+        "chown",
+        "snapshot",
+        "snapshot_delete",
+        "trash",
+        "trash_restore",
+        "trash_purge",
+        "data_ref",
+        "compressed_write",
+        "blob",
+    ];
+
+    /// short name of the record type, as shown by the tools.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Record::Header(_) => "header",
+            Record::Checkpoint(_) => "checkpoint",
+            Record::InodeAlloc(_) => "inode_alloc",
+            Record::DirEntryAdd { .. } => "dir_entry_add",
+            Record::DirEntryRemove { .. } => "dir_entry_remove",
+            Record::DataWrite { .. } => "data_write",
+            Record::Truncate { .. } => "truncate",
+            Record::SetTimes { .. } => "set_times",
+            Record::Rename { .. } => "rename",
+            Record::SetXattr { .. } => "set_xattr",
+            Record::RemoveXattr { .. } => "remove_xattr",
+            Record::SetPermissions { .. } => "set_permissions",
+            Record::Chown { .. } => "chown",
+            Record::Snapshot { .. } => "snapshot",
+            Record::SnapshotDelete { .. } => "snapshot_delete",
+            Record::Trash { .. } => "trash",
+            Record::TrashRestore { .. } => "trash_restore",
+            Record::TrashPurge { .. } => "trash_purge",
+            Record::DataRef { .. } => "data_ref",
+            Record::CompressedWrite { .. } => "compressed_write",
+            Record::Blob { .. } => "blob",
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -156,8 +802,15 @@ pub enum VfsError {
     NotAFile(String),
     NotADir(String),
     InvalidPath(String),
+    NoSpace(String),
+    PermissionDenied(String),
+    ReadOnly(String),
+    Unsupported(String),
+    WrongKey(String),
     CorruptLog(String),
     UnsupportedVersion(u32),
+    /// operatie intre doua montari diferite (ex. rename din `/deps` in `/`).
+    CrossesMount(String),
     Io(std::io::Error),
 }
 
@@ -169,8 +822,14 @@ impl std::fmt::Display for VfsError {
             VfsError::NotAFile(p) => write!(f, "not a file: {p}"),
             VfsError::NotADir(p) => write!(f, "not a dir: {p}"),
             VfsError::InvalidPath(p) => write!(f, "invalid path: {p}"),
+            VfsError::NoSpace(m) => write!(f, "no space: {m}"),
+            VfsError::PermissionDenied(p) => write!(f, "permission denied: {p}"),
+            VfsError::ReadOnly(p) => write!(f, "read-only: {p}"),
+            VfsError::Unsupported(m) => write!(f, "unsupported: {m}"),
+            VfsError::WrongKey(m) => write!(f, "wrong key: {m}"),
             VfsError::CorruptLog(m) => write!(f, "corrupt log: {m}"),
             VfsError::UnsupportedVersion(v) => write!(f, "unsupported version: {v}"),
+            VfsError::CrossesMount(m) => write!(f, "crosses a mount boundary: {m}"),
             VfsError::Io(e) => write!(f, "io error: {e}"),
         }
     }
@@ -0,0 +1,427 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::fs::replace_by_rename;
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::Vfs;
+
+// scrierile in vfs se fac in bucati de marimea asta (un record per bucata)
+const COPY_BUF: usize = 1 << 20;
+
+impl Vfs {
+    /// copiaza recursiv continutul directorului `host` sub `dest` (creat daca
+    /// lipseste), pastrand timpii si permisiunile.
+    pub fn import_dir<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        host: P,
+        dest: Q,
+    ) -> Result<SyncReport> {
+        self.import_dir_with(host, dest, SyncOptions::default())
+    }
+
+    /// ca `import_dir`; cu `incremental` copiaza doar fisierele cu alt size sau
+    /// alt mtime, cu `delete` sterge din `dest` ce nu mai exista pe host.
+    pub fn import_dir_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        host: P,
+        dest: Q,
+        options: SyncOptions,
+    ) -> Result<SyncReport> {
+        let host = host.as_ref();
+        if !fs::metadata(host)?.is_dir() {
+            return Err(VfsError::NotADir(host.display().to_string()));
+        }
+        let dest = VfsPath::new(dest)?;
+        let mut report = SyncReport::default();
+        self.ensure_vfs_dir(&dest, &mut report)?;
+        self.import_tree(host, &dest, options, &mut report)?;
+        Ok(report)
+    }
+
+    /// copiaza recursiv directorul `src` din vfs in `host` (creat daca lipseste).
+    pub fn export_dir<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        src: P,
+        host: Q,
+    ) -> Result<SyncReport> {
+        self.export_dir_with(src, host, SyncOptions::default())
+    }
+
+    /// ca `export_dir`, cu aceleasi optiuni ca `import_dir_with`.
+    pub fn export_dir_with<P: AsRef<Path>, Q: AsRef<Path>>(
+        &self,
+        src: P,
+        host: Q,
+        options: SyncOptions,
+    ) -> Result<SyncReport> {
+        let src = VfsPath::new(src)?;
+        let host = host.as_ref();
+        let mut report = SyncReport::default();
+        if !host.is_dir() {
+            fs::create_dir_all(host)?;
+            report.dirs_created += 1;
+        }
+        self.export_tree(&src, host, options, &mut report)?;
+        Ok(report)
+    }
+
+    fn ensure_vfs_dir(&mut self, path: &VfsPath, report: &mut SyncReport) -> Result<()> {
+        match self.read_dir(path.as_str()) {
+            Ok(_) => Ok(()),
+            Err(VfsError::NotFound(_)) => {
+                self.create_dir(path.as_str())?;
+                report.dirs_created += 1;
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn import_tree(
+        &mut self,
+        host: &Path,
+        dest: &VfsPath,
+        options: SyncOptions,
+        report: &mut SyncReport,
+    ) -> Result<()> {
+        let mut seen = HashSet::new();
+        let mut entries: Vec<fs::DirEntry> = fs::read_dir(host)?.collect::<std::io::Result<_>>()?;
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                report.skipped.push(entry.path());
+                continue;
+            };
+            let meta = entry.metadata()?;
+            let path = dest.join(&name)?;
+            let existing = self.metadata(path.as_str()).ok();
+
+            if meta.is_dir() {
+                seen.insert(name);
+                match existing {
+                    None => {
+                        self.create_dir(path.as_str())?;
+                        report.dirs_created += 1;
+                    }
+                    // ca la export: un fisier cu acelasi nume nu devine director
+                    Some(_) if self.kind_of(path.as_ref())? != NodeKind::Dir => {
+                        return Err(VfsError::NotADir(path.to_string()));
+                    }
+                    // un director importat read-only data trecuta; modul revine dupa copii
+                    Some(m) if m.mode & 0o700 != 0o700 => {
+                        self.set_permissions(path.as_str(), m.mode | 0o700)?;
+                    }
+                    Some(_) => {}
+                }
+                self.import_tree(&entry.path(), &path, options, report)?;
+            } else if meta.is_file() {
+                seen.insert(name);
+                let unchanged = existing.as_ref().is_some_and(|m| {
+                    m.size == meta.len() && m.modified_at == host_time(meta.modified())
+                });
+                if options.incremental && unchanged {
+                    report.unchanged += 1;
+                    continue;
+                }
+                // un fisier existent se scrie alaturi si e inlocuit abia la sfarsit: o
+                // eroare de citire de pe host nu pierde continutul vechi, iar unul
+                // read-only nu trebuie deschis pt scriere
+                let target = match existing {
+                    Some(_) if self.kind_of(path.as_ref())? != NodeKind::File => {
+                        return Err(VfsError::NotAFile(path.to_string()));
+                    }
+                    Some(_) => self.unused_sibling(&path, "vfs-import")?,
+                    None => path.clone(),
+                };
+                let copied = (|| -> Result<u64> {
+                    let mut out = BufWriter::with_capacity(COPY_BUF, self.create(target.as_str())?);
+                    let n = std::io::copy(&mut fs::File::open(entry.path())?, &mut out)?;
+                    out.flush()?;
+                    Ok(n)
+                })();
+                let bytes = match copied {
+                    Ok(n) => n,
+                    Err(e) => {
+                        if self.exists(target.as_str()) {
+                            self.remove_file(target.as_str())?;
+                        }
+                        return Err(e);
+                    }
+                };
+                if target != path {
+                    for name in self.list_xattrs(path.as_str())? {
+                        if let Some(value) = self.get_xattr(path.as_str(), &name)? {
+                            self.set_xattr(target.as_str(), &name, &value)?;
+                        }
+                    }
+                    let mut vfs = self.clone();
+                    replace_by_rename(
+                        &*self,
+                        target.as_ref(),
+                        path.as_ref(),
+                        NodeKind::File,
+                        |a, b| vfs.rename(a, b),
+                    )?;
+                }
+                report.bytes += bytes;
+                report.copied += 1;
+            } else {
+                // symlink-uri, fifo-uri, device-uri
+                report.skipped.push(entry.path());
+                continue;
+            }
+
+            self.set_permissions(path.as_str(), host_mode(&meta))?;
+            let modified = host_time(meta.modified());
+            self.set_times(
+                path.as_str(),
+                FileTimes {
+                    created_at: Some(meta.created().map_or(modified, Timestamp::from)),
+                    modified_at: Some(modified),
+                    accessed_at: Some(meta.accessed().map_or(modified, Timestamp::from)),
+                },
+            )?;
+        }
+
+        if options.delete {
+            let mut stale: Vec<DirEntry> = self
+                .read_dir(dest.as_str())?
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .filter(|e| !seen.contains(&e.name))
+                .collect();
+            stale.sort_by(|a, b| a.name.cmp(&b.name));
+            for entry in stale {
+                report.deleted += self.remove_vfs_tree(&dest.join(&entry.name)?, entry.kind)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_to_host(&self, path: &VfsPath, host: &Path) -> Result<u64> {
+        let mut from = self.open(path.as_str())?;
+        let mut to = fs::File::create_new(host)?;
+        let mut buf = vec![0u8; COPY_BUF];
+        let mut total = 0;
+        loop {
+            let n = from.read(&mut buf)?;
+            if n == 0 {
+                return Ok(total);
+            }
+            to.write_all(&buf[..n])?;
+            total += n as u64;
+        }
+    }
+
+    // `.nume.tag-N` langa `path`, inca nefolosit
+    fn unused_sibling(&self, path: &VfsPath, tag: &str) -> Result<VfsPath> {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(VfsError::InvalidPath(path.to_string()));
+        };
+        let mut n = 0;
+        loop {
+            let candidate = parent.join(format!(".{name}.{tag}-{n}"))?;
+            if !self.exists(candidate.as_str()) {
+                return Ok(candidate);
+            }
+            n += 1;
+        }
+    }
+
+    // sterge recursiv; intoarce cate intrari au disparut
+    fn remove_vfs_tree(&mut self, path: &VfsPath, kind: NodeKind) -> Result<u64> {
+        if kind == NodeKind::File {
+            self.remove_file(path.as_str())?;
+            return Ok(1);
+        }
+        let children: Vec<DirEntry> = self.read_dir(path.as_str())?.collect::<Result<_>>()?;
+        let mut removed = 1;
+        for child in children {
+            removed += self.remove_vfs_tree(&path.join(&child.name)?, child.kind)?;
+        }
+        self.remove_dir(path.as_str())?;
+        Ok(removed)
+    }
+
+    fn export_tree(
+        &self,
+        src: &VfsPath,
+        host: &Path,
+        options: SyncOptions,
+        report: &mut SyncReport,
+    ) -> Result<()> {
+        let mut entries: Vec<DirEntry> = self.read_dir(src.as_str())?.collect::<Result<_>>()?;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let names: HashSet<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+
+        if options.delete {
+            for stale in fs::read_dir(host)? {
+                let stale = stale?;
+                if stale
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|n| names.contains(n))
+                {
+                    continue;
+                }
+                let path = stale.path();
+                let ty = stale.file_type()?;
+                if ty.is_dir() {
+                    report.deleted += count_host_tree(&path)?;
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                    report.deleted += 1;
+                }
+            }
+        }
+
+        for entry in &entries {
+            let path = src.join(&entry.name)?;
+            let target = host.join(&entry.name);
+            let meta = self.metadata(path.as_str())?;
+            let existing = fs::symlink_metadata(&target).ok();
+
+            match entry.kind {
+                NodeKind::Dir => {
+                    match &existing {
+                        // modul exportat data trecuta poate fi read-only; se pune la
+                        // loc dupa copii
+                        Some(m) if m.is_dir() => make_host_writable(&target, m)?,
+                        Some(_) => return Err(VfsError::NotADir(target.display().to_string())),
+                        None => {
+                            fs::create_dir(&target)?;
+                            report.dirs_created += 1;
+                        }
+                    }
+                    self.export_tree(&path, &target, options, report)?;
+                }
+                NodeKind::File => {
+                    let unchanged = existing.as_ref().is_some_and(|m| {
+                        m.is_file()
+                            && m.len() == meta.size
+                            && host_time(m.modified()) == meta.modified_at
+                    });
+                    if options.incremental && unchanged {
+                        report.unchanged += 1;
+                        continue;
+                    }
+                    if existing.as_ref().is_some_and(|m| !m.is_file()) {
+                        return Err(VfsError::NotAFile(target.display().to_string()));
+                    }
+                    // se scrie alaturi si se muta peste tinta: un fisier exportat
+                    // read-only nu poate fi deschis pt scriere, iar o eroare la
+                    // jumatate nu trebuie sa lase tinta trunchiata
+                    let temp = unused_host_sibling(&target, "vfs-export");
+                    match self.copy_to_host(&path, &temp) {
+                        Ok(n) => report.bytes += n,
+                        Err(e) => {
+                            let _ = fs::remove_file(&temp);
+                            return Err(e);
+                        }
+                    }
+                    fs::rename(&temp, &target)?;
+                    report.copied += 1;
+                }
+            }
+
+            // timpii directoarelor se pun dupa copii, altfel i-ar schimba scrierile
+            set_host_metadata(&target, &meta)?;
+        }
+        Ok(())
+    }
+}
+
+fn unused_host_sibling(path: &Path, tag: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut n = 0;
+    loop {
+        let candidate = path.with_file_name(format!(".{name}.{tag}-{n}"));
+        if fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+#[cfg(unix)]
+fn make_host_writable(path: &Path, meta: &fs::Metadata) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = meta.permissions().mode();
+    if mode & 0o700 != 0o700 {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode | 0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_host_writable(path: &Path, meta: &fs::Metadata) -> Result<()> {
+    let mut perms = meta.permissions();
+    if perms.readonly() {
+        perms.set_readonly(false);
+        fs::set_permissions(path, perms)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn host_time(t: std::io::Result<SystemTime>) -> Timestamp {
+    t.map_or(Timestamp(0), Timestamp::from)
+}
+
+#[cfg(unix)]
+pub(crate) fn host_mode(meta: &fs::Metadata) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+pub(crate) fn host_mode(meta: &fs::Metadata) -> u32 {
+    let mode = if meta.is_dir() {
+        DEFAULT_DIR_MODE
+    } else {
+        DEFAULT_FILE_MODE
+    };
+    if meta.permissions().readonly() {
+        mode & !0o222
+    } else {
+        mode
+    }
+}
+
+fn set_host_metadata(target: &Path, meta: &Metadata) -> Result<()> {
+    let times = fs::FileTimes::new()
+        .set_modified(meta.modified_at.into())
+        .set_accessed(meta.accessed_at.into());
+    fs::File::open(target)?.set_times(times)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target, fs::Permissions::from_mode(meta.mode & 0o7777))?;
+    }
+    #[cfg(not(unix))]
+    {
+        let mut perms = fs::metadata(target)?.permissions();
+        perms.set_readonly(meta.mode & 0o222 == 0);
+        fs::set_permissions(target, perms)?;
+    }
+    Ok(())
+}
+
+fn count_host_tree(path: &Path) -> Result<u64> {
+    let mut n = 1;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        n += if entry.file_type()?.is_dir() {
+            count_host_tree(&entry.path())?
+        } else {
+            1
+        };
+    }
+    Ok(n)
+}
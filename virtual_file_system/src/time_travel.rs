@@ -0,0 +1,536 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::compact::visible_extents;
+use crate::structs::*;
+use crate::vfs::{Inner, Vfs};
+
+impl Vfs {
+    /// monteaza read-only starea imaginii de la momentul `until`.
+    pub fn mount_at<P: AsRef<Path>>(path: P, until: Until) -> Result<Vfs> {
+        Self::mount_at_with(path, until, MountOptions::default())
+    }
+
+    /// ca `mount_at`, cu optiuni (de ex. cheia unei imagini criptate);
+    /// montarea e mereu read-only.
+    pub fn mount_at_with<P: AsRef<Path>>(
+        path: P,
+        until: Until,
+        options: MountOptions,
+    ) -> Result<Vfs> {
+        let options = MountOptions {
+            read_only: true,
+            ..options
+        };
+        let mut inner = Inner::open(path.as_ref(), options, u64::MAX)?;
+        let limit = inner.resolve_until(until)?;
+        inner.replay_until(limit)?;
+        Ok(Vfs::from_inner(Rc::new(RefCell::new(inner))))
+    }
+
+    /// readuce imaginea live la starea de la `until`. istoricul ramane in log:
+    /// diferentele fata de starea veche se scriu ca operatii obisnuite (stergeri,
+    /// mutari, truncate + referinte la datele vechi, permisiuni...), deci replicile,
+    /// montarile care urmaresc log-ul si `watch` le vad ca pe orice alta schimbare.
+    pub fn restore_to(&mut self, until: Until) -> Result<()> {
+        self.inner.borrow_mut().restore_to(until)
+    }
+}
+
+impl Inner {
+    /// offset-ul pana la care trebuie facut replay pentru `until`.
+    pub(crate) fn resolve_until(&mut self, until: Until) -> Result<u64> {
+        // primul record e inode-ul root; inainte de el nu exista nicio stare
+        let Some((_, first)) = self.read_record(self.header.log_start())? else {
+            return Err(VfsError::CorruptLog("empty log".into()));
+        };
+
+        let limit = match until {
+            Until::Offset(n) => n,
+            Until::Time(ts) => {
+                // record-urile fara timp (date, intrari in director...) apartin
+                // operatiei care se incheie cu urmatorul record cu timp
+                let mut limit = 0;
+                let mut off = self.header.log_start();
+                loop {
+                    let (decoded, next) = match self.read_record(off) {
+                        Ok(Some(r)) => r,
+                        Ok(None) | Err(VfsError::CorruptLog(_)) => break,
+                        Err(e) => return Err(e),
+                    };
+                    if let Some(t) = record_time(&decoded.record) {
+                        if t > ts {
+                            break;
+                        }
+                        limit = next;
+                    }
+                    off = next;
+                }
+                limit
+            }
+        };
+
+        if limit < first {
+            return Err(VfsError::NotFound(format!(
+                "no state at {until:?} (image starts at offset {first})"
+            )));
+        }
+        Ok(limit)
+    }
+
+    fn restore_to(&mut self, until: Until) -> Result<()> {
+        self.ensure_writable()?;
+        let limit = self.resolve_until(until)?;
+        let past = Inner::open(&self.path, self.past_options(), limit)?;
+        let now = self.options.clock.now();
+        self.converge(&past, Some(now))?;
+        self.file.sync_all()?;
+        Ok(())
+    }
+
+    /// scrie in log operatiile obisnuite care aduc starea curenta la `target`
+    /// (alta stare a aceleiasi imagini, cu aceleasi id-uri de inode).
+    ///
+    /// cu `now`, inode-urile atinse primesc `changed_at = now`, ca la orice
+    /// modificare; fara, se copiaza si timpii de schimbare ai lui `target`.
+    pub(crate) fn converge(&mut self, target: &Inner, now: Option<Timestamp>) -> Result<()> {
+        let mut kids: HashMap<InodeId, usize> = HashMap::new();
+        for (parent, _) in self.children.keys() {
+            *kids.entry(*parent).or_default() += 1;
+        }
+        Restore {
+            inner: self,
+            past: target,
+            now,
+            kids,
+        }
+        .run()
+    }
+}
+
+/// unde trebuie sa ajunga un inode din starea veche.
+enum Place {
+    Linked(InodeId, String),
+    Trashed(TrashEntry),
+}
+
+/// diferenta dintre starea curenta si una veche, scrisa ca record-uri obisnuite.
+struct Restore<'a> {
+    inner: &'a mut Inner,
+    past: &'a Inner,
+    now: Option<Timestamp>,
+    /// cate intrari are acum fiecare director.
+    kids: HashMap<InodeId, usize>,
+}
+
+impl Restore<'_> {
+    fn run(&mut self) -> Result<()> {
+        let root = self.inner.header.root;
+        let mut ids: Vec<InodeId> = self.past.inodes.keys().copied().collect();
+        ids.sort_by_key(|id| id.0);
+
+        // primul record cu timp deschide operatia: o cautare dupa timp nu vede
+        // niciodata doar o parte din restore
+        if let Some(now) = self.now {
+            self.emit(Record::SetTimes {
+                inode: root,
+                created_at: None,
+                modified_at: None,
+                accessed_at: None,
+                changed_at: Some(now),
+            })?;
+        }
+
+        // inode-urile sterse intre timp revin cu datele lor, inca nelegate
+        for id in &ids {
+            if !self.inner.inodes.contains_key(id) {
+                let mut snap = snapshot(&self.past.inodes[id]);
+                if let Some(now) = self.now {
+                    snap.metadata.changed_at = now;
+                }
+                self.emit(Record::InodeAlloc(snap))?;
+            }
+        }
+
+        let mut doomed: Vec<InodeId> = self
+            .inner
+            .inodes
+            .keys()
+            .filter(|id| !self.past.inodes.contains_key(id))
+            .copied()
+            .collect();
+        doomed.sort_by_key(|id| id.0);
+        let mut pending: Vec<InodeId> = ids
+            .iter()
+            .copied()
+            .filter(|id| *id != root && !self.in_place(*id))
+            .collect();
+        let mut moved: HashSet<InodeId> = pending.iter().copied().collect();
+        let mut parked: HashSet<InodeId> = HashSet::new();
+
+        // stergerile asteapta sa se goleasca directorul, mutarile un loc liber;
+        // cand nimic nu mai avanseaza (de ex. doua nume schimbate intre ele),
+        // un inode se muta temporar in root sub un nume nefolosit
+        while !doomed.is_empty() || !pending.is_empty() {
+            let before = doomed.len() + pending.len();
+            for id in std::mem::take(&mut doomed) {
+                if !self.remove(id)? {
+                    doomed.push(id);
+                }
+            }
+            for id in std::mem::take(&mut pending) {
+                if !self.place(id)? {
+                    pending.push(id);
+                }
+            }
+            if doomed.len() + pending.len() < before {
+                continue;
+            }
+            let stuck = pending
+                .iter()
+                .copied()
+                .find(|id| !parked.contains(id) && self.linked_at(*id).is_some());
+            let Some(id) = stuck else {
+                return Err(VfsError::CorruptLog(
+                    "restore cannot rebuild the old tree".into(),
+                ));
+            };
+            let (old_parent, old_name) = self.linked_at(id).expect("checked above");
+            let new_name = self.temp_name(id);
+            self.emit(Record::Rename {
+                inode: id,
+                old_parent,
+                new_parent: root,
+                old_name,
+                new_name,
+            })?;
+            parked.insert(id);
+            moved.insert(root);
+        }
+
+        // la restore, root-ul inchide operatia cu un record cu timp
+        for id in ids {
+            let closes = id == root && self.now.is_some();
+            self.restore_inode(id, moved.contains(&id) || closes)?;
+        }
+        Ok(())
+    }
+
+    // scrie si aplica un record, tinand la zi numarul de intrari din directoare
+    fn emit(&mut self, rec: Record) -> Result<()> {
+        let (added, removed) = match &rec {
+            Record::DirEntryAdd { entry } => (Some(entry.parent), None),
+            Record::TrashRestore { parent, .. } => (Some(*parent), None),
+            Record::DirEntryRemove { parent, .. } | Record::Trash { parent, .. } => {
+                (None, Some(*parent))
+            }
+            Record::Rename {
+                old_parent,
+                new_parent,
+                ..
+            } => (Some(*new_parent), Some(*old_parent)),
+            _ => (None, None),
+        };
+        self.inner.log(&rec)?;
+        self.inner.apply_record(&rec)?;
+        if let Some(p) = added {
+            *self.kids.entry(p).or_default() += 1;
+        }
+        if let Some(p) = removed {
+            *self.kids.entry(p).or_default() -= 1;
+        }
+        Ok(())
+    }
+
+    fn target(&self, id: InodeId) -> Place {
+        if let Some(entry) = self.past.trash.get(&id) {
+            return Place::Trashed(entry.clone());
+        }
+        let node = &self.past.inodes[&id];
+        Place::Linked(
+            node.parent.unwrap_or(self.past.header.root),
+            node.name.clone(),
+        )
+    }
+
+    fn in_place(&self, id: InodeId) -> bool {
+        match self.target(id) {
+            Place::Linked(parent, name) => {
+                self.inner.children.get(&(parent, name)) == Some(&id)
+                    && !self.inner.trash.contains_key(&id)
+            }
+            Place::Trashed(_) => self.inner.trash.contains_key(&id),
+        }
+    }
+
+    /// intrarea prin care e legat acum inode-ul, daca e legat.
+    fn linked_at(&self, id: InodeId) -> Option<(InodeId, String)> {
+        if self.inner.trash.contains_key(&id) {
+            return None;
+        }
+        let node = self.inner.inodes.get(&id)?;
+        let key = (node.parent?, node.name.clone());
+        (self.inner.children.get(&key) == Some(&id)).then_some(key)
+    }
+
+    fn has_kids(&self, id: InodeId) -> bool {
+        self.kids.get(&id).is_some_and(|n| *n > 0)
+    }
+
+    /// true daca `id` e acum in subarborele lui `dir` (sau chiar `dir`).
+    fn is_under(&self, mut id: InodeId, dir: InodeId) -> bool {
+        loop {
+            if id == dir {
+                return true;
+            }
+            match self.linked_at(id) {
+                Some((parent, _)) => id = parent,
+                None => return false,
+            }
+        }
+    }
+
+    /// un nume liber in root, atat acum cat si in starea veche.
+    fn temp_name(&self, id: InodeId) -> String {
+        let root = self.inner.header.root;
+        let mut name = format!(".restore-{}", id.0);
+        let mut n = 0;
+        while self.inner.children.contains_key(&(root, name.clone()))
+            || self.past.children.contains_key(&(root, name.clone()))
+        {
+            n += 1;
+            name = format!(".restore-{}-{n}", id.0);
+        }
+        name
+    }
+
+    // sterge un inode care nu exista in starea veche; `false` daca mai are copii
+    fn remove(&mut self, id: InodeId) -> Result<bool> {
+        if self.has_kids(id) {
+            return Ok(false);
+        }
+        if self.inner.trash.contains_key(&id) {
+            self.emit(Record::TrashPurge { inode: id })?;
+        } else if let Some((parent, name)) = self.linked_at(id) {
+            self.emit(Record::DirEntryRemove {
+                parent,
+                name,
+                inode: id,
+            })?;
+        }
+        Ok(true)
+    }
+
+    // aduce un inode la locul lui vechi; `false` daca locul nu e inca liber
+    fn place(&mut self, id: InodeId) -> Result<bool> {
+        let root = self.inner.header.root;
+        let current = self.linked_at(id);
+        match self.target(id) {
+            Place::Linked(parent, name) => {
+                if self.inner.children.contains_key(&(parent, name.clone()))
+                    || self.is_under(parent, id)
+                {
+                    return Ok(false);
+                }
+                let rec = if let Some((old_parent, old_name)) = current {
+                    Record::Rename {
+                        inode: id,
+                        old_parent,
+                        new_parent: parent,
+                        old_name,
+                        new_name: name,
+                    }
+                } else if self.inner.trash.contains_key(&id) {
+                    Record::TrashRestore {
+                        inode: id,
+                        parent,
+                        name,
+                    }
+                } else {
+                    // inviat mai sus: snapshot-ul are deja parintele si numele vechi
+                    Record::DirEntryAdd {
+                        entry: DirEntry {
+                            parent,
+                            inode: id,
+                            name,
+                            kind: self.inner.inodes[&id].kind,
+                        },
+                    }
+                };
+                self.emit(rec)?;
+            }
+            Place::Trashed(entry) => {
+                if self.has_kids(id) {
+                    return Ok(false);
+                }
+                // in trash se ajunge doar dintr-un director; un inode inviat
+                // trece pe scurt printr-un nume temporar din root
+                let (parent, name) = match current {
+                    Some(at) => at,
+                    None => {
+                        let name = self.temp_name(id);
+                        self.emit(Record::DirEntryAdd {
+                            entry: DirEntry {
+                                parent: root,
+                                inode: id,
+                                name: name.clone(),
+                                kind: entry.kind,
+                            },
+                        })?;
+                        (root, name)
+                    }
+                };
+                self.emit(Record::Trash {
+                    parent,
+                    name,
+                    entry,
+                })?;
+            }
+        }
+        Ok(true)
+    }
+
+    // continutul, permisiunile, xattr-urile si timpii unui inode din starea veche
+    fn restore_inode(&mut self, id: InodeId, moved: bool) -> Result<()> {
+        let old = &self.past.inodes[&id];
+        let cur = &self.inner.inodes[&id];
+        let mut recs = Vec::new();
+        if old.kind == NodeKind::File {
+            recs = content_records(id, cur, old);
+        }
+        if cur.metadata.mode != old.metadata.mode {
+            recs.push(Record::SetPermissions {
+                inode: id,
+                mode: old.metadata.mode,
+            });
+        }
+        if (cur.metadata.uid, cur.metadata.gid) != (old.metadata.uid, old.metadata.gid) {
+            recs.push(Record::Chown {
+                inode: id,
+                uid: old.metadata.uid,
+                gid: old.metadata.gid,
+            });
+        }
+        for name in cur.xattrs.keys() {
+            if !old.xattrs.contains_key(name) {
+                recs.push(Record::RemoveXattr {
+                    inode: id,
+                    name: name.clone(),
+                });
+            }
+        }
+        for (name, value) in &old.xattrs {
+            if cur.xattrs.get(name) != Some(value) {
+                recs.push(Record::SetXattr {
+                    inode: id,
+                    name: name.clone(),
+                    value: value.clone(),
+                });
+            }
+        }
+
+        let (o, c) = (&old.metadata, &cur.metadata);
+        let times_differ = (o.created_at, o.modified_at, o.accessed_at)
+            != (c.created_at, c.modified_at, c.accessed_at)
+            || (self.now.is_none() && o.changed_at != c.changed_at);
+        if moved || times_differ || !recs.is_empty() {
+            recs.push(Record::SetTimes {
+                inode: id,
+                created_at: Some(o.created_at),
+                modified_at: Some(o.modified_at),
+                accessed_at: Some(o.accessed_at),
+                changed_at: Some(self.now.unwrap_or(o.changed_at)),
+            });
+        }
+        for rec in recs {
+            self.emit(rec)?;
+        }
+        Ok(())
+    }
+}
+
+/// record-urile care aduc continutul lui `cur` la cel al lui `old`.
+fn content_records(id: InodeId, cur: &Inode, old: &Inode) -> Vec<Record> {
+    let size = old.metadata.size;
+    let want = by_offset(visible_extents(&old.extents, size));
+    let mut len = cur.metadata.size;
+    if len == size && by_offset(visible_extents(&cur.extents, len)) == want {
+        return Vec::new();
+    }
+
+    // de obicei s-au schimbat doar cateva bucati: se pun doar ele peste
+    // continutul de acum, daca rezultatul e acelasi
+    let mut recs = Vec::new();
+    if size < len {
+        recs.push(Record::Truncate {
+            inode: id,
+            len: size,
+        });
+        len = size;
+    }
+    let have = by_offset(visible_extents(&cur.extents, len));
+    let fresh: ExtentList = want
+        .iter()
+        .filter(
+            |ex| match have.binary_search_by_key(&ex.logical_offset, |h| h.logical_offset) {
+                Ok(i) => have[i] != **ex,
+                Err(_) => true,
+            },
+        )
+        .copied()
+        .collect();
+    let layered: ExtentList = have.iter().chain(&fresh).copied().collect();
+    let extents = if by_offset(visible_extents(&layered, size)) == want {
+        fresh
+    } else {
+        recs = vec![Record::Truncate { inode: id, len: 0 }];
+        len = 0;
+        want
+    };
+    for extent in extents {
+        len = len.max(extent.logical_offset + extent.len);
+        recs.push(Record::DataRef { inode: id, extent });
+    }
+    // golul de la coada, lasat de un truncate care a crescut fisierul
+    if len != size {
+        recs.push(Record::Truncate {
+            inode: id,
+            len: size,
+        });
+    }
+    recs
+}
+
+fn by_offset(mut extents: ExtentList) -> ExtentList {
+    extents.sort_by_key(|ex| ex.logical_offset);
+    extents
+}
+
+fn snapshot(node: &Inode) -> InodeSnapshot {
+    InodeSnapshot {
+        id: node.id,
+        parent: node.parent,
+        name: node.name.clone(),
+        kind: node.kind,
+        metadata: node.metadata.clone(),
+        extents: node.extents.clone(),
+        xattrs: node.xattrs.clone(),
+    }
+}
+
+/// momentul la care a avut loc operatia descrisa de record, daca il poarta.
+fn record_time(rec: &Record) -> Option<Timestamp> {
+    match rec {
+        Record::InodeAlloc(snap) => Some(snap.metadata.changed_at),
+        Record::SetTimes {
+            changed_at,
+            accessed_at,
+            ..
+        } => changed_at.or(*accessed_at),
+        Record::Snapshot { created_at, .. } => Some(*created_at),
+        Record::Trash { entry, .. } => Some(entry.deleted_at),
+        _ => None,
+    }
+}
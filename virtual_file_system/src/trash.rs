@@ -0,0 +1,137 @@
+use std::path::Path;
+
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::{Inner, Vfs};
+
+impl Vfs {
+    /// intrarile din trash, de la cea mai veche stergere la cea mai noua.
+    pub fn trash_list(&self) -> Vec<TrashEntry> {
+        let mut out: Vec<TrashEntry> = self.inner.borrow().trash.values().cloned().collect();
+        out.sort_by_key(|t| (t.deleted_at, t.inode.0));
+        out
+    }
+
+    /// pune intrarea inapoi la path-ul original si il intoarce. parintele
+    /// trebuie sa existe inca, iar numele sa fie liber.
+    pub fn restore(&mut self, inode: InodeId) -> Result<VfsPath> {
+        let cwd = self.cwd;
+        self.inner.borrow_mut().restore(cwd, inode, None)
+    }
+
+    /// ca `restore`, dar la alt path (cand originalul e ocupat sau a disparut).
+    pub fn restore_as<P: AsRef<Path>>(&mut self, inode: InodeId, path: P) -> Result<VfsPath> {
+        let path = VfsPath::new(path)?;
+        self.inner
+            .borrow_mut()
+            .restore(self.cwd, inode, Some(&path))
+    }
+
+    /// sterge definitiv tot ce e in trash.
+    pub fn empty_trash(&mut self) -> Result<()> {
+        let mut inner = self.inner.borrow_mut();
+        let all: Vec<InodeId> = inner.trash.keys().copied().collect();
+        inner.purge(&all)
+    }
+}
+
+impl Inner {
+    fn restore(&mut self, cwd: InodeId, inode: InodeId, to: Option<&VfsPath>) -> Result<VfsPath> {
+        let entry = self
+            .trash
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(format!("{inode:?} in trash")))?;
+        let path = to.unwrap_or(&entry.original_path).clone();
+
+        let (parent, name) = self.resolve_parent(cwd, &path)?;
+        if self.children.contains_key(&(parent, name.clone())) {
+            return Err(VfsError::AlreadyExists(path.to_string()));
+        }
+
+        let rec = Record::TrashRestore {
+            inode,
+            parent,
+            name,
+        };
+        self.log(&rec)?;
+        self.apply_record(&rec)?;
+        self.touch(parent, true)?;
+        self.touch(inode, false)?;
+        self.inode_path(inode)
+    }
+
+    pub(crate) fn purge(&mut self, inodes: &[InodeId]) -> Result<()> {
+        for &inode in inodes {
+            let rec = Record::TrashPurge { inode };
+            self.log(&rec)?;
+            self.apply_record(&rec)?;
+        }
+        Ok(())
+    }
+
+    /// aplica `TrashPolicy::purge_after`; apelat inainte de compactare.
+    pub(crate) fn purge_expired(&mut self) -> Result<()> {
+        let Some(ttl) = self.options.trash.purge_after else {
+            return Ok(());
+        };
+        let now = self.options.clock.now();
+        let mut expired: Vec<InodeId> = self
+            .trash
+            .values()
+            .filter(|t| {
+                let age = (now.0 - t.deleted_at.0).max(0);
+                age >= ttl.as_nanos().min(i128::MAX as u128) as i128
+            })
+            .map(|t| t.inode)
+            .collect();
+        expired.sort_by_key(|i| i.0);
+        self.purge(&expired)
+    }
+
+    pub(crate) fn apply_trash(
+        &mut self,
+        parent: InodeId,
+        name: &str,
+        entry: &TrashEntry,
+    ) -> Result<()> {
+        let key = (parent, name.to_string());
+        if self.children.get(&key) != Some(&entry.inode) {
+            return Err(VfsError::CorruptLog("trash entry mismatch".into()));
+        }
+        self.children.remove(&key);
+        self.trash.insert(entry.inode, entry.clone());
+        Ok(())
+    }
+
+    pub(crate) fn apply_trash_restore(
+        &mut self,
+        inode: InodeId,
+        parent: InodeId,
+        name: &str,
+    ) -> Result<()> {
+        if self.trash.remove(&inode).is_none() {
+            return Err(VfsError::CorruptLog("restore of inode not in trash".into()));
+        }
+        if self.inodes.get(&parent).map(|p| p.kind) != Some(NodeKind::Dir) {
+            return Err(VfsError::CorruptLog("restore parent not a dir".into()));
+        }
+        let key = (parent, name.to_string());
+        if self.children.contains_key(&key) {
+            return Err(VfsError::CorruptLog("restore duplicate name".into()));
+        }
+
+        let node = self.node_mut(inode, "restore")?;
+        node.parent = Some(parent);
+        node.name = name.to_string();
+        self.children.insert(key, inode);
+        Ok(())
+    }
+
+    pub(crate) fn apply_trash_purge(&mut self, inode: InodeId) -> Result<()> {
+        if self.trash.remove(&inode).is_none() {
+            return Err(VfsError::CorruptLog("purge of inode not in trash".into()));
+        }
+        self.inodes.remove(&inode);
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crate::compact::visible_extents;
+use crate::file_ops::VfsFile;
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::{ACCESS_R, Inner, Vfs};
+
+impl Vfs {
+    /// starile istorice ale fisierului de la `path`, de la cea mai veche la cea
+    /// curenta. istoria urmeaza inode-ul (supravietuieste rename-urilor), nu numele.
+    pub fn versions<P: AsRef<Path>>(&self, path: P) -> Result<Vec<FileVersion>> {
+        let path = VfsPath::new(path)?;
+        let mut inner = self.inner.borrow_mut();
+        let inode = inner.resolve(self.cwd, &path)?;
+        if inner.inodes.get(&inode).map(|n| n.kind) != Some(NodeKind::File) {
+            return Err(VfsError::NotAFile(path.to_string()));
+        }
+        inner.check_access(inode, ACCESS_R, &path)?;
+        Ok(inner.scan_versions()?.remove(&inode).unwrap_or_default())
+    }
+
+    /// deschide read-only continutul fisierului asa cum era la `version`; cere
+    /// drept de citire pe fisierul de acum.
+    pub fn open_version<P: AsRef<Path>>(&self, path: P, version: &FileVersion) -> Result<VfsFile> {
+        let path = VfsPath::new(path)?;
+        let (inode, image, options) = {
+            let inner = self.inner.borrow();
+            let inode = inner.resolve(self.cwd, &path)?;
+            inner.check_access(inode, ACCESS_R, &path)?;
+            (inode, inner.path.clone(), inner.past_options())
+        };
+
+        let past = Inner::open(&image, options, version.offset)?;
+        if past.inodes.get(&inode).map(|n| n.kind) != Some(NodeKind::File) {
+            return Err(VfsError::NotFound(format!(
+                "{path} at offset {}",
+                version.offset
+            )));
+        }
+        Ok(VfsFile::new(Rc::new(RefCell::new(past)), inode, false))
+    }
+}
+
+impl Inner {
+    /// versiunile tuturor fisierelor din log.
+    ///
+    /// o versiune se incheie cu `SetTimes`-ul care urmeaza unei scrieri sau unui
+    /// truncate (si compactarea scrie versiunile retinute tot asa). la un
+    /// checkpoint, un fisier are o versiune noua daca continutul lui difera de cel stiut.
+    pub(crate) fn scan_versions(&mut self) -> Result<HashMap<InodeId, Vec<FileVersion>>> {
+        let mut versions: HashMap<InodeId, Vec<FileVersion>> = HashMap::new();
+        // continutul fiecarui fisier, urmarit ca la replay
+        let mut content: HashMap<InodeId, (u64, ExtentList)> = HashMap::new();
+        let mut pending: HashSet<InodeId> = HashSet::new();
+
+        let mut off = self.header.log_start();
+        loop {
+            let (decoded, next) = match self.read_record(off) {
+                Ok(Some(r)) => r,
+                Ok(None) | Err(VfsError::CorruptLog(_)) => break,
+                Err(e) => return Err(e),
+            };
+            match decoded.record {
+                Record::InodeAlloc(snap) if snap.kind == NodeKind::File => {
+                    versions.entry(snap.id).or_default().push(FileVersion {
+                        offset: next,
+                        modified_at: snap.metadata.modified_at,
+                        size: snap.metadata.size,
+                    });
+                    content.insert(snap.id, (snap.metadata.size, snap.extents));
+                }
+                Record::DataWrite { inode, .. } | Record::CompressedWrite { inode, .. } => {
+                    let (size, extents) = content.entry(inode).or_default();
+                    if let Some(ex) = decoded.extent() {
+                        *size = (*size).max(ex.logical_offset + ex.len);
+                        extents.push(ex);
+                    }
+                    pending.insert(inode);
+                }
+                Record::DataRef { inode, extent } => {
+                    let (size, extents) = content.entry(inode).or_default();
+                    *size = (*size).max(extent.logical_offset + extent.len);
+                    extents.push(extent);
+                    pending.insert(inode);
+                }
+                Record::Truncate { inode, len } => {
+                    let (size, extents) = content.entry(inode).or_default();
+                    *size = len;
+                    *extents = visible_extents(extents, len);
+                    pending.insert(inode);
+                }
+                Record::SetTimes {
+                    inode,
+                    modified_at: Some(t),
+                    ..
+                } if pending.remove(&inode) => {
+                    versions.entry(inode).or_default().push(FileVersion {
+                        offset: next,
+                        modified_at: t,
+                        size: content.get(&inode).map_or(0, |c| c.0),
+                    });
+                }
+                Record::Checkpoint(cp) => {
+                    pending.clear();
+                    for snap in cp.inodes.into_iter().filter(|s| s.kind == NodeKind::File) {
+                        let size = snap.metadata.size;
+                        let seen = content.get(&snap.id).is_some_and(|(s, ex)| {
+                            *s == size
+                                && visible_extents(ex, size) == visible_extents(&snap.extents, size)
+                        });
+                        if !seen {
+                            versions.entry(snap.id).or_default().push(FileVersion {
+                                offset: next,
+                                modified_at: snap.metadata.modified_at,
+                                size,
+                            });
+                        }
+                        content.insert(snap.id, (size, snap.extents));
+                    }
+                }
+                _ => {}
+            }
+            off = next;
+        }
+
+        Ok(versions)
+    }
+
+    /// offset-urile versiunilor vechi pe care `compact` trebuie sa le pastreze.
+    pub(crate) fn retained_versions(&mut self) -> Result<Vec<u64>> {
+        let retention = self.options.retention;
+        if retention.keep_last.is_none() && retention.keep_for.is_none() {
+            return Ok(Vec::new());
+        }
+        let now = self.options.clock.now();
+
+        let mut out = Vec::new();
+        for (inode, mut list) in self.scan_versions()? {
+            // istoria fisierelor sterse nu mai e accesibila
+            if self.inodes.get(&inode).map(|n| n.kind) != Some(NodeKind::File) {
+                continue;
+            }
+            // ultima versiune e chiar starea curenta
+            list.pop();
+            let keep_from = retention
+                .keep_last
+                .map_or(list.len(), |n| (list.len() + 1).saturating_sub(n));
+            for (i, v) in list.iter().enumerate() {
+                let recent = retention
+                    .keep_for
+                    .is_some_and(|ttl| age(now, v.modified_at) < ttl);
+                if i >= keep_from || recent {
+                    out.push(v.offset);
+                }
+            }
+        }
+        out.sort_unstable();
+        out.dedup();
+        Ok(out)
+    }
+}
+
+fn age(now: Timestamp, then: Timestamp) -> Duration {
+    let ns = (now.0 - then.0).max(0);
+    Duration::from_nanos(ns.min(u64::MAX as i128) as u64)
+}
@@ -1,16 +1,25 @@
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{File, OpenOptions};
-use std::io::{Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{Seek, SeekFrom};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+use crate::codec::read_extent;
+use crate::crypt::{Cipher, new_image, unlock};
 use crate::file_ops::*;
+use crate::mounts::Mount;
 use crate::no_sql::*;
+use crate::path::VfsPath;
 use crate::structs::*;
+use crate::watch::Watcher;
 
+/// handle catre un vfs montat. fiecare handle are propriul director curent,
+/// `clone` da un handle nou peste acelasi backing file.
+#[derive(Clone)]
 pub struct Vfs {
     pub(crate) inner: Rc<RefCell<Inner>>,
+    pub(crate) cwd: InodeId,
 }
 
 pub struct ReadDir {
@@ -18,16 +27,47 @@ pub struct ReadDir {
     pos: usize,
 }
 
+impl ReadDir {
+    pub(crate) fn new(entries: Vec<DirEntry>) -> Self {
+        Self { entries, pos: 0 }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Inner {
-    file: File,
-    header: Header,
-    next_inode: InodeId,
-    inodes: HashMap<InodeId, Inode>,
-    children: HashMap<(InodeId, String), InodeId>,
-    scratch: Vec<u8>,
+    pub(crate) file: File,
+    pub(crate) path: PathBuf,
+    pub(crate) header: Header,
+    pub(crate) next_inode: InodeId,
+    pub(crate) inodes: HashMap<InodeId, Inode>,
+    pub(crate) children: HashMap<(InodeId, String), InodeId>,
+    pub(crate) snapshots: BTreeMap<String, SnapshotInfo>,
+    /// inode-urile din trash; nu apar in `children`.
+    pub(crate) trash: HashMap<InodeId, TrashEntry>,
+    /// (crc32, len) -> offset-urile chunk-urilor cu continutul asta.
+    pub(crate) dedup: HashMap<(u32, u64), Vec<Extent>>,
+    /// ultimul extent comprimat citit, decomprimat (offset in backing file, date).
+    pub(crate) blob_cache: Option<(u64, Vec<u8>)>,
+    /// `Some` pt imaginile criptate.
+    pub(crate) cipher: Option<Cipher>,
+    pub(crate) scratch: Vec<u8>,
+    pub(crate) options: MountOptions,
+    /// montarile atasate peste directoare din imagine; nu ajung in log.
+    pub(crate) mounts: HashMap<InodeId, Mount>,
+    /// receptorii de la `Vfs::watch`; cei inchisi se scot la primul eveniment.
+    pub(crate) watchers: Vec<Watcher>,
+    /// finalul partii din log aplicate deja; de aici continua `tail`.
+    pub(crate) log_end: u64,
 }
 
+// bitii ceruti la verificarea accesului
+pub(crate) const ACCESS_R: u32 = 4;
+pub(crate) const ACCESS_W: u32 = 2;
+pub(crate) const ACCESS_X: u32 = 1;
+
+// relatime: atime-ul se actualizeaza oricum daca e mai vechi de o zi
+const RELATIME_INTERVAL_NS: i128 = 24 * 60 * 60 * 1_000_000_000;
+
 impl Vfs {
     pub(crate) fn read_at(&self, inode: InodeId, off: u64, buf: &mut [u8]) -> Result<usize> {
         self.inner.borrow_mut().read_at(inode, off, buf)
@@ -45,171 +85,495 @@ impl Vfs {
         self.inner.borrow_mut().truncate(inode, len)
     }
 
-    pub fn mount<P: AsRef<Path>>(path: P) -> Result<Self> {
-        // backing file pt vfs
-        let mut file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(path)?;
-
-        // fisier gol -> init
-        let len = file.metadata()?.len();
-        if len == 0 {
-            // header + root inode
-            let root = InodeId(1);
-
-            // scriem header la începutul fișierului
-            write_header(&mut file, DEFAULT_BLOCK_SIZE, root)?;
-
-            // creăm root snapshot (inode alloc)
-            let now = Timestamp::now();
-            let root_snap = InodeSnapshot {
-                id: root,
-                parent: None,
-                name: "".to_string(),
-                kind: NodeKind::Dir,
-                metadata: Metadata {
-                    size: 0,
-                    created_at: now,
-                    modified_at: now,
-                },
-                extents: vec![],
-            };
-
-            // în log, root-ul devine "prima operație" după header
-            write_record(&mut file, &Record::InodeAlloc(root_snap.clone()))?;
-
-            // apoi damn mount în memorie ca și cum am făcut replay
-            let header = Header {
-                magic: *b"CCCCCCCC",
-                version: 1,
-                block_size: DEFAULT_BLOCK_SIZE,
-                root,
-            };
-
-            let mut inner = Inner {
-                file,
-                header,
-                next_inode: InodeId(2), // următorul inode după root
-                inodes: HashMap::new(),
-                children: HashMap::new(),
-                scratch: Vec::new(),
-            };
-
-            // aplicăm record-ul root ca să fie consistent cu log-ul
-            inner.apply_record(&Record::InodeAlloc(root_snap))?;
-
-            return Ok(Self {
-                inner: Rc::new(RefCell::new(inner)),
-            });
-        }
-
-        // dacă nu e gol citim header și facem replay
-        let header = read_header(&mut file)?;
+    pub(crate) fn from_inner(inner: Rc<RefCell<Inner>>) -> Self {
+        let cwd = inner.borrow().header.root;
+        Self { inner, cwd }
+    }
 
-        let mut inner = Inner {
-            file,
-            header: header.clone(),
-            next_inode: InodeId(1), // se va seta din replay
-            inodes: HashMap::new(),
-            children: HashMap::new(),
-            scratch: Vec::new(),
-        };
+    pub fn mount<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::mount_with(path, MountOptions::default())
+    }
 
-        inner.mount_replay()?;
+    pub fn mount_with<P: AsRef<Path>>(path: P, options: MountOptions) -> Result<Self> {
+        let inner = Inner::open(path.as_ref(), options, u64::MAX)?;
+        Ok(Self::from_inner(Rc::new(RefCell::new(inner))))
+    }
 
-        Ok(Self {
-            inner: Rc::new(RefCell::new(inner)),
-        })
+    /// directorul curent al acestui handle, ca path absolut.
+    pub fn current_dir(&self) -> Result<VfsPath> {
+        self.inner.borrow().inode_path(self.cwd)
     }
 
-    fn split_path(path: &str) -> Result<Vec<&str>> {
-        if path.is_empty() {
-            return Err(VfsError::InvalidPath("empty path".into()));
-        }
-        let parts: Vec<&str> = path.split('/').collect();
-        if parts
-            .iter()
-            .any(|p| p.is_empty() || *p == "." || *p == "..")
+    /// schimba directorul curent; path-urile relative se rezolva de aici.
+    pub fn set_current_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        // cwd-ul e un inode din imaginea proprie; in montare se intra doar prin punctul ei
+        if let Some(c) = self.crossing(&path)?
+            && !c.rest.is_root()
         {
-            return Err(VfsError::InvalidPath(format!("invalid path: {path}")));
+            return Err(VfsError::CrossesMount(format!(
+                "cannot change into {path} under a mount"
+            )));
+        }
+        let inner = self.inner.borrow();
+        let inode = inner.resolve(self.cwd, &path)?;
+        let node = inner
+            .inodes
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?;
+        if node.kind != NodeKind::Dir {
+            return Err(VfsError::NotADir(path.to_string()));
         }
-        Ok(parts)
+        inner.check_access(inode, ACCESS_X, &path)?;
+        drop(inner);
+        self.cwd = inode;
+        Ok(())
     }
 
-    pub fn create_dir(&mut self, path: &str) -> Result<()> {
+    pub fn create_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.create_dir(&c.rest);
+        }
         let mut inner = self.inner.borrow_mut();
-        inner.create_dir(path)
+        inner.create_dir(self.cwd, &path)
     }
 
-    pub fn read_dir(&self, path: &str) -> Result<ReadDir> {
-        let inner = self.inner.borrow();
-        inner.read_dir(path)
+    pub fn read_dir<P: AsRef<Path>>(&self, path: P) -> Result<ReadDir> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.crossing(&path)? {
+            return c.mount.read_dir(&c.rest);
+        }
+        let mut inner = self.inner.borrow_mut();
+        inner.read_dir(self.cwd, &path)
     }
 
-    pub fn create(&self, path: &str) -> Result<VfsFile> {
-        let inode = self.inner.borrow_mut().create_file(path)?;
+    pub fn create<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.create(&c.rest);
+        }
+        let inode = self.inner.borrow_mut().create_file(self.cwd, &path)?;
         Ok(VfsFile::new(self.inner.clone(), inode, true))
     }
 
-    pub fn open_file(&self, path: &str) -> Result<VfsFile> {
-        let inode = self.inner.borrow().path_to_inode(path)?;
+    pub fn open_file<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.crossing(&path)? {
+            return c.mount.open(&c.rest, false);
+        }
         let inner = self.inner.borrow();
+        let inode = inner.resolve(self.cwd, &path)?;
         let node = inner
             .inodes
             .get(&inode)
-            .ok_or_else(|| VfsError::NotFound(path.into()))?;
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?;
         if node.kind != NodeKind::File {
-            return Err(VfsError::NotAFile(path.into()));
+            return Err(VfsError::NotAFile(path.to_string()));
         }
+        inner.check_access(inode, ACCESS_R, &path)?;
         Ok(VfsFile::new(self.inner.clone(), inode, false))
     }
 
-    pub fn open(&self, path: &str) -> Result<VfsFile> {
+    pub fn open<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
         self.open_file(path)
     }
 
-    pub fn exists(&self, path: &str) -> bool {
+    /// deschide un fisier existent pentru citire si scriere (fara truncate).
+    pub fn open_rw<P: AsRef<Path>>(&self, path: P) -> Result<VfsFile> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.open(&c.rest, true);
+        }
         let inner = self.inner.borrow();
-        inner.path_to_inode(path).is_ok()
+        inner.ensure_writable()?;
+        let inode = inner.resolve(self.cwd, &path)?;
+        if inner.inodes.get(&inode).map(|n| n.kind) != Some(NodeKind::File) {
+            return Err(VfsError::NotAFile(path.to_string()));
+        }
+        inner.check_access(inode, ACCESS_R | ACCESS_W, &path)?;
+        Ok(VfsFile::new(self.inner.clone(), inode, true))
     }
 
-    pub fn metadata(&self, path: &str) -> Result<Metadata> {
+    pub fn exists<P: AsRef<Path>>(&self, path: P) -> bool {
+        let Ok(path) = VfsPath::new(path) else {
+            return false;
+        };
+        if let Ok(Some(c)) = self.crossing(&path) {
+            return c.mount.exists(&c.rest);
+        }
         let inner = self.inner.borrow();
-        inner.metadata(path)
+        inner.resolve(self.cwd, &path).is_ok()
     }
 
-    pub fn remove_file(&mut self, path: &str) -> Result<()> {
-        self.inner.borrow_mut().unlink(path, NodeKind::File)
+    pub fn metadata<P: AsRef<Path>>(&self, path: P) -> Result<Metadata> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.crossing(&path)? {
+            return c.mount.metadata(&c.rest);
+        }
+        let inner = self.inner.borrow();
+        inner.metadata(self.cwd, &path)
     }
 
-    pub fn remove_dir(&mut self, path: &str) -> Result<()> {
-        self.inner.borrow_mut().unlink(path, NodeKind::Dir)
+    pub fn remove_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.remove(&c.rest, NodeKind::File);
+        }
+        self.inner
+            .borrow_mut()
+            .unlink(self.cwd, &path, NodeKind::File)
     }
 
-    pub fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
-        self.inner.borrow_mut().rename(old_path, new_path)
+    pub fn remove_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.remove(&c.rest, NodeKind::Dir);
+        }
+        self.inner
+            .borrow_mut()
+            .unlink(self.cwd, &path, NodeKind::Dir)
+    }
+
+    pub fn rename<P: AsRef<Path>, Q: AsRef<Path>>(
+        &mut self,
+        old_path: P,
+        new_path: Q,
+    ) -> Result<()> {
+        let old_path = VfsPath::new(old_path)?;
+        let new_path = VfsPath::new(new_path)?;
+        // in interiorul aceleiasi montari merge; peste granita ar strica ambele log-uri
+        match (
+            self.writable_crossing(&old_path)?,
+            self.writable_crossing(&new_path)?,
+        ) {
+            (None, None) => {}
+            (Some(a), Some(b)) if a.point == b.point => return a.mount.rename(&a.rest, &b.rest),
+            _ => {
+                return Err(VfsError::CrossesMount(format!("{old_path} -> {new_path}")));
+            }
+        }
+        self.inner
+            .borrow_mut()
+            .rename(self.cwd, &old_path, &new_path)
     }
 
     pub fn checkpoint(&mut self) -> Result<()> {
         self.inner.borrow_mut().write_checkpoint()
     }
+
+    /// seteaza explicit timpii unui inode (ex. la importul unei arhive).
+    /// `changed_at` devine momentul apelului. doar owner-ul sau root-ul.
+    pub fn set_times<P: AsRef<Path>>(&mut self, path: P, times: FileTimes) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.set_times(&c.rest, times);
+        }
+        self.inner.borrow_mut().set_times(self.cwd, &path, times)
+    }
+
+    /// schimba bitii de permisiuni (`0o7777`). doar owner-ul sau root-ul.
+    pub fn set_permissions<P: AsRef<Path>>(&mut self, path: P, mode: u32) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.set_permissions(&c.rest, mode);
+        }
+        self.inner
+            .borrow_mut()
+            .set_permissions(self.cwd, &path, mode)
+    }
+
+    /// schimba owner-ul si/sau grupul, ca `std::os::unix::fs::chown`.
+    pub fn chown<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.chown(&c.rest, uid, gid);
+        }
+        self.inner.borrow_mut().chown(self.cwd, &path, uid, gid)
+    }
+
+    /// seteaza (sau inlocuieste) un atribut extins pe un fisier sau director.
+    pub fn set_xattr<P: AsRef<Path>>(&mut self, path: P, name: &str, value: &[u8]) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.set_xattr(&c.rest, name, value);
+        }
+        self.inner
+            .borrow_mut()
+            .set_xattr(self.cwd, &path, name, value)
+    }
+
+    /// valoarea atributului, sau `None` daca nu e setat.
+    pub fn get_xattr<P: AsRef<Path>>(&self, path: P, name: &str) -> Result<Option<Vec<u8>>> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.crossing(&path)? {
+            return c.mount.get_xattr(&c.rest, name);
+        }
+        let inner = self.inner.borrow();
+        let node = inner.node(self.cwd, &path)?;
+        inner.check_access(node.id, ACCESS_R, &path)?;
+        Ok(node.xattrs.get(name).cloned())
+    }
+
+    /// numele atributelor, sortate.
+    pub fn list_xattrs<P: AsRef<Path>>(&self, path: P) -> Result<Vec<String>> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.crossing(&path)? {
+            return c.mount.list_xattrs(&c.rest);
+        }
+        let inner = self.inner.borrow();
+        let node = inner.node(self.cwd, &path)?;
+        inner.check_access(node.id, ACCESS_R, &path)?;
+        Ok(node.xattrs.keys().cloned().collect())
+    }
+
+    pub fn remove_xattr<P: AsRef<Path>>(&mut self, path: P, name: &str) -> Result<()> {
+        let path = VfsPath::new(path)?;
+        if let Some(c) = self.writable_crossing(&path)? {
+            return c.mount.remove_xattr(&c.rest, name);
+        }
+        self.inner.borrow_mut().remove_xattr(self.cwd, &path, name)
+    }
+
+    /// toate path-urile care au atributul `name` egal cu `value`, sortate.
+    pub fn find_by_xattr(&self, name: &str, value: &[u8]) -> Result<Vec<VfsPath>> {
+        let inner = self.inner.borrow();
+        let mut out = Vec::new();
+        for node in inner.inodes.values() {
+            if node.xattrs.get(name).map(|v| v.as_slice()) != Some(value) {
+                continue;
+            }
+            // inode-urile sterse nu mai au path
+            if let Ok(path) = inner.inode_path(node.id)
+                && inner.resolve(inner.header.root, &path).ok() == Some(node.id)
+            {
+                out.push(path);
+            }
+        }
+        out.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        Ok(out)
+    }
 }
 
 impl Inner {
-    fn mount_replay(&mut self) -> Result<()> {
-        let header_len: u64 = 24;
-        let mut offset = header_len;
+    /// deschide backing file-ul si reconstruieste starea din record-urile care
+    /// se termina pana la `limit` (`u64::MAX` = tot log-ul).
+    pub(crate) fn open(path: &Path, options: MountOptions, limit: u64) -> Result<Self> {
+        // backing file pt vfs; read-only nu creeaza nimic
+        let mut file = if options.read_only {
+            OpenOptions::new().read(true).open(path)?
+        } else {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(path)?
+        };
+
+        // fisier gol -> init
+        let len = file.metadata()?.len();
+        if len == 0 {
+            if options.read_only {
+                return Err(VfsError::ReadOnly(format!(
+                    "cannot initialize {}",
+                    path.display()
+                )));
+            }
+            return Self::init(file, path, options);
+        }
+
+        // dacă nu e gol citim header și facem replay
+        let header = read_header(&mut file)?;
+        crate::codec::ensure_supported(header.compression)?;
+        let cipher = unlock(options.encryption.as_ref(), &header)?;
+        // un format vechi se scrie doar dupa `Vfs::upgrade`
+        if header.version < VERSION && !options.read_only {
+            return Err(VfsError::ReadOnly(format!(
+                "{} uses image format v{}; upgrade it (Vfs::upgrade, vfsctl upgrade) before writing",
+                path.display(),
+                header.version
+            )));
+        }
+        let log_start = header.log_start();
+
+        let mut inner = Inner {
+            file,
+            path: path.to_path_buf(),
+            header,
+            next_inode: InodeId(1), // se va seta din replay
+            inodes: HashMap::new(),
+            children: HashMap::new(),
+            snapshots: BTreeMap::new(),
+            trash: HashMap::new(),
+            dedup: HashMap::new(),
+            blob_cache: None,
+            cipher,
+            scratch: Vec::new(),
+            options,
+            mounts: HashMap::new(),
+            watchers: Vec::new(),
+            log_end: log_start,
+        };
+
+        inner.replay_until(limit)?;
+        Ok(inner)
+    }
+
+    fn init(mut file: File, path: &Path, options: MountOptions) -> Result<Self> {
+        // header + root inode
+        let root = InodeId(1);
+
+        // scriem header la începutul fișierului; algoritmul de compresie ramane al imaginii
+        let compression = options.compression.map_or(Compression::None, |p| p.algo);
+        crate::codec::ensure_supported(compression)?;
+        let (cipher, encryption) = match &options.encryption {
+            Some(key) => {
+                let (cipher, enc) = new_image(key)?;
+                (Some(cipher), Some(enc))
+            }
+            None => (None, None),
+        };
+        let header = Header {
+            magic: *b"CCCCCCCC",
+            version: VERSION,
+            block_size: DEFAULT_BLOCK_SIZE,
+            root,
+            compression,
+            encryption,
+            generation: 0,
+        };
+        write_image_header(&mut file, &header)?;
+
+        // creăm root snapshot (inode alloc); root-ul e al celui care creeaza imaginea
+        let now = options.clock.now();
+        let (uid, gid) = options.user.as_ref().map_or((0, 0), |u| (u.uid, u.gid));
+        let root_snap = InodeSnapshot {
+            id: root,
+            parent: None,
+            name: "".to_string(),
+            kind: NodeKind::Dir,
+            metadata: Metadata {
+                size: 0,
+                created_at: now,
+                modified_at: now,
+                accessed_at: now,
+                changed_at: now,
+                mode: DEFAULT_DIR_MODE,
+                uid,
+                gid,
+            },
+            extents: vec![],
+            xattrs: XattrMap::new(),
+        };
+
+        // în log, root-ul devine "prima operație" după header
+        write_record_with(
+            &mut file,
+            &Record::InodeAlloc(root_snap.clone()),
+            cipher.as_ref(),
+        )?;
+
+        let log_end = file.metadata()?.len();
+
+        // apoi damn mount în memorie ca și cum am făcut replay
+
+        let mut inner = Inner {
+            file,
+            path: path.to_path_buf(),
+            header,
+            next_inode: InodeId(2), // următorul inode după root
+            inodes: HashMap::new(),
+            children: HashMap::new(),
+            snapshots: BTreeMap::new(),
+            trash: HashMap::new(),
+            dedup: HashMap::new(),
+            blob_cache: None,
+            cipher,
+            scratch: Vec::new(),
+            options,
+            mounts: HashMap::new(),
+            watchers: Vec::new(),
+            log_end,
+        };
+
+        // aplicăm record-ul root ca să fie consistent cu log-ul
+        inner.apply_record(&Record::InodeAlloc(root_snap))?;
+        Ok(inner)
+    }
+
+    /// scrie un record la finalul log-ului; singurul drum prin care se modifica imaginea.
+    pub(crate) fn log(&mut self, rec: &Record) -> Result<u64> {
+        self.ensure_writable()?;
+        write_record_with(&mut self.file, rec, self.cipher.as_ref())
+    }
+
+    /// record-ul de la `off`, desigilat daca imaginea e criptata.
+    pub(crate) fn read_record(&mut self, off: u64) -> Result<Option<(DecodedRecord, u64)>> {
+        read_versioned_record(
+            &mut self.file,
+            off,
+            self.header.version,
+            self.cipher.as_ref(),
+        )
+    }
+
+    /// optiunile pt o vedere read-only peste alta stare a aceleiasi imagini;
+    /// userul ramane acelasi, ca vederea sa nu ocoleasca permisiunile.
+    pub(crate) fn past_options(&self) -> MountOptions {
+        MountOptions {
+            user: self.options.user.clone(),
+            atime: self.options.atime,
+            clock: self.options.clock.clone(),
+            read_only: true,
+            encryption: self.options.encryption.clone(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn ensure_writable(&self) -> Result<()> {
+        if self.options.read_only {
+            return Err(VfsError::ReadOnly(self.path.display().to_string()));
+        }
+        Ok(())
+    }
+
+    /// reconstruieste starea din record-urile care se termina pana la `limit`.
+    pub(crate) fn replay_until(&mut self, limit: u64) -> Result<()> {
+        let mut offset = self.header.log_start();
 
         let mut last_cp: Option<(crate::structs::Checkpoint, u64)> = None;
+        self.snapshots.clear();
+        self.blob_cache = None;
 
+        // prima trecere: ultimul checkpoint, snapshot-urile si finalul valid al log-ului
         loop {
-            match read_next_record(&mut self.file, offset) {
+            match self.read_record(offset) {
                 Ok(Some((decoded, next))) => {
-                    if let Record::Checkpoint(cp) = decoded.record {
-                        last_cp = Some((cp, next));
+                    if next > limit {
+                        break;
+                    }
+                    match decoded.record {
+                        Record::Checkpoint(cp) => last_cp = Some((cp, next)),
+                        Record::Snapshot { name, created_at } => {
+                            self.snapshots.insert(
+                                name.clone(),
+                                SnapshotInfo {
+                                    name,
+                                    offset: next,
+                                    created_at,
+                                },
+                            );
+                        }
+                        Record::SnapshotDelete { name } => {
+                            self.snapshots.remove(&name);
+                        }
+                        _ => {}
                     }
                     offset = next;
                 }
@@ -218,34 +582,33 @@ impl Inner {
                 Err(e) => return Err(e),               // Eroare critică
             }
         }
+        let end = offset;
 
-        // Dacă am găsit checkpoint:
-        if let Some((cp, replay_from)) = last_cp {
-            self.load_from_checkpoint(&cp)?;
-
-            // replay doar după checkpoint
-            let mut off2 = replay_from;
-            while let Some((decoded, next)) = read_next_record(&mut self.file, off2)? {
-                self.apply_decoded(decoded)?;
-                off2 = next;
+        // Dacă am găsit checkpoint pornim de la el, altfel replay normal de la început
+        let mut off = match last_cp {
+            Some((cp, replay_from)) => {
+                self.load_from_checkpoint(&cp)?;
+                replay_from
             }
+            None => {
+                self.inodes.clear();
+                self.children.clear();
+                self.trash.clear();
+                self.dedup.clear();
+                self.header.log_start()
+            }
+        };
 
-            self.recalc_next_inode();
-
-            return Ok(());
-        }
-
-        // Dacă nu există checkpoint: replay normal de la început
-        self.inodes.clear();
-        self.children.clear();
-
-        let mut off3 = header_len;
-        while let Some((decoded, next)) = read_next_record(&mut self.file, off3)? {
+        while off < end {
+            let Some((decoded, next)) = self.read_record(off)? else {
+                break;
+            };
             self.apply_decoded(decoded)?;
-            off3 = next;
+            off = next;
         }
 
         self.recalc_next_inode();
+        self.log_end = end;
 
         if !self.inodes.contains_key(&self.header.root) {
             return Err(VfsError::CorruptLog(
@@ -256,43 +619,40 @@ impl Inner {
         Ok(())
     }
 
-    fn apply_decoded(&mut self, decoded: crate::no_sql::DecodedRecord) -> Result<()> {
+    pub(crate) fn apply_decoded(&mut self, decoded: crate::no_sql::DecodedRecord) -> Result<()> {
         match &decoded.record {
             Record::DataWrite {
-                inode,
-                logical_offset,
-                len,
-                ..
+                inode, checksum, ..
+            }
+            | Record::CompressedWrite {
+                inode, checksum, ..
             } => {
-                let data_off = decoded
-                    .data_payload_offset
-                    .ok_or_else(|| VfsError::CorruptLog("DataWrite missing data offset".into()))?;
-
-                // găsim inode-ul și adăugăm extent
-                let node = self
-                    .inodes
-                    .get_mut(inode)
-                    .ok_or_else(|| VfsError::CorruptLog("DataWrite inode missing".into()))?;
-
-                node.extents.push(crate::structs::Extent {
-                    logical_offset: *logical_offset,
-                    file_offset: data_off,
-                    len: *len,
-                });
-
-                // update size (max)
-                let end = logical_offset.saturating_add(*len);
-                if end > node.metadata.size {
-                    node.metadata.size = end;
+                let ex = decoded.extent().ok_or_else(|| {
+                    VfsError::CorruptLog("data record missing data offset".into())
+                })?;
+                if self.options.dedup.is_some() {
+                    self.index_chunk(*checksum, ex);
                 }
-
-                Ok(())
+                self.push_extent(*inode, ex)
+                    .map_err(|_| VfsError::CorruptLog("data record inode missing".into()))
             }
             _ => self.apply_record(&decoded.record),
         }
     }
 
-    fn apply_record(&mut self, rec: &Record) -> Result<()> {
+    pub(crate) fn apply_record(&mut self, rec: &Record) -> Result<()> {
+        // path-urile se calculeaza inainte: dupa aplicare, un nume sters nu mai exista
+        let events = if self.watchers.is_empty() {
+            Vec::new()
+        } else {
+            self.watch_events(rec)
+        };
+        self.apply_change(rec)?;
+        self.notify(events);
+        Ok(())
+    }
+
+    fn apply_change(&mut self, rec: &Record) -> Result<()> {
         match rec {
             Record::InodeAlloc(snap) => {
                 self.apply_inode_alloc(snap)?;
@@ -307,8 +667,21 @@ impl Inner {
                 inode,
                 created_at,
                 modified_at,
+                accessed_at,
+                changed_at,
             } => {
-                self.apply_set_times(*inode, created_at, modified_at)?;
+                let node = self.node_mut(*inode, "set_times")?;
+                let m = &mut node.metadata;
+                for (field, value) in [
+                    (&mut m.created_at, created_at),
+                    (&mut m.modified_at, modified_at),
+                    (&mut m.accessed_at, accessed_at),
+                    (&mut m.changed_at, changed_at),
+                ] {
+                    if let Some(t) = value {
+                        *field = *t;
+                    }
+                }
             }
             Record::DirEntryRemove {
                 parent,
@@ -326,6 +699,40 @@ impl Inner {
             } => {
                 self.apply_rename(*inode, *old_parent, *new_parent, old_name, new_name)?;
             }
+            Record::SetXattr { inode, name, value } => {
+                self.apply_set_xattr(*inode, name, value)?;
+            }
+            Record::RemoveXattr { inode, name } => {
+                self.apply_remove_xattr(*inode, name)?;
+            }
+            Record::SetPermissions { inode, mode } => {
+                self.node_mut(*inode, "set_permissions")?.metadata.mode = *mode;
+            }
+            Record::Chown { inode, uid, gid } => {
+                let node = self.node_mut(*inode, "chown")?;
+                node.metadata.uid = *uid;
+                node.metadata.gid = *gid;
+            }
+            Record::Trash {
+                parent,
+                name,
+                entry,
+            } => {
+                self.apply_trash(*parent, name, entry)?;
+            }
+            Record::TrashRestore {
+                inode,
+                parent,
+                name,
+            } => {
+                self.apply_trash_restore(*inode, *parent, name)?;
+            }
+            Record::TrashPurge { inode } => {
+                self.apply_trash_purge(*inode)?;
+            }
+            Record::DataRef { inode, extent } => {
+                self.push_extent(*inode, *extent)?;
+            }
             _ => {}
         }
         Ok(())
@@ -340,6 +747,7 @@ impl Inner {
             kind: snap.kind,
             metadata: snap.metadata.clone(),
             extents: snap.extents.clone(),
+            xattrs: snap.xattrs.clone(),
         };
 
         // dacă există deja, e corupție / log inconsistent
@@ -383,43 +791,48 @@ impl Inner {
         Ok(())
     }
 
-    fn path_to_inode(&self, path: &str) -> Result<InodeId> {
-        let parts = Vfs::split_path(path)?;
-        let mut cur = self.header.root;
-
-        for name in parts {
-            let key = (cur, name.to_string());
-            let next = self
-                .children
-                .get(&key)
-                .copied()
-                .ok_or_else(|| VfsError::NotFound(path.into()))?;
-            cur = next;
+    /// un pas in arbore: `..` urca la parintele real (root ramane root).
+    fn lookup(&self, dir: InodeId, name: &str, path: &VfsPath) -> Result<InodeId> {
+        let node = self
+            .inodes
+            .get(&dir)
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?;
+        if node.kind != NodeKind::Dir {
+            return Err(VfsError::NotADir(path.to_string()));
         }
-        Ok(cur)
+        self.check_access(dir, ACCESS_X, path)?;
+        if name == ".." {
+            return Ok(node.parent.unwrap_or(self.header.root));
+        }
+        self.children
+            .get(&(dir, name.to_string()))
+            .copied()
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))
     }
 
-    fn create_dir(&mut self, path: &str) -> Result<()> {
-        let parts = Vfs::split_path(path)?;
-        let (parent_parts, leaf) = parts.split_at(parts.len() - 1);
-        let name = leaf[0];
-
-        // parent inode
-        let parent = if parent_parts.is_empty() {
+    pub(crate) fn resolve(&self, cwd: InodeId, path: &VfsPath) -> Result<InodeId> {
+        let mut cur = if path.is_absolute() {
             self.header.root
         } else {
-            let mut cur = self.header.root;
-            for p in parent_parts {
-                let key = (cur, (*p).to_string());
-                cur = *self
-                    .children
-                    .get(&key)
-                    .ok_or_else(|| VfsError::NotFound(path.into()))?;
-            }
-            cur
+            cwd
         };
+        for name in path.components() {
+            cur = self.lookup(cur, name, path)?;
+        }
+        Ok(cur)
+    }
+
+    /// parintele (verificat ca e director) si numele ultimei componente.
+    pub(crate) fn resolve_parent(&self, cwd: InodeId, path: &VfsPath) -> Result<(InodeId, String)> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| VfsError::InvalidPath(format!("path has no file name: {path}")))?
+            .to_string();
+        let parent_path = path
+            .parent()
+            .ok_or_else(|| VfsError::InvalidPath(format!("path has no parent: {path}")))?;
+        let parent = self.resolve(cwd, &parent_path)?;
 
-        // verificare ca parintele sa fie folder
         let p_inode = self
             .inodes
             .get(&parent)
@@ -427,34 +840,61 @@ impl Inner {
         if p_inode.kind != NodeKind::Dir {
             return Err(VfsError::NotADir(path.to_string()));
         }
+        // orice schimbare de intrari cere write + search pe parinte
+        self.check_access(parent, ACCESS_W | ACCESS_X, path)?;
+        Ok((parent, name))
+    }
+
+    /// reconstruieste path-ul absolut al unui inode urcand pe parinti.
+    pub(crate) fn inode_path(&self, inode: InodeId) -> Result<VfsPath> {
+        let mut names = Vec::new();
+        let mut cur = inode;
+        while cur != self.header.root {
+            let node = self
+                .inodes
+                .get(&cur)
+                .ok_or_else(|| VfsError::NotFound(format!("{cur:?}")))?;
+            names.push(node.name.clone());
+            cur = node
+                .parent
+                .ok_or_else(|| VfsError::NotFound(format!("{inode:?}")))?;
+        }
+        names.reverse();
+        VfsPath::new(format!("/{}", names.join("/")))
+    }
+
+    fn create_dir(&mut self, cwd: InodeId, path: &VfsPath) -> Result<()> {
+        if path.is_root() {
+            return Err(VfsError::AlreadyExists(path.to_string()));
+        }
+        // parent inode (verificat ca e folder)
+        let (parent, name) = self.resolve_parent(cwd, path)?;
+        let name = name.as_str();
 
         // există deja în parent -> AlreadyExists
         let key = (parent, name.to_string());
         if self.children.contains_key(&key) {
-            return Err(VfsError::AlreadyExists(path.into()));
+            return Err(VfsError::AlreadyExists(path.to_string()));
         }
 
         // inode nou pentru director
         let new_id = self.next_inode;
         self.next_inode = InodeId(self.next_inode.0 + 1);
 
-        let now = Timestamp::now();
+        let now = self.options.clock.now();
         let snap = InodeSnapshot {
             id: new_id,
             parent: Some(parent),
             name: name.to_string(),
             kind: NodeKind::Dir,
-            metadata: Metadata {
-                size: 0,
-                created_at: now,
-                modified_at: now,
-            },
+            metadata: self.new_metadata(now, DEFAULT_DIR_MODE),
             extents: vec![],
+            xattrs: XattrMap::new(),
         };
 
         // scriem record-uri în log
         // scriem pe disk înainte să modificăm definitiv structurile
-        write_record(&mut self.file, &Record::InodeAlloc(snap.clone()))?;
+        self.log(&Record::InodeAlloc(snap.clone()))?;
 
         let de = DirEntry {
             parent,
@@ -462,39 +902,38 @@ impl Inner {
             name: name.to_string(),
             kind: NodeKind::Dir,
         };
-        write_record(&mut self.file, &Record::DirEntryAdd { entry: de.clone() })?;
+        self.log(&Record::DirEntryAdd { entry: de.clone() })?;
 
-        write_record(
-            &mut self.file,
-            &Record::SetTimes {
-                inode: new_id,
-                created_at: Some(now),
-                modified_at: Some(now),
-            },
-        )?;
+        self.log(&Record::SetTimes {
+            inode: new_id,
+            created_at: Some(now),
+            modified_at: Some(now),
+            accessed_at: Some(now),
+            changed_at: Some(now),
+        })?;
         self.apply_record(&Record::InodeAlloc(snap))?;
         self.apply_record(&Record::DirEntryAdd { entry: de })?;
 
+        // parintele si-a schimbat continutul
+        self.touch(parent, true)?;
+
         Ok(())
     }
 
-    fn read_dir(&self, path: &str) -> Result<ReadDir> {
-        // determinăm inode-ul directorului "" inseamna ca e root idk daca o sa schimb asta
-        let dir_id = if path.is_empty() {
-            self.header.root
-        } else {
-            self.path_to_inode(path)?
-        };
+    fn read_dir(&mut self, cwd: InodeId, path: &VfsPath) -> Result<ReadDir> {
+        // determinăm inode-ul directorului ("" e directorul curent, "/" e root)
+        let dir_id = self.resolve(cwd, path)?;
 
         // verificăm că e director
         let inode = self
             .inodes
             .get(&dir_id)
-            .ok_or_else(|| VfsError::NotFound(path.into()))?;
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?;
 
         if inode.kind != NodeKind::Dir {
-            return Err(VfsError::NotADir(path.into()));
+            return Err(VfsError::NotADir(path.to_string()));
         }
+        self.check_access(dir_id, ACCESS_R, path)?;
 
         // colectăm toate intrările cu parent == dir_id
         let mut entries = Vec::new();
@@ -516,130 +955,75 @@ impl Inner {
 
         // sortăm pentru rezultate deterministe
         entries.sort_by(|a, b| a.name.cmp(&b.name));
+        self.note_access(dir_id)?;
 
-        Ok(ReadDir { entries, pos: 0 })
+        Ok(ReadDir::new(entries))
     }
 
-    fn create_file(&mut self, path: &str) -> Result<InodeId> {
-        let parts = Vfs::split_path(path)?;
-        let (parent_parts, leaf) = parts.split_at(parts.len() - 1);
-        let name = leaf[0];
-
-        let parent = if parent_parts.is_empty() {
-            self.header.root
-        } else {
-            let mut cur = self.header.root;
-            for p in parent_parts {
-                let key = (cur, (*p).to_string());
-                cur = *self
-                    .children
-                    .get(&key)
-                    .ok_or_else(|| VfsError::NotFound(path.into()))?;
-            }
-            cur
-        };
-
-        let p_inode = self
-            .inodes
-            .get(&parent)
-            .ok_or_else(|| VfsError::CorruptLog("parent inode missing".into()))?;
-        if p_inode.kind != NodeKind::Dir {
-            return Err(VfsError::NotADir(path.into()));
-        }
+    fn create_file(&mut self, cwd: InodeId, path: &VfsPath) -> Result<InodeId> {
+        let (parent, name) = self.resolve_parent(cwd, path)?;
+        let name = name.as_str();
 
         let key = (parent, name.to_string());
         if self.children.contains_key(&key) {
-            return Err(VfsError::AlreadyExists(path.into()));
+            return Err(VfsError::AlreadyExists(path.to_string()));
         }
 
         let new_id = self.next_inode;
         self.next_inode = InodeId(self.next_inode.0 + 1);
 
-        let now = Timestamp::now();
+        let now = self.options.clock.now();
         let snap = InodeSnapshot {
             id: new_id,
             parent: Some(parent),
             name: name.to_string(),
             kind: NodeKind::File,
-            metadata: Metadata {
-                size: 0,
-                created_at: now,
-                modified_at: now,
-            },
+            metadata: self.new_metadata(now, DEFAULT_FILE_MODE),
             extents: vec![],
+            xattrs: XattrMap::new(),
         };
 
         // persist (write → apply)
-        write_record(&mut self.file, &Record::InodeAlloc(snap.clone()))?;
+        self.log(&Record::InodeAlloc(snap.clone()))?;
         let de = DirEntry {
             parent,
             inode: new_id,
             name: name.to_string(),
             kind: NodeKind::File,
         };
-        write_record(&mut self.file, &Record::DirEntryAdd { entry: de.clone() })?;
-        write_record(
-            &mut self.file,
-            &Record::SetTimes {
-                inode: new_id,
-                created_at: Some(now),
-                modified_at: Some(now),
-            },
-        )?;
+        self.log(&Record::DirEntryAdd { entry: de.clone() })?;
+        self.log(&Record::SetTimes {
+            inode: new_id,
+            created_at: Some(now),
+            modified_at: Some(now),
+            accessed_at: Some(now),
+            changed_at: Some(now),
+        })?;
         self.apply_record(&Record::InodeAlloc(snap))?;
         self.apply_record(&Record::DirEntryAdd { entry: de })?;
+        self.touch(parent, true)?;
 
         Ok(new_id)
     }
 
     fn write_at(&mut self, inode: InodeId, off: u64, buf: &[u8]) -> Result<usize> {
+        self.ensure_writable()?;
         let node = self
             .inodes
-            .get_mut(&inode)
+            .get(&inode)
             .ok_or_else(|| VfsError::NotFound(format!("{inode:?}")))?;
 
         if node.kind != NodeKind::File {
             return Err(VfsError::NotAFile(node.name.clone()));
         }
 
-        // mergem la final (append-only)
-        self.file.seek(SeekFrom::End(0))?;
-
-        let (_data_crc, data_payload_offset) =
-            write_data_write_record(&mut self.file, inode, off, buf, &mut self.scratch)?;
-
-        // aplicăm în memorie ca la replay
-        node.extents.push(Extent {
-            logical_offset: off,
-            file_offset: data_payload_offset,
-            len: buf.len() as u64,
-        });
-
-        let end = off.saturating_add(buf.len() as u64);
-        if end > node.metadata.size {
-            node.metadata.size = end;
-        }
-        node.metadata.modified_at = Timestamp::now();
-
-        let now = Timestamp::now();
-        write_record(
-            &mut self.file,
-            &Record::SetTimes {
-                inode,
-                created_at: None,
-                modified_at: Some(now),
-            },
-        )?;
-        self.apply_record(&Record::SetTimes {
-            inode,
-            created_at: None,
-            modified_at: Some(now),
-        })?;
+        self.store_data(inode, off, buf)?;
+        self.touch(inode, true)?;
 
         Ok(buf.len())
     }
 
-    fn read_at(&mut self, inode: InodeId, off: u64, buf: &mut [u8]) -> Result<usize> {
+    pub(crate) fn read_at(&mut self, inode: InodeId, off: u64, buf: &mut [u8]) -> Result<usize> {
         let node = self
             .inodes
             .get(&inode)
@@ -715,15 +1099,21 @@ impl Inner {
 
                 let logical_w_lo = off + w_lo as u64; // offset logic real
                 let within_extent = logical_w_lo - ex.logical_offset; // offset în interiorul extentului
-                let backing_off = ex.file_offset + within_extent;
 
-                self.file.seek(SeekFrom::Start(backing_off))?;
-                self.file.read_exact(&mut buf[w_lo..w_hi])?;
+                read_extent(
+                    &mut self.file,
+                    &mut self.blob_cache,
+                    self.cipher.as_ref(),
+                    ex,
+                    within_extent,
+                    &mut buf[w_lo..w_hi],
+                )?;
             }
 
             holes = new_holes;
         }
 
+        self.note_access(inode)?;
         Ok(n)
     }
 
@@ -748,25 +1138,38 @@ impl Inner {
             return Err(VfsError::NotAFile(node.name.clone()));
         }
 
-        write_record(&mut self.file, &Record::Truncate { inode, len })?;
+        self.log(&Record::Truncate { inode, len })?;
 
         // apply in-memory
         self.apply_record(&Record::Truncate { inode, len })?;
 
-        let now = Timestamp::now();
-        write_record(
-            &mut self.file,
-            &Record::SetTimes {
-                inode,
-                created_at: None,
-                modified_at: Some(now),
-            },
-        )?;
-        self.apply_record(&Record::SetTimes {
-            inode,
-            created_at: None,
-            modified_at: Some(now),
-        })?;
+        self.touch(inode, true)
+    }
+
+    /// scrie datele in log (deduplicate/comprimate dupa optiuni) si le aplica
+    /// pe inode, fara sa atinga timpii.
+    pub(crate) fn store_data(&mut self, inode: InodeId, off: u64, buf: &[u8]) -> Result<()> {
+        match self.options.dedup {
+            Some(chunking) => self.write_chunks(inode, off, buf, chunking),
+            None => {
+                // append-only, apoi aplicăm în memorie ca la replay
+                let ex = self.append_data(inode, off, buf)?;
+                self.push_extent(inode, ex)
+            }
+        }
+    }
+
+    /// adauga un extent (cel mai nou castiga la citire) si creste size-ul daca e cazul.
+    pub(crate) fn push_extent(&mut self, inode: InodeId, ex: Extent) -> Result<()> {
+        let node = self.node_mut(inode, "write")?;
+        node.extents.push(ex);
+        let end = ex.logical_offset.saturating_add(ex.len);
+        if end > node.metadata.size {
+            node.metadata.size = end;
+        }
+        if !self.watchers.is_empty() {
+            self.notify_modified(inode);
+        }
         Ok(())
     }
 
@@ -782,40 +1185,25 @@ impl Inner {
 
         node.metadata.size = len;
 
-        Ok(())
-    }
-
-    fn apply_set_times(
-        &mut self,
-        inode: InodeId,
-        created_at: &Option<Timestamp>,
-        modified_at: &Option<Timestamp>,
-    ) -> Result<()> {
-        let node = self
-            .inodes
-            .get_mut(&inode)
-            .ok_or_else(|| VfsError::CorruptLog("set_times inode missing".into()))?;
+        // bytes de dupa `len` nu trebuie sa reapara daca fisierul creste la loc
+        node.extents.retain_mut(|ex| {
+            if ex.logical_offset >= len {
+                return false;
+            }
+            ex.len = ex.len.min(len - ex.logical_offset);
+            true
+        });
 
-        if let Some(c) = created_at {
-            node.metadata.created_at = *c;
-        }
-        if let Some(m) = modified_at {
-            node.metadata.modified_at = *m;
-        }
         Ok(())
     }
 
-    fn metadata(&self, path: &str) -> Result<Metadata> {
-        let inode_id = if path.is_empty() {
-            self.header.root
-        } else {
-            self.path_to_inode(path)?
-        };
+    fn metadata(&self, cwd: InodeId, path: &VfsPath) -> Result<Metadata> {
+        let inode_id = self.resolve(cwd, path)?;
 
         let inode = self
             .inodes
             .get(&inode_id)
-            .ok_or_else(|| VfsError::NotFound(path.into()))?;
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?;
 
         Ok(inode.metadata.clone())
     }
@@ -854,40 +1242,23 @@ impl Inner {
         }
 
         self.children.remove(&key);
+        // fara hard link-uri, inode-ul nu mai e accesibil; altfel ar reaparea din checkpoint
+        self.inodes.remove(&inode);
         Ok(())
     }
 
-    fn find_parent_and_leaf(&self, path: &str) -> Result<(InodeId, String)> {
-        let parts = Vfs::split_path(path)?;
-        let (parent_parts, leaf) = parts.split_at(parts.len() - 1);
-        let name = leaf[0].to_string();
-
-        let parent = if parent_parts.is_empty() {
-            self.header.root
-        } else {
-            let mut cur = self.header.root;
-            for p in parent_parts {
-                let key = (cur, (*p).to_string());
-                cur = *self
-                    .children
-                    .get(&key)
-                    .ok_or_else(|| VfsError::NotFound(path.into()))?;
-            }
-            cur
-        };
-
-        Ok((parent, name))
-    }
-
-    fn unlink(&mut self, path: &str, expect_kind: NodeKind) -> Result<()> {
-        let (parent, name) = self.find_parent_and_leaf(path)?;
+    fn unlink(&mut self, cwd: InodeId, path: &VfsPath, expect_kind: NodeKind) -> Result<()> {
+        if path.is_root() {
+            return Err(VfsError::InvalidPath("cannot remove the root".into()));
+        }
+        let (parent, name) = self.resolve_parent(cwd, path)?;
         let key = (parent, name.clone());
 
         let inode = self
             .children
             .get(&key)
             .copied()
-            .ok_or_else(|| VfsError::NotFound(path.into()))?;
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?;
 
         let node = self
             .inodes
@@ -896,8 +1267,8 @@ impl Inner {
 
         if node.kind != expect_kind {
             return match expect_kind {
-                NodeKind::File => Err(VfsError::NotAFile(path.into())),
-                NodeKind::Dir => Err(VfsError::NotADir(path.into())),
+                NodeKind::File => Err(VfsError::NotAFile(path.to_string())),
+                NodeKind::Dir => Err(VfsError::NotADir(path.to_string())),
             };
         }
 
@@ -909,19 +1280,32 @@ impl Inner {
             }
         }
 
-        // persist
-        let rec = Record::DirEntryRemove {
-            parent,
-            name: name.clone(),
-            inode,
+        // persist; cu trash-ul activ inode-ul ramane, doar se dezleaga de parinte
+        let rec = if self.options.trash.enabled {
+            Record::Trash {
+                parent,
+                name: name.clone(),
+                entry: TrashEntry {
+                    inode,
+                    kind: expect_kind,
+                    original_path: self.inode_path(inode)?,
+                    deleted_at: self.options.clock.now(),
+                },
+            }
+        } else {
+            Record::DirEntryRemove {
+                parent,
+                name: name.clone(),
+                inode,
+            }
         };
         self.file.seek(SeekFrom::End(0))?;
-        write_record(&mut self.file, &rec)?;
+        self.log(&rec)?;
         self.file.sync_all()?;
 
         // apply
         self.apply_record(&rec)?;
-        Ok(())
+        self.touch(parent, true)
     }
 
     fn apply_rename(
@@ -986,33 +1370,35 @@ impl Inner {
         Ok(())
     }
 
-    fn rename(&mut self, old_path: &str, new_path: &str) -> Result<()> {
+    fn rename(&mut self, cwd: InodeId, old_path: &VfsPath, new_path: &VfsPath) -> Result<()> {
         // old: (old_parent, old_name, inode)
-        let (old_parent, old_name) = self.find_parent_and_leaf(old_path)?;
+        let (old_parent, old_name) = self.resolve_parent(cwd, old_path)?;
         let old_key = (old_parent, old_name.clone());
 
         let inode = self
             .children
             .get(&old_key)
             .copied()
-            .ok_or_else(|| VfsError::NotFound(old_path.into()))?;
+            .ok_or_else(|| VfsError::NotFound(old_path.to_string()))?;
 
-        // new: (new_parent, new_name)
-        let (new_parent, new_name) = self.find_parent_and_leaf(new_path)?;
+        // new: (new_parent, new_name), parintele e verificat ca e dir
+        let (new_parent, new_name) = self.resolve_parent(cwd, new_path)?;
         let new_key = (new_parent, new_name.clone());
 
-        // new parent trebuie să existe și să fie dir
-        let np = self
-            .inodes
-            .get(&new_parent)
-            .ok_or_else(|| VfsError::NotFound(new_path.into()))?;
-        if np.kind != NodeKind::Dir {
-            return Err(VfsError::NotADir(new_path.into()));
-        }
-
         // destinația trebuie să fie liberă (MVP)
         if self.children.contains_key(&new_key) {
-            return Err(VfsError::AlreadyExists(new_path.into()));
+            return Err(VfsError::AlreadyExists(new_path.to_string()));
+        }
+
+        // un director nu poate fi mutat in propriul subarbore
+        let mut cur = Some(new_parent);
+        while let Some(id) = cur {
+            if id == inode {
+                return Err(VfsError::InvalidPath(format!(
+                    "cannot move {old_path} inside itself"
+                )));
+            }
+            cur = self.inodes.get(&id).and_then(|n| n.parent);
         }
 
         // persist record
@@ -1023,25 +1409,279 @@ impl Inner {
             old_name: old_name.clone(),
             new_name: new_name.clone(),
         };
-        write_record(&mut self.file, &rec)?;
+        self.log(&rec)?;
 
         // apply in-memory
         self.apply_record(&rec)?;
 
-        // persist SetTimes (modified_at)
-        let now = Timestamp::now();
-        let times = Record::SetTimes {
+        // rename-ul schimba doar inode-ul (ctime), continutul ramane; parintii
+        // si-au schimbat intrarile
+        self.touch(inode, false)?;
+        self.touch(old_parent, true)?;
+        if new_parent != old_parent {
+            self.touch(new_parent, true)?;
+        }
+
+        Ok(())
+    }
+
+    fn node(&self, cwd: InodeId, path: &VfsPath) -> Result<&Inode> {
+        let inode = self.resolve(cwd, path)?;
+        self.inodes
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))
+    }
+
+    pub(crate) fn node_mut(&mut self, inode: InodeId, op: &str) -> Result<&mut Inode> {
+        self.inodes
+            .get_mut(&inode)
+            .ok_or_else(|| VfsError::CorruptLog(format!("{op} inode missing")))
+    }
+
+    pub(crate) fn new_metadata(&self, now: Timestamp, mode: u32) -> Metadata {
+        let (uid, gid) = self
+            .options
+            .user
+            .as_ref()
+            .map_or((0, 0), |u| (u.uid, u.gid));
+        Metadata {
+            size: 0,
+            created_at: now,
+            modified_at: now,
+            accessed_at: now,
+            changed_at: now,
+            mode,
+            uid,
+            gid,
+        }
+    }
+
+    /// verifica bitii `want` (r/w/x) pentru user-ul montarii; fara user nu se verifica nimic.
+    pub(crate) fn check_access(&self, inode: InodeId, want: u32, path: &VfsPath) -> Result<()> {
+        let Some(user) = &self.options.user else {
+            return Ok(());
+        };
+        if user.uid == 0 {
+            return Ok(());
+        }
+        let meta = &self
+            .inodes
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?
+            .metadata;
+
+        let bits = if meta.uid == user.uid {
+            meta.mode >> 6
+        } else if user.in_group(meta.gid) {
+            meta.mode >> 3
+        } else {
+            meta.mode
+        } & 0o7;
+
+        if bits & want != want {
+            return Err(VfsError::PermissionDenied(path.to_string()));
+        }
+        Ok(())
+    }
+
+    /// root-ul sau owner-ul inode-ului.
+    pub(crate) fn check_owner(&self, inode: InodeId, path: &VfsPath) -> Result<()> {
+        let Some(user) = &self.options.user else {
+            return Ok(());
+        };
+        let owner = self
+            .inodes
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(path.to_string()))?
+            .metadata
+            .uid;
+        if user.uid != 0 && user.uid != owner {
+            return Err(VfsError::PermissionDenied(path.to_string()));
+        }
+        Ok(())
+    }
+
+    /// marcheaza o modificare: ctime mereu, mtime doar daca s-a schimbat continutul.
+    pub(crate) fn touch(&mut self, inode: InodeId, content: bool) -> Result<()> {
+        let now = self.options.clock.now();
+        let rec = Record::SetTimes {
             inode,
             created_at: None,
-            modified_at: Some(now),
+            modified_at: content.then_some(now),
+            accessed_at: None,
+            changed_at: Some(now),
+        };
+        self.log(&rec)?;
+        self.apply_record(&rec)
+    }
+
+    /// actualizeaza atime dupa o citire, conform politicii montarii.
+    fn note_access(&mut self, inode: InodeId) -> Result<()> {
+        let now = self.options.clock.now();
+        let m = &self
+            .inodes
+            .get(&inode)
+            .ok_or_else(|| VfsError::NotFound(format!("{inode:?}")))?
+            .metadata;
+        let update = !self.options.read_only
+            && match self.options.atime {
+                AtimePolicy::NoAtime => false,
+                AtimePolicy::Strict => true,
+                AtimePolicy::Relatime => {
+                    m.accessed_at <= m.modified_at
+                        || m.accessed_at <= m.changed_at
+                        || now.0 - m.accessed_at.0 >= RELATIME_INTERVAL_NS
+                }
+            };
+        if !update {
+            return Ok(());
+        }
+
+        let rec = Record::SetTimes {
+            inode,
+            created_at: None,
+            modified_at: None,
+            accessed_at: Some(now),
+            changed_at: None,
+        };
+        self.log(&rec)?;
+        self.apply_record(&rec)
+    }
+
+    fn set_times(&mut self, cwd: InodeId, path: &VfsPath, times: FileTimes) -> Result<()> {
+        let inode = self.resolve(cwd, path)?;
+        self.check_owner(inode, path)?;
+
+        let rec = Record::SetTimes {
+            inode,
+            created_at: times.created_at,
+            modified_at: times.modified_at,
+            accessed_at: times.accessed_at,
+            changed_at: Some(self.options.clock.now()),
+        };
+        self.log(&rec)?;
+        self.apply_record(&rec)
+    }
+
+    fn set_permissions(&mut self, cwd: InodeId, path: &VfsPath, mode: u32) -> Result<()> {
+        if mode & !0o7777 != 0 {
+            return Err(VfsError::InvalidPath(format!(
+                "invalid mode {mode:o} for {path}"
+            )));
+        }
+        let inode = self.resolve(cwd, path)?;
+        self.check_owner(inode, path)?;
+
+        let rec = Record::SetPermissions { inode, mode };
+        self.log(&rec)?;
+        self.apply_record(&rec)?;
+        self.touch(inode, false)
+    }
+
+    fn chown(
+        &mut self,
+        cwd: InodeId,
+        path: &VfsPath,
+        uid: Option<u32>,
+        gid: Option<u32>,
+    ) -> Result<()> {
+        let node = self.node(cwd, path)?;
+        let inode = node.id;
+        let uid = uid.unwrap_or(node.metadata.uid);
+        let gid = gid.unwrap_or(node.metadata.gid);
+
+        // ca pe unix: doar root schimba owner-ul; owner-ul poate schimba
+        // grupul doar intr-unul din grupurile lui
+        if let Some(user) = &self.options.user
+            && user.uid != 0
+        {
+            let allowed = uid == node.metadata.uid
+                && user.uid == node.metadata.uid
+                && (gid == node.metadata.gid || user.in_group(gid));
+            if !allowed {
+                return Err(VfsError::PermissionDenied(path.to_string()));
+            }
+        }
+
+        let rec = Record::Chown { inode, uid, gid };
+        self.log(&rec)?;
+        self.apply_record(&rec)?;
+        self.touch(inode, false)
+    }
+
+    fn set_xattr(&mut self, cwd: InodeId, path: &VfsPath, name: &str, value: &[u8]) -> Result<()> {
+        if name.is_empty() || name.len() > XATTR_NAME_MAX {
+            return Err(VfsError::InvalidPath(format!(
+                "invalid xattr name on {path}: {name:?}"
+            )));
+        }
+        let node = self.node(cwd, path)?;
+        let inode = node.id;
+        self.check_access(inode, ACCESS_W, path)?;
+
+        // limita e pe tot inode-ul, nu per atribut
+        let used: usize = node
+            .xattrs
+            .iter()
+            .filter(|(n, _)| n.as_str() != name)
+            .map(|(n, v)| n.len() + v.len())
+            .sum();
+        if used + name.len() + value.len() > XATTR_INODE_MAX {
+            return Err(VfsError::NoSpace(format!(
+                "xattrs on {path} exceed {XATTR_INODE_MAX} bytes"
+            )));
+        }
+
+        let rec = Record::SetXattr {
+            inode,
+            name: name.to_string(),
+            value: value.to_vec(),
+        };
+        self.log(&rec)?;
+        self.apply_record(&rec)?;
+        self.touch(inode, false)
+    }
+
+    fn remove_xattr(&mut self, cwd: InodeId, path: &VfsPath, name: &str) -> Result<()> {
+        let node = self.node(cwd, path)?;
+        self.check_access(node.id, ACCESS_W, path)?;
+        if !node.xattrs.contains_key(name) {
+            return Err(VfsError::NotFound(format!("xattr {name} on {path}")));
+        }
+
+        let inode = node.id;
+        let rec = Record::RemoveXattr {
+            inode,
+            name: name.to_string(),
         };
-        write_record(&mut self.file, &times)?;
-        self.apply_record(&times)?;
+        self.log(&rec)?;
+        self.apply_record(&rec)?;
+        self.touch(inode, false)
+    }
+
+    fn apply_set_xattr(&mut self, inode: InodeId, name: &str, value: &[u8]) -> Result<()> {
+        let node = self
+            .inodes
+            .get_mut(&inode)
+            .ok_or_else(|| VfsError::CorruptLog("set_xattr inode missing".into()))?;
+        node.xattrs.insert(name.to_string(), value.to_vec());
+        Ok(())
+    }
 
+    fn apply_remove_xattr(&mut self, inode: InodeId, name: &str) -> Result<()> {
+        let node = self
+            .inodes
+            .get_mut(&inode)
+            .ok_or_else(|| VfsError::CorruptLog("remove_xattr inode missing".into()))?;
+        if node.xattrs.remove(name).is_none() {
+            return Err(VfsError::CorruptLog(
+                "remove_xattr missing attribute".into(),
+            ));
+        }
         Ok(())
     }
 
-    fn make_checkpoint(&self) -> Checkpoint {
+    pub(crate) fn make_checkpoint(&self) -> Checkpoint {
         let mut snaps = Vec::with_capacity(self.inodes.len());
         for inode in self.inodes.values() {
             snaps.push(InodeSnapshot {
@@ -1051,30 +1691,41 @@ impl Inner {
                 kind: inode.kind,
                 metadata: inode.metadata.clone(),
                 extents: inode.extents.clone(),
+                xattrs: inode.xattrs.clone(),
             });
         }
 
         snaps.sort_by_key(|s| s.id.0);
 
+        let mut trash: Vec<TrashEntry> = self.trash.values().cloned().collect();
+        trash.sort_by_key(|t| t.inode.0);
+
         Checkpoint {
             next_inode: self.next_inode,
             free_extents: vec![],
             inodes: snaps,
+            trash,
+            dedup: self.dedup_entries(),
         }
     }
 
-    fn write_checkpoint(&mut self) -> Result<()> {
+    pub(crate) fn write_checkpoint(&mut self) -> Result<()> {
         let cp = self.make_checkpoint();
         let rec = Record::Checkpoint(cp);
 
-        write_record(&mut self.file, &rec)?;
+        self.log(&rec)?;
 
         Ok(())
     }
 
-    fn load_from_checkpoint(&mut self, cp: &crate::structs::Checkpoint) -> Result<()> {
+    pub(crate) fn load_from_checkpoint(&mut self, cp: &crate::structs::Checkpoint) -> Result<()> {
         self.inodes.clear();
         self.children.clear();
+        self.trash = cp.trash.iter().map(|t| (t.inode, t.clone())).collect();
+        self.dedup.clear();
+        for e in &cp.dedup {
+            self.index_chunk(e.checksum, e.extent);
+        }
 
         // reconstruim inodes
         for snap in &cp.inodes {
@@ -1085,12 +1736,16 @@ impl Inner {
                 kind: snap.kind,
                 metadata: snap.metadata.clone(),
                 extents: snap.extents.clone(),
+                xattrs: snap.xattrs.clone(),
             };
             self.inodes.insert(inode.id, inode);
         }
 
         // reconstruim children
         for inode in self.inodes.values() {
+            if self.trash.contains_key(&inode.id) {
+                continue;
+            }
             if let Some(p) = inode.parent {
                 let key = (p, inode.name.clone());
 
@@ -1113,12 +1768,13 @@ impl Inner {
         Ok(())
     }
 
-    fn recalc_next_inode(&mut self) {
+    pub(crate) fn recalc_next_inode(&mut self) {
         let mut max_id = 0u64;
         for id in self.inodes.keys() {
             max_id = max_id.max(id.0);
         }
-        self.next_inode = InodeId(max_id + 1);
+        // nu refolosim id-uri deja date (checkpoint-ul poate sti de inode-uri sterse)
+        self.next_inode = InodeId(self.next_inode.0.max(max_id + 1));
     }
 }
 
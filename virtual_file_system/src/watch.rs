@@ -0,0 +1,332 @@
+//! notificari de schimbari: `Vfs::watch` da un `Receiver` care primeste un
+//! `WatchEvent` pt fiecare record aplicat in `Inner` sub path-ul urmarit.
+//!
+//! evenimentele vin sincron, in ordinea record-urilor, din handle-ul care face
+//! modificarea. pt o imagine scrisa de alt proces, o montare read-only cu
+//! `Vfs::tail` aplica record-urile noi si emite aceleasi evenimente.
+//!
+//! un salt de stare (un `Checkpoint` in log sau o imagine compactata intre timp)
+//! nu are operatii de anuntat; evenimentele lui vin din diferenta dintre starea
+//! de dinainte si cea de dupa.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+use crate::no_sql::{DecodedRecord, read_header};
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::{Inner, Vfs};
+
+#[derive(Debug)]
+pub(crate) struct Watcher {
+    path: VfsPath,
+    recursive: bool,
+    tx: Sender<WatchEvent>,
+}
+
+impl Watcher {
+    // path-ul urmarit insusi, copiii directi sau, recursiv, tot subarborele
+    fn covers(&self, path: &VfsPath) -> bool {
+        if path == &self.path {
+            return true;
+        }
+        if self.recursive {
+            self.path.is_root()
+                || path
+                    .as_str()
+                    .starts_with(&format!("{}/", self.path.as_str()))
+        } else {
+            path.parent().as_ref() == Some(&self.path)
+        }
+    }
+
+    fn wants(&self, event: &WatchEvent) -> bool {
+        match event {
+            WatchEvent::Created(p) | WatchEvent::Modified(p) | WatchEvent::Removed(p) => {
+                self.covers(p)
+            }
+            WatchEvent::Renamed { from, to } => self.covers(from) || self.covers(to),
+        }
+    }
+}
+
+impl Inner {
+    fn entry_path(&self, parent: InodeId, name: &str) -> Option<VfsPath> {
+        self.inode_path(parent).ok()?.join(name).ok()
+    }
+
+    /// evenimentele produse de `rec`, calculate pe starea de dinainte de aplicare.
+    pub(crate) fn watch_events(&self, rec: &Record) -> Vec<WatchEvent> {
+        let event = match rec {
+            Record::DirEntryAdd { entry } => self
+                .entry_path(entry.parent, &entry.name)
+                .map(WatchEvent::Created),
+            Record::TrashRestore { parent, name, .. } => {
+                self.entry_path(*parent, name).map(WatchEvent::Created)
+            }
+            Record::DirEntryRemove { parent, name, .. } | Record::Trash { parent, name, .. } => {
+                self.entry_path(*parent, name).map(WatchEvent::Removed)
+            }
+            Record::Rename {
+                old_parent,
+                new_parent,
+                old_name,
+                new_name,
+                ..
+            } => self
+                .entry_path(*old_parent, old_name)
+                .zip(self.entry_path(*new_parent, new_name))
+                .map(|(from, to)| WatchEvent::Renamed { from, to }),
+            Record::Truncate { inode, .. }
+            | Record::SetXattr { inode, .. }
+            | Record::RemoveXattr { inode, .. }
+            | Record::SetPermissions { inode, .. }
+            | Record::Chown { inode, .. } => self.inode_path(*inode).ok().map(WatchEvent::Modified),
+            // datele (si DataRef) trec prin `push_extent`, care anunta singur
+            _ => None,
+        };
+        event.into_iter().collect()
+    }
+
+    pub(crate) fn notify_modified(&mut self, inode: InodeId) {
+        if let Ok(path) = self.inode_path(inode) {
+            self.notify(vec![WatchEvent::Modified(path)]);
+        }
+    }
+
+    /// trimite evenimentele la receptorii interesati; un receptor inchis e scos.
+    pub(crate) fn notify(&mut self, events: Vec<WatchEvent>) {
+        if events.is_empty() {
+            return;
+        }
+        self.watchers.retain(|w| {
+            events
+                .iter()
+                .filter(|e| w.wants(e))
+                .all(|e| w.tx.send(e.clone()).is_ok())
+        });
+    }
+
+    /// aplica record-urile scrise de alt proces dupa `log_end`; un record inca
+    /// incomplet la coada se lasa pt apelul urmator.
+    pub(crate) fn tail(&mut self) -> Result<usize> {
+        if !self.options.read_only {
+            return Err(VfsError::Unsupported(
+                "tail needs a read-only mount; a writable one is the only writer".into(),
+            ));
+        }
+        let mut applied = 0;
+        loop {
+            let (decoded, next) = match self.read_record(self.log_end) {
+                Ok(Some(rec)) => rec,
+                Ok(None) | Err(VfsError::CorruptLog(_)) => break,
+                Err(e) => return Err(e),
+            };
+            self.follow(decoded, next)?;
+            applied += 1;
+        }
+        // un `compact` pune alt fisier in locul celui deschis aici, care nu mai
+        // creste; generatia din header-ul de pe disc spune daca s-a intamplat
+        let on_disk = read_header(&mut File::open(&self.path)?)?;
+        if on_disk.generation != self.header.generation {
+            self.reopen()?;
+            applied += 1;
+        }
+        self.drop_unlinked_mounts();
+        Ok(applied)
+    }
+
+    // trece pe imaginea compactata, cu montarile si receptorii de acum
+    fn reopen(&mut self) -> Result<()> {
+        let mut fresh = Inner::open(&self.path, self.options.clone(), u64::MAX)?;
+        let events = if self.watchers.is_empty() {
+            Vec::new()
+        } else {
+            // offset-urile datelor difera intre fisiere, deci se compara continutul
+            let before = self.visible();
+            let after = fresh.visible();
+            jump_events(&before, &after, |inode, size| {
+                same_data(self, &mut fresh, inode, size).map(|same| !same)
+            })?
+        };
+        fresh.watchers = std::mem::take(&mut self.watchers);
+        fresh.mounts = std::mem::take(&mut self.mounts);
+        *self = fresh;
+        self.notify(events);
+        Ok(())
+    }
+
+    // nodurile legate pana la radacina, cu path-urile lor; ce e in trash (si tot
+    // ce e sub el) nu se vede
+    fn visible(&self) -> HashMap<InodeId, (VfsPath, Inode)> {
+        self.inodes
+            .values()
+            .filter(|node| self.linked(node.id))
+            .filter_map(|node| Some((node.id, (self.inode_path(node.id).ok()?, node.clone()))))
+            .collect()
+    }
+
+    pub(crate) fn linked(&self, inode: InodeId) -> bool {
+        let mut cur = inode;
+        while cur != self.header.root {
+            let Some(node) = self.inodes.get(&cur) else {
+                return false;
+            };
+            let Some(parent) = node.parent else {
+                return false;
+            };
+            if self.children.get(&(parent, node.name.clone())) != Some(&cur) {
+                return false;
+            }
+            cur = parent;
+        }
+        true
+    }
+
+    /// aplica un record gasit dupa `log_end` (scris de alt proces sau primit de o
+    /// replica), inclusiv evidenta snapshot-urilor pe care o face replay-ul.
+    pub(crate) fn follow(&mut self, decoded: DecodedRecord, next: u64) -> Result<()> {
+        match &decoded.record {
+            Record::Snapshot { name, created_at } => {
+                self.snapshots.insert(
+                    name.clone(),
+                    SnapshotInfo {
+                        name: name.clone(),
+                        offset: next,
+                        created_at: *created_at,
+                    },
+                );
+            }
+            Record::SnapshotDelete { name } => {
+                self.snapshots.remove(name);
+            }
+            Record::InodeAlloc(snap) => {
+                self.next_inode = InodeId(self.next_inode.0.max(snap.id.0 + 1));
+            }
+            Record::Checkpoint(cp) => {
+                // starea se ia din checkpoint, ca la mount; acelasi fisier, deci
+                // extent-uri diferite inseamna date diferite
+                let before = (!self.watchers.is_empty()).then(|| self.visible());
+                self.load_from_checkpoint(cp)?;
+                self.recalc_next_inode();
+                if let Some(before) = before {
+                    let events = jump_events(&before, &self.visible(), |_, _| Ok(true))?;
+                    self.notify(events);
+                }
+                self.log_end = next;
+                return Ok(());
+            }
+            _ => {}
+        }
+        self.apply_decoded(decoded)?;
+        self.log_end = next;
+        Ok(())
+    }
+}
+
+/// evenimentele unui salt de la `before` la `after`: stergerile (copiii inaintea
+/// parintilor), mutarile, crearile si modificarile. `data_changed` decide pt un
+/// fisier cu aceeasi marime dar alte extent-uri.
+fn jump_events(
+    before: &HashMap<InodeId, (VfsPath, Inode)>,
+    after: &HashMap<InodeId, (VfsPath, Inode)>,
+    mut data_changed: impl FnMut(InodeId, u64) -> Result<bool>,
+) -> Result<Vec<WatchEvent>> {
+    let mut removed: Vec<&VfsPath> = before
+        .iter()
+        .filter(|(id, _)| !after.contains_key(id))
+        .map(|(_, (path, _))| path)
+        .collect();
+    removed.sort_by(|a, b| b.as_str().cmp(a.as_str()));
+
+    let mut renamed = Vec::new();
+    let mut created = Vec::new();
+    let mut modified = Vec::new();
+    for (id, (path, node)) in after {
+        let Some((old_path, old)) = before.get(id) else {
+            created.push(path);
+            continue;
+        };
+        if (old.parent, &old.name) != (node.parent, &node.name) {
+            renamed.push((old_path, path));
+        }
+        let (m, o) = (&node.metadata, &old.metadata);
+        let changed = (m.size, m.mode, m.uid, m.gid) != (o.size, o.mode, o.uid, o.gid)
+            || node.xattrs != old.xattrs
+            || (node.extents != old.extents && data_changed(*id, m.size)?);
+        if changed {
+            modified.push(path);
+        }
+    }
+    renamed.sort_by(|a, b| a.1.as_str().cmp(b.1.as_str()));
+    created.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+    modified.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+
+    let mut events: Vec<WatchEvent> = removed
+        .into_iter()
+        .map(|p| WatchEvent::Removed(p.clone()))
+        .collect();
+    events.extend(renamed.into_iter().map(|(from, to)| WatchEvent::Renamed {
+        from: from.clone(),
+        to: to.clone(),
+    }));
+    events.extend(created.into_iter().map(|p| WatchEvent::Created(p.clone())));
+    events.extend(
+        modified
+            .into_iter()
+            .map(|p| WatchEvent::Modified(p.clone())),
+    );
+    Ok(events)
+}
+
+// acelasi continut pt `inode` in doua imagini, citit bucata cu bucata
+fn same_data(a: &mut Inner, b: &mut Inner, inode: InodeId, size: u64) -> Result<bool> {
+    let (mut x, mut y) = (vec![0u8; 64 * 1024], vec![0u8; 64 * 1024]);
+    let mut off = 0;
+    while off < size {
+        let n = a.read_at(inode, off, &mut x)?;
+        if n == 0 {
+            break;
+        }
+        if b.read_at(inode, off, &mut y)? != n || x[..n] != y[..n] {
+            return Ok(false);
+        }
+        off += n as u64;
+    }
+    Ok(true)
+}
+
+impl Vfs {
+    /// urmareste schimbarile lui `path` (si ale copiilor directi, sau ale intregului
+    /// subarbore cu `recursive`). receptorul ramane valid si daca path-ul e sters.
+    pub fn watch<P: AsRef<Path>>(&self, path: P, recursive: bool) -> Result<Receiver<WatchEvent>> {
+        let path = VfsPath::new(path)?;
+        if self.crossing(&path)?.is_some() {
+            return Err(VfsError::Unsupported(format!(
+                "cannot watch inside an attached mount: {path}"
+            )));
+        }
+        let mut inner = self.inner.borrow_mut();
+        let inode = inner.resolve(self.cwd, &path)?;
+        let path = inner.inode_path(inode)?;
+        let (tx, rx) = channel();
+        inner.watchers.push(Watcher {
+            path,
+            recursive,
+            tx,
+        });
+        Ok(rx)
+    }
+
+    /// pt o montare read-only a unei imagini scrise de alt proces: aplica
+    /// record-urile adaugate de la mount (sau de la ultimul apel) si emite
+    /// evenimentele lor. intoarce cate record-uri au fost aplicate.
+    ///
+    /// dupa un `compact` facut de celalalt proces imaginea se redeschide singura;
+    /// saltul conteaza ca un record, iar evenimentele lui vin din diferenta de stare.
+    pub fn tail(&mut self) -> Result<usize> {
+        self.inner.borrow_mut().tail()
+    }
+}
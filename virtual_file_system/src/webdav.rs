@@ -0,0 +1,822 @@
+//! server WebDAV (clasa 1) peste un `Vfs` montat, ca imaginea sa poata fi
+//! deschisa din file manager fara driver de kernel.
+//!
+//! serverul e intentionat mic: un singur fir, conexiunile sunt servite pe rand
+//! (cu keep-alive si timeout de inactivitate), fara lock-uri si fara proprietati
+//! scrise de client. suporta OPTIONS, PROPFIND (depth 0/1), GET/HEAD, PUT, MKCOL,
+//! MOVE si DELETE.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+use crate::file_ops::VfsFile;
+use crate::path::VfsPath;
+use crate::structs::*;
+use crate::vfs::Vfs;
+
+// cat se copiaza o data intre socket si fisier
+const COPY_BUF: usize = 1 << 20;
+// limite pt header-e, ca un client stricat sa nu umple memoria
+const MAX_LINE: u64 = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// server WebDAV legat de un socket TCP.
+///
+/// `Vfs` nu e `Send`, deci serverul traieste pe firul care a montat imaginea.
+pub struct DavServer {
+    vfs: Vfs,
+    listener: TcpListener,
+    idle_timeout: Duration,
+}
+
+impl DavServer {
+    pub fn bind<A: ToSocketAddrs>(vfs: Vfs, addr: A) -> Result<Self> {
+        Ok(Self {
+            vfs,
+            listener: TcpListener::bind(addr)?,
+            idle_timeout: Duration::from_secs(30),
+        })
+    }
+
+    /// cat asteapta o conexiune keep-alive urmatoarea cerere inainte sa fie inchisa.
+    pub fn idle_timeout(mut self, timeout: Duration) -> Self {
+        self.idle_timeout = timeout;
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    pub fn vfs(&self) -> &Vfs {
+        &self.vfs
+    }
+
+    pub fn into_vfs(self) -> Vfs {
+        self.vfs
+    }
+
+    /// serveste conexiuni pana la o eroare a socket-ului de ascultare.
+    pub fn serve(&mut self) -> Result<()> {
+        loop {
+            self.accept_one()?;
+        }
+    }
+
+    /// accepta o singura conexiune si o serveste pana clientul o inchide.
+    ///
+    /// erorile de pe conexiune (client disparut, timeout) nu sunt intoarse; doar
+    /// cele de la `accept`.
+    pub fn accept_one(&mut self) -> Result<()> {
+        let (stream, _) = self.listener.accept()?;
+        let _ = self.handle_connection(stream);
+        Ok(())
+    }
+
+    fn handle_connection(&mut self, stream: TcpStream) -> std::io::Result<()> {
+        stream.set_read_timeout(Some(self.idle_timeout))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = stream;
+        loop {
+            let request = match read_request(&mut reader) {
+                Ok(Some(request)) => request,
+                Ok(None) => return Ok(()),
+                Err(e) => {
+                    let _ = Response::text(400, &e.to_string()).send(&mut writer, false, false);
+                    return Err(e);
+                }
+            };
+            if request.header("expect") == Some("100-continue") {
+                writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+            }
+            let keep_alive = request.keep_alive();
+            let head = request.method == "HEAD";
+
+            let mut body = match Body::new(&mut reader, &request) {
+                Ok(body) => body,
+                Err(e) => {
+                    let _ = Response::text(400, &e.to_string()).send(&mut writer, false, false);
+                    return Err(e);
+                }
+            };
+            let response = self.dispatch(&request, &mut body);
+            // ce n-a citit handler-ul trebuie consumat, altfel strica cererea urmatoare
+            body.drain()?;
+            response.send(&mut writer, head, keep_alive)?;
+            if !keep_alive {
+                return Ok(());
+            }
+        }
+    }
+
+    fn dispatch(&mut self, request: &Request, body: &mut Body<'_>) -> Response {
+        let path = match request_path(&request.target) {
+            Ok(path) => path,
+            Err(e) => return error_response(&e),
+        };
+        let result = match request.method.as_str() {
+            "OPTIONS" => Ok(Response::empty(200)
+                .header("DAV", "1")
+                .header("Allow", ALLOW)),
+            "PROPFIND" => self.propfind(request, &path),
+            "GET" | "HEAD" => self.get(&path),
+            "PUT" => self.put(&path, body),
+            "MKCOL" => self.mkcol(&path),
+            "MOVE" => self.move_to(request, &path),
+            "DELETE" => self.delete(&path),
+            _ => Ok(Response::text(501, "method not implemented").header("Allow", ALLOW)),
+        };
+        result.unwrap_or_else(|e| error_response(&e))
+    }
+
+    fn propfind(&self, request: &Request, path: &VfsPath) -> Result<Response> {
+        // `infinity` e tratat ca 1; RFC 4918 lasa serverul sa nu parcurga tot arborele
+        let depth = request.header("depth").unwrap_or("infinity");
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<D:multistatus xmlns:D=\"DAV:\">\n",
+        );
+        let kind = node_kind(&self.vfs, path)?;
+        prop_response(&mut xml, path, kind, &self.vfs.metadata(path)?);
+        if kind == NodeKind::Dir && depth != "0" {
+            for entry in sorted_dir(&self.vfs, path)? {
+                let child = path.join(&entry.name)?;
+                prop_response(&mut xml, &child, entry.kind, &self.vfs.metadata(&child)?);
+            }
+        }
+        xml.push_str("</D:multistatus>\n");
+        Ok(Response::bytes(
+            207,
+            "application/xml; charset=utf-8",
+            xml.into_bytes(),
+        ))
+    }
+
+    fn get(&self, path: &VfsPath) -> Result<Response> {
+        let meta = self.vfs.metadata(path)?;
+        let response = if node_kind(&self.vfs, path)? == NodeKind::Dir {
+            Response::bytes(
+                200,
+                "text/html; charset=utf-8",
+                dir_listing(&self.vfs, path)?.into_bytes(),
+            )
+        } else {
+            let file = self.vfs.open_file(path)?;
+            Response {
+                status: 200,
+                headers: vec![("Content-Type".into(), content_type(path).into())],
+                body: ResponseBody::File(file, meta.size),
+            }
+        };
+        Ok(response
+            .header("Last-Modified", &http_date(meta.modified_at))
+            .header("ETag", &etag(&meta)))
+    }
+
+    fn put(&mut self, path: &VfsPath, body: &mut Body<'_>) -> Result<Response> {
+        let existed = match node_kind(&self.vfs, path) {
+            Ok(NodeKind::Dir) => return Ok(Response::text(405, "is a collection")),
+            Ok(NodeKind::File) => {
+                // drepturile se verifica pe fisierul vechi, ca la o suprascriere pe loc
+                drop(self.vfs.open_rw(path)?);
+                true
+            }
+            Err(VfsError::NotFound(_)) => false,
+            Err(e) => return Err(e),
+        };
+        // corpul ajunge intai intr-un fisier alaturat si inlocuieste tinta abia cand
+        // a sosit tot; un client disparut la jumatate nu strica fisierul vechi
+        let temp = self.unused_sibling(path, "dav-put")?;
+        let mut file = self.vfs.create(&temp).map_err(missing_parent)?;
+        let copied = copy_body(body, &mut file);
+        drop(file);
+        if let Err(e) = copied {
+            self.vfs.remove_file(&temp)?;
+            return Err(e);
+        }
+        let aside = if existed {
+            let mode = self.vfs.metadata(path)?.mode;
+            self.vfs.set_permissions(&temp, mode)?;
+            Some(self.set_aside(path)?)
+        } else {
+            None
+        };
+        if let Err(e) = self.vfs.rename(&temp, path) {
+            if let Some(aside) = &aside {
+                self.vfs.rename(aside, path)?;
+            }
+            self.vfs.remove_file(&temp)?;
+            return Err(e);
+        }
+        if let Some(aside) = aside {
+            self.vfs.remove_file(&aside)?;
+        }
+        Ok(Response::empty(if existed { 204 } else { 201 }))
+    }
+
+    fn mkcol(&mut self, path: &VfsPath) -> Result<Response> {
+        if self.vfs.exists(path) {
+            return Ok(Response::text(405, "already exists"));
+        }
+        self.vfs.create_dir(path).map_err(missing_parent)?;
+        Ok(Response::empty(201))
+    }
+
+    fn move_to(&mut self, request: &Request, path: &VfsPath) -> Result<Response> {
+        let Some(destination) = request.header("destination") else {
+            return Ok(Response::text(400, "missing Destination header"));
+        };
+        let destination = request_path(destination)?;
+        if path.is_root() || destination.is_root() {
+            return Ok(Response::text(403, "cannot move the root"));
+        }
+        if destination == *path {
+            return Ok(Response::text(403, "source and destination are the same"));
+        }
+        node_kind(&self.vfs, path)?;
+
+        let overwrite = request.header("overwrite").unwrap_or("T") != "F";
+        let existed = self.vfs.exists(&destination);
+        if existed && !overwrite {
+            return Ok(Response::text(412, "destination exists"));
+        }
+        // destinatia veche se da deoparte si se sterge abia dupa ce mutarea a
+        // reusit; altfel (de ex. destinatia e in subarborele sursei) revine la loc
+        let aside = if existed {
+            Some(self.set_aside(&destination)?)
+        } else {
+            None
+        };
+        if let Err(e) = self.vfs.rename(path, &destination) {
+            if let Some(aside) = &aside {
+                self.vfs.rename(aside, &destination)?;
+            }
+            return Err(missing_parent(e));
+        }
+        if let Some(aside) = aside {
+            self.remove(&aside)?;
+        }
+        Ok(Response::empty(if existed { 204 } else { 201 }))
+    }
+
+    // muta `path` langa el, sub un nume nefolosit
+    fn set_aside(&mut self, path: &VfsPath) -> Result<VfsPath> {
+        let aside = self.unused_sibling(path, "dav-move")?;
+        self.vfs.rename(path, &aside)?;
+        Ok(aside)
+    }
+
+    // `.nume.tag-N` in acelasi director cu `path`, inca nefolosit
+    fn unused_sibling(&self, path: &VfsPath, tag: &str) -> Result<VfsPath> {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(VfsError::InvalidPath(path.to_string()));
+        };
+        let mut n = 0;
+        loop {
+            let candidate = parent.join(format!(".{name}.{tag}-{n}"))?;
+            if !self.vfs.exists(&candidate) {
+                return Ok(candidate);
+            }
+            n += 1;
+        }
+    }
+
+    fn delete(&mut self, path: &VfsPath) -> Result<Response> {
+        if path.is_root() {
+            return Ok(Response::text(403, "cannot delete the root"));
+        }
+        self.remove(path)?;
+        Ok(Response::empty(204))
+    }
+
+    fn remove(&mut self, path: &VfsPath) -> Result<()> {
+        match node_kind(&self.vfs, path)? {
+            NodeKind::Dir => remove_tree(&mut self.vfs, path),
+            NodeKind::File => self.vfs.remove_file(path),
+        }
+    }
+}
+
+fn copy_body(body: &mut Body<'_>, file: &mut VfsFile) -> Result<()> {
+    let mut buf = vec![0u8; COPY_BUF];
+    loop {
+        let n = body.read(&mut buf)?;
+        if n == 0 {
+            return Ok(());
+        }
+        file.write_all(&buf[..n])?;
+    }
+}
+
+const ALLOW: &str = "OPTIONS, PROPFIND, GET, HEAD, PUT, MKCOL, MOVE, DELETE";
+
+// ---------- http ----------
+
+struct Request {
+    method: String,
+    target: String,
+    version: String,
+    /// nume lowercase
+    headers: Vec<(String, String)>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn keep_alive(&self) -> bool {
+        let connection = self.header("connection").map(str::to_ascii_lowercase);
+        match connection.as_deref() {
+            Some("close") => false,
+            Some("keep-alive") => true,
+            _ => self.version == "HTTP/1.1",
+        }
+    }
+}
+
+fn bad_request(msg: impl Into<String>) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.into())
+}
+
+// o linie terminata cu CRLF, fara terminator; `None` la EOF
+fn read_line(reader: &mut impl BufRead) -> std::io::Result<Option<String>> {
+    let mut line = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_LINE)
+        .read_until(b'\n', &mut line)?;
+    if line.is_empty() {
+        return Ok(None);
+    }
+    if line.pop() != Some(b'\n') {
+        return Err(bad_request("line too long or truncated"));
+    }
+    if line.last() == Some(&b'\r') {
+        line.pop();
+    }
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|_| bad_request("non utf-8 header"))
+}
+
+// `None` = clientul a inchis conexiunea intre cereri
+fn read_request(reader: &mut impl BufRead) -> std::io::Result<Option<Request>> {
+    let line = loop {
+        match read_line(reader) {
+            Ok(Some(line)) if line.is_empty() => continue,
+            Ok(Some(line)) => break line,
+            Ok(None) => return Ok(None),
+            // timeout-ul de inactivitate inchide conexiunea in liniste
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target), Some(version), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(bad_request(format!("malformed request line: {line}")));
+    };
+
+    let mut headers = Vec::new();
+    loop {
+        let line = read_line(reader)?.ok_or_else(|| bad_request("truncated headers"))?;
+        if line.is_empty() {
+            break;
+        }
+        if headers.len() == MAX_HEADERS {
+            return Err(bad_request("too many headers"));
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| bad_request(format!("malformed header: {line}")))?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+    Ok(Some(Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        version: version.to_string(),
+        headers,
+    }))
+}
+
+/// corpul unei cereri, delimitat de `Content-Length` sau `chunked`.
+struct Body<'a> {
+    reader: &'a mut dyn BufRead,
+    framing: Framing,
+}
+
+enum Framing {
+    Length(u64),
+    /// bytes ramasi din chunk-ul curent
+    Chunked(u64),
+    Done,
+}
+
+impl<'a> Body<'a> {
+    fn new(reader: &'a mut dyn BufRead, request: &Request) -> std::io::Result<Self> {
+        let chunked = request
+            .header("transfer-encoding")
+            .is_some_and(|te| te.to_ascii_lowercase().ends_with("chunked"));
+        let framing = if chunked {
+            Framing::Chunked(0)
+        } else {
+            match request.header("content-length") {
+                Some(len) => Framing::Length(
+                    len.parse()
+                        .map_err(|_| bad_request("invalid Content-Length"))?,
+                ),
+                None => Framing::Done,
+            }
+        };
+        Ok(Self { reader, framing })
+    }
+
+    // citeste antetul urmatorului chunk; la chunk-ul 0 consuma si trailer-ul
+    fn next_chunk(&mut self) -> std::io::Result<()> {
+        let line = read_line(&mut self.reader)?.ok_or_else(|| bad_request("truncated chunk"))?;
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = u64::from_str_radix(size, 16).map_err(|_| bad_request("invalid chunk size"))?;
+        if size > 0 {
+            self.framing = Framing::Chunked(size);
+            return Ok(());
+        }
+        while read_line(&mut self.reader)?.is_some_and(|l| !l.is_empty()) {}
+        self.framing = Framing::Done;
+        Ok(())
+    }
+
+    fn drain(&mut self) -> std::io::Result<()> {
+        let mut buf = vec![0u8; 64 * 1024];
+        while self.read(&mut buf)? > 0 {}
+        Ok(())
+    }
+}
+
+impl Read for Body<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if let Framing::Chunked(0) = self.framing {
+            self.next_chunk()?;
+        }
+        let remaining = match self.framing {
+            Framing::Length(n) | Framing::Chunked(n) => n,
+            Framing::Done => return Ok(0),
+        };
+        if remaining == 0 {
+            self.framing = Framing::Done;
+            return Ok(0);
+        }
+        let want = buf.len().min(remaining as usize);
+        let n = self.reader.read(&mut buf[..want])?;
+        if n == 0 {
+            return Err(bad_request("request body truncated"));
+        }
+        let left = remaining - n as u64;
+        match self.framing {
+            Framing::Length(_) => self.framing = Framing::Length(left),
+            Framing::Chunked(_) => {
+                self.framing = Framing::Chunked(left);
+                if left == 0 {
+                    // CRLF dupa datele chunk-ului
+                    read_line(&mut self.reader)?;
+                }
+            }
+            Framing::Done => {}
+        }
+        Ok(n)
+    }
+}
+
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: ResponseBody,
+}
+
+enum ResponseBody {
+    Bytes(Vec<u8>),
+    File(VfsFile, u64),
+}
+
+impl Response {
+    fn empty(status: u16) -> Self {
+        Self::bytes(status, "", Vec::new())
+    }
+
+    fn text(status: u16, msg: &str) -> Self {
+        Self::bytes(
+            status,
+            "text/plain; charset=utf-8",
+            format!("{msg}\n").into_bytes(),
+        )
+    }
+
+    fn bytes(status: u16, content_type: &str, body: Vec<u8>) -> Self {
+        let headers = if content_type.is_empty() {
+            vec![]
+        } else {
+            vec![("Content-Type".into(), content_type.into())]
+        };
+        Self {
+            status,
+            headers,
+            body: ResponseBody::Bytes(body),
+        }
+    }
+
+    fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    fn send(self, out: &mut impl Write, head: bool, keep_alive: bool) -> std::io::Result<()> {
+        let len = match &self.body {
+            ResponseBody::Bytes(bytes) => bytes.len() as u64,
+            ResponseBody::File(_, len) => *len,
+        };
+        let mut head_text = format!("HTTP/1.1 {} {}\r\n", self.status, reason(self.status));
+        for (name, value) in &self.headers {
+            head_text.push_str(&format!("{name}: {value}\r\n"));
+        }
+        head_text.push_str(&format!("Content-Length: {len}\r\n"));
+        let connection = if keep_alive { "keep-alive" } else { "close" };
+        head_text.push_str(&format!("Connection: {connection}\r\n\r\n"));
+        out.write_all(head_text.as_bytes())?;
+        if head {
+            return out.flush();
+        }
+        match self.body {
+            ResponseBody::Bytes(bytes) => out.write_all(&bytes)?,
+            ResponseBody::File(mut file, len) => {
+                // trimitem exact cat am anuntat in Content-Length
+                let mut buf = vec![0u8; COPY_BUF];
+                let mut left = len;
+                while left > 0 {
+                    let want = buf.len().min(left as usize);
+                    let n = file.read(&mut buf[..want])?;
+                    if n == 0 {
+                        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof));
+                    }
+                    out.write_all(&buf[..n])?;
+                    left -= n as u64;
+                }
+            }
+        }
+        out.flush()
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        201 => "Created",
+        204 => "No Content",
+        207 => "Multi-Status",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        412 => "Precondition Failed",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        507 => "Insufficient Storage",
+        _ => "Internal Server Error",
+    }
+}
+
+fn error_response(err: &VfsError) -> Response {
+    let status = match err {
+        VfsError::NotFound(_) => 404,
+        VfsError::AlreadyExists(_) => 405,
+        VfsError::NotAFile(_) | VfsError::NotADir(_) => 409,
+        VfsError::PermissionDenied(_) | VfsError::ReadOnly(_) => 403,
+        VfsError::InvalidPath(_) => 400,
+        VfsError::NoSpace(_) => 507,
+        // MOVE spre alta montare: RFC 4918 cere 502 cand destinatia e "pe alt server"
+        VfsError::CrossesMount(_) => 502,
+        _ => 500,
+    };
+    Response::text(status, &err.to_string())
+}
+
+// la creare, un parinte lipsa inseamna 409 (RFC 4918), nu 404
+fn missing_parent(err: VfsError) -> VfsError {
+    match err {
+        VfsError::NotFound(p) => VfsError::NotADir(p),
+        other => other,
+    }
+}
+
+// ---------- path-uri si xml ----------
+
+// `http://host/a%20b?x` -> `/a b`
+fn request_path(target: &str) -> Result<VfsPath> {
+    let path = match target.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |i| &rest[i..]),
+        None => target,
+    };
+    let path = path.split(['?', '#']).next().unwrap_or("/");
+    let decoded = percent_decode(path)
+        .ok_or_else(|| VfsError::InvalidPath(format!("bad percent-encoding: {path}")))?;
+    // `..` se rezolva aici (RFC 3986); `/docs/..` ar ocoli verificarile pt root
+    let mut out = VfsPath::root();
+    for part in VfsPath::new(decoded)?.components() {
+        out = match part {
+            ".." => out.parent().unwrap_or_else(VfsPath::root),
+            name => out.join(name)?,
+        };
+    }
+    Ok(out)
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+fn percent_encode(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for b in s.bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{b:02X}"));
+        }
+    }
+    out
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn href(path: &VfsPath, kind: NodeKind) -> String {
+    let mut href = percent_encode(path.as_str());
+    if kind == NodeKind::Dir && !path.is_root() {
+        href.push('/');
+    }
+    href
+}
+
+fn prop_response(xml: &mut String, path: &VfsPath, kind: NodeKind, meta: &Metadata) {
+    let name = path.file_name().unwrap_or("");
+    xml.push_str(&format!(
+        "<D:response><D:href>{}</D:href><D:propstat><D:prop>",
+        xml_escape(&href(path, kind))
+    ));
+    xml.push_str(&format!(
+        "<D:displayname>{}</D:displayname>",
+        xml_escape(name)
+    ));
+    match kind {
+        NodeKind::Dir => xml.push_str("<D:resourcetype><D:collection/></D:resourcetype>"),
+        NodeKind::File => xml.push_str(&format!(
+            "<D:resourcetype/><D:getcontentlength>{}</D:getcontentlength>\
+             <D:getcontenttype>{}</D:getcontenttype>",
+            meta.size,
+            content_type(path)
+        )),
+    }
+    xml.push_str(&format!(
+        "<D:getlastmodified>{}</D:getlastmodified>\
+         <D:creationdate>{}</D:creationdate>\
+         <D:getetag>{}</D:getetag>",
+        http_date(meta.modified_at),
+        iso_date(meta.created_at),
+        xml_escape(&etag(meta))
+    ));
+    xml.push_str("</D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat></D:response>\n");
+}
+
+fn dir_listing(vfs: &Vfs, path: &VfsPath) -> Result<String> {
+    let title = xml_escape(path.as_str());
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title></head>\n\
+         <body><h1>{title}</h1><ul>\n"
+    );
+    if let Some(parent) = path.parent() {
+        html.push_str(&format!(
+            "<li><a href=\"{}\">..</a></li>\n",
+            href(&parent, NodeKind::Dir)
+        ));
+    }
+    for entry in sorted_dir(vfs, path)? {
+        let child = path.join(&entry.name)?;
+        let suffix = if entry.kind == NodeKind::Dir { "/" } else { "" };
+        html.push_str(&format!(
+            "<li><a href=\"{}\">{}{suffix}</a></li>\n",
+            xml_escape(&href(&child, entry.kind)),
+            xml_escape(&entry.name)
+        ));
+    }
+    html.push_str("</ul></body></html>\n");
+    Ok(html)
+}
+
+fn content_type(path: &VfsPath) -> &'static str {
+    let ext = path
+        .file_name()
+        .and_then(|n| n.rsplit_once('.'))
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("txt" | "md" | "rs" | "toml") => "text/plain; charset=utf-8",
+        Some("html" | "htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("xml") => "application/xml",
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+fn etag(meta: &Metadata) -> String {
+    format!("\"{:x}-{:x}\"", meta.size, meta.modified_at.0)
+}
+
+// RFC 1123: `Sun, 06 Nov 1994 08:49:37 GMT`
+fn http_date(t: Timestamp) -> String {
+    const DAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let t = t.utc();
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        DAYS[t.weekday as usize],
+        t.day,
+        MONTHS[t.month as usize - 1],
+        t.year,
+        t.hour,
+        t.minute,
+        t.second
+    )
+}
+
+// RFC 3339: `1994-11-06T08:49:37Z`
+fn iso_date(t: Timestamp) -> String {
+    let t = t.utc();
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        t.year, t.month, t.day, t.hour, t.minute, t.second
+    )
+}
+
+// ---------- vfs ----------
+
+fn node_kind(vfs: &Vfs, path: &VfsPath) -> Result<NodeKind> {
+    vfs.metadata(path)?;
+    Ok(if vfs.read_dir(path).is_ok() {
+        NodeKind::Dir
+    } else {
+        NodeKind::File
+    })
+}
+
+fn sorted_dir(vfs: &Vfs, path: &VfsPath) -> Result<Vec<DirEntry>> {
+    let mut entries: Vec<DirEntry> = vfs.read_dir(path)?.collect::<Result<_>>()?;
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+fn remove_tree(vfs: &mut Vfs, path: &VfsPath) -> Result<()> {
+    for entry in sorted_dir(vfs, path)? {
+        let child = path.join(&entry.name)?;
+        match entry.kind {
+            NodeKind::Dir => remove_tree(vfs, &child)?,
+            NodeKind::File => vfs.remove_file(&child)?,
+        }
+    }
+    vfs.remove_dir(path)
+}
@@ -1,10 +1,14 @@
 use std::fs::OpenOptions;
-use std::io::{Read, Write};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::rc::Rc;
 use std::thread::sleep;
-use std::time::Duration;
-use virtual_file_system::Vfs;
+use std::time::{Duration, SystemTime};
 use virtual_file_system::no_sql::*;
 use virtual_file_system::structs::*;
+use virtual_file_system::{
+    Chunking, CompressionPolicy, Credentials, EncryptionKey, ManualClock, MountOptions, Retention,
+    SourceDateEpochClock, SyncOptions, TrashPolicy, Until, Vfs, VfsPath,
+};
 
 #[test]
 fn record_roundtrip_inode_alloc() -> Result<()> {
@@ -13,6 +17,7 @@ fn record_roundtrip_inode_alloc() -> Result<()> {
 
     let mut f = OpenOptions::new()
         .create(true)
+        .truncate(true)
         .read(true)
         .write(true)
         .open(path)?;
@@ -29,15 +34,20 @@ fn record_roundtrip_inode_alloc() -> Result<()> {
             size: 0,
             created_at: now,
             modified_at: now,
+            accessed_at: now,
+            changed_at: now,
+            mode: DEFAULT_DIR_MODE,
+            uid: 0,
+            gid: 0,
         },
         extents: vec![],
+        xattrs: XattrMap::new(),
     };
 
     write_record(&mut f, &Record::InodeAlloc(snap))?;
 
-    let off: u32 = 24;
-    let (got, _) = read_next_record(&mut f, off as u64)?
-        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::Other, "no record found"))?;
+    let (got, _) = read_next_record(&mut f, HEADER_LEN)?
+        .ok_or_else(|| std::io::Error::other("no record found"))?;
 
     match &got.record {
         Record::InodeAlloc(s) => assert_eq!(s.id.0, 1),
@@ -118,7 +128,7 @@ fn example_like_assignment() -> Result<()> {
     for entry in vfs2.read_dir("rs")? {
         let entry = entry?;
         out.clear();
-        let mut file = vfs2.open_file(&format!("rs/{}", entry.name))?;
+        let mut file = vfs2.open_file(format!("rs/{}", entry.name))?;
         file.read_to_string(&mut out)?;
         total.push_str(&out);
     }
@@ -389,3 +399,4439 @@ fn test_cerinta() -> Result<()> {
     println!();
     Ok(())
 }
+
+#[test]
+fn absolute_and_relative_paths_agree() -> Result<()> {
+    let path = "target/paths_abs_rel.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("/rs")?;
+    v.create_dir("rs/sub")?;
+    v.create("/rs/sub/a.txt")?.write_all(b"hello")?;
+
+    assert!(v.exists("rs/sub/a.txt"));
+    assert!(v.exists("/rs/sub/a.txt"));
+    assert!(v.exists("./rs//sub/./a.txt"));
+    assert!(v.exists(std::path::Path::new("/rs/sub/a.txt")));
+    assert_eq!(v.metadata("/rs/sub/a.txt")?, v.metadata("rs/sub/a.txt")?);
+
+    let names: Vec<String> = v.read_dir("/")?.map(|e| e.unwrap().name).collect();
+    assert_eq!(names, vec!["rs".to_string()]);
+    assert!(v.metadata("")?.size == 0);
+
+    // root-ul exista deja si nu poate fi sters
+    assert!(matches!(v.create_dir("/"), Err(VfsError::AlreadyExists(_))));
+    assert!(v.remove_dir("/").is_err());
+    Ok(())
+}
+
+#[test]
+fn parent_dir_components_resolve_against_real_parent() -> Result<()> {
+    let path = "target/paths_dotdot.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("a")?;
+    v.create_dir("a/b")?;
+    v.create("a/x.txt")?.write_all(b"x")?;
+
+    assert!(v.exists("a/b/../x.txt"));
+    assert!(v.exists("/../../a/x.txt"));
+    assert!(!v.exists("a/missing/../x.txt"));
+
+    // `..` printr-un fisier nu e valid
+    assert!(matches!(
+        v.metadata("a/x.txt/.."),
+        Err(VfsError::NotADir(_))
+    ));
+
+    // `..` ca ultima componenta nu poate fi creat
+    assert!(matches!(
+        v.create_dir("a/.."),
+        Err(VfsError::InvalidPath(_))
+    ));
+
+    // dupa rename, `..` urmeaza parintele nou
+    v.create_dir("c")?;
+    v.rename("a/b", "c/b")?;
+    v.create("c/b/../y.txt")?;
+    assert!(v.exists("/c/y.txt"));
+
+    // nu putem muta un director in el insusi
+    assert!(v.rename("c", "c/b/c").is_err());
+    Ok(())
+}
+
+#[test]
+fn current_dir_is_per_handle() -> Result<()> {
+    let path = "target/paths_cwd.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("rs")?;
+    v.create_dir("rs/src")?;
+
+    let mut other = v.clone();
+    other.set_current_dir("/rs/src")?;
+    assert_eq!(other.current_dir()?, VfsPath::new("/rs/src")?);
+    assert_eq!(v.current_dir()?, VfsPath::root());
+
+    other.create("main.rs")?.write_all(b"fn main() {}")?;
+    assert!(v.exists("rs/src/main.rs"));
+    assert!(other.exists("../src/main.rs"));
+    assert!(!v.exists("main.rs"));
+
+    other.set_current_dir("..")?;
+    assert_eq!(other.current_dir()?.as_str(), "/rs");
+    assert!(other.set_current_dir("src/main.rs").is_err());
+    Ok(())
+}
+
+#[test]
+fn vfs_path_normalization() -> Result<()> {
+    assert_eq!(VfsPath::new("/a//b/./c/")?.as_str(), "/a/b/c");
+    assert_eq!(VfsPath::new("a/./b")?.as_str(), "a/b");
+    assert_eq!(VfsPath::new("/../a")?.as_str(), "/a");
+    assert_eq!(VfsPath::new("../a/..")?.as_str(), "../a/..");
+    assert_eq!(VfsPath::new(".")?.as_str(), "");
+    assert!(VfsPath::new("/")?.is_root());
+
+    let p = VfsPath::new("/a/b")?;
+    assert_eq!(p.file_name(), Some("b"));
+    assert_eq!(p.parent(), Some(VfsPath::new("/a")?));
+    assert_eq!(p.join("c/d")?.as_str(), "/a/b/c/d");
+    assert_eq!(p.join("/x")?.as_str(), "/x");
+    Ok(())
+}
+
+#[test]
+fn xattrs_roundtrip_and_persist() -> Result<()> {
+    let path = "target/xattrs.vfs";
+    let _ = std::fs::remove_file(path);
+
+    {
+        let mut v = Vfs::mount(path)?;
+        v.create_dir("out")?;
+        v.create("out/app.bin")?.write_all(b"\x7fELF")?;
+
+        v.set_xattr("out/app.bin", "user.mime", b"application/x-executable")?;
+        v.set_xattr("out/app.bin", "user.origin", b"https://ci.example/42")?;
+        v.set_xattr("out", "user.build", b"42")?;
+        v.set_xattr("out/app.bin", "user.origin", b"https://ci.example/43")?;
+        v.remove_xattr("out/app.bin", "user.mime")?;
+
+        assert!(matches!(
+            v.remove_xattr("out/app.bin", "user.mime"),
+            Err(VfsError::NotFound(_))
+        ));
+    }
+
+    let v2 = Vfs::mount(path)?;
+    assert_eq!(
+        v2.list_xattrs("out/app.bin")?,
+        vec!["user.origin".to_string()]
+    );
+    assert_eq!(
+        v2.get_xattr("/out/app.bin", "user.origin")?,
+        Some(b"https://ci.example/43".to_vec())
+    );
+    assert_eq!(v2.get_xattr("out/app.bin", "user.mime")?, None);
+    assert_eq!(v2.get_xattr("out", "user.build")?, Some(b"42".to_vec()));
+    Ok(())
+}
+
+#[test]
+fn xattrs_survive_checkpoint_and_rename() -> Result<()> {
+    let path = "target/xattrs_cp.vfs";
+    let _ = std::fs::remove_file(path);
+
+    {
+        let mut v = Vfs::mount(path)?;
+        v.create_dir("a")?;
+        v.create("a/x")?;
+        v.create("a/y")?;
+        v.set_xattr("a/x", "user.build", b"7")?;
+        v.set_xattr("a/y", "user.build", b"8")?;
+        v.checkpoint()?;
+        v.rename("a/x", "a/z")?;
+    }
+
+    let v2 = Vfs::mount(path)?;
+    assert_eq!(v2.get_xattr("a/z", "user.build")?, Some(b"7".to_vec()));
+    assert_eq!(
+        v2.find_by_xattr("user.build", b"7")?,
+        vec![VfsPath::new("/a/z")?]
+    );
+    assert!(v2.find_by_xattr("user.build", b"9")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn xattrs_are_size_limited() -> Result<()> {
+    let path = "target/xattrs_limit.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create("f")?;
+
+    let big = vec![0u8; XATTR_INODE_MAX / 2];
+    v.set_xattr("f", "user.a", &big)?;
+    assert!(matches!(
+        v.set_xattr("f", "user.b", &big),
+        Err(VfsError::NoSpace(_))
+    ));
+    // inlocuirea aceluiasi atribut nu se aduna la limita
+    v.set_xattr("f", "user.a", &big)?;
+    assert!(v.set_xattr("f", "", b"x").is_err());
+    Ok(())
+}
+
+#[test]
+fn permissions_and_ownership_persist() -> Result<()> {
+    let path = "target/perms.vfs";
+    let _ = std::fs::remove_file(path);
+
+    {
+        let mut v = Vfs::mount(path)?;
+        v.create_dir("bin")?;
+        v.create("bin/tool")?.write_all(b"#!/bin/sh")?;
+
+        let m = v.metadata("bin/tool")?;
+        assert_eq!(m.mode, DEFAULT_FILE_MODE);
+        assert_eq!((m.uid, m.gid), (0, 0));
+        assert!(!m.is_executable());
+        assert_eq!(v.metadata("bin")?.mode, DEFAULT_DIR_MODE);
+
+        v.set_permissions("bin/tool", 0o755)?;
+        v.chown("bin/tool", Some(1000), None)?;
+        v.checkpoint()?;
+        v.chown("bin/tool", None, Some(100))?;
+        assert!(v.set_permissions("bin/tool", 0o100755).is_err());
+    }
+
+    let v2 = Vfs::mount(path)?;
+    let m = v2.metadata("bin/tool")?;
+    assert_eq!(m.mode, 0o755);
+    assert!(m.is_executable());
+    assert_eq!((m.uid, m.gid), (1000, 100));
+    Ok(())
+}
+
+#[test]
+fn mount_as_user_enforces_access() -> Result<()> {
+    let path = "target/perms_enforce.vfs";
+    let _ = std::fs::remove_file(path);
+
+    {
+        let mut v = Vfs::mount(path)?;
+        v.create_dir("home")?;
+        v.create_dir("home/alice")?;
+        v.chown("home/alice", Some(1000), Some(1000))?;
+        v.create("secret")?.write_all(b"root only")?;
+        v.set_permissions("secret", 0o600)?;
+        v.create("shared")?.write_all(b"hi")?;
+    }
+
+    let alice = MountOptions {
+        user: Some(Credentials::new(1000, 1000)),
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(path, alice)?;
+
+    assert!(matches!(
+        v.open("secret"),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    let mut s = String::new();
+    v.open("shared")?.read_to_string(&mut s)?;
+    assert_eq!(s, "hi");
+
+    // root-ul e 0o755 si al lui root: nu putem crea sau sterge acolo
+    assert!(matches!(
+        v.create("mine.txt"),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    assert!(v.remove_file("shared").is_err());
+    assert!(v.set_permissions("shared", 0o777).is_err());
+    assert!(v.chown("shared", Some(1000), None).is_err());
+
+    // in home-ul propriu merge, iar fisierele noi sunt ale ei
+    v.create("home/alice/notes.txt")?.write_all(b"mine")?;
+    let m = v.metadata("home/alice/notes.txt")?;
+    assert_eq!((m.uid, m.gid), (1000, 1000));
+    v.set_permissions("home/alice", 0o700)?;
+
+    // un handle read-only nu poate trunchia
+    assert!(v.open("home/alice/notes.txt")?.set_len(0).is_err());
+
+    // alt user nu poate intra in home-ul ei
+    let bob = MountOptions {
+        user: Some(Credentials::new(1001, 1001)),
+        ..Default::default()
+    };
+    let v2 = Vfs::mount_with(path, bob)?;
+    assert!(matches!(
+        v2.metadata("home/alice/notes.txt"),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    assert!(v2.read_dir("home/alice").is_err());
+    Ok(())
+}
+
+#[test]
+fn set_times_persists_through_checkpoint() -> Result<()> {
+    let path = "target/times_set.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let created = Timestamp(1_000_000_000);
+    let modified = Timestamp(2_000_000_000);
+    let accessed = Timestamp(3_000_000_000);
+    {
+        let mut v = Vfs::mount(path)?;
+        v.create("a.txt")?.write_all(b"imported")?;
+        v.create("b.txt")?;
+        v.set_times(
+            "a.txt",
+            FileTimes {
+                created_at: Some(created),
+                modified_at: Some(modified),
+                accessed_at: Some(accessed),
+            },
+        )?;
+        v.checkpoint()?;
+        v.set_times(
+            "b.txt",
+            FileTimes {
+                modified_at: Some(modified),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    let v2 = Vfs::mount_with(
+        path,
+        MountOptions {
+            atime: AtimePolicy::NoAtime,
+            ..Default::default()
+        },
+    )?;
+    let m = v2.metadata("a.txt")?;
+    assert_eq!(m.created_at, created);
+    assert_eq!(m.modified_at, modified);
+    assert_eq!(m.accessed_at, accessed);
+    assert!(m.changed_at > modified);
+
+    let b = v2.metadata("b.txt")?;
+    assert_eq!(b.modified_at, modified);
+    assert!(b.created_at > modified);
+    Ok(())
+}
+
+#[test]
+fn rename_changes_ctime_not_mtime() -> Result<()> {
+    let path = "target/times_rename.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("d")?;
+    v.create("d/a.txt")?.write_all(b"x")?;
+    let old = Timestamp(1_000);
+    v.set_times(
+        "d/a.txt",
+        FileTimes {
+            modified_at: Some(old),
+            ..Default::default()
+        },
+    )?;
+    v.set_times(
+        "d",
+        FileTimes {
+            modified_at: Some(old),
+            ..Default::default()
+        },
+    )?;
+    let before = v.metadata("d/a.txt")?;
+
+    v.rename("d/a.txt", "d/b.txt")?;
+
+    let after = v.metadata("d/b.txt")?;
+    assert_eq!(after.modified_at, old);
+    assert!(after.changed_at > before.changed_at);
+    // directorul parinte are intrari noi -> mtime actualizat
+    assert!(v.metadata("d")?.modified_at > old);
+
+    // metadata-only: permisiunile nu ating mtime
+    v.set_permissions("d/b.txt", 0o600)?;
+    assert_eq!(v.metadata("d/b.txt")?.modified_at, old);
+    Ok(())
+}
+
+#[test]
+fn atime_follows_mount_policy() -> Result<()> {
+    let path = "target/times_atime.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create("a.txt")?.write_all(b"hello")?;
+    v.set_times(
+        "a.txt",
+        FileTimes {
+            modified_at: Some(Timestamp(2_000)),
+            accessed_at: Some(Timestamp(1_000)),
+            ..Default::default()
+        },
+    )?;
+    drop(v);
+
+    let read = |v: &Vfs| -> Result<()> {
+        let mut s = String::new();
+        v.open("a.txt")?.read_to_string(&mut s)?;
+        Ok(())
+    };
+
+    // noatime: nimic nu se schimba
+    let v = Vfs::mount_with(
+        path,
+        MountOptions {
+            atime: AtimePolicy::NoAtime,
+            ..Default::default()
+        },
+    )?;
+    read(&v)?;
+    assert_eq!(v.metadata("a.txt")?.accessed_at, Timestamp(1_000));
+    drop(v);
+
+    // relatime: atime < mtime -> se actualizeaza o data, apoi ramane
+    let v = Vfs::mount(path)?;
+    read(&v)?;
+    let t1 = v.metadata("a.txt")?.accessed_at;
+    assert!(t1 > Timestamp(2_000));
+    read(&v)?;
+    assert_eq!(v.metadata("a.txt")?.accessed_at, t1);
+    drop(v);
+
+    // strict: fiecare citire il muta
+    let v = Vfs::mount_with(
+        path,
+        MountOptions {
+            atime: AtimePolicy::Strict,
+            ..Default::default()
+        },
+    )?;
+    sleep(Duration::from_millis(2));
+    read(&v)?;
+    let t2 = v.metadata("a.txt")?.accessed_at;
+    assert!(t2 > t1);
+
+    // si persista
+    drop(v);
+    assert_eq!(Vfs::mount(path)?.metadata("a.txt")?.accessed_at, t2);
+    Ok(())
+}
+
+#[test]
+fn manual_clock_drives_all_timestamps() -> Result<()> {
+    let path = "target/clock_manual.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let clock = Rc::new(ManualClock::new(Timestamp(1_000)));
+    let opts = MountOptions {
+        clock: clock.clone(),
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(path, opts)?;
+    assert_eq!(v.metadata("/")?.created_at, Timestamp(1_000));
+
+    clock.set(Timestamp(5_000));
+    v.create_dir("rs")?;
+    clock.advance(Duration::from_nanos(10));
+    v.create("rs/a.txt")?.write_all(b"hi")?;
+
+    let m = v.metadata("rs/a.txt")?;
+    assert_eq!(m.created_at, Timestamp(5_010));
+    assert_eq!(m.modified_at, Timestamp(5_010));
+    assert_eq!(v.metadata("rs")?.created_at, Timestamp(5_000));
+    assert_eq!(v.metadata("rs")?.modified_at, Timestamp(5_010));
+
+    clock.set(Timestamp(9_000));
+    v.rename("rs/a.txt", "rs/b.txt")?;
+    let m = v.metadata("rs/b.txt")?;
+    assert_eq!(m.changed_at, Timestamp(9_000));
+    assert_eq!(m.modified_at, Timestamp(5_010));
+    Ok(())
+}
+
+#[test]
+fn same_ops_with_same_clock_give_identical_images() -> Result<()> {
+    let build = |path: &str| -> Result<Vec<u8>> {
+        let _ = std::fs::remove_file(path);
+        let opts = MountOptions {
+            clock: Rc::new(SourceDateEpochClock::new(Timestamp(
+                1_700_000_000_000_000_000,
+            ))),
+            ..Default::default()
+        };
+        let mut v = Vfs::mount_with(path, opts)?;
+        v.create_dir("pkg")?;
+        v.create("pkg/lib.rs")?.write_all(b"pub fn f() {}")?;
+        v.set_xattr("pkg/lib.rs", "user.build", b"1")?;
+        v.checkpoint()?;
+        drop(v);
+        Ok(std::fs::read(path)?)
+    };
+
+    let a = build("target/clock_repro_a.vfs")?;
+    let b = build("target/clock_repro_b.vfs")?;
+    assert_eq!(a, b);
+
+    let v = Vfs::mount("target/clock_repro_a.vfs")?;
+    assert_eq!(
+        v.metadata("pkg/lib.rs")?.modified_at,
+        Timestamp(1_700_000_000_000_000_000)
+    );
+    Ok(())
+}
+
+fn read_all(v: &Vfs, path: &str) -> Result<String> {
+    let mut s = String::new();
+    v.open(path)?.read_to_string(&mut s)?;
+    Ok(s)
+}
+
+#[test]
+fn snapshot_mount_shows_old_state_and_is_read_only() -> Result<()> {
+    let path = "target/snap_basic.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("rs")?;
+    v.create("rs/a.txt")?.write_all(b"v1")?;
+    let info = v.snapshot("before")?;
+    assert_eq!(info.name, "before");
+
+    v.remove_file("rs/a.txt")?;
+    v.create("rs/a.txt")?.write_all(b"version two")?;
+    v.create("rs/new.txt")?.write_all(b"new")?;
+    v.remove_file("rs/a.txt")?;
+
+    let old = Vfs::mount_snapshot(path, "before")?;
+    assert_eq!(read_all(&old, "rs/a.txt")?, "v1");
+    assert!(!old.exists("rs/new.txt"));
+    assert!(matches!(old.create("rs/x.txt"), Err(VfsError::ReadOnly(_))));
+    assert!(old.open("rs/a.txt")?.write_all(b"x").is_err());
+
+    // starea curenta nu e afectata
+    assert!(!v.exists("rs/a.txt"));
+    assert_eq!(read_all(&v, "rs/new.txt")?, "new");
+    Ok(())
+}
+
+#[test]
+fn snapshots_are_named_listed_and_deleted() -> Result<()> {
+    let path = "target/snap_list.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.snapshot("a")?;
+    v.create("f")?.write_all(b"x")?;
+    v.snapshot("b")?;
+    assert!(matches!(v.snapshot("a"), Err(VfsError::AlreadyExists(_))));
+    assert!(v.snapshot("").is_err());
+
+    let names: Vec<String> = v.list_snapshots().into_iter().map(|s| s.name).collect();
+    assert_eq!(names, ["a", "b"]);
+
+    v.delete_snapshot("a")?;
+    assert!(matches!(v.delete_snapshot("a"), Err(VfsError::NotFound(_))));
+    drop(v);
+
+    let v = Vfs::mount(path)?;
+    let names: Vec<String> = v.list_snapshots().into_iter().map(|s| s.name).collect();
+    assert_eq!(names, ["b"]);
+    assert!(matches!(
+        Vfs::mount_snapshot(path, "a"),
+        Err(VfsError::NotFound(_))
+    ));
+    assert!(Vfs::mount_snapshot(path, "b")?.exists("f"));
+    Ok(())
+}
+
+#[test]
+fn read_only_mount_rejects_writes() -> Result<()> {
+    let path = "target/read_only.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let opts = || MountOptions {
+        read_only: true,
+        ..Default::default()
+    };
+    assert!(Vfs::mount_with(path, opts()).is_err());
+
+    let mut v = Vfs::mount(path)?;
+    v.create("a.txt")?.write_all(b"hello")?;
+    v.create_dir("host")?;
+    drop(v);
+    let before = std::fs::read(path)?;
+
+    let mut v = Vfs::mount_with(path, opts())?;
+    assert_eq!(read_all(&v, "a.txt")?, "hello");
+    assert!(matches!(v.create_dir("d"), Err(VfsError::ReadOnly(_))));
+    assert!(matches!(v.remove_file("a.txt"), Err(VfsError::ReadOnly(_))));
+    assert!(matches!(v.checkpoint(), Err(VfsError::ReadOnly(_))));
+    assert!(matches!(v.compact(), Err(VfsError::ReadOnly(_))));
+    assert_eq!(std::fs::read(path)?, before);
+
+    // nici prin montari: directorul de pe host ramane neatins
+    let host = std::path::Path::new("target/read_only_host");
+    let _ = std::fs::remove_dir_all(host);
+    std::fs::create_dir_all(host.join("d"))?;
+    std::fs::write(host.join("f.txt"), b"host")?;
+    v.attach_host("host", host)?;
+    assert_eq!(read_all(&v, "host/f.txt")?, "host");
+    assert!(matches!(v.create("host/x"), Err(VfsError::ReadOnly(_))));
+    assert!(matches!(
+        v.open_rw("host/f.txt"),
+        Err(VfsError::ReadOnly(_))
+    ));
+    assert!(matches!(v.create_dir("host/e"), Err(VfsError::ReadOnly(_))));
+    assert!(matches!(
+        v.remove_file("host/f.txt"),
+        Err(VfsError::ReadOnly(_))
+    ));
+    assert!(matches!(v.remove_dir("host/d"), Err(VfsError::ReadOnly(_))));
+    assert!(matches!(
+        v.rename("host/f.txt", "host/g.txt"),
+        Err(VfsError::ReadOnly(_))
+    ));
+    let mut names: Vec<_> = std::fs::read_dir(host)?
+        .map(|e| e.map(|e| e.file_name()))
+        .collect::<std::io::Result<_>>()?;
+    names.sort();
+    assert_eq!(names, ["d", "f.txt"]);
+    assert_eq!(std::fs::read(host.join("f.txt"))?, b"host");
+    assert_eq!(std::fs::read(path)?, before);
+    Ok(())
+}
+
+#[test]
+fn compact_reclaims_space_and_keeps_snapshots() -> Result<()> {
+    let path = "target/compact.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("rs")?;
+    v.create("rs/big.bin")?;
+    for i in 0..50 {
+        v.remove_file("rs/big.bin")?;
+        let mut f = v.create("rs/big.bin")?;
+        f.write_all(format!("{i:04}").repeat(1024).as_bytes())?;
+    }
+    v.create("rs/keep.txt")?.write_all(b"keep me")?;
+    v.snapshot("s1")?;
+    v.remove_file("rs/keep.txt")?;
+    v.create("rs/keep.txt")?.write_all(b"KEEP me")?;
+    v.create("rs/tmp.bin")?.write_all(&[7u8; 8192])?;
+    v.remove_file("rs/tmp.bin")?;
+
+    let before = std::fs::metadata(path)?.len();
+    v.compact()?;
+    let after = std::fs::metadata(path)?.len();
+    assert!(after * 4 < before, "{after} vs {before}");
+
+    let check = |v: &Vfs| -> Result<()> {
+        assert_eq!(read_all(v, "rs/big.bin")?, "0049".repeat(1024));
+        assert_eq!(read_all(v, "rs/keep.txt")?, "KEEP me");
+        assert!(!v.exists("rs/tmp.bin"));
+        Ok(())
+    };
+    check(&v)?;
+    v.create("rs/after.txt")?.write_all(b"after")?;
+    drop(v);
+
+    let v = Vfs::mount(path)?;
+    check(&v)?;
+    assert_eq!(read_all(&v, "rs/after.txt")?, "after");
+
+    let old = Vfs::mount_snapshot(path, "s1")?;
+    assert_eq!(read_all(&old, "rs/keep.txt")?, "keep me");
+    assert_eq!(read_all(&old, "rs/big.bin")?, "0049".repeat(1024));
+    assert!(!old.exists("rs/after.txt"));
+    Ok(())
+}
+
+#[test]
+fn removed_file_stays_removed_after_checkpoint() -> Result<()> {
+    let path = "target/remove_checkpoint.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create("gone.txt")?.write_all(b"x")?;
+    v.remove_file("gone.txt")?;
+    v.checkpoint()?;
+    drop(v);
+
+    let v = Vfs::mount(path)?;
+    assert!(!v.exists("gone.txt"));
+    assert!(v.read_dir("/")?.next().is_none());
+    Ok(())
+}
+
+#[test]
+fn grow_after_truncate_reads_zeros() -> Result<()> {
+    let path = "target/truncate_grow.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let v = Vfs::mount(path)?;
+    let mut f = v.create("a.bin")?;
+    f.write_all(b"abcdef")?;
+    f.set_len(2)?;
+    f.set_len(6)?;
+    drop(f);
+
+    let mut out = Vec::new();
+    v.open("a.bin")?.read_to_end(&mut out)?;
+    assert_eq!(out, b"ab\0\0\0\0");
+    Ok(())
+}
+
+#[test]
+fn mount_at_time_and_offset_shows_past_state() -> Result<()> {
+    let path = "target/time_travel.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let clock = Rc::new(ManualClock::new(Timestamp(1_000)));
+    let opts = MountOptions {
+        clock: clock.clone(),
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(path, opts)?;
+    v.create_dir("config")?;
+    clock.set(Timestamp(2_000));
+    v.create("config/app.toml")?.write_all(b"port = 80")?;
+    let offset_v1 = std::fs::metadata(path)?.len();
+
+    // deploy-ul de "ieri"
+    clock.set(Timestamp(3_000));
+    v.remove_file("config/app.toml")?;
+    v.create("config/app.toml")?.write_all(b"port = 8080")?;
+    v.checkpoint()?;
+    drop(v);
+
+    let before = Vfs::mount_at(path, Until::Time(Timestamp(2_999)))?;
+    assert_eq!(read_all(&before, "config/app.toml")?, "port = 80");
+    assert!(matches!(before.create("x"), Err(VfsError::ReadOnly(_))));
+
+    let at_offset = Vfs::mount_at(path, Until::Offset(offset_v1))?;
+    assert_eq!(read_all(&at_offset, "config/app.toml")?, "port = 80");
+
+    let early = Vfs::mount_at(path, Until::Time(Timestamp(1_500)))?;
+    assert!(early.exists("config"));
+    assert!(!early.exists("config/app.toml"));
+
+    let now = Vfs::mount_at(path, Until::Time(Timestamp(10_000)))?;
+    assert_eq!(read_all(&now, "config/app.toml")?, "port = 8080");
+
+    assert!(matches!(
+        Vfs::mount_at(path, Until::Time(Timestamp(999))),
+        Err(VfsError::NotFound(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn restore_to_rewinds_live_image_and_keeps_history() -> Result<()> {
+    let path = "target/restore_to.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let clock = Rc::new(ManualClock::new(Timestamp(1_000)));
+    let opts = || MountOptions {
+        clock: clock.clone(),
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.create("a.txt")?.write_all(b"good")?;
+
+    clock.set(Timestamp(2_000));
+    v.remove_file("a.txt")?;
+    v.create("a.txt")?.write_all(b"bad")?;
+    v.create("b.txt")?.write_all(b"junk")?;
+
+    clock.set(Timestamp(2_500));
+    v.restore_to(Until::Time(Timestamp(1_500)))?;
+    assert_eq!(read_all(&v, "a.txt")?, "good");
+    assert!(!v.exists("b.txt"));
+
+    // se poate scrie dupa restore, iar id-urile noi nu se ciocnesc
+    clock.set(Timestamp(3_000));
+    v.create("c.txt")?.write_all(b"new")?;
+    drop(v);
+
+    let v = Vfs::mount_with(path, opts())?;
+    assert_eq!(read_all(&v, "a.txt")?, "good");
+    assert_eq!(read_all(&v, "c.txt")?, "new");
+    assert!(!v.exists("b.txt"));
+
+    // starea dinainte de restore e inca in log
+    let bad = Vfs::mount_at(path, Until::Time(Timestamp(2_400)))?;
+    assert_eq!(read_all(&bad, "a.txt")?, "bad");
+    assert!(bad.exists("b.txt"));
+    Ok(())
+}
+
+#[test]
+fn restore_to_writes_ordinary_records_that_followers_see() -> Result<()> {
+    use virtual_file_system::WatchEvent::*;
+
+    let (path, replica) = ("target/restore_ops.vfs", "target/restore_ops_dst.vfs");
+    let _ = std::fs::remove_file(path);
+    let _ = std::fs::remove_file(replica);
+    let clock = Rc::new(ManualClock::new(Timestamp(1_000)));
+    let opts = || MountOptions {
+        clock: clock.clone(),
+        trash: TrashPolicy {
+            enabled: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let ship = |src: &Vfs, dst: &mut Vfs| -> Result<usize> {
+        let mut wire = Vec::new();
+        for change in src.changes_since(dst.applied_offset())? {
+            change?.write_to(&mut wire)?;
+        }
+        dst.apply_changes(&wire[..])
+    };
+    let p = |s: &str| VfsPath::new(s).unwrap();
+
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.create_dir("a")?;
+    v.create_dir("b")?;
+    v.create("a/x")?.write_all(b"x1")?;
+    v.create("b/y")?.write_all(b"y1")?;
+    v.create("keep.txt")?.write_all(b"keep")?;
+    v.set_xattr("keep.txt", "user.k", b"1")?;
+    v.create("gone.txt")?.write_all(b"gone")?;
+    v.remove_file("gone.txt")?;
+
+    let mut dst = Vfs::mount_replica(replica, MountOptions::default())?;
+    ship(&v, &mut dst)?;
+    let mut tailer = Vfs::mount_with(
+        path,
+        MountOptions {
+            read_only: true,
+            ..Default::default()
+        },
+    )?;
+    let seen = tailer.watch("/", true)?;
+
+    // a si b isi schimba numele: restore-ul trebuie sa treaca printr-un nume temporar
+    clock.set(Timestamp(2_000));
+    v.rename("a", "tmp")?;
+    v.rename("b", "a")?;
+    v.rename("tmp", "b")?;
+    v.open_rw("keep.txt")?.write_all(b"changed!")?;
+    v.set_permissions("keep.txt", 0o600)?;
+    v.remove_xattr("keep.txt", "user.k")?;
+    let gone = v.trash_list()[0].inode;
+    v.restore(gone)?;
+    v.remove_file("a/y")?;
+    v.create("new.txt")?.write_all(b"new")?;
+
+    clock.set(Timestamp(2_500));
+    v.restore_to(Until::Time(Timestamp(1_500)))?;
+    let check = |v: &Vfs| -> Result<()> {
+        assert_eq!(read_all(v, "a/x")?, "x1");
+        assert_eq!(read_all(v, "b/y")?, "y1");
+        assert_eq!(read_all(v, "keep.txt")?, "keep");
+        assert_eq!(v.metadata("keep.txt")?.mode, 0o644);
+        assert_eq!(v.get_xattr("keep.txt", "user.k")?, Some(b"1".to_vec()));
+        assert!(!v.exists("new.txt") && !v.exists("gone.txt"));
+        assert!(!v.exists("tmp") && v.read_dir("/")?.count() == 3);
+        let trash = v.trash_list();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(
+            (trash[0].inode, trash[0].deleted_at),
+            (gone, Timestamp(1_000))
+        );
+        assert!(v.fsck()?.problems.is_empty());
+        Ok(())
+    };
+    check(&v)?;
+    assert!(!v.info()?.records.contains_key("checkpoint"));
+    assert_eq!(v.metadata("keep.txt")?.changed_at, Timestamp(2_500));
+
+    // replica si montarea care urmareste log-ul ajung in aceeasi stare
+    assert!(ship(&v, &mut dst)? > 0);
+    check(&dst)?;
+    assert!(tailer.tail()? > 0);
+    check(&tailer)?;
+    let events: Vec<_> = seen.try_iter().collect();
+    let start = events.len() - 10;
+    assert_eq!(
+        events[start..],
+        [
+            Removed(p("/new.txt")),
+            // y revine in directorul lui, care inca se numeste /a
+            Created(p("/a/y")),
+            Removed(p("/gone.txt")),
+            Renamed {
+                from: p("/b"),
+                to: p("/.restore-2"),
+            },
+            Renamed {
+                from: p("/a"),
+                to: p("/b"),
+            },
+            Renamed {
+                from: p("/.restore-2"),
+                to: p("/a"),
+            },
+            // truncate, datele vechi, permisiunile, xattr-ul
+            Modified(p("/keep.txt")),
+            Modified(p("/keep.txt")),
+            Modified(p("/keep.txt")),
+            Modified(p("/keep.txt")),
+        ]
+    );
+
+    // istoria de dinainte ramane; dupa restore se scrie normal
+    drop(v);
+    let v = Vfs::mount_with(path, opts())?;
+    check(&v)?;
+    let before = Vfs::mount_at(path, Until::Time(Timestamp(2_400)))?;
+    assert_eq!(read_all(&before, "keep.txt")?, "changed!");
+    assert!(before.exists("new.txt"));
+    clock.set(Timestamp(3_000));
+    v.create("after.txt")?.write_all(b"ok")?;
+    assert_eq!(read_all(&v, "after.txt")?, "ok");
+    Ok(())
+}
+
+#[test]
+fn versions_list_and_open_old_contents() -> Result<()> {
+    let path = "target/versions.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let clock = Rc::new(ManualClock::new(Timestamp(1_000)));
+    let opts = MountOptions {
+        clock: clock.clone(),
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(path, opts)?;
+    let mut f = v.create("notes.txt")?;
+    clock.set(Timestamp(2_000));
+    f.write_all(b"first draft")?;
+    clock.set(Timestamp(3_000));
+    f.set_len(5)?;
+    drop(f);
+    clock.set(Timestamp(4_000));
+    v.rename("notes.txt", "final.txt")?;
+
+    let versions = v.versions("final.txt")?;
+    let summary: Vec<(i128, u64)> = versions.iter().map(|x| (x.modified_at.0, x.size)).collect();
+    assert_eq!(summary, [(1_000, 0), (2_000, 11), (3_000, 5)]);
+
+    let mut s = String::new();
+    v.open_version("final.txt", &versions[1])?
+        .read_to_string(&mut s)?;
+    assert_eq!(s, "first draft");
+    assert!(
+        v.open_version("final.txt", &versions[1])?
+            .write_all(b"x")
+            .is_err()
+    );
+
+    // checkpoint-ul nu adauga versiuni noi
+    v.checkpoint()?;
+    assert_eq!(v.versions("final.txt")?.len(), 3);
+    assert!(matches!(v.versions("/"), Err(VfsError::NotAFile(_))));
+
+    // istoria unui fisier pe care userul nu-l poate citi e la fel de inchisa
+    v.set_permissions("final.txt", 0o600)?;
+    drop(v);
+    let bob = Vfs::mount_with(
+        path,
+        MountOptions {
+            user: Some(Credentials::new(1000, 1000)),
+            ..Default::default()
+        },
+    )?;
+    assert!(matches!(
+        bob.versions("final.txt"),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        bob.open_version("final.txt", &versions[1]),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn compaction_honors_version_retention() -> Result<()> {
+    let build = |path: &str, retention: Retention| -> Result<Vfs> {
+        let _ = std::fs::remove_file(path);
+        let clock = Rc::new(ManualClock::new(Timestamp(0)));
+        let opts = MountOptions {
+            clock: clock.clone(),
+            retention,
+            ..Default::default()
+        };
+        let mut v = Vfs::mount_with(path, opts)?;
+        let mut f = v.create("log.txt")?;
+        for i in 1..=5u8 {
+            clock.set(Timestamp(i as i128 * 1_000_000_000));
+            f.seek(SeekFrom::Start(0))?;
+            f.write_all(&[b'0' + i; 4096])?;
+        }
+        drop(f);
+        clock.set(Timestamp(10_000_000_000));
+        v.compact()?;
+        Ok(v)
+    };
+    let times = |v: &Vfs| -> Result<Vec<i128>> {
+        Ok(v.versions("log.txt")?
+            .iter()
+            .map(|x| x.modified_at.0 / 1_000_000_000)
+            .collect())
+    };
+
+    let v = build("target/retention_none.vfs", Retention::default())?;
+    assert_eq!(times(&v)?, [5]);
+    assert_eq!(v.versions("log.txt")?[0].size, 4096);
+
+    let v = build(
+        "target/retention_last.vfs",
+        Retention {
+            keep_last: Some(2),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(times(&v)?, [4, 5]);
+    let old = v.versions("log.txt")?[0];
+    let mut out = Vec::new();
+    v.open_version("log.txt", &old)?.read_to_end(&mut out)?;
+    assert_eq!(out, [b'4'; 4096]);
+    drop(v);
+    let v = Vfs::mount("target/retention_last.vfs")?;
+    assert_eq!(times(&v)?, [4, 5]);
+
+    let v = build(
+        "target/retention_for.vfs",
+        Retention {
+            keep_for: Some(Duration::from_secs(8)),
+            ..Default::default()
+        },
+    )?;
+    assert_eq!(times(&v)?, [3, 4, 5]);
+    Ok(())
+}
+
+#[test]
+fn compaction_history_costs_only_its_changes() -> Result<()> {
+    let build = |path: &str, keep_last: Option<usize>| -> Result<Vfs> {
+        let _ = std::fs::remove_file(path);
+        let clock = Rc::new(ManualClock::new(Timestamp(0)));
+        let opts = MountOptions {
+            clock: clock.clone(),
+            retention: Retention {
+                keep_last,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut v = Vfs::mount_with(path, opts)?;
+        v.create_dir("many")?;
+        for i in 0..100 {
+            v.create(format!("many/f{i}.txt"))?.write_all(&[b'x'; 64])?;
+        }
+        for i in 1..=20u8 {
+            clock.set(Timestamp(i as i128 * 1_000_000_000));
+            v.open_rw("log.txt")
+                .or_else(|_| v.create("log.txt"))?
+                .write_all(&[i; 4096])?;
+            if i == 10 {
+                v.snapshot("mid")?;
+            }
+        }
+        v.compact()?;
+        Ok(v)
+    };
+
+    let bare = build("target/history_none.vfs", None)?;
+    let mut full = build("target/history_all.vfs", Some(20))?;
+    assert_eq!(full.versions("log.txt")?.len(), 20);
+
+    // starea cea mai veche e o copie a arborelui; fiecare versiune de dupa ea
+    // costa doar datele ei si cateva record-uri, nu inca o copie
+    let size = |v: &Vfs| v.info().map(|i| i.image_bytes);
+    let extra = size(&full)? - size(&bare)?;
+    assert!(
+        extra < size(&bare)? + 20 * (4096 + 512),
+        "history costs {extra} bytes"
+    );
+    assert_eq!(full.info()?.records["checkpoint"], 2);
+
+    let mut old = Vec::new();
+    let v3 = full.versions("log.txt")?[2];
+    full.open_version("log.txt", &v3)?.read_to_end(&mut old)?;
+    assert_eq!(old, [3u8; 4096]);
+    let mid = Vfs::mount_snapshot("target/history_all.vfs", "mid")?;
+    let mut at_mid = Vec::new();
+    mid.open("log.txt")?.read_to_end(&mut at_mid)?;
+    assert_eq!(at_mid, [10u8; 4096]);
+    assert_eq!(mid.read_dir("many")?.count(), 100);
+
+    // a doua compactare pastreaza aceeasi istorie, fara sa creasca
+    let before = size(&full)?;
+    full.compact()?;
+    assert_eq!(full.versions("log.txt")?.len(), 20);
+    assert!(size(&full)? <= before);
+    assert!(full.fsck()?.problems.is_empty());
+    Ok(())
+}
+
+#[test]
+fn trash_keeps_removed_entries_until_restored_or_emptied() -> Result<()> {
+    let path = "target/trash.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let clock = Rc::new(ManualClock::new(Timestamp(1_000)));
+    let opts = || MountOptions {
+        clock: clock.clone(),
+        trash: TrashPolicy {
+            enabled: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.create_dir("docs")?;
+    v.create_dir("docs/old")?;
+    v.create("docs/a.txt")?.write_all(b"alpha")?;
+    v.create("docs/b.txt")?.write_all(b"beta")?;
+
+    clock.set(Timestamp(2_000));
+    v.remove_file("docs/a.txt")?;
+    clock.set(Timestamp(3_000));
+    v.remove_dir("docs/old")?;
+    v.remove_file("docs/b.txt")?;
+    assert!(!v.exists("docs/a.txt"));
+    assert!(v.read_dir("docs")?.next().is_none());
+
+    let trash = v.trash_list();
+    let listed: Vec<(&str, NodeKind, i128)> = trash
+        .iter()
+        .map(|t| (t.original_path.as_str(), t.kind, t.deleted_at.0))
+        .collect();
+    assert_eq!(
+        listed,
+        [
+            ("/docs/a.txt", NodeKind::File, 2_000),
+            ("/docs/old", NodeKind::Dir, 3_000),
+            ("/docs/b.txt", NodeKind::File, 3_000),
+        ]
+    );
+    let a = trash[0].inode;
+    let b = trash[2].inode;
+
+    // persista prin checkpoint + remount
+    v.checkpoint()?;
+    drop(v);
+    let mut v = Vfs::mount_with(path, opts())?;
+    assert_eq!(v.trash_list().len(), 3);
+
+    assert_eq!(v.restore(a)?.as_str(), "/docs/a.txt");
+    assert_eq!(read_all(&v, "docs/a.txt")?, "alpha");
+    assert!(matches!(v.restore(a), Err(VfsError::NotFound(_))));
+
+    // numele original e ocupat
+    v.create("docs/b.txt")?.write_all(b"newer")?;
+    assert!(matches!(v.restore(b), Err(VfsError::AlreadyExists(_))));
+    assert_eq!(v.restore_as(b, "docs/b.old")?.as_str(), "/docs/b.old");
+    assert_eq!(read_all(&v, "docs/b.old")?, "beta");
+
+    v.empty_trash()?;
+    assert!(v.trash_list().is_empty());
+    drop(v);
+    let v = Vfs::mount(path)?;
+    assert!(v.trash_list().is_empty());
+    assert!(!v.exists("docs/old"));
+    assert_eq!(read_all(&v, "docs/a.txt")?, "alpha");
+    Ok(())
+}
+
+#[test]
+fn compaction_purges_expired_trash() -> Result<()> {
+    let path = "target/trash_purge.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let clock = Rc::new(ManualClock::new(Timestamp(0)));
+    let opts = MountOptions {
+        clock: clock.clone(),
+        trash: TrashPolicy {
+            enabled: true,
+            purge_after: Some(Duration::from_secs(60)),
+        },
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(path, opts)?;
+    v.create("old.bin")?.write_all(&[1u8; 64 * 1024])?;
+    v.create("recent.bin")?.write_all(&[2u8; 16])?;
+    v.remove_file("old.bin")?;
+    clock.set(Timestamp(100_000_000_000));
+    v.remove_file("recent.bin")?;
+
+    clock.set(Timestamp(120_000_000_000));
+    v.compact()?;
+    let names: Vec<String> = v
+        .trash_list()
+        .into_iter()
+        .map(|t| t.original_path.to_string())
+        .collect();
+    assert_eq!(names, ["/recent.bin"]);
+    assert!(std::fs::metadata(path)?.len() < 64 * 1024);
+
+    let recent = v.trash_list()[0].inode;
+    v.restore(recent)?;
+    let mut out = Vec::new();
+    v.open("recent.bin")?.read_to_end(&mut out)?;
+    assert_eq!(out, [2u8; 16]);
+    Ok(())
+}
+
+#[test]
+fn clone_file_shares_extents_until_written() -> Result<()> {
+    let path = "target/reflink.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    let big = vec![b'x'; 256 * 1024];
+    v.create("base.img")?.write_all(&big)?;
+    v.set_permissions("base.img", 0o600)?;
+
+    let before = std::fs::metadata(path)?.len();
+    v.clone_file("base.img", "copy.img")?;
+    assert!(std::fs::metadata(path)?.len() - before < 4096);
+    assert_eq!(v.metadata("copy.img")?.size, big.len() as u64);
+    assert_eq!(v.metadata("copy.img")?.mode, 0o600);
+
+    let s = v.stats()?;
+    assert_eq!(s.files, 2);
+    assert_eq!(s.logical_bytes, 2 * big.len() as u64);
+    assert_eq!(s.referenced_bytes, big.len() as u64);
+    assert_eq!(s.shared_bytes, big.len() as u64);
+
+    // scrierea in clona nu atinge sursa, si invers
+    v.open_rw("copy.img")?.write_all(b"COPY")?;
+    let mut f = v.open_rw("base.img")?;
+    f.seek(SeekFrom::Start(4))?;
+    f.write_all(b"BASE")?;
+    drop(f);
+
+    let mut head = [0u8; 8];
+    v.open("copy.img")?.read_exact(&mut head)?;
+    assert_eq!(&head, b"COPYxxxx");
+    v.open("base.img")?.read_exact(&mut head)?;
+    assert_eq!(&head, b"xxxxBASE");
+
+    let s = v.stats()?;
+    assert_eq!(s.referenced_bytes, big.len() as u64 + 8);
+    assert_eq!(s.shared_bytes, big.len() as u64 - 8);
+
+    // compactarea copiaza o singura data datele comune
+    v.compact()?;
+    assert!(std::fs::metadata(path)?.len() < big.len() as u64 + 4096);
+    v.open("copy.img")?.read_exact(&mut head)?;
+    assert_eq!(&head, b"COPYxxxx");
+    assert_eq!(v.stats()?.shared_bytes, big.len() as u64 - 8);
+
+    assert!(matches!(
+        v.clone_file("base.img", "copy.img"),
+        Err(VfsError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        v.clone_file("/", "dir.img"),
+        Err(VfsError::NotAFile(_))
+    ));
+    Ok(())
+}
+
+// date pseudo-aleatoare, deterministe, greu de comprimat/deduplicat accidental
+fn noise(seed: u64, len: usize) -> Vec<u8> {
+    let mut x = seed | 1;
+    (0..len)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x as u8
+        })
+        .collect()
+}
+
+#[test]
+fn dedup_references_existing_chunks() -> Result<()> {
+    let path = "target/dedup_fixed.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let opts = || MountOptions {
+        dedup: Some(Chunking::Fixed(4096)),
+        ..Default::default()
+    };
+    let artifact = noise(1, 64 * 1024);
+
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.create_dir("build1")?;
+    v.create_dir("build2")?;
+    v.create("build1/app.bin")?.write_all(&artifact)?;
+    v.create("build2/app.bin")?.write_all(&artifact)?;
+
+    let s = v.stats()?;
+    assert_eq!(s.logical_bytes, 2 * artifact.len() as u64);
+    assert_eq!(s.physical_bytes, artifact.len() as u64);
+    assert_eq!(s.shared_bytes, artifact.len() as u64);
+
+    // indexul supravietuieste checkpoint-ului si compactarii
+    v.checkpoint()?;
+    drop(v);
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.compact()?;
+    v.create("third.bin")?.write_all(&artifact)?;
+    drop(v);
+
+    let v = Vfs::mount_with(path, opts())?;
+    assert_eq!(v.stats()?.physical_bytes, artifact.len() as u64);
+    for p in ["build1/app.bin", "build2/app.bin", "third.bin"] {
+        let mut out = Vec::new();
+        v.open(p)?.read_to_end(&mut out)?;
+        assert_eq!(out, artifact);
+    }
+    Ok(())
+}
+
+#[test]
+fn content_defined_chunking_survives_insertions() -> Result<()> {
+    let path = "target/dedup_cdc.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let opts = MountOptions {
+        dedup: Some(Chunking::ContentDefined { avg: 2048 }),
+        ..Default::default()
+    };
+    let base = noise(7, 128 * 1024);
+    let mut shifted = b"header v2\n".to_vec();
+    shifted.extend_from_slice(&base);
+
+    let v = Vfs::mount_with(path, opts)?;
+    v.create("a.bin")?.write_all(&base)?;
+    v.create("b.bin")?.write_all(&shifted)?;
+
+    // doar chunk-ul de la inceput difera
+    let s = v.stats()?;
+    assert!(
+        s.physical_bytes < base.len() as u64 + 16 * 1024,
+        "{}",
+        s.physical_bytes
+    );
+    let mut out = Vec::new();
+    v.open("b.bin")?.read_to_end(&mut out)?;
+    assert_eq!(out, shifted);
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compressed_writes_shrink_image_and_read_back() -> Result<()> {
+    let path = "target/compress_zstd.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let opts = || MountOptions {
+        compression: Some(CompressionPolicy::default()),
+        ..Default::default()
+    };
+    let text = "lorem ipsum dolor sit amet ".repeat(4000).into_bytes();
+    let random = noise(7, 16 * 1024);
+
+    let v = Vfs::mount_with(path, opts())?;
+    v.create("text.txt")?.write_all(&text)?;
+    v.create("random.bin")?.write_all(&random)?;
+    // suprascriere in mijlocul unui extent comprimat
+    let mut f = v.open_rw("text.txt")?;
+    f.seek(SeekFrom::Start(100))?;
+    f.write_all(b"PATCH")?;
+    drop(f);
+
+    let s = v.stats()?;
+    assert!(s.physical_bytes < (text.len() / 10 + random.len() + 5) as u64);
+    // zgomotul nu se comprima, ramane brut
+    assert!(s.physical_bytes >= random.len() as u64);
+
+    let mut expected = text.clone();
+    expected[100..105].copy_from_slice(b"PATCH");
+    let check = |v: &Vfs| -> Result<()> {
+        let mut out = Vec::new();
+        v.open("text.txt")?.read_to_end(&mut out)?;
+        assert_eq!(out, expected);
+        let mut out = Vec::new();
+        v.open("random.bin")?.read_to_end(&mut out)?;
+        assert_eq!(out, random);
+        Ok(())
+    };
+    check(&v)?;
+
+    // algoritmul e al imaginii: se citeste si fara politica la montare
+    drop(v);
+    check(&Vfs::mount(path)?)?;
+
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.compact()?;
+    check(&v)?;
+    drop(v);
+    check(&Vfs::mount(path)?)?;
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compression_policy_skips_small_and_incompressible_writes() -> Result<()> {
+    let path = "target/compress_policy.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let policy = CompressionPolicy {
+        min_size: 4096,
+        ..Default::default()
+    };
+    let v = Vfs::mount_with(
+        path,
+        MountOptions {
+            compression: Some(policy),
+            ..Default::default()
+        },
+    )?;
+    let small = vec![b'a'; 1000];
+    v.create("small.txt")?.write_all(&small)?;
+    assert_eq!(v.stats()?.physical_bytes, small.len() as u64);
+
+    let random = noise(3, 8192);
+    v.create("random.bin")?.write_all(&random)?;
+    assert_eq!(
+        v.stats()?.physical_bytes,
+        (small.len() + random.len()) as u64
+    );
+    Ok(())
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn compression_works_with_dedup() -> Result<()> {
+    let path = "target/compress_dedup.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let opts = || MountOptions {
+        dedup: Some(Chunking::Fixed(4096)),
+        compression: Some(CompressionPolicy::default()),
+        ..Default::default()
+    };
+    let data = "0123456789abcdef".repeat(2048).into_bytes();
+
+    let v = Vfs::mount_with(path, opts())?;
+    v.create("a.txt")?.write_all(&data)?;
+    let after_first = v.stats()?.physical_bytes;
+    assert!(after_first < data.len() as u64 / 4);
+    v.create("b.txt")?.write_all(&data)?;
+    assert_eq!(v.stats()?.physical_bytes, after_first);
+    drop(v);
+
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.compact()?;
+    v.create("c.txt")?.write_all(&data)?;
+    for p in ["a.txt", "b.txt", "c.txt"] {
+        let mut out = Vec::new();
+        v.open(p)?.read_to_end(&mut out)?;
+        assert_eq!(out, data);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "zstd"))]
+#[test]
+fn compression_needs_the_cargo_feature() -> Result<()> {
+    let path = "target/compress_missing.vfs";
+    let _ = std::fs::remove_file(path);
+    let _ = Vfs::mount(path)?;
+
+    // imagine marcata zstd, scrisa de un build cu feature-ul
+    drop(std::fs::remove_file(path));
+    let mut f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    write_image_header(
+        &mut f,
+        &Header {
+            magic: *b"CCCCCCCC",
+            version: VERSION,
+            block_size: 4096,
+            root: InodeId(1),
+            compression: virtual_file_system::Compression::Zstd,
+            encryption: None,
+            generation: 0,
+        },
+    )?;
+    drop(f);
+    assert!(matches!(Vfs::mount(path), Err(VfsError::Unsupported(_))));
+
+    let _ = std::fs::remove_file(path);
+    let opts = MountOptions {
+        compression: Some(CompressionPolicy::default()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        Vfs::mount_with(path, opts),
+        Err(VfsError::Unsupported(_))
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_image_hides_names_and_contents() -> Result<()> {
+    let path = "target/encrypted.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let opts = |key: u8| MountOptions {
+        encryption: Some(EncryptionKey::from_bytes([key; 32])),
+        ..Default::default()
+    };
+    let secret = "the launch code is 0000".repeat(50);
+
+    let mut v = Vfs::mount_with(path, opts(7))?;
+    v.create_dir("classified")?;
+    v.create("classified/plans.txt")?
+        .write_all(secret.as_bytes())?;
+    v.snapshot("before-edit")?;
+    v.create("classified/plans.txt.bak")?
+        .write_all(b"second copy")?;
+    v.checkpoint()?;
+    drop(v);
+
+    let raw = std::fs::read(path)?;
+    for needle in ["classified", "plans.txt", "launch code", "before-edit"] {
+        assert!(!contains(&raw, needle.as_bytes()), "{needle} leaked");
+    }
+
+    // cheie gresita sau lipsa: eroare clara, nu CorruptLog
+    assert!(matches!(
+        Vfs::mount_with(path, opts(8)),
+        Err(VfsError::WrongKey(_))
+    ));
+    assert!(matches!(Vfs::mount(path), Err(VfsError::WrongKey(_))));
+
+    let mut v = Vfs::mount_with(path, opts(7))?;
+    assert_eq!(read_all(&v, "classified/plans.txt")?, secret);
+    v.compact()?;
+    assert_eq!(read_all(&v, "classified/plans.txt.bak")?, "second copy");
+    drop(v);
+
+    let raw = std::fs::read(path)?;
+    assert!(!contains(&raw, b"launch code"));
+    let v = Vfs::mount_with(path, opts(7))?;
+    assert_eq!(read_all(&v, "classified/plans.txt")?, secret);
+    let snap = Vfs::mount_snapshot_with(path, "before-edit", opts(7))?;
+    assert!(!snap.exists("classified/plans.txt.bak"));
+    assert_eq!(read_all(&snap, "classified/plans.txt")?, secret);
+    Ok(())
+}
+
+#[cfg(all(feature = "encryption", feature = "zstd"))]
+#[test]
+fn encryption_combines_with_compression_and_dedup() -> Result<()> {
+    let path = "target/encrypted_zstd.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let opts = || MountOptions {
+        encryption: Some(EncryptionKey::from_bytes([1; 32])),
+        compression: Some(CompressionPolicy::default()),
+        dedup: Some(Chunking::Fixed(4096)),
+        ..Default::default()
+    };
+    let data = "0123456789abcdef".repeat(2048).into_bytes();
+
+    let mut v = Vfs::mount_with(path, opts())?;
+    v.create("a.txt")?.write_all(&data)?;
+    let after_first = v.stats()?.physical_bytes;
+    assert!(after_first < data.len() as u64 / 4);
+    v.create("b.txt")?.write_all(&data)?;
+    assert_eq!(v.stats()?.physical_bytes, after_first);
+    v.compact()?;
+    drop(v);
+
+    let v = Vfs::mount_with(path, opts())?;
+    for p in ["a.txt", "b.txt"] {
+        let mut out = Vec::new();
+        v.open(p)?.read_to_end(&mut out)?;
+        assert_eq!(out, data);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "encryption"))]
+#[test]
+fn encryption_needs_the_cargo_feature() {
+    let path = "target/encrypted_missing.vfs";
+    let _ = std::fs::remove_file(path);
+    let opts = MountOptions {
+        encryption: Some(EncryptionKey::from_bytes([7; 32])),
+        ..Default::default()
+    };
+    assert!(matches!(
+        Vfs::mount_with(path, opts),
+        Err(VfsError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn tar_export_import_roundtrip() -> Result<()> {
+    let src_path = "target/tar_src.vfs";
+    let dst_path = "target/tar_dst.vfs";
+    let _ = std::fs::remove_file(src_path);
+    let _ = std::fs::remove_file(dst_path);
+
+    let clock = Rc::new(ManualClock::new(Timestamp(1_700_000_000_123_456_789)));
+    let opts = || MountOptions {
+        clock: clock.clone(),
+        ..Default::default()
+    };
+    let long_dir = "a-directory-name-long-enough-to-need-pax-".repeat(3);
+    let body = noise(9, 3000);
+
+    let mut v = Vfs::mount_with(src_path, opts())?;
+    v.create_dir("project")?;
+    v.create_dir(format!("project/{long_dir}"))?;
+    v.create_dir("project/empty")?;
+    v.create(format!("project/{long_dir}/data.bin"))?
+        .write_all(&body)?;
+    clock.advance(Duration::from_secs(60));
+    v.create("project/run.sh")?
+        .write_all(b"#!/bin/sh\necho hi\n")?;
+    v.set_permissions("project/run.sh", 0o750)?;
+    v.set_xattr("project/run.sh", "user.origin", b"build\0farm")?;
+    v.set_times(
+        "project/empty",
+        FileTimes {
+            created_at: Some(Timestamp(1_000_000_000_000_000_001)),
+            modified_at: Some(Timestamp(1_100_000_000_000_000_002)),
+            accessed_at: None,
+        },
+    )?;
+
+    let mut archive = Vec::new();
+    virtual_file_system::archive::export_tar(&v, "/project", &mut archive)?;
+    assert_eq!(archive.len() % 512, 0);
+
+    clock.advance(Duration::from_secs(3600));
+    let mut w = Vfs::mount_with(dst_path, opts())?;
+    w.create_dir("restored")?;
+    let report = virtual_file_system::archive::import_tar(&mut w, "/restored", archive.as_slice())?;
+    assert_eq!(report.files, 2);
+    assert_eq!(report.dirs, 2);
+    assert!(report.skipped.is_empty());
+    drop(w);
+
+    let w = Vfs::mount(dst_path)?;
+    let mut out = Vec::new();
+    w.open(format!("restored/{long_dir}/data.bin"))?
+        .read_to_end(&mut out)?;
+    assert_eq!(out, body);
+    assert_eq!(read_all(&w, "restored/run.sh")?, "#!/bin/sh\necho hi\n");
+    assert_eq!(
+        w.get_xattr("restored/run.sh", "user.origin")?,
+        Some(b"build\0farm".to_vec())
+    );
+
+    for p in ["run.sh", "empty", long_dir.as_str()] {
+        let a = v.metadata(format!("project/{p}"))?;
+        let b = w.metadata(format!("restored/{p}"))?;
+        assert_eq!(a.mode, b.mode, "{p}");
+        assert_eq!(a.size, b.size, "{p}");
+        assert_eq!(a.created_at, b.created_at, "{p}");
+        assert_eq!(a.modified_at, b.modified_at, "{p}");
+    }
+    Ok(())
+}
+
+// header ustar minimal, pt arhive pe care exportul nu le-ar produce
+fn tar_header(name: &str, typeflag: u8, size: usize) -> [u8; 512] {
+    let mut h = [0u8; 512];
+    h[..name.len()].copy_from_slice(name.as_bytes());
+    h[100..107].copy_from_slice(b"0000644");
+    h[124..135].copy_from_slice(format!("{size:011o}").as_bytes());
+    h[136..147].copy_from_slice(b"00000000000");
+    h[156] = typeflag;
+    h[257..263].copy_from_slice(b"ustar\0");
+    h[148..156].fill(b' ');
+    let sum: u32 = h.iter().map(|&b| b as u32).sum();
+    h[148..154].copy_from_slice(format!("{sum:06o}").as_bytes());
+    h[154] = 0;
+    h
+}
+
+#[test]
+fn tar_import_skips_links_and_rejects_escaping_paths() -> Result<()> {
+    let path = "target/tar_hostile.vfs";
+    let _ = std::fs::remove_file(path);
+    let mut v = Vfs::mount(path)?;
+
+    // fara intrari pt directoare: se creeaza implicit; symlink-ul e sarit
+    let mut archive = Vec::new();
+    archive.extend_from_slice(&tar_header("a/b/c.txt", b'0', 5));
+    archive.extend_from_slice(&{
+        let mut block = [0u8; 512];
+        block[..5].copy_from_slice(b"hello");
+        block
+    });
+    archive.extend_from_slice(&tar_header("a/link", b'2', 0));
+    archive.extend_from_slice(&[0u8; 1024]);
+
+    let report = virtual_file_system::archive::import_tar(&mut v, "/", archive.as_slice())?;
+    assert_eq!(report.files, 1);
+    assert_eq!(report.skipped, vec!["a/link".to_string()]);
+    assert_eq!(read_all(&v, "a/b/c.txt")?, "hello");
+
+    let mut evil = Vec::new();
+    evil.extend_from_slice(&tar_header("../outside.txt", b'0', 0));
+    evil.extend_from_slice(&[0u8; 1024]);
+    v.create_dir("jail")?;
+    assert!(matches!(
+        virtual_file_system::archive::import_tar(&mut v, "/jail", evil.as_slice()),
+        Err(VfsError::InvalidPath(_))
+    ));
+    assert!(!v.exists("outside.txt"));
+
+    // marimea unui header pax vine din arhiva: nu se aloca orbeste
+    let mut huge = tar_header("pax", b'x', 0);
+    huge[124..136].copy_from_slice(&[0x80, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+    huge[148..156].fill(b' ');
+    let sum: u32 = huge.iter().map(|&b| b as u32).sum();
+    huge[148..154].copy_from_slice(format!("{sum:06o}").as_bytes());
+    huge[154] = 0;
+    assert!(matches!(
+        virtual_file_system::archive::import_tar(&mut v, "/jail", &huge[..]),
+        Err(VfsError::InvalidPath(m)) if m.contains("metadata entry")
+    ));
+
+    // drepturile: ca la create, create_dir, set_permissions
+    v.create_dir("shared")?;
+    v.set_permissions("shared", 0o777)?;
+    v.create_dir("shared/ro")?;
+    drop(v);
+    let mut alice = Vfs::mount_with(
+        path,
+        MountOptions {
+            user: Some(Credentials::new(1000, 1000)),
+            ..Default::default()
+        },
+    )?;
+    let archive_of = |entries: &[(&str, u8)]| {
+        let mut out = Vec::new();
+        for (name, typeflag) in entries {
+            out.extend_from_slice(&tar_header(name, *typeflag, 0));
+        }
+        out.extend_from_slice(&[0u8; 1024]);
+        out
+    };
+    let import = |v: &mut Vfs, dest: &str, tar: Vec<u8>| {
+        virtual_file_system::archive::import_tar(v, dest, tar.as_slice())
+    };
+    assert!(matches!(
+        import(&mut alice, "/", archive_of(&[("new.txt", b'0')])),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    assert!(matches!(
+        import(&mut alice, "shared", archive_of(&[("./", b'5')])),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    assert_eq!(alice.metadata("shared")?.mode, 0o777);
+    assert!(matches!(
+        import(&mut alice, "shared", archive_of(&[("ro/x/y.txt", b'0')])),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    assert!(!alice.exists("shared/ro/x"));
+    let report = import(&mut alice, "shared", archive_of(&[("mine/z.txt", b'0')]))?;
+    assert_eq!((report.files, report.dirs), (1, 1));
+    assert_eq!(alice.metadata("shared/mine/z.txt")?.uid, 1000);
+    Ok(())
+}
+
+#[test]
+fn host_dir_sync_roundtrip_incremental_and_delete() -> Result<()> {
+    let image = "target/sync.vfs";
+    let host = std::path::Path::new("target/sync_host");
+    let back = std::path::Path::new("target/sync_back");
+    let _ = std::fs::remove_file(image);
+    let _ = std::fs::remove_dir_all(host);
+    let _ = std::fs::remove_dir_all(back);
+
+    std::fs::create_dir_all(host.join("src/nested"))?;
+    std::fs::write(host.join("README"), b"hello")?;
+    std::fs::write(host.join("src/main.rs"), b"fn main() {}")?;
+    std::fs::write(host.join("src/nested/big.bin"), noise(4, 200_000))?;
+    let old = std::time::UNIX_EPOCH + Duration::from_secs(1_600_000_000);
+    std::fs::File::options()
+        .write(true)
+        .open(host.join("README"))?
+        .set_modified(old)?;
+
+    let mut v = Vfs::mount(image)?;
+    let report = v.import_dir(host, "/mirror")?;
+    assert_eq!(report.copied, 3);
+    assert_eq!(report.dirs_created, 3);
+    assert_eq!(report.bytes, 5 + 12 + 200_000);
+    assert_eq!(read_all(&v, "mirror/src/main.rs")?, "fn main() {}");
+    assert_eq!(
+        v.metadata("mirror/README")?.modified_at,
+        Timestamp::from(old)
+    );
+
+    // nimic schimbat: incremental nu copiaza nimic
+    let incremental = SyncOptions {
+        incremental: true,
+        ..Default::default()
+    };
+    let report = v.import_dir_with(host, "/mirror", incremental)?;
+    assert_eq!((report.copied, report.unchanged), (0, 3));
+
+    std::fs::write(host.join("src/main.rs"), b"fn main() { run() }")?;
+    std::fs::remove_dir_all(host.join("src/nested"))?;
+    let report = v.import_dir_with(
+        host,
+        "/mirror",
+        SyncOptions {
+            incremental: true,
+            delete: true,
+        },
+    )?;
+    assert_eq!((report.copied, report.unchanged, report.deleted), (1, 1, 2));
+    assert!(!v.exists("mirror/src/nested"));
+    drop(v);
+
+    let v = Vfs::mount(image)?;
+    assert_eq!(read_all(&v, "mirror/src/main.rs")?, "fn main() { run() }");
+    let report = v.export_dir("/mirror", back)?;
+    assert_eq!(report.copied, 2);
+    assert_eq!(
+        std::fs::read(back.join("src/main.rs"))?,
+        b"fn main() { run() }"
+    );
+    assert_eq!(
+        std::fs::metadata(back.join("README"))?.modified()?,
+        SystemTime::from(Timestamp::from(old))
+    );
+
+    std::fs::write(back.join("stray.txt"), b"x")?;
+    let report = v.export_dir_with(
+        "/mirror",
+        back,
+        SyncOptions {
+            incremental: true,
+            delete: true,
+        },
+    )?;
+    assert_eq!((report.copied, report.unchanged, report.deleted), (0, 2, 1));
+    assert!(!back.join("stray.txt").exists());
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn host_dir_sync_replaces_read_only_files_and_dirs() -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let image = "target/sync_ro.vfs";
+    let host = std::path::Path::new("target/sync_ro_host");
+    let back = std::path::Path::new("target/sync_ro_back");
+    let _ = std::fs::remove_file(image);
+    for dir in [host, back] {
+        if dir.exists() {
+            std::fs::set_permissions(dir.join("locked"), std::fs::Permissions::from_mode(0o755))
+                .ok();
+            std::fs::remove_dir_all(dir)?;
+        }
+    }
+    let chmod = |p: &std::path::Path, mode: u32| {
+        std::fs::set_permissions(p, std::fs::Permissions::from_mode(mode))
+    };
+    let mode = |p: &std::path::Path| -> Result<u32> {
+        Ok(std::fs::metadata(p)?.permissions().mode() & 0o7777)
+    };
+    std::fs::create_dir_all(host.join("locked"))?;
+    std::fs::write(host.join("ro.txt"), b"one")?;
+    std::fs::write(host.join("locked/inner.txt"), b"in")?;
+    chmod(&host.join("ro.txt"), 0o444)?;
+    chmod(&host.join("locked"), 0o555)?;
+
+    let mut root = Vfs::mount(image)?;
+    root.create_dir("m")?;
+    root.chown("m", Some(1000), Some(1000))?;
+    let alice = MountOptions {
+        user: Some(Credentials::new(1000, 1000)),
+        ..Default::default()
+    };
+    let mut v = Vfs::mount_with(image, alice)?;
+    v.import_dir(host, "/m")?;
+    assert_eq!(v.metadata("m/ro.txt")?.mode, 0o444);
+    v.set_permissions("m/ro.txt", 0o644)?;
+    v.set_xattr("m/ro.txt", "user.tag", b"kept")?;
+    v.set_permissions("m/ro.txt", 0o444)?;
+
+    // a doua oara, fisierul si directorul read-only ale ei se inlocuiesc
+    std::fs::write(host.join("ro.txt"), b"two")?;
+    std::fs::write(host.join("locked/inner.txt"), b"in 2")?;
+    std::fs::write(host.join("locked/new.txt"), b"new")?;
+    v.import_dir(host, "/m")?;
+    assert_eq!(read_all(&v, "m/ro.txt")?, "two");
+    assert_eq!(read_all(&v, "m/locked/inner.txt")?, "in 2");
+    assert_eq!(read_all(&v, "m/locked/new.txt")?, "new");
+    assert_eq!(v.metadata("m/ro.txt")?.mode, 0o444);
+    assert_eq!(v.metadata("m/locked")?.mode, 0o555);
+    assert_eq!(v.get_xattr("m/ro.txt", "user.tag")?, Some(b"kept".to_vec()));
+    let names: Vec<String> = v
+        .read_dir("m")?
+        .map(|e| e.map(|e| e.name))
+        .collect::<Result<_>>()?;
+    assert_eq!(names, ["locked", "ro.txt"]);
+
+    // exportul peste un export read-only de data trecuta
+    v.export_dir("/m", back)?;
+    std::fs::write(host.join("ro.txt"), b"three")?;
+    v.import_dir(host, "/m")?;
+    v.export_dir("/m", back)?;
+    assert_eq!(std::fs::read(back.join("ro.txt"))?, b"three");
+    assert_eq!(mode(&back.join("ro.txt"))?, 0o444);
+    assert_eq!(mode(&back.join("locked"))?, 0o555);
+    let mut names: Vec<String> = std::fs::read_dir(back)?
+        .map(|e| e.map(|e| e.file_name().to_string_lossy().into_owned()))
+        .collect::<std::io::Result<_>>()?;
+    names.sort();
+    assert_eq!(names, ["locked", "ro.txt"]);
+    Ok(())
+}
+
+#[test]
+fn host_dir_sync_rejects_mismatched_kinds_and_skips_links() -> Result<()> {
+    let image = "target/sync_errors.vfs";
+    let host = std::path::Path::new("target/sync_errors_host");
+    let back = std::path::Path::new("target/sync_errors_back");
+    let _ = std::fs::remove_file(image);
+    let _ = std::fs::remove_dir_all(host);
+    let _ = std::fs::remove_dir_all(back);
+    std::fs::create_dir_all(host.join("conf"))?;
+    std::fs::write(host.join("app.toml"), b"x = 1")?;
+
+    let mut v = Vfs::mount(image)?;
+    // sursa lipsa sau fisier, destinatie fisier: nimic nu se scrie
+    assert!(v.import_dir("target/sync_errors_missing", "/m").is_err());
+    assert!(matches!(
+        v.import_dir(host.join("app.toml"), "/m"),
+        Err(VfsError::NotADir(_))
+    ));
+    v.create("/file")?;
+    assert!(matches!(
+        v.import_dir(host, "/file"),
+        Err(VfsError::NotADir(_))
+    ));
+    assert!(!v.exists("/m"));
+
+    // acelasi nume, alt tip: directorul de pe host peste un fisier din vfs si invers
+    v.create_dir("/m")?;
+    v.create("/m/conf")?;
+    assert!(matches!(
+        v.import_dir(host, "/m"),
+        Err(VfsError::NotADir(_))
+    ));
+    v.remove_file("/m/conf")?;
+    v.create_dir("/m/conf")?;
+    // `app.toml` vine inaintea lui `conf`, deci a fost deja copiat
+    v.remove_file("/m/app.toml")?;
+    v.create_dir("/m/app.toml")?;
+    assert!(matches!(
+        v.import_dir(host, "/m"),
+        Err(VfsError::NotAFile(_))
+    ));
+    v.remove_dir("/m/app.toml")?;
+    v.import_dir(host, "/m")?;
+    assert_eq!(read_all(&v, "/m/app.toml")?, "x = 1");
+
+    assert!(matches!(
+        v.export_dir("/missing", back),
+        Err(VfsError::NotFound(_))
+    ));
+    assert!(matches!(
+        v.export_dir("/file", back),
+        Err(VfsError::NotADir(_))
+    ));
+    std::fs::create_dir_all(back.join("app.toml"))?;
+    assert!(matches!(
+        v.export_dir("/m", back),
+        Err(VfsError::NotAFile(_))
+    ));
+    std::fs::remove_dir(back.join("app.toml"))?;
+    std::fs::write(back.join("conf"), b"")?;
+    assert!(matches!(
+        v.export_dir("/m", back),
+        Err(VfsError::NotADir(_))
+    ));
+    std::fs::remove_file(back.join("conf"))?;
+
+    // un symlink de pe host e sarit la import, iar la export nu se scrie prin el
+    #[cfg(unix)]
+    {
+        let outside = std::path::Path::new("target/sync_errors_outside.txt");
+        std::fs::write(outside, b"keep")?;
+        std::os::unix::fs::symlink(std::fs::canonicalize(outside)?, host.join("link"))?;
+        let report = v.import_dir(host, "/m")?;
+        assert_eq!(report.skipped, [host.join("link")]);
+        assert!(!v.exists("/m/link"));
+
+        std::fs::remove_file(back.join("app.toml"))?;
+        std::os::unix::fs::symlink(std::fs::canonicalize(outside)?, back.join("app.toml"))?;
+        assert!(matches!(
+            v.export_dir("/m", back),
+            Err(VfsError::NotAFile(_))
+        ));
+        assert_eq!(std::fs::read(outside)?, b"keep");
+    }
+    Ok(())
+}
+
+#[test]
+fn fsck_and_info_report_image_state() -> Result<()> {
+    let path = "target/fsck.vfs";
+    let _ = std::fs::remove_file(path);
+
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("d")?;
+    v.create("d/f")?.write_all(&[0xAB; 4096])?;
+    v.checkpoint()?;
+    let report = v.fsck()?;
+    assert!(report.is_clean(), "{:?}", report.problems);
+    assert_eq!(report.log_end, report.image_bytes);
+
+    let info = v.info()?;
+    assert_eq!(info.header.block_size, DEFAULT_BLOCK_SIZE);
+    assert_eq!(info.records["data_write"], 1);
+    assert_eq!(info.records["checkpoint"], 1);
+    assert_eq!(info.records.values().sum::<u64>(), report.records);
+    drop(v);
+
+    // un byte de date stricat: record-ul ramane valid, crc-ul datelor nu
+    let mut image = std::fs::read(path)?;
+    let at = image.windows(64).position(|w| w == [0xAB; 64]).unwrap();
+    image[at + 10] ^= 1;
+    image.extend_from_slice(b"torn");
+    std::fs::write(path, &image)?;
+
+    let report = Vfs::mount(path)?.fsck()?;
+    assert_eq!(report.problems.len(), 2, "{:?}", report.problems);
+    assert!(report.problems[0].contains("crc mismatch"));
+    assert!(report.problems[1].contains(&format!("offset {}", report.log_end)));
+    assert_eq!(report.image_bytes - report.log_end, 4);
+    Ok(())
+}
+
+#[test]
+fn log_reader_reports_corruption_and_resyncs() -> Result<()> {
+    use virtual_file_system::debug::{LogReader, record_inodes};
+
+    let path = "target/log_reader.vfs";
+    let _ = std::fs::remove_file(path);
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("victim")?;
+    v.create("victim/f")?.write_all(b"payload")?;
+    drop(v);
+
+    let clean: Vec<(u64, Record, Option<u64>)> = LogReader::open(path)?.collect::<Result<_>>()?;
+    assert_eq!(clean[0].0, HEADER_LEN);
+    assert!(clean.windows(2).all(|w| w[0].0 < w[1].0));
+    let (_, write, data_offset) = clean
+        .iter()
+        .find(|(_, r, _)| r.kind() == "data_write")
+        .unwrap();
+    assert_eq!(record_inodes(write), vec![InodeId(3)]);
+    let data_offset = data_offset.unwrap() as usize;
+    assert_eq!(
+        &std::fs::read(path)?[data_offset..data_offset + 7],
+        b"payload"
+    );
+
+    // stricam numele din primul record care il contine, plus o coada rupta
+    let mut image = std::fs::read(path)?;
+    let at = image.windows(6).position(|w| w == b"victim").unwrap();
+    image[at] ^= 1;
+    image.extend_from_slice(b"VFSR\x01");
+    std::fs::write(path, &image)?;
+    let bad = clean
+        .iter()
+        .rev()
+        .find(|(off, ..)| (*off as usize) < at)
+        .unwrap()
+        .0;
+
+    let items: Vec<_> = LogReader::open(path)?.collect();
+    assert!(
+        matches!(items.last(), Some(Err(VfsError::CorruptLog(m))) if m.contains(&format!("offset {bad}")))
+    );
+    assert_eq!(items.iter().filter(|i| i.is_ok()).count(), 1);
+
+    let items: Vec<_> = LogReader::open(path)?.resync(true).collect();
+    let errors: Vec<String> = items
+        .iter()
+        .filter_map(|i| i.as_ref().err().map(|e| e.to_string()))
+        .collect();
+    assert_eq!(errors.len(), 2, "{errors:?}");
+    assert!(errors[0].contains("crc mismatch"));
+    assert!(errors[1].contains("torn"));
+    assert_eq!(items.iter().filter(|i| i.is_ok()).count(), clean.len() - 1);
+    Ok(())
+}
+
+#[test]
+fn dump_log_rejects_bad_arguments_and_images() -> Result<()> {
+    use std::process::Command;
+    use virtual_file_system::debug::LogReader;
+
+    let dir = std::path::Path::new("target/dump_errors");
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir)?;
+    let image = dir.join("img.vfs");
+    let mut v = Vfs::mount(&image)?;
+    v.create_dir("victim")?;
+    v.create("victim/f")?.write_all(b"payload")?;
+    drop(v);
+
+    // nu e o imagine, e goala, lipseste sau cheia nu are ce deschide
+    std::fs::write(dir.join("text.vfs"), "not an image at all")?;
+    std::fs::write(dir.join("empty.vfs"), "")?;
+    assert!(matches!(
+        LogReader::open(dir.join("text.vfs")),
+        Err(VfsError::CorruptLog(m)) if m.contains("magic")
+    ));
+    assert!(matches!(
+        LogReader::open(dir.join("empty.vfs")),
+        Err(VfsError::Io(_))
+    ));
+    assert!(matches!(
+        LogReader::open(dir.join("missing.vfs")),
+        Err(VfsError::Io(_))
+    ));
+    let key = EncryptionKey::from_bytes([3; 32]);
+    assert!(matches!(
+        LogReader::open_with(&image, Some(&key)),
+        Err(VfsError::Unsupported(_))
+    ));
+
+    // fiecare tip scris de o imagine e unul din `Record::KINDS`
+    let kinds: Vec<&str> = LogReader::open(&image)?
+        .map(|item| item.map(|(_, r, _)| r.kind()))
+        .collect::<Result<_>>()?;
+    assert!(kinds.iter().all(|k| Record::KINDS.contains(k)), "{kinds:?}");
+
+    // (cod de iesire, stdout)
+    let dump = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_vfsctl"))
+            .arg("dump-log")
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .map(|out| {
+                (
+                    out.status.code(),
+                    String::from_utf8_lossy(&out.stdout).into_owned(),
+                )
+            })
+    };
+    for args in [
+        &["img.vfs", "--type", "data_writ"][..],
+        &["img.vfs", "--type", "data_write,"],
+        &["img.vfs", "--from"],
+        &["img.vfs", "--inode", "three"],
+        &["img.vfs", "--to", "-1"],
+        &["img.vfs", "--bogus", "1"],
+    ] {
+        assert_eq!(dump(args)?.0, Some(2), "{args:?}");
+    }
+    for args in [&["text.vfs"][..], &["empty.vfs"], &["missing.vfs"]] {
+        let (code, out) = dump(args)?;
+        assert_eq!(code, Some(1), "{args:?}");
+        assert!(out.is_empty(), "{args:?}: {out}");
+    }
+    let (code, out) = dump(&["img.vfs", "--type", "data_write,inode_alloc"])?;
+    assert_eq!(code, Some(0));
+    assert_eq!(
+        out.lines().count(),
+        kinds
+            .iter()
+            .filter(|k| **k == "data_write" || **k == "inode_alloc")
+            .count()
+    );
+
+    // un record stricat: exit 1 doar daca eroarea e in intervalul afisat
+    let mut bytes = std::fs::read(&image)?;
+    let at = bytes.windows(6).position(|w| w == b"victim").unwrap();
+    bytes[at] ^= 1;
+    std::fs::write(&image, &bytes)?;
+    let (code, out) = dump(&["img.vfs"])?;
+    assert_eq!(code, Some(1));
+    assert_eq!(out.lines().filter(|l| l.contains("!!")).count(), 1);
+    let (code, out) = dump(&["img.vfs", "--from", &at.to_string()])?;
+    assert_eq!(code, Some(0), "{out}");
+    assert!(!out.is_empty() && !out.contains("!!"), "{out}");
+    let (code, out) = dump(&["img.vfs", "--from", &bytes.len().to_string()])?;
+    assert_eq!((code, out.as_str()), (Some(0), ""));
+    Ok(())
+}
+
+// output-ul shell-ului, citibil si dupa ce shell-ul l-a luat
+#[derive(Clone, Default)]
+struct SharedBuf(Rc<std::cell::RefCell<Vec<u8>>>);
+
+impl Write for SharedBuf {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn shell_runs_scripts_and_completes_paths() -> Result<()> {
+    use virtual_file_system::shell::Shell;
+
+    let path = "target/shell.vfs";
+    let _ = std::fs::remove_file(path);
+    let out = SharedBuf::default();
+    let mut shell = Shell::new(Vfs::mount(path)?, out.clone());
+
+    let script = r#"
+# comentariu
+mkdir -p src/bin
+cd src
+write bin/main.rs "fn main() {}"
+append bin/main.rs // end
+cat bin/main.rs
+mv bin/main.rs .
+ls
+rm bin
+cat missing.rs
+cd /
+history
+checkpoint
+"#;
+    let failed = shell.run_script(script.as_bytes())?;
+    assert_eq!(failed, 1);
+    let text = String::from_utf8(out.0.borrow().clone()).unwrap();
+    assert!(
+        text.starts_with("fn main() {}\n// end\nbin/\nmain.rs\n"),
+        "{text}"
+    );
+    assert!(text.contains("line 11: not found"), "{text}");
+    assert!(text.contains("   2  cd src\n"), "{text}");
+
+    assert_eq!(shell.prompt(), "/> ");
+    let (start, found) = shell.complete("cat src/m", 9);
+    assert_eq!((start, found), (4, vec!["src/main.rs".to_string()]));
+    assert_eq!(shell.complete("ch", 2).1, vec!["checkpoint".to_string()]);
+    assert!(shell.execute("cd src").is_ok());
+    assert_eq!(shell.complete("ls ", 3).1, vec!["main.rs".to_string()]);
+
+    let v = shell.into_vfs();
+    assert!(!v.exists("/src/bin"));
+    assert_eq!(read_all(&v, "/src/main.rs")?, "fn main() {}\n// end\n");
+    Ok(())
+}
+
+#[test]
+fn shell_reports_errors_and_keeps_the_image_intact() -> Result<()> {
+    use virtual_file_system::shell::{Shell, ShellError};
+
+    let path = "target/shell_errors.vfs";
+    let _ = std::fs::remove_file(path);
+    let out = SharedBuf::default();
+    let mut shell = Shell::new(Vfs::mount(path)?, out.clone());
+    for line in ["mkdir -p d/sub", "write d/f x", "cd d/sub"] {
+        shell
+            .execute(line)
+            .map_err(|e| VfsError::Unsupported(e.to_string()))?;
+    }
+
+    let usage = |r: std::result::Result<_, ShellError>| match r {
+        Err(ShellError::Usage(u)) => u,
+        other => panic!("{other:?}"),
+    };
+    assert_eq!(usage(shell.execute("cd a b")), "cd [dir]");
+    assert_eq!(usage(shell.execute("pwd x")), "pwd ");
+    assert_eq!(usage(shell.execute("history 3")), "history ");
+    assert_eq!(usage(shell.execute("checkpoint now")), "checkpoint ");
+    assert_eq!(usage(shell.execute("ls -a")), "ls [-l] [path]...");
+    assert_eq!(usage(shell.execute("rm -r")), "rm [-r] <path>...");
+    assert_eq!(usage(shell.execute("write")), "write <path> <text>...");
+    assert_eq!(usage(shell.execute("mv ../f")), "mv <from> <to>");
+    assert_eq!(usage(shell.execute("cat 'open")), "unterminated quote");
+    assert!(matches!(shell.execute("frob"), Err(ShellError::UnknownCommand(c)) if c == "frob"));
+
+    // `/`, `.` si `..` nu se sterg, nici cu `-r`
+    for line in ["rm -r /", "rm -r .", "rm -r ..", "rm -r ../.."] {
+        assert!(
+            matches!(
+                shell.execute(line),
+                Err(ShellError::Vfs(VfsError::InvalidPath(_)))
+            ),
+            "{line}"
+        );
+    }
+    let vfs_err = |r: std::result::Result<_, ShellError>| match r {
+        Err(ShellError::Vfs(e)) => e,
+        other => panic!("{other:?}"),
+    };
+    assert!(matches!(
+        vfs_err(shell.execute("cd /missing")),
+        VfsError::NotFound(_)
+    ));
+    assert!(matches!(
+        vfs_err(shell.execute("mkdir -p ../f/x")),
+        VfsError::NotADir(_)
+    ));
+    assert!(matches!(
+        vfs_err(shell.execute("write /d text")),
+        VfsError::NotAFile(_)
+    ));
+    assert!(matches!(
+        vfs_err(shell.execute("rm /d")),
+        VfsError::InvalidPath(_)
+    ));
+    assert_eq!(shell.prompt(), "/d/sub> ");
+
+    // in script erorile se numara, iar `exit` opreste restul
+    let failed = shell.run_script("frob\npwd\nrm -r /\nexit\nrm -r /d\n".as_bytes())?;
+    assert_eq!(failed, 2);
+    let text = String::from_utf8(out.0.borrow().clone()).unwrap();
+    assert_eq!(
+        text,
+        "line 1: unknown command: frob\n/d/sub\nline 3: invalid path: refusing to remove /\n"
+    );
+    let v = shell.into_vfs();
+    assert!(v.exists("/d/sub") && !v.exists("/d/f/x"));
+    assert_eq!(read_all(&v, "/d/f")?, "x\n");
+    Ok(())
+}
+
+// o cerere http pe o conexiune keep-alive; intoarce (status, header-e, corp)
+#[cfg(feature = "webdav")]
+fn dav(
+    conn: &mut std::io::BufReader<std::net::TcpStream>,
+    request: &str,
+    body: &[u8],
+) -> std::io::Result<(u16, String, Vec<u8>)> {
+    use std::io::BufRead;
+
+    let head = request.replace('\n', "\r\n");
+    let stream = conn.get_mut();
+    stream.write_all(format!("{head}Content-Length: {}\r\n\r\n", body.len()).as_bytes())?;
+    stream.write_all(body)?;
+
+    let mut headers = String::new();
+    loop {
+        let mut line = String::new();
+        conn.read_line(&mut line)?;
+        if line == "\r\n" || line.is_empty() {
+            break;
+        }
+        headers.push_str(&line);
+    }
+    let status = headers[9..12].parse().unwrap();
+    let len: usize = headers
+        .lines()
+        .find_map(|l| l.strip_prefix("Content-Length: "))
+        .unwrap()
+        .parse()
+        .unwrap();
+    let mut body = vec![0u8; if request.starts_with("HEAD") { 0 } else { len }];
+    conn.read_exact(&mut body)?;
+    Ok((status, headers, body))
+}
+
+#[cfg(feature = "webdav")]
+#[test]
+fn webdav_server_handles_a_localhost_client() -> Result<()> {
+    use std::io::BufReader;
+    use std::net::TcpStream;
+    use virtual_file_system::webdav::DavServer;
+
+    let path = "target/webdav.vfs";
+    let _ = std::fs::remove_file(path);
+
+    // Vfs nu e Send: serverul monteaza imaginea pe firul lui
+    let (tx, rx) = std::sync::mpsc::channel();
+    let server = std::thread::spawn(move || -> Result<()> {
+        let mut server = DavServer::bind(Vfs::mount(path)?, "127.0.0.1:0")?;
+        tx.send(server.local_addr()?).unwrap();
+        // o conexiune keep-alive si una care trimite corpul chunked
+        server.accept_one()?;
+        server.accept_one()
+    });
+    let addr = rx.recv().unwrap();
+    let mut conn = BufReader::new(TcpStream::connect(addr)?);
+
+    let (status, headers, _) = dav(&mut conn, "OPTIONS / HTTP/1.1\nHost: x\n", b"")?;
+    assert_eq!(status, 200);
+    assert!(headers.contains("DAV: 1\r\n"), "{headers}");
+
+    let put = |conn: &mut _, target: &str, body: &[u8]| {
+        dav(conn, &format!("PUT {target} HTTP/1.1\nHost: x\n"), body)
+    };
+    assert_eq!(put(&mut conn, "/docs/a.txt", b"x")?.0, 409);
+    assert_eq!(dav(&mut conn, "MKCOL /docs HTTP/1.1\n", b"")?.0, 201);
+    assert_eq!(dav(&mut conn, "MKCOL /docs HTTP/1.1\n", b"")?.0, 405);
+    assert_eq!(put(&mut conn, "/docs/hello%20world.txt", b"salut")?.0, 201);
+    assert_eq!(
+        put(&mut conn, "/docs/hello%20world.txt", b"hello, dav")?.0,
+        204
+    );
+
+    let (status, headers, body) = dav(&mut conn, "GET /docs/hello%20world.txt HTTP/1.1\n", b"")?;
+    assert_eq!((status, body.as_slice()), (200, &b"hello, dav"[..]));
+    assert!(headers.contains("Content-Type: text/plain"), "{headers}");
+    assert!(headers.contains(" GMT\r\n"), "{headers}");
+    let (status, _, body) = dav(&mut conn, "HEAD /docs/hello%20world.txt HTTP/1.1\n", b"")?;
+    assert_eq!((status, body.len()), (200, 0));
+    assert_eq!(dav(&mut conn, "GET /nope HTTP/1.1\n", b"")?.0, 404);
+
+    let (status, _, body) = dav(&mut conn, "PROPFIND /docs HTTP/1.1\nDepth: 1\n", b"")?;
+    let xml = String::from_utf8(body).unwrap();
+    assert_eq!(status, 207);
+    assert!(xml.contains("<D:href>/docs/</D:href>"), "{xml}");
+    assert!(xml.contains("<D:collection/>"), "{xml}");
+    assert!(
+        xml.contains("<D:href>/docs/hello%20world.txt</D:href>"),
+        "{xml}"
+    );
+    assert!(
+        xml.contains("<D:displayname>hello world.txt</D:displayname>"),
+        "{xml}"
+    );
+    assert!(
+        xml.contains("<D:getcontentlength>10</D:getcontentlength>"),
+        "{xml}"
+    );
+    let (_, _, body) = dav(&mut conn, "PROPFIND / HTTP/1.1\nDepth: 0\n", b"")?;
+    assert!(!String::from_utf8(body).unwrap().contains("docs"));
+
+    assert_eq!(put(&mut conn, "/b.txt", b"b")?.0, 201);
+    let mv = "MOVE /b.txt HTTP/1.1\nDestination: http://x/docs/hello%20world.txt\nOverwrite: F\n";
+    assert_eq!(dav(&mut conn, mv, b"")?.0, 412);
+    let mv = "MOVE /b.txt HTTP/1.1\nDestination: http://x/docs/hello%20world.txt\n";
+    assert_eq!(dav(&mut conn, mv, b"")?.0, 204);
+    let mv = "MOVE /docs HTTP/1.1\nDestination: /archive\n";
+    assert_eq!(dav(&mut conn, mv, b"")?.0, 201);
+    let (_, _, body) = dav(&mut conn, "GET /archive/hello%20world.txt HTTP/1.1\n", b"")?;
+    assert_eq!(body, b"b");
+
+    assert_eq!(put(&mut conn, "/archive/sub/c.txt", b"")?.0, 409);
+    assert_eq!(dav(&mut conn, "MKCOL /archive/sub HTTP/1.1\n", b"")?.0, 201);
+    assert_eq!(put(&mut conn, "/archive/sub/c.txt", b"c")?.0, 201);
+    // mutarea esueaza (destinatia e in sursa): destinatia veche ramane intreaga
+    let mv = "MOVE /archive HTTP/1.1\nDestination: /archive/sub\n";
+    assert_eq!(dav(&mut conn, mv, b"")?.0, 400);
+    let (status, _, body) = dav(&mut conn, "GET /archive/sub/c.txt HTTP/1.1\n", b"")?;
+    assert_eq!((status, body.as_slice()), (200, &b"c"[..]));
+    let (_, _, body) = dav(&mut conn, "PROPFIND /archive HTTP/1.1\nDepth: 1\n", b"")?;
+    assert!(!String::from_utf8(body).unwrap().contains("dav-move"));
+    assert_eq!(dav(&mut conn, "DELETE /archive HTTP/1.1\n", b"")?.0, 204);
+    assert_eq!(dav(&mut conn, "LOCK /x HTTP/1.1\n", b"")?.0, 501);
+    drop(conn);
+
+    // corp chunked, conexiune inchisa de server dupa raspuns
+    let mut stream = TcpStream::connect(addr)?;
+    stream.write_all(
+        b"PUT /chunked.bin HTTP/1.1\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n\
+          5\r\nhello\r\n7;ext=1\r\n, world\r\n0\r\n\r\n",
+    )?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply)?;
+    assert!(reply.starts_with("HTTP/1.1 201 Created\r\n"), "{reply}");
+
+    server.join().unwrap()?;
+    let v = Vfs::mount(path)?;
+    assert_eq!(read_all(&v, "/chunked.bin")?, "hello, world");
+    assert!(!v.exists("/archive"));
+    assert!(!v.exists("/b.txt"));
+    Ok(())
+}
+
+#[cfg(feature = "webdav")]
+#[test]
+fn webdav_server_rejects_bad_requests() -> Result<()> {
+    use std::io::BufReader;
+    use std::net::TcpStream;
+    use virtual_file_system::webdav::DavServer;
+
+    let path = "target/webdav_errors.vfs";
+    let _ = std::fs::remove_file(path);
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("docs")?;
+    v.create("docs/a.txt")?.write_all(b"a")?;
+    drop(v);
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let server = std::thread::spawn(move || -> Result<()> {
+        let mut server = DavServer::bind(Vfs::mount(path)?, "127.0.0.1:0")?;
+        tx.send(server.local_addr()?).unwrap();
+        for _ in 0..5 {
+            server.accept_one()?;
+        }
+        drop(server);
+        let read_only = MountOptions {
+            read_only: true,
+            ..Default::default()
+        };
+        let mut server = DavServer::bind(Vfs::mount_with(path, read_only)?, "127.0.0.1:0")?;
+        tx.send(server.local_addr()?).unwrap();
+        server.accept_one()
+    });
+    let addr = rx.recv().unwrap();
+    let mut conn = BufReader::new(TcpStream::connect(addr)?);
+    let req = |conn: &mut _, request: &str| dav(conn, request, b"").map(|r| r.0);
+
+    // `..` nu ajunge la root pe ocolite
+    assert_eq!(req(&mut conn, "DELETE /docs/.. HTTP/1.1\n")?, 403);
+    assert_eq!(req(&mut conn, "DELETE /docs/%2E%2E/.. HTTP/1.1\n")?, 403);
+    let mv = "MOVE /docs/.. HTTP/1.1\nDestination: /other\n";
+    assert_eq!(req(&mut conn, mv)?, 403);
+    let mv = "MOVE /docs HTTP/1.1\nDestination: /docs/../x/..\n";
+    assert_eq!(req(&mut conn, mv)?, 403);
+    assert_eq!(req(&mut conn, "GET /x/../docs/a.txt HTTP/1.1\n")?, 200);
+
+    assert_eq!(req(&mut conn, "GET /%zz HTTP/1.1\n")?, 400);
+    assert_eq!(req(&mut conn, "DELETE / HTTP/1.1\n")?, 403);
+    assert_eq!(req(&mut conn, "DELETE /missing HTTP/1.1\n")?, 404);
+    assert_eq!(req(&mut conn, "PROPFIND /missing HTTP/1.1\n")?, 404);
+    assert_eq!(req(&mut conn, "MKCOL /missing/sub HTTP/1.1\n")?, 409);
+    assert_eq!(req(&mut conn, "MKCOL /docs/a.txt HTTP/1.1\n")?, 405);
+    assert_eq!(req(&mut conn, "PUT /docs HTTP/1.1\n")?, 405);
+    assert_eq!(req(&mut conn, "PUT /docs/a.txt/x HTTP/1.1\n")?, 409);
+    assert_eq!(req(&mut conn, "MOVE /docs HTTP/1.1\n")?, 400);
+    let mv = "MOVE /missing HTTP/1.1\nDestination: /x\n";
+    assert_eq!(req(&mut conn, mv)?, 404);
+    let mv = "MOVE /docs/a.txt HTTP/1.1\nDestination: /docs/a.txt\n";
+    assert_eq!(req(&mut conn, mv)?, 403);
+    let mv = "MOVE /docs/a.txt HTTP/1.1\nDestination: /missing/a.txt\n";
+    assert_eq!(req(&mut conn, mv)?, 409);
+    drop(conn);
+
+    // cereri stricate: 400, apoi serverul inchide conexiunea
+    for raw in [
+        &b"PUT /docs/b.txt HTTP/1.1\r\nContent-Length: lots\r\n\r\n"[..],
+        b"GET /docs/a.txt\r\n\r\n",
+    ] {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(raw)?;
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply)?;
+        assert!(reply.starts_with("HTTP/1.1 400 Bad Request\r\n"), "{reply}");
+    }
+
+    // un corp taiat de client nu atinge fisierul vechi si nu lasa resturi
+    for target in ["/docs/a.txt", "/docs/b.txt"] {
+        let mut stream = TcpStream::connect(addr)?;
+        stream.write_all(
+            format!("PUT {target} HTTP/1.1\r\nContent-Length: 100\r\n\r\npartial").as_bytes(),
+        )?;
+        stream.shutdown(std::net::Shutdown::Write)?;
+        let mut reply = String::new();
+        stream.read_to_string(&mut reply)?;
+        assert!(!reply.starts_with("HTTP/1.1 2"), "{reply}");
+    }
+
+    // pe o imagine read-only nicio scriere nu trece
+    let addr = rx.recv().unwrap();
+    let mut conn = BufReader::new(TcpStream::connect(addr)?);
+    assert_eq!(dav(&mut conn, "PUT /docs/a.txt HTTP/1.1\n", b"b")?.0, 403);
+    assert_eq!(req(&mut conn, "MKCOL /new HTTP/1.1\n")?, 403);
+    assert_eq!(req(&mut conn, "DELETE /docs/a.txt HTTP/1.1\n")?, 403);
+    let mv = "MOVE /docs/a.txt HTTP/1.1\nDestination: /b.txt\n";
+    assert_eq!(req(&mut conn, mv)?, 403);
+    assert_eq!(req(&mut conn, "GET /docs/a.txt HTTP/1.1\n")?, 200);
+    drop(conn);
+
+    server.join().unwrap()?;
+    let v = Vfs::mount(path)?;
+    assert_eq!(read_all(&v, "/docs/a.txt")?, "a");
+    assert!(!v.exists("/docs/b.txt") && !v.exists("/b.txt") && !v.exists("/x"));
+    let names: Vec<String> = v
+        .read_dir("/docs")?
+        .map(|e| e.map(|e| e.name))
+        .collect::<Result<_>>()?;
+    assert_eq!(names, ["a.txt"]);
+    Ok(())
+}
+
+// transport 9p in acelasi proces: fiecare mesaj intreg scris ajunge direct la server
+struct Loopback {
+    server: virtual_file_system::ninep::NinepServer,
+    pending: Vec<u8>,
+    replies: std::collections::VecDeque<u8>,
+}
+
+impl Write for Loopback {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        while self.pending.len() >= 4 {
+            let size = u32::from_le_bytes(self.pending[..4].try_into().unwrap()) as usize;
+            if self.pending.len() < size {
+                break;
+            }
+            let frame: Vec<u8> = self.pending.drain(..size).collect();
+            self.replies.extend(self.server.handle(&frame));
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Loopback {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.replies.read(buf)
+    }
+}
+
+#[test]
+fn ninep_server_serves_an_in_process_client() -> Result<()> {
+    use virtual_file_system::ninep::*;
+
+    let path = "target/ninep.vfs";
+    let _ = std::fs::remove_file(path);
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("/etc")?;
+    v.create("/etc/hosts")?
+        .write_all(b"127.0.0.1 localhost\n")?;
+
+    // codec-ul e simetric
+    let msg = Tmsg::Walk {
+        fid: 1,
+        newfid: 2,
+        names: vec!["etc".into(), "hosts".into()],
+    };
+    assert_eq!(Tmsg::decode(&msg.encode(7))?, (7, msg));
+    let reply = Rmsg::Lopen {
+        qid: Qid {
+            kind: 0x80,
+            version: 3,
+            path: 9,
+        },
+        iounit: 4096,
+    };
+    assert_eq!(Rmsg::decode(&reply.encode(7))?, (7, reply));
+    assert!(Tmsg::decode(&[7, 0, 0, 0, 250, 1, 0]).is_err());
+
+    let mut c = Client::connect(Loopback {
+        server: NinepServer::new(v.clone()),
+        pending: Vec::new(),
+        replies: Default::default(),
+    })?;
+    let errno = |r: Result<Rmsg>| match r {
+        Err(VfsError::Io(e)) => e.raw_os_error(),
+        other => panic!("expected an errno, got {other:?}"),
+    };
+
+    assert!(c.attach(0)?.is_dir());
+    let qids = c.walk(0, 1, &["etc", "hosts"])?;
+    assert!(qids[0].is_dir() && !qids[1].is_dir());
+    assert!(c.walk(0, 9, &["etc", "missing"]).is_err());
+    let partial = c.call(&Tmsg::Walk {
+        fid: 0,
+        newfid: 9,
+        names: vec!["etc".into(), "missing".into()],
+    })?;
+    assert!(matches!(partial, Rmsg::Walk { qids } if qids.len() == 1));
+    assert_eq!(errno(c.call(&Tmsg::Clunk { fid: 9 })), Some(9));
+
+    // Tread inainte de Tlopen e EBADF
+    assert_eq!(
+        errno(c.call(&Tmsg::Read {
+            fid: 1,
+            offset: 0,
+            count: 10
+        })),
+        Some(9)
+    );
+    c.lopen(1, 0)?;
+    assert_eq!(c.read(1, 10, 100)?, b"localhost\n");
+    let attr = c.getattr(1)?;
+    assert_eq!((attr.size, attr.mode & 0o170000), (20, 0o100000));
+    c.clunk(1)?;
+
+    // un fisier nou prin Tlcreate pe fid-ul directorului
+    c.walk(0, 2, &["etc"])?;
+    c.lcreate(2, "motd", 2, 0o640)?;
+    assert_eq!(c.write(2, 0, b"hello")?, 5);
+    assert_eq!(c.write(2, 5, b", 9p")?, 4);
+    c.clunk(2)?;
+    assert_eq!(read_all(&v, "/etc/motd")?, "hello, 9p");
+    assert_eq!(v.metadata("/etc/motd")?.mode, 0o640);
+
+    c.call(&Tmsg::Mkdir {
+        dfid: 0,
+        name: "var".into(),
+        mode: 0o755,
+        gid: 0,
+    })?;
+    c.walk(0, 3, &[])?;
+    c.lopen(3, 0)?;
+    let names: Vec<String> = c.readdir_all(3)?.into_iter().map(|e| e.name).collect();
+    assert_eq!(names, [".", "..", "etc", "var"]);
+    c.clunk(3)?;
+
+    // fid-ul tine inode-ul: dupa rename citeste tot fisierul mutat
+    c.walk(0, 4, &["etc", "motd"])?;
+    c.call(&Tmsg::Renameat {
+        olddfid: 0,
+        oldname: "etc".into(),
+        newdfid: 0,
+        newname: "config".into(),
+    })?;
+    c.call(&Tmsg::Setattr {
+        fid: 4,
+        attr: SetAttr {
+            valid: setattr::SIZE | setattr::MTIME | setattr::MTIME_SET,
+            size: 5,
+            mtime: (1_000, 0),
+            ..Default::default()
+        },
+    })?;
+    c.lopen(4, 0)?;
+    assert_eq!(c.read(4, 0, 100)?, b"hello");
+    assert_eq!(c.getattr(4)?.mtime, (1_000, 0));
+    c.clunk(4)?;
+    assert!(v.exists("/config/motd") && !v.exists("/etc"));
+
+    let unlink = |name: &str, flags| Tmsg::Unlinkat {
+        dfid: 0,
+        name: name.into(),
+        flags,
+    };
+    assert_eq!(errno(c.call(&unlink("config", 0x200))), Some(39));
+    assert_eq!(errno(c.call(&unlink("../x", 0))), Some(22));
+    c.call(&unlink("var", 0x200))?;
+    assert!(!v.exists("/var"));
+    assert!(matches!(
+        c.call(&Tmsg::Statfs { fid: 0 })?,
+        Rmsg::Statfs(s) if s.files == 4
+    ));
+    Ok(())
+}
+
+#[test]
+fn ninep_walk_readdir_lopen_and_frame_edge_cases() -> Result<()> {
+    use virtual_file_system::ninep::*;
+
+    let path = "target/ninep_edges.vfs";
+    let _ = std::fs::remove_file(path);
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("/etc")?;
+    v.create("/etc/hosts")?
+        .write_all(b"127.0.0.1 localhost\n")?;
+    v.create_dir("/many")?;
+    let mut expected = vec![".".to_string(), "..".to_string()];
+    for i in 0..40 {
+        let name = format!("entry-{i:02}");
+        v.create(format!("/many/{name}"))?;
+        expected.push(name);
+    }
+
+    let mut c = Client::connect(Loopback {
+        server: NinepServer::new(v.clone()),
+        pending: Vec::new(),
+        replies: Default::default(),
+    })?;
+    let errno = |r: Result<Rmsg>| match r {
+        Err(VfsError::Io(e)) => e.raw_os_error(),
+        other => panic!("expected an errno, got {other:?}"),
+    };
+    let walk = |fid, newfid, names: &[&str]| Tmsg::Walk {
+        fid,
+        newfid,
+        names: names.iter().map(|n| n.to_string()).collect(),
+    };
+    let root = c.attach(0)?;
+
+    // `..` urca, iar in radacina ramane pe loc
+    assert_eq!(c.walk(0, 1, &[".."])?, [root]);
+    let qids = c.walk(0, 2, &["etc", "..", "etc", "hosts"])?;
+    assert_eq!(qids[1], root);
+    assert!(!qids[3].is_dir());
+    // un pas lipsa in mijloc: qid-urile de pana la el, fara fid nou
+    assert!(matches!(
+        c.call(&walk(0, 3, &["etc", "missing", "hosts"]))?,
+        Rmsg::Walk { qids } if qids.len() == 1
+    ));
+    assert_eq!(errno(c.call(&Tmsg::Clunk { fid: 3 })), Some(9));
+    assert_eq!(errno(c.call(&walk(0, 3, &["missing", "etc"]))), Some(2));
+    // un fid nou deja folosit e EBADF
+    assert_eq!(errno(c.call(&walk(0, 2, &["etc"]))), Some(9));
+
+    // un nume cu `/` (sau gol) nu e o componenta, nici macar `/many` sau `hosts/`
+    c.walk(0, 4, &["etc"])?;
+    for bad in ["/many", "hosts/", "etc/hosts", ""] {
+        assert_eq!(errno(c.call(&walk(4, 5, &[bad]))), Some(22), "{bad:?}");
+    }
+    assert!(matches!(
+        c.call(&walk(0, 5, &["etc", "/many"]))?,
+        Rmsg::Walk { qids } if qids.len() == 1
+    ));
+    assert_eq!(errno(c.call(&Tmsg::Clunk { fid: 5 })), Some(9));
+
+    // Tlopen cu O_TRUNC taie fisierul; un fid deschis nu se redeschide
+    c.lopen(2, 1 | 0o1000)?;
+    assert_eq!(v.metadata("/etc/hosts")?.size, 0);
+    assert_eq!(c.write(2, 0, b"::1")?, 3);
+    assert_eq!(errno(c.call(&Tmsg::Lopen { fid: 2, flags: 0 })), Some(9));
+    c.clunk(2)?;
+    assert_eq!(read_all(&v, "/etc/hosts")?, "::1");
+    // un director nu se deschide pt scriere
+    assert_eq!(
+        errno(c.call(&Tmsg::Lopen {
+            fid: 4,
+            flags: 1 | 0o1000
+        })),
+        Some(21)
+    );
+
+    // Treaddir la un msize mic: fiecare raspuns incape, paginile se leaga fara gauri
+    let msize = 100;
+    assert!(matches!(
+        c.call(&Tmsg::Version {
+            msize,
+            version: VERSION.into()
+        })?,
+        Rmsg::Version { msize: m, .. } if m == msize
+    ));
+    c.attach(0)?;
+    c.walk(0, 1, &["many"])?;
+    c.lopen(1, 0)?;
+    let (mut names, mut offset, mut pages) = (Vec::new(), 0, 0);
+    loop {
+        let reply = c.call(&Tmsg::Readdir {
+            fid: 1,
+            offset,
+            count: u32::MAX,
+        })?;
+        assert!(reply.encode(0).len() <= msize as usize);
+        let Rmsg::Readdir { data } = reply else {
+            panic!("expected Rreaddir, got {reply:?}");
+        };
+        let page = Dirent::decode_all(&data)?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        offset = last.offset;
+        pages += 1;
+        names.extend(page.into_iter().map(|e| e.name));
+    }
+    assert_eq!(names, expected);
+    assert!(pages > 10);
+    // doua intrari de 32 de bytes: `count` exact le da pe amandoua, unul mai putin doar una
+    let page = |c: &mut Client<Loopback>, count| -> Result<usize> {
+        match c.call(&Tmsg::Readdir {
+            fid: 1,
+            offset: 2,
+            count,
+        })? {
+            Rmsg::Readdir { data } => Ok(Dirent::decode_all(&data)?.len()),
+            other => panic!("expected Rreaddir, got {other:?}"),
+        }
+    };
+    assert_eq!(page(&mut c, 64)?, 2);
+    assert_eq!(page(&mut c, 63)?, 1);
+
+    // mesaje stricate intr-un cadru bun primesc Rlerror, cu tag-ul lor daca se poate citi
+    let mut server = NinepServer::new(v.clone());
+    let mut reply = |frame: &[u8]| Rmsg::decode(&server.handle(frame));
+    let einval = Rmsg::Lerror { ecode: 22 };
+    assert_eq!(
+        reply(&[7, 0, 0, 0, 250, 5, 0])?,
+        (5, Rmsg::Lerror { ecode: 95 })
+    );
+    assert_eq!(reply(&[5, 0, 0, 0, 120])?, (NOTAG, einval.clone()));
+    let mut trailing = Tmsg::Clunk { fid: 1 }.encode(6);
+    trailing.push(0);
+    trailing[0] += 1;
+    assert_eq!(reply(&trailing)?, (6, einval.clone()));
+    let mut short = walk(0, 1, &["etc"]).encode(8);
+    short.truncate(short.len() - 1);
+    short[0] -= 1;
+    assert_eq!(reply(&short)?, (8, einval));
+
+    // iar cadrele cu marimea gresita sau taiate opresc conexiunea
+    let frame = |bytes: &[u8]| read_frame(&mut &bytes[..], 8192);
+    assert!(frame(&[])?.is_none());
+    assert!(frame(&[6, 0, 0, 0, 0, 0]).is_err());
+    assert!(frame(&u32::MAX.to_le_bytes()).is_err());
+    assert!(frame(&[20, 0, 0, 0, 100]).is_err());
+    assert!(frame(&[20, 0]).is_err());
+
+    struct Pipe(std::io::Cursor<Vec<u8>>, Vec<u8>);
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.1.write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut input = Tmsg::Version {
+        msize: 4096,
+        version: VERSION.into(),
+    }
+    .encode(NOTAG);
+    input.extend(
+        Tmsg::Write {
+            fid: 0,
+            offset: 0,
+            data: vec![0; 8000],
+        }
+        .encode(1),
+    );
+    let mut pipe = Pipe(std::io::Cursor::new(input), Vec::new());
+    assert!(NinepServer::new(v.clone()).serve(&mut pipe).is_err());
+    assert_eq!(
+        Rmsg::decode(&pipe.1)?,
+        (
+            NOTAG,
+            Rmsg::Version {
+                msize: 4096,
+                version: VERSION.into()
+            }
+        )
+    );
+    Ok(())
+}
+
+// acelasi cod, scris o data pt orice `FileSystem`
+fn exercise_filesystem<F: virtual_file_system::FileSystem>(fs: &F) -> Result<()> {
+    use virtual_file_system::FsFile;
+
+    fs.create_dir_all("/app/data/cache")?;
+    fs.create_dir_all("/app/data")?;
+    fs.create("/app/data/config.toml")?
+        .write_all(b"name = \"demo\"\n")?;
+    fs.create("/app/data/log.txt")?.write_all(b"first run\n")?;
+
+    let mut f = fs.create("/app/data/log.txt")?;
+    assert!(f.is_empty()?);
+    f.write_all(b"0123456789")?;
+    f.seek(SeekFrom::Start(2))?;
+    f.write_all(b"xx")?;
+    f.set_len(6)?;
+    drop(f);
+    let mut text = String::new();
+    fs.open("app/./data/../data/log.txt")?
+        .read_to_string(&mut text)?;
+    assert_eq!(text, "01xx45");
+
+    let names = |dir| -> Result<Vec<(String, NodeKind)>> {
+        Ok(fs
+            .read_dir(dir)?
+            .into_iter()
+            .map(|e| (e.name, e.kind))
+            .collect())
+    };
+    assert_eq!(
+        names("/app/data")?,
+        [
+            ("cache".to_string(), NodeKind::Dir),
+            ("config.toml".to_string(), NodeKind::File),
+            ("log.txt".to_string(), NodeKind::File),
+        ]
+    );
+    let meta = fs.metadata("/app/data/config.toml")?;
+    assert!(meta.is_file() && meta.metadata.size == 14);
+    assert!(fs.metadata("/app/data")?.is_dir());
+
+    // rename inlocuieste un fisier existent, ca std::fs::rename
+    fs.rename("/app/data/log.txt", "/app/data/config.toml")?;
+    assert!(!fs.exists("/app/data/log.txt"));
+    assert_eq!(fs.metadata("/app/data/config.toml")?.metadata.size, 6);
+    fs.rename("/app/data", "/app/state")?;
+    assert!(fs.exists("/app/state/cache") && !fs.exists("/app/data"));
+
+    assert!(matches!(fs.open("/missing"), Err(VfsError::NotFound(_))));
+    assert!(matches!(fs.open("/app/state"), Err(VfsError::NotAFile(_))));
+    assert!(matches!(
+        fs.read_dir("/app/state/config.toml"),
+        Err(VfsError::NotADir(_))
+    ));
+    assert!(matches!(fs.create("/nope/x"), Err(VfsError::NotFound(_))));
+    assert!(fs.remove("/app/state").is_err());
+    assert!(fs.open("/app/state/config.toml")?.write_all(b"x").is_err());
+
+    fs.remove("/app/state/config.toml")?;
+    fs.remove("/app/state/cache")?;
+    fs.remove("/app/state")?;
+    assert_eq!(names("/app")?, []);
+    Ok(())
+}
+
+// aceleasi erori pe fiecare backend; `fs` trebuie sa fie gol
+fn exercise_filesystem_errors<F: virtual_file_system::FileSystem>(fs: &F) -> Result<()> {
+    let is = |r: Result<()>, want: fn(&VfsError) -> bool, what: &str| match r {
+        Err(e) if want(&e) => {}
+        other => panic!("{what}: {other:?}"),
+    };
+    let invalid = |e: &VfsError| matches!(e, VfsError::InvalidPath(_));
+    let not_found = |e: &VfsError| matches!(e, VfsError::NotFound(_));
+    let not_dir = |e: &VfsError| matches!(e, VfsError::NotADir(_));
+    let not_file = |e: &VfsError| matches!(e, VfsError::NotAFile(_));
+
+    // radacina nu se sterge si nu se muta, nici pe ocolite
+    is(fs.remove("/"), invalid, "remove /");
+    is(fs.rename("/", "/x"), invalid, "rename /");
+    assert!(fs.metadata("/")?.is_dir());
+
+    fs.create_dir_all("/d/sub")?;
+    fs.create_dir_all("/full/x")?;
+    is(fs.remove("/d/sub/../.."), invalid, "remove /d/sub/../..");
+    is(fs.rename("/d/..", "/x"), invalid, "rename /d/..");
+    fs.create("/f")?.write_all(b"f")?;
+    is(
+        fs.create_dir_all("/f").map(drop),
+        not_dir,
+        "create_dir_all over a file",
+    );
+    is(
+        fs.create_dir_all("/f/sub"),
+        not_dir,
+        "create_dir_all under a file",
+    );
+    is(fs.create("/d").map(drop), not_file, "create over a dir");
+    is(fs.create("/f/x").map(drop), not_dir, "create under a file");
+    is(
+        fs.create("/missing/x").map(drop),
+        not_found,
+        "create under a missing dir",
+    );
+    is(fs.remove("/missing"), not_found, "remove missing");
+    is(fs.remove("/full"), invalid, "remove a full dir");
+    is(
+        fs.metadata("/missing").map(drop),
+        not_found,
+        "metadata of missing",
+    );
+
+    is(fs.rename("/missing", "/x"), not_found, "rename missing");
+    is(
+        fs.rename("/f", "/missing/f"),
+        not_found,
+        "rename under a missing dir",
+    );
+    is(fs.rename("/f", "/d"), not_file, "rename a file over a dir");
+    is(fs.rename("/d", "/f"), not_dir, "rename a dir over a file");
+    is(fs.rename("/d", "/full"), invalid, "rename over a full dir");
+    // destinatia nu se pierde cand mutarea nu poate reusi
+    is(fs.rename("/d", "/d/sub"), invalid, "rename into itself");
+    assert!(fs.metadata("/d/sub")?.is_dir());
+    assert!(fs.metadata("/full/x")?.is_dir());
+    assert_eq!(fs.metadata("/f")?.metadata.size, 1);
+    Ok(())
+}
+
+#[test]
+fn filesystem_trait_behaves_the_same_on_every_backend() -> Result<()> {
+    use virtual_file_system::{FileSystem, HostFs, MemFs};
+
+    let path = "target/fs_trait.vfs";
+    let _ = std::fs::remove_file(path);
+    exercise_filesystem(&Vfs::mount(path)?)?;
+
+    exercise_filesystem(&MemFs::new())?;
+
+    let host = "target/fs_trait_host";
+    let _ = std::fs::remove_dir_all(host);
+    std::fs::create_dir_all(host)?;
+    let fs = HostFs::new(host);
+    exercise_filesystem(&fs)?;
+    // `..` nu iese din radacina
+    fs.create("/../../escape.txt")?;
+    assert!(std::path::Path::new(host).join("escape.txt").exists());
+
+    let path = "target/fs_trait_errors.vfs";
+    let _ = std::fs::remove_file(path);
+    exercise_filesystem_errors(&Vfs::mount(path)?)?;
+    exercise_filesystem_errors(&MemFs::new())?;
+    let host = "target/fs_trait_errors_host";
+    let _ = std::fs::remove_dir_all(host);
+    std::fs::create_dir_all(host)?;
+    exercise_filesystem_errors(&HostFs::new(host))?;
+    assert!(std::path::Path::new(host).is_dir());
+
+    // peste o montare nu se muta, iar fisierul de acolo ramane
+    let mut vfs = Vfs::mount(path)?;
+    vfs.create_dir("m")?;
+    vfs.attach_host("m", host)?;
+    FileSystem::create(&vfs, "/m/x")?.write_all(b"host")?;
+    assert!(matches!(
+        FileSystem::rename(&vfs, "/f", "/m/x"),
+        Err(VfsError::CrossesMount(_))
+    ));
+    assert_eq!(std::fs::read(format!("{host}/x"))?, b"host");
+
+    // o mutare refuzata de permisiuni lasa destinatia cum era
+    let path = "target/fs_trait_perms.vfs";
+    let _ = std::fs::remove_file(path);
+    let mut vfs = Vfs::mount(path)?;
+    vfs.create_dir("ro")?;
+    vfs.create("ro/src")?.write_all(b"src")?;
+    vfs.create_dir("rw")?;
+    vfs.create("rw/dst")?.write_all(b"keep")?;
+    vfs.chown("rw", Some(1000), Some(1000))?;
+    vfs.chown("rw/dst", Some(1000), Some(1000))?;
+    drop(vfs);
+    let alice = MountOptions {
+        user: Some(Credentials::new(1000, 1000)),
+        ..Default::default()
+    };
+    let vfs = Vfs::mount_with(path, alice)?;
+    assert!(matches!(
+        FileSystem::rename(&vfs, "/ro/src", "/rw/dst"),
+        Err(VfsError::PermissionDenied(_))
+    ));
+    assert_eq!(read_all(&vfs, "rw/dst")?, "keep");
+    assert_eq!(read_all(&vfs, "ro/src")?, "src");
+    let names: Vec<String> = FileSystem::read_dir(&vfs, "/rw")?
+        .into_iter()
+        .map(|e| e.name)
+        .collect();
+    assert_eq!(names, ["dst"]);
+    // iar una reusita o inlocuieste fara sa lase ceva in urma
+    FileSystem::create(&vfs, "/rw/new")?.write_all(b"new")?;
+    FileSystem::rename(&vfs, "/rw/new", "/rw/dst")?;
+    assert_eq!(read_all(&vfs, "rw/dst")?, "new");
+    assert_eq!(FileSystem::read_dir(&vfs, "/rw")?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn overlay_copies_up_whiteouts_and_commits() -> Result<()> {
+    use virtual_file_system::OverlayVfs;
+
+    let (lower_path, upper_path, base_path) = (
+        "target/overlay_lower.vfs",
+        "target/overlay_upper.vfs",
+        "target/overlay_base.vfs",
+    );
+    for p in [lower_path, upper_path, base_path] {
+        let _ = std::fs::remove_file(p);
+    }
+    let mut lower = Vfs::mount(lower_path)?;
+    lower.create_dir("etc")?;
+    lower
+        .create("etc/hosts")?
+        .write_all(b"127.0.0.1 localhost\n")?;
+    lower.create("etc/motd")?.write_all(b"welcome\n")?;
+    lower.create_dir("var")?;
+    lower.create_dir("var/cache")?;
+    lower.create("var/cache/old.bin")?.write_all(b"stale")?;
+    lower.set_xattr("etc/hosts", "user.origin", b"lower")?;
+    lower.set_permissions("etc/motd", 0o600)?;
+    drop(lower);
+    let lower_before = std::fs::read(lower_path)?;
+
+    let lower = Vfs::mount_with(
+        lower_path,
+        MountOptions {
+            read_only: true,
+            ..Default::default()
+        },
+    )?;
+    let mut ov = OverlayVfs::new(lower, Vfs::mount(upper_path)?);
+    let names = |ov: &OverlayVfs, dir| -> Result<Vec<String>> {
+        Ok(ov.read_dir(dir)?.into_iter().map(|e| e.name).collect())
+    };
+
+    // copy-up la prima scriere; lower ramane neatins
+    assert!(!ov.upper().exists("etc/hosts"));
+    let mut f = ov.open_rw("/etc/hosts")?;
+    f.seek(SeekFrom::End(0))?;
+    f.write_all(b"10.0.0.1 db\n")?;
+    drop(f);
+    assert_eq!(
+        read_all(ov.upper(), "etc/hosts")?,
+        "127.0.0.1 localhost\n10.0.0.1 db\n"
+    );
+    assert_eq!(read_all(ov.lower(), "etc/hosts")?, "127.0.0.1 localhost\n");
+    assert_eq!(
+        ov.upper().get_xattr("etc/hosts", "user.origin")?.as_deref(),
+        Some(&b"lower"[..])
+    );
+
+    // stergere din lower = whiteout, invizibil in listari
+    ov.remove_file("etc/motd")?;
+    assert!(!ov.exists("etc/motd"));
+    assert!(ov.upper().exists("etc/motd"));
+    ov.create("etc/resolv.conf")?
+        .write_all(b"nameserver 1.1.1.1\n")?;
+    assert_eq!(names(&ov, "etc")?, ["hosts", "resolv.conf"]);
+    ov.create("etc/motd")?.write_all(b"new\n")?;
+    assert_eq!(names(&ov, "etc")?, ["hosts", "motd", "resolv.conf"]);
+    assert_eq!(ov.metadata("etc/motd")?.metadata.mode, DEFAULT_FILE_MODE);
+
+    // un director recreat dupa stergere nu mai arata continutul vechi
+    assert!(matches!(
+        ov.remove_dir("var/cache"),
+        Err(VfsError::InvalidPath(_))
+    ));
+    ov.remove_file("var/cache/old.bin")?;
+    ov.remove_dir("var/cache")?;
+    assert_eq!(names(&ov, "var")?, Vec::<String>::new());
+    ov.create_dir("var/cache")?;
+    assert_eq!(names(&ov, "var/cache")?, Vec::<String>::new());
+    assert!(!ov.exists("var/cache/old.bin"));
+
+    // rename muta tot subarborele in upper si lasa un whiteout in urma
+    ov.rename("etc", "config")?;
+    assert!(!ov.exists("etc") && !ov.exists("etc/hosts"));
+    assert_eq!(names(&ov, "/")?, ["config", "var"]);
+    assert_eq!(names(&ov, "config")?, ["hosts", "motd", "resolv.conf"]);
+    assert!(matches!(
+        ov.rename("var", "config"),
+        Err(VfsError::AlreadyExists(_))
+    ));
+    assert!(matches!(ov.open("etc/hosts"), Err(VfsError::NotFound(_))));
+
+    // commit: vederea combinata devine o imagine noua, fara marcaje overlay
+    assert!(matches!(
+        ov.commit(upper_path),
+        Err(VfsError::AlreadyExists(_))
+    ));
+    drop(ov.commit(base_path)?);
+    let base = Vfs::mount(base_path)?;
+    assert_eq!(
+        read_all(&base, "config/hosts")?,
+        "127.0.0.1 localhost\n10.0.0.1 db\n"
+    );
+    assert_eq!(read_all(&base, "config/motd")?, "new\n");
+    assert!(!base.exists("etc") && base.exists("var/cache"));
+    assert!(!base.exists("var/cache/old.bin"));
+    assert!(base.list_xattrs("config")?.is_empty());
+    assert_eq!(
+        base.get_xattr("config/hosts", "user.origin")?.as_deref(),
+        Some(&b"lower"[..])
+    );
+
+    drop(ov);
+    assert_eq!(std::fs::read(lower_path)?, lower_before);
+
+    // si prin trait-ul comun, peste un lower care are deja continut
+    let fresh = "target/overlay_fs_upper.vfs";
+    let _ = std::fs::remove_file(fresh);
+    let lower = Vfs::mount_with(
+        lower_path,
+        MountOptions {
+            read_only: true,
+            ..Default::default()
+        },
+    )?;
+    exercise_filesystem(&OverlayVfs::new(lower, Vfs::mount(fresh)?))?;
+    Ok(())
+}
+
+#[test]
+fn overlay_reports_errors_without_touching_lower() -> Result<()> {
+    use virtual_file_system::{FileSystem, OverlayVfs};
+
+    let paths = [
+        "target/overlay_err_lower.vfs",
+        "target/overlay_err_upper.vfs",
+        "target/overlay_err_empty_lower.vfs",
+        "target/overlay_err_empty_upper.vfs",
+        "target/overlay_err_base.vfs",
+    ];
+    for p in paths {
+        let _ = std::fs::remove_file(p);
+    }
+    let [lower_path, upper_path, empty_lower, empty_upper, base_path] = paths;
+    exercise_filesystem_errors(&OverlayVfs::new(
+        Vfs::mount(empty_lower)?,
+        Vfs::mount(empty_upper)?,
+    ))?;
+
+    let mut lower = Vfs::mount(lower_path)?;
+    lower.create_dir("d")?;
+    lower.create_dir("d/sub")?;
+    lower.create("d/f")?.write_all(b"lower")?;
+    lower.create_dir("full")?;
+    lower.create("full/x")?;
+    drop(lower);
+    let lower_before = std::fs::read(lower_path)?;
+    let read_only = || MountOptions {
+        read_only: true,
+        ..Default::default()
+    };
+    let mut ov = OverlayVfs::new(
+        Vfs::mount_with(lower_path, read_only())?,
+        Vfs::mount(upper_path)?,
+    );
+
+    let is = |r: Result<()>, want: fn(&VfsError) -> bool, what: &str| match r {
+        Err(e) if want(&e) => {}
+        other => panic!("{what}: {other:?}"),
+    };
+    let invalid = |e: &VfsError| matches!(e, VfsError::InvalidPath(_));
+    let not_dir = |e: &VfsError| matches!(e, VfsError::NotADir(_));
+    let not_file = |e: &VfsError| matches!(e, VfsError::NotAFile(_));
+    let exists = |e: &VfsError| matches!(e, VfsError::AlreadyExists(_));
+
+    // destinatia din lower nu dispare cand mutarea e refuzata
+    is(
+        FileSystem::rename(&ov, "/d", "/d/sub"),
+        invalid,
+        "rename into itself",
+    );
+    assert!(ov.metadata("/d/sub")?.is_dir());
+    is(
+        OverlayVfs::rename(&mut ov, "/d/f", "/full"),
+        exists,
+        "rename over an existing name",
+    );
+    is(
+        OverlayVfs::rename(&mut ov, "/d", "/"),
+        invalid,
+        "rename over the root",
+    );
+    is(ov.remove_dir("/"), invalid, "remove the root");
+    is(ov.remove_dir("/full"), invalid, "remove a full dir");
+    is(ov.remove_dir("/d/f"), not_dir, "remove_dir of a file");
+    is(ov.remove_file("/d"), not_file, "remove_file of a dir");
+    is(ov.open_rw("/d").map(drop), not_file, "open_rw of a dir");
+    is(ov.create("/d/f").map(drop), exists, "create over a file");
+    is(
+        ov.create("/d/f/x").map(drop),
+        not_dir,
+        "create under a file",
+    );
+    is(ov.read_dir("/d/f").map(drop), not_dir, "read_dir of a file");
+    assert!(matches!(ov.metadata("/gone"), Err(VfsError::NotFound(_))));
+    assert!(!ov.upper().exists("/d"), "a refused call copied up");
+
+    // commit nu suprascrie o imagine existenta
+    std::fs::write(base_path, "keep")?;
+    is(
+        ov.commit(base_path).map(drop),
+        exists,
+        "commit over an image",
+    );
+    assert_eq!(std::fs::read(base_path)?, b"keep");
+
+    // cu upper read-only, nici copy-up-ul nu are loc
+    let mut frozen = OverlayVfs::new(
+        Vfs::mount_with(lower_path, read_only())?,
+        Vfs::mount_with(upper_path, read_only())?,
+    );
+    let read_only_err = |e: &VfsError| matches!(e, VfsError::ReadOnly(_));
+    is(
+        frozen.set_permissions("/d/f", 0o600),
+        read_only_err,
+        "chmod",
+    );
+    is(frozen.remove_file("/d/f"), read_only_err, "remove");
+    is(frozen.create("/new").map(drop), read_only_err, "create");
+    assert!(frozen.exists("/d/f") && !frozen.upper().exists("/d"));
+
+    drop((ov, frozen));
+    assert_eq!(std::fs::read(lower_path)?, lower_before);
+
+    // o mutare refuzata in upper nu lasa un whiteout peste destinatia din lower
+    let (perm_lower, perm_upper) = (
+        "target/overlay_err_perm_lower.vfs",
+        "target/overlay_err_perm_upper.vfs",
+    );
+    let _ = std::fs::remove_file(perm_lower);
+    let _ = std::fs::remove_file(perm_upper);
+    let mut lower = Vfs::mount(perm_lower)?;
+    lower.create_dir("rw")?;
+    lower.create("rw/dst")?.write_all(b"lower")?;
+    let mut upper = Vfs::mount(perm_upper)?;
+    upper.create_dir("rw")?;
+    upper.chown("rw", Some(1000), Some(1000))?;
+    upper.create("rw/mine")?.write_all(b"upper")?;
+    upper.create_dir("ro")?;
+    upper.create("ro/src")?.write_all(b"src")?;
+    drop((lower, upper));
+    let alice = MountOptions {
+        user: Some(Credentials::new(1000, 1000)),
+        ..Default::default()
+    };
+    let ov = OverlayVfs::new(
+        Vfs::mount_with(perm_lower, read_only())?,
+        Vfs::mount_with(perm_upper, alice)?,
+    );
+    is(
+        FileSystem::rename(&ov, "/ro/src", "/rw/dst"),
+        |e| matches!(e, VfsError::PermissionDenied(_)),
+        "rename out of a read-only dir",
+    );
+    is(
+        FileSystem::rename(&ov, "/ro/src", "/rw/mine"),
+        |e| matches!(e, VfsError::PermissionDenied(_)),
+        "rename over an upper file",
+    );
+    for (path, want) in [
+        ("/rw/dst", "lower"),
+        ("/rw/mine", "upper"),
+        ("/ro/src", "src"),
+    ] {
+        let mut s = String::new();
+        ov.open(path)?.read_to_string(&mut s)?;
+        assert_eq!(s, want, "{path}");
+    }
+    let names: Vec<String> = ov.read_dir("/rw")?.into_iter().map(|e| e.name).collect();
+    assert_eq!(names, ["dst", "mine"]);
+    Ok(())
+}
+
+#[test]
+fn nested_mounts_cross_transparently_and_reject_cross_renames() -> Result<()> {
+    let (main_path, deps_path) = ("target/mount_main.vfs", "target/mount_deps.vfs");
+    let host = "target/mount_host";
+    let _ = std::fs::remove_file(main_path);
+    let _ = std::fs::remove_file(deps_path);
+    let _ = std::fs::remove_dir_all(host);
+    std::fs::create_dir_all(format!("{host}/src"))?;
+    std::fs::write(format!("{host}/src/main.rs"), "fn main() {}\n")?;
+
+    let deps = Vfs::mount(deps_path)?;
+    {
+        let mut deps = deps.clone();
+        deps.create_dir("serde")?;
+        deps.create("serde/lib.rs")?.write_all(b"pub mod de;\n")?;
+    }
+    let mut v = Vfs::mount(main_path)?;
+    v.create_dir("deps")?;
+    v.create("deps/shadowed.txt")?.write_all(b"hidden")?;
+    v.create_dir("host")?;
+    v.create("readme")?.write_all(b"top")?;
+
+    v.attach("/deps", deps.clone())?;
+    v.attach_host("host", host)?;
+    assert_eq!(
+        v.attachments()?
+            .iter()
+            .map(|p| p.as_str().to_string())
+            .collect::<Vec<_>>(),
+        ["/deps", "/host"]
+    );
+    assert!(matches!(
+        v.attach("deps", deps.clone()),
+        Err(VfsError::AlreadyExists(_))
+    ));
+    assert!(matches!(
+        v.attach("readme", deps.clone()),
+        Err(VfsError::NotADir(_))
+    ));
+    assert!(v.attach("/x", v.clone()).is_err());
+
+    // read_dir, open si metadata trec granita
+    let names = |v: &Vfs, dir| -> Result<Vec<String>> {
+        v.read_dir(dir)?.map(|e| e.map(|e| e.name)).collect()
+    };
+    assert_eq!(names(&v, "/deps")?, ["serde"]);
+    assert_eq!(read_all(&v, "/deps/serde/lib.rs")?, "pub mod de;\n");
+    assert!(!v.exists("deps/shadowed.txt"));
+    assert_eq!(v.metadata("deps/serde/lib.rs")?.size, 12);
+    assert_eq!(names(&v, "host")?, ["src"]);
+    assert_eq!(read_all(&v, "host/src/main.rs")?, "fn main() {}\n");
+    assert_eq!(v.metadata("/host/src/main.rs")?.size, 13);
+    assert!(matches!(
+        v.open_file("host/src"),
+        Err(VfsError::NotAFile(_))
+    ));
+    // `..` nu iese din montare
+    assert_eq!(read_all(&v, "/deps/../../serde/lib.rs")?, "pub mod de;\n");
+    v.set_current_dir("deps")?;
+    assert_eq!(names(&v, ".")?, ["serde"]);
+    v.set_current_dir("/")?;
+
+    // scrierile ajung in imaginea sau directorul montat
+    v.create("deps/serde/ser.rs")?
+        .write_all(b"pub mod ser;\n")?;
+    v.create_dir("host/out")?;
+    v.create("host/out/a.o")?.write_all(b"obj")?;
+    assert_eq!(std::fs::read(format!("{host}/out/a.o"))?, b"obj");
+    let mut f = v.open_rw("host/out/a.o")?;
+    f.seek(SeekFrom::End(0))?;
+    f.write_all(b"!")?;
+    drop(f);
+    assert_eq!(std::fs::read(format!("{host}/out/a.o"))?, b"obj!");
+    assert!(matches!(
+        v.create("host/out/a.o"),
+        Err(VfsError::AlreadyExists(_))
+    ));
+    assert_eq!(read_all(&deps, "serde/ser.rs")?, "pub mod ser;\n");
+
+    // rename in interiorul unei montari merge, peste granita nu
+    v.rename("deps/serde/ser.rs", "deps/serde/ser2.rs")?;
+    assert!(deps.exists("serde/ser2.rs"));
+    v.rename("host/out/a.o", "host/out/b.o")?;
+    assert!(std::path::Path::new(host).join("out/b.o").exists());
+    for (from, to) in [
+        ("readme", "deps/readme"),
+        ("deps/serde/lib.rs", "lib.rs"),
+        ("deps/serde/lib.rs", "host/lib.rs"),
+        ("host/out", "deps/out"),
+    ] {
+        assert!(matches!(v.rename(from, to), Err(VfsError::CrossesMount(_))));
+    }
+    assert!(v.exists("readme") && v.exists("deps/serde/lib.rs") && v.exists("host/out"));
+
+    v.remove_file("host/out/b.o")?;
+    v.remove_dir("host/out")?;
+    assert!(!std::path::Path::new(host).join("out").exists());
+
+    // dupa detach, directorul vechi reapare; nimic din montari nu e in log
+    v.detach("deps")?;
+    v.detach("/host")?;
+    assert!(matches!(v.detach("deps"), Err(VfsError::NotFound(_))));
+    assert_eq!(names(&v, "deps")?, ["shadowed.txt"]);
+    v.checkpoint()?;
+    drop(v);
+    let v = Vfs::mount(main_path)?;
+    assert_eq!(names(&v, "/")?, ["deps", "host", "readme"]);
+    assert_eq!(names(&v, "host")?, Vec::<String>::new());
+    assert_eq!(
+        names(&Vfs::mount(deps_path)?, "serde")?,
+        ["lib.rs", "ser2.rs"]
+    );
+    Ok(())
+}
+
+#[test]
+fn mounts_refuse_bad_points_and_forget_removed_ones() -> Result<()> {
+    let (main_path, inner_path, deep_path) = (
+        "target/mount_err_main.vfs",
+        "target/mount_err_inner.vfs",
+        "target/mount_err_deep.vfs",
+    );
+    let host = "target/mount_err_host";
+    for p in [main_path, inner_path, deep_path] {
+        let _ = std::fs::remove_file(p);
+    }
+    let _ = std::fs::remove_dir_all(host);
+    std::fs::create_dir_all(host)?;
+    std::fs::write(format!("{host}/file"), "f")?;
+
+    let mut v = Vfs::mount(main_path)?;
+    for dir in ["img", "h", "gone"] {
+        v.create_dir(dir)?;
+    }
+    let image = Vfs::mount(inner_path)?;
+    assert!(matches!(
+        v.attach("/", image.clone()),
+        Err(VfsError::InvalidPath(_))
+    ));
+    assert!(matches!(
+        v.attach("/missing", image.clone()),
+        Err(VfsError::NotFound(_))
+    ));
+    assert!(matches!(
+        v.attach_host("h", format!("{host}/missing")),
+        Err(VfsError::NotFound(_))
+    ));
+    assert!(matches!(
+        v.attach_host("h", format!("{host}/file")),
+        Err(VfsError::NotADir(_))
+    ));
+    v.attach("img", image.clone())?;
+    v.attach_host("h", host)?;
+
+    // prin host nu se ataseaza nimic; in imaginea atasata, da
+    assert!(matches!(
+        v.attach("h/sub", Vfs::mount(deep_path)?),
+        Err(VfsError::Unsupported(_))
+    ));
+    assert!(matches!(v.detach("h/sub"), Err(VfsError::NotFound(_))));
+    v.create_dir("img/deep")?;
+    v.attach("img/deep", Vfs::mount(deep_path)?)?;
+    assert_eq!(image.attachments()?, [VfsPath::new("/deep")?]);
+    v.detach("img/deep")?;
+    assert!(image.attachments()?.is_empty());
+
+    // punctul de montare nu se sterge si nu se recreeaza cat e ocupat
+    for at in ["img", "h"] {
+        assert!(
+            matches!(v.remove_dir(at), Err(VfsError::InvalidPath(_))),
+            "{at}"
+        );
+        assert!(
+            matches!(v.create_dir(at), Err(VfsError::AlreadyExists(_))),
+            "{at}"
+        );
+    }
+    assert!(std::path::Path::new(host).is_dir());
+    assert_eq!(v.attachments()?.len(), 2);
+
+    // un director sters de alt proces isi ia montarea cu el
+    let mut reader = Vfs::mount_with(
+        main_path,
+        MountOptions {
+            read_only: true,
+            ..Default::default()
+        },
+    )?;
+    reader.attach_host("gone", host)?;
+    reader.attach("img", image.clone())?;
+    v.remove_dir("gone")?;
+    reader.tail()?;
+    assert_eq!(reader.attachments()?, [VfsPath::new("/img")?]);
+    assert!(!reader.exists("gone/file"));
+    assert!(matches!(reader.detach("gone"), Err(VfsError::NotFound(_))));
+    v.create_dir("gone")?;
+    reader.tail()?;
+    assert!(reader.read_dir("gone")?.next().is_none());
+    assert_eq!(std::fs::read(format!("{host}/file"))?, b"f");
+    Ok(())
+}
+
+#[test]
+fn mounts_route_metadata_clones_and_cwd_through_the_attachment() -> Result<()> {
+    let (main_path, deps_path) = ("target/mount_meta_main.vfs", "target/mount_meta_deps.vfs");
+    let host = "target/mount_meta_host";
+    for p in [main_path, deps_path] {
+        let _ = std::fs::remove_file(p);
+    }
+    let _ = std::fs::remove_dir_all(host);
+    std::fs::create_dir_all(host)?;
+    std::fs::write(format!("{host}/file"), "f")?;
+
+    let mut deps = Vfs::mount(deps_path)?;
+    deps.create_dir("sub")?;
+    deps.create("lib.rs")?.write_all(b"pub fn f() {}")?;
+    let mut v = Vfs::mount(main_path)?;
+    v.create_dir("deps")?;
+    v.create("deps/hidden.txt")?.write_all(b"hidden")?;
+    v.create("src.txt")?.write_all(b"src")?;
+    v.create_dir("h")?;
+    v.attach("deps", deps.clone())?;
+    v.attach_host("h", host)?;
+
+    // clone_file nu scrie in directorul ascuns de montare
+    assert!(matches!(
+        v.clone_file("/src.txt", "/deps/x"),
+        Err(VfsError::CrossesMount(_))
+    ));
+    assert!(matches!(
+        v.clone_file("/deps/lib.rs", "/copy.rs"),
+        Err(VfsError::CrossesMount(_))
+    ));
+    v.clone_file("/deps/lib.rs", "/deps/sub/copy.rs")?;
+    assert_eq!(read_all(&deps, "sub/copy.rs")?, "pub fn f() {}");
+    assert!(matches!(
+        v.clone_file("/h/file", "/h/copy"),
+        Err(VfsError::Unsupported(_))
+    ));
+
+    // permisiunile, timpii, owner-ul si xattr-urile ajung in imaginea atasata
+    v.set_permissions("deps/lib.rs", 0o600)?;
+    v.chown("deps/lib.rs", Some(1000), Some(100))?;
+    v.set_times(
+        "deps/lib.rs",
+        FileTimes {
+            modified_at: Some(Timestamp(42)),
+            ..Default::default()
+        },
+    )?;
+    v.set_xattr("deps/lib.rs", "user.lang", b"rust")?;
+    let m = deps.metadata("lib.rs")?;
+    assert_eq!(
+        (m.mode, m.uid, m.gid, m.modified_at),
+        (0o600, 1000, 100, Timestamp(42))
+    );
+    assert_eq!(
+        deps.get_xattr("lib.rs", "user.lang")?,
+        Some(b"rust".to_vec())
+    );
+    assert_eq!(
+        v.get_xattr("deps/lib.rs", "user.lang")?,
+        Some(b"rust".to_vec())
+    );
+    assert_eq!(v.list_xattrs("deps/lib.rs")?, ["user.lang"]);
+    v.remove_xattr("deps/lib.rs", "user.lang")?;
+    assert!(deps.list_xattrs("lib.rs")?.is_empty());
+
+    // pe host: modul se aplica, xattr-urile nu exista
+    v.set_permissions("h/file", 0o640)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(format!("{host}/file"))?
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o7777, 0o640);
+    }
+    assert!(v.list_xattrs("h/file")?.is_empty());
+    assert_eq!(v.get_xattr("h/file", "user.x")?, None);
+    assert!(matches!(
+        v.set_xattr("h/file", "user.x", b"y"),
+        Err(VfsError::Unsupported(_))
+    ));
+    assert!(matches!(
+        v.list_xattrs("h/missing"),
+        Err(VfsError::NotFound(_))
+    ));
+
+    // cwd-ul intra doar pana la punctul de montare
+    assert!(matches!(
+        v.set_current_dir("/deps/sub"),
+        Err(VfsError::CrossesMount(_))
+    ));
+    v.set_current_dir("/deps")?;
+    assert_eq!(read_all(&v, "lib.rs")?, "pub fn f() {}");
+    assert!(matches!(
+        v.set_current_dir("sub"),
+        Err(VfsError::CrossesMount(_))
+    ));
+    v.set_current_dir("/")?;
+
+    // dupa detach, directorul de dedesubt e neatins
+    v.detach("deps")?;
+    let names: Vec<String> = v
+        .read_dir("deps")?
+        .map(|e| e.map(|e| e.name))
+        .collect::<Result<_>>()?;
+    assert_eq!(names, ["hidden.txt"]);
+    let m = v.metadata("deps/hidden.txt")?;
+    assert_eq!((m.mode, m.uid), (DEFAULT_FILE_MODE, 0));
+    assert!(v.list_xattrs("deps/hidden.txt")?.is_empty());
+    Ok(())
+}
+
+#[test]
+fn watch_reports_changes_and_tails_another_writer() -> Result<()> {
+    use virtual_file_system::WatchEvent::*;
+
+    let path = "target/watch.vfs";
+    let _ = std::fs::remove_file(path);
+    let mut v = Vfs::mount(path)?;
+    v.create_dir("cache")?;
+    v.create_dir("cache/img")?;
+
+    let all = v.watch("/", true)?;
+    let top = v.watch("cache", false)?;
+    let p = |s: &str| VfsPath::new(s).unwrap();
+
+    v.create("cache/a.bin")?.write_all(b"data")?;
+    v.create("cache/img/logo.png")?;
+    v.rename("cache/a.bin", "cache/b.bin")?;
+    v.set_permissions("cache/b.bin", 0o600)?;
+    v.open_rw("cache/b.bin")?.set_len(1)?;
+    v.remove_file("cache/img/logo.png")?;
+    // a doua citire nu produce nimic (timpii singuri nu se anunta)
+    read_all(&v, "cache/b.bin")?;
+
+    assert_eq!(
+        all.try_iter().collect::<Vec<_>>(),
+        [
+            Created(p("/cache/a.bin")),
+            Modified(p("/cache/a.bin")),
+            Created(p("/cache/img/logo.png")),
+            Renamed {
+                from: p("/cache/a.bin"),
+                to: p("/cache/b.bin"),
+            },
+            Modified(p("/cache/b.bin")),
+            Modified(p("/cache/b.bin")),
+            Removed(p("/cache/img/logo.png")),
+        ]
+    );
+    // ne-recursiv: doar copiii directi ai lui /cache
+    assert_eq!(
+        top.try_iter().collect::<Vec<_>>(),
+        [
+            Created(p("/cache/a.bin")),
+            Modified(p("/cache/a.bin")),
+            Renamed {
+                from: p("/cache/a.bin"),
+                to: p("/cache/b.bin"),
+            },
+            Modified(p("/cache/b.bin")),
+            Modified(p("/cache/b.bin")),
+        ]
+    );
+    drop(top);
+    v.remove_file("cache/b.bin")?;
+    assert_eq!(all.try_recv().ok(), Some(Removed(p("/cache/b.bin"))));
+    assert!(matches!(
+        v.watch("missing", true),
+        Err(VfsError::NotFound(_))
+    ));
+    assert!(matches!(v.tail(), Err(VfsError::Unsupported(_))));
+
+    // alt "proces": o montare read-only care urmareste log-ul scris de `v`
+    let mut follower = Vfs::mount_with(
+        path,
+        MountOptions {
+            read_only: true,
+            ..Default::default()
+        },
+    )?;
+    let seen = follower.watch("/", true)?;
+    assert_eq!(follower.tail()?, 0);
+    v.create("cache/new.txt")?.write_all(b"fresh")?;
+    v.rename("cache", "store")?;
+    assert!(!follower.exists("store"));
+    assert!(follower.tail()? > 0);
+    assert_eq!(
+        seen.try_iter().collect::<Vec<_>>(),
+        [
+            Created(p("/cache/new.txt")),
+            Modified(p("/cache/new.txt")),
+            Renamed {
+                from: p("/cache"),
+                to: p("/store"),
+            },
+        ]
+    );
+    assert_eq!(read_all(&follower, "store/new.txt")?, "fresh");
+    assert_eq!(follower.tail()?, 0);
+
+    // un checkpoint scris de celalalt proces nu schimba nimic
+    v.checkpoint()?;
+    assert_eq!(follower.tail()?, 1);
+    assert!(seen.try_recv().is_err());
+
+    // compactarea inlocuieste fisierul: ce era scris inainte se citeste din cel
+    // vechi, restul vine din diferenta fata de imaginea noua
+    v.create("store/keep.txt")?.write_all(b"keep")?;
+    v.create("store/stable.txt")?.write_all(b"stable")?;
+    v.compact()?;
+    v.rename("store/img", "store/pics")?;
+    v.open_rw("store/new.txt")?.write_all(b"FRESH")?;
+    v.create("store/late.txt")?;
+    v.remove_file("store/keep.txt")?;
+    assert!(follower.tail()? > 1);
+    assert_eq!(
+        seen.try_iter().collect::<Vec<_>>(),
+        [
+            Created(p("/store/keep.txt")),
+            Modified(p("/store/keep.txt")),
+            Created(p("/store/stable.txt")),
+            Modified(p("/store/stable.txt")),
+            Removed(p("/store/keep.txt")),
+            Renamed {
+                from: p("/store/img"),
+                to: p("/store/pics"),
+            },
+            Created(p("/store/late.txt")),
+            Modified(p("/store/new.txt")),
+        ]
+    );
+    assert_eq!(read_all(&follower, "store/new.txt")?, "FRESH");
+    assert_eq!(follower.tail()?, 0);
+    v.create("store/after.txt")?;
+    assert!(follower.tail()? > 0);
+    assert_eq!(seen.try_recv().ok(), Some(Created(p("/store/after.txt"))));
+    Ok(())
+}
+
+#[test]
+fn watch_refuses_attachments_and_tail_waits_for_torn_records() -> Result<()> {
+    use virtual_file_system::WatchEvent::*;
+    use virtual_file_system::debug::LogReader;
+
+    let (path, copy, inner_path) = (
+        "target/watch_err.vfs",
+        "target/watch_err_copy.vfs",
+        "target/watch_err_inner.vfs",
+    );
+    for p in [path, copy, inner_path] {
+        let _ = std::fs::remove_file(p);
+    }
+    let mut w = Vfs::mount(path)?;
+    w.create_dir("img")?;
+    w.create_dir("sub")?;
+
+    // sub o montare nu exista log de urmarit
+    w.attach("img", Vfs::mount(inner_path)?)?;
+    for at in ["img", "/img/x/..", "img/missing"] {
+        assert!(
+            matches!(w.watch(at, true), Err(VfsError::Unsupported(_))),
+            "{at}"
+        );
+    }
+    w.detach("img")?;
+    w.set_current_dir("sub")?;
+    let rx = w.watch(".", false)?;
+    w.create("f")?;
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        [Created(VfsPath::new("/sub/f")?)]
+    );
+
+    // un record scris pe jumatate asteapta restul
+    let before = std::fs::read(path)?;
+    std::fs::write(copy, &before)?;
+    let mut follower = Vfs::mount_with(
+        copy,
+        MountOptions {
+            read_only: true,
+            ..Default::default()
+        },
+    )?;
+    let events = follower.watch("/", true)?;
+    w.create_dir("/x")?;
+    let after = std::fs::read(path)?;
+    let first_new = LogReader::open(path)?
+        .map(|item| item.map(|(offset, ..)| offset))
+        .find(|offset| offset.as_ref().is_ok_and(|o| *o >= before.len() as u64))
+        .unwrap()?;
+    std::fs::write(copy, &after[..first_new as usize + 3])?;
+    assert_eq!(follower.tail()?, 0);
+    assert!(events.try_iter().next().is_none());
+    std::fs::write(copy, &after)?;
+    assert!(follower.tail()? > 0);
+    assert_eq!(
+        events.try_iter().collect::<Vec<_>>(),
+        [Created(VfsPath::new("/x")?)]
+    );
+
+    // altceva in locul imaginii: eroare, nu o stare inventata
+    std::fs::write(copy, vec![0u8; after.len()])?;
+    assert!(matches!(follower.tail(), Err(VfsError::CorruptLog(_))));
+    std::fs::remove_file(copy)?;
+    assert!(matches!(follower.tail(), Err(VfsError::Io(_))));
+    assert!(follower.exists("/x"));
+    Ok(())
+}
+
+#[test]
+fn replica_follows_source_by_log_shipping() -> Result<()> {
+    use virtual_file_system::{Change, WatchEvent};
+
+    let (src_path, dst_path) = ("target/repl_src.vfs", "target/repl_dst.vfs");
+    let _ = std::fs::remove_file(src_path);
+    let _ = std::fs::remove_file(dst_path);
+    let ship = |vfs: &Vfs, from: u64| -> Result<Vec<u8>> {
+        let mut wire = Vec::new();
+        for change in vfs.changes_since(from)? {
+            change?.write_to(&mut wire)?;
+        }
+        Ok(wire)
+    };
+
+    let mut src = Vfs::mount(src_path)?;
+    src.create_dir("docs")?;
+    src.create("docs/a.txt")?.write_all(b"alpha")?;
+    src.set_xattr("docs/a.txt", "user.tag", b"1")?;
+    src.snapshot("v1")?;
+
+    // o replica noua primeste totul, incepand cu header-ul
+    let mut dst = Vfs::mount_replica(dst_path, MountOptions::default())?;
+    assert_eq!(dst.applied_offset(), 0);
+    let applied = dst.apply_changes(&ship(&src, 0)?[..])?;
+    assert!(applied > 2);
+    let src_len = std::fs::metadata(src_path)?.len();
+    assert_eq!(dst.applied_offset(), src_len);
+    assert_eq!(std::fs::read(dst_path)?, std::fs::read(src_path)?);
+    assert_eq!(read_all(&dst, "docs/a.txt")?, "alpha");
+    assert_eq!(dst.list_snapshots().len(), 1);
+    assert!(matches!(
+        dst.create("docs/local.txt"),
+        Err(VfsError::ReadOnly(_))
+    ));
+
+    // doar ce lipseste; evenimentele ajung si la cine urmareste replica
+    let events = dst.watch("/", true)?;
+    src.rename("docs/a.txt", "docs/b.txt")?;
+    src.create("docs/c.txt")?.write_all(&[7u8; 10_000])?;
+    src.remove_xattr("docs/b.txt", "user.tag")?;
+    assert!(dst.apply_changes(&ship(&src, dst.applied_offset())?[..])? >= 3);
+    assert_eq!(read_all(&dst, "docs/b.txt")?, "alpha");
+    assert_eq!(dst.metadata("docs/c.txt")?.size, 10_000);
+    assert!(dst.list_xattrs("docs/b.txt")?.is_empty());
+    assert_eq!(
+        events.try_iter().next(),
+        Some(WatchEvent::Renamed {
+            from: VfsPath::new("/docs/a.txt")?,
+            to: VfsPath::new("/docs/b.txt")?,
+        })
+    );
+    // retrimis de la inceput (ex. dupa o reconectare): nimic nou
+    assert_eq!(dst.apply_changes(&ship(&src, 0)?[..])?, 0);
+
+    // crc-uri: cadrul, record-ul si datele sunt verificate; replica ramane neatinsa
+    let at = dst.applied_offset();
+    src.create("docs/d.txt")?.write_all(b"delta")?;
+    let changes: Vec<Change> = src.changes_since(at)?.collect::<Result<_>>()?;
+    let mut wire = Vec::new();
+    changes[0].write_to(&mut wire)?;
+    wire[30] ^= 0xff;
+    assert!(matches!(
+        dst.apply_changes(&wire[..]),
+        Err(VfsError::CorruptLog(_))
+    ));
+    assert_eq!(dst.applied_offset(), at);
+    for change in &changes {
+        let mut bad = change.clone();
+        let n = bad.bytes.len();
+        bad.bytes[n - 1] ^= 0xff;
+        assert!(matches!(
+            dst.apply_change(&bad),
+            Err(VfsError::CorruptLog(_))
+        ));
+        assert_eq!(dst.applied_offset(), change.offset);
+        assert_eq!(std::fs::metadata(dst_path)?.len(), change.offset);
+        assert!(dst.apply_change(change)?);
+    }
+    assert_eq!(read_all(&dst, "docs/d.txt")?, "delta");
+    assert!(matches!(
+        dst.apply_change(&Change {
+            generation: 0,
+            offset: dst.applied_offset() + 10,
+            bytes: vec![0; 4],
+        }),
+        Err(VfsError::CorruptLog(_))
+    ));
+
+    // dupa remontare, replica continua de la offset-ul ei; un checkpoint trece
+    // ca orice record
+    drop((dst, events));
+    src.checkpoint()?;
+    src.create("docs/e.txt")?.write_all(b"echo")?;
+    let mut dst = Vfs::mount_replica(dst_path, MountOptions::default())?;
+    dst.apply_changes(&ship(&src, dst.applied_offset())?[..])?;
+    assert_eq!(read_all(&dst, "docs/e.txt")?, "echo");
+    assert_eq!(std::fs::read(dst_path)?, std::fs::read(src_path)?);
+    assert!(dst.fsck()?.problems.is_empty());
+    assert!(matches!(
+        src.apply_changes(&[][..])
+            .and_then(|_| src.apply_change(&changes[0])),
+        Err(VfsError::Unsupported(_))
+    ));
+    assert!(src.changes_since(3).is_err());
+
+    // un `compact` muta toate offset-urile sursei: replica refuza schimbarile
+    // noii generatii, oricum ar veni, si ramane neatinsa
+    let stale = Vfs::mount_with(
+        src_path,
+        MountOptions {
+            read_only: true,
+            ..Default::default()
+        },
+    )?;
+    src.compact()?;
+    src.create("docs/f.txt")?.write_all(b"fox")?;
+    let before = std::fs::read(dst_path)?;
+    assert!(matches!(
+        dst.apply_changes(&ship(&src, 0)?[..]),
+        Err(VfsError::CorruptLog(_))
+    ));
+    let resumed = src.changes_since(dst.applied_offset()).and_then(|changes| {
+        for change in changes {
+            dst.apply_change(&change?)?;
+        }
+        Ok(())
+    });
+    assert!(resumed.is_err());
+    assert_eq!(std::fs::read(dst_path)?, before);
+    assert!(!dst.exists("docs/f.txt"));
+    // o montare facuta inainte de compactare nu mai are ce da
+    assert!(matches!(
+        stale.changes_since(0),
+        Err(VfsError::CorruptLog(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn replica_rejects_broken_streams_and_recovers_a_torn_tail() -> Result<()> {
+    use virtual_file_system::Change;
+
+    let (src_path, dst_path, junk_path) = (
+        "target/repl_err_src.vfs",
+        "target/repl_err_dst.vfs",
+        "target/repl_err_junk.vfs",
+    );
+    for p in [src_path, dst_path, junk_path] {
+        let _ = std::fs::remove_file(p);
+    }
+    let ship = |vfs: &Vfs, from: u64| -> Result<Vec<u8>> {
+        let mut wire = Vec::new();
+        for change in vfs.changes_since(from)? {
+            change?.write_to(&mut wire)?;
+        }
+        Ok(wire)
+    };
+    let mut src = Vfs::mount(src_path)?;
+    src.create_dir("docs")?;
+    src.create("docs/a.txt")?.write_all(b"alpha")?;
+    let changes: Vec<Change> = src.changes_since(0)?.collect::<Result<_>>()?;
+    let wire = ship(&src, 0)?;
+
+    // o replica noua incepe doar cu header-ul sursei; altfel ramane goala
+    let mut dst = Vfs::mount_replica(dst_path, MountOptions::default())?;
+    let not_a_header = Change {
+        generation: 0,
+        offset: 0,
+        bytes: vec![0; changes[0].bytes.len()],
+    };
+    for bad in [&changes[1], &not_a_header] {
+        assert!(matches!(
+            dst.apply_change(bad),
+            Err(VfsError::CorruptLog(_))
+        ));
+        assert_eq!(dst.applied_offset(), 0);
+        assert_eq!(std::fs::metadata(dst_path)?.len(), 0);
+    }
+
+    // un flux taiat sau strain e o eroare, nu un EOF
+    let mut strange = wire.clone();
+    strange[0] = b'X';
+    assert!(matches!(
+        dst.apply_changes(&strange[..]),
+        Err(VfsError::CorruptLog(m)) if m.contains("magic")
+    ));
+    for cut in [1, 3, 4, 20, wire.len() - 1] {
+        assert!(
+            matches!(
+                dst.apply_changes(&wire[..cut]),
+                Err(VfsError::CorruptLog(m)) if m.contains("cut short")
+            ),
+            "{cut}"
+        );
+    }
+    assert_eq!(dst.apply_changes(&[][..])?, 0);
+    dst.apply_changes(&wire[..])?;
+    assert_eq!(read_all(&dst, "docs/a.txt")?, "alpha");
+
+    // offset-uri pe care sursa nu le poate servi
+    let end = std::fs::metadata(src_path)?.len();
+    for offset in [end + 1, changes[1].offset + 1] {
+        assert!(
+            matches!(src.changes_since(offset), Err(VfsError::InvalidPath(_))),
+            "{offset}"
+        );
+    }
+    assert_eq!(src.changes_since(end)?.count(), 0);
+
+    // o coada rupta de o oprire brusca e inlocuita de urmatoarea schimbare
+    drop(dst);
+    let mut torn = std::fs::OpenOptions::new().append(true).open(dst_path)?;
+    torn.write_all(b"VFSR\x05half")?;
+    drop(torn);
+    let mut dst = Vfs::mount_replica(dst_path, MountOptions::default())?;
+    assert_eq!(dst.applied_offset(), end);
+    src.create("docs/b.txt")?.write_all(b"bravo")?;
+    dst.apply_changes(&ship(&src, dst.applied_offset())?[..])?;
+    assert_eq!(std::fs::read(dst_path)?, std::fs::read(src_path)?);
+    assert!(dst.fsck()?.problems.is_empty());
+
+    // un fisier care nu e o imagine nu devine replica
+    std::fs::write(junk_path, "not an image")?;
+    assert!(Vfs::mount_replica(junk_path, MountOptions::default()).is_err());
+    assert_eq!(std::fs::read(junk_path)?, b"not an image");
+    Ok(())
+}
+
+#[test]
+fn vfsctl_creates_images_only_with_init() -> Result<()> {
+    use std::process::Command;
+
+    let dir = std::path::Path::new("target/vfsctl_init");
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir)?;
+    let ctl = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_vfsctl"))
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .map(|out| out.status.code())
+    };
+
+    assert_eq!(ctl(&["--help"])?, Some(0));
+    // `-p` nu e o imagine, iar o imagine lipsa nu apare la prima scriere
+    assert_eq!(ctl(&["mkdir", "-p", "img.vfs", "a/b"])?, Some(2));
+    assert!(!dir.join("-p").exists());
+    assert_eq!(ctl(&["mkdir", "img.vfs", "a"])?, Some(1));
+    assert_eq!(ctl(&["ls", "img.vfs"])?, Some(1));
+    assert!(!dir.join("img.vfs").exists());
+
+    assert_eq!(ctl(&["init", "img.vfs"])?, Some(0));
+    assert_eq!(ctl(&["init", "img.vfs"])?, Some(1));
+    assert_eq!(ctl(&["mkdir", "img.vfs", "a"])?, Some(0));
+    assert!(Vfs::mount(dir.join("img.vfs"))?.exists("a"));
+    Ok(())
+}
+
+#[test]
+fn vfsctl_reports_errors_with_exit_codes() -> Result<()> {
+    use std::process::Command;
+
+    let dir = std::path::Path::new("target/vfsctl_errors");
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir)?;
+    // (cod de iesire, stderr)
+    let ctl = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_vfsctl"))
+            .args(args)
+            .current_dir(dir)
+            .output()
+            .map(|out| {
+                (
+                    out.status.code(),
+                    String::from_utf8_lossy(&out.stderr).into_owned(),
+                )
+            })
+    };
+    assert_eq!(ctl(&["init", "img.vfs"])?.0, Some(0));
+    assert_eq!(ctl(&["mkdir", "img.vfs", "d"])?.0, Some(0));
+    std::fs::write(dir.join("in.txt"), "x")?;
+    assert_eq!(ctl(&["put", "img.vfs", "in.txt", "d/f"])?.0, Some(0));
+
+    // argumente gresite: 2 si textul de ajutor
+    for args in [
+        &["frob", "img.vfs"][..],
+        &["cat", "img.vfs"],
+        &["mv", "img.vfs", "d"],
+        &["ls", "img.vfs", "-z"],
+        &["rm", "img.vfs", "-r"],
+        &["replicate", "img.vfs", "-", "extra"],
+        &["replicate", "-", "-"],
+        &["init"],
+    ] {
+        let (code, err) = ctl(args)?;
+        assert_eq!(code, Some(2), "{args:?}");
+        assert!(err.contains("usage"), "{args:?}: {err}");
+    }
+
+    // erori din vfs: 1 si un singur mesaj prefixat
+    for args in [
+        &["cat", "img.vfs", "missing"][..],
+        &["stat", "img.vfs", "d/missing"],
+        &["put", "img.vfs", "in.txt", "nodir/f"],
+        &["put", "img.vfs", "no-such-host-file", "d/g"],
+        &["rm", "img.vfs", "d"],
+        &["mkdir", "img.vfs", "d"],
+        &["mv", "img.vfs", "missing", "d/x"],
+        &["replicate", "img.vfs", "unix:no-such-dir/r.sock"],
+    ] {
+        let (code, err) = ctl(args)?;
+        assert_eq!(code, Some(1), "{args:?}");
+        assert!(err.starts_with("vfsctl: "), "{args:?}: {err}");
+    }
+    assert!(!dir.join("no-such-dir").exists());
+
+    // o cheie lipsa sau scurta nu monteaza imaginea fara criptare
+    let (code, err) = ctl(&["cat", "img.vfs", "d/f", "--key-file"])?;
+    assert_eq!(code, Some(2));
+    assert!(err.contains("--key-file"), "{err}");
+    std::fs::write(dir.join("short.key"), [7u8; 16])?;
+    let (code, err) = ctl(&["--key-file", "short.key", "cat", "img.vfs", "d/f"])?;
+    assert_eq!(code, Some(1));
+    assert!(err.contains("32 bytes"), "{err}");
+
+    let vfs = Vfs::mount(dir.join("img.vfs"))?;
+    assert_eq!(read_all(&vfs, "d/f")?, "x");
+    assert!(!vfs.exists("d/g") && !vfs.exists("d/x"));
+    Ok(())
+}
+
+#[test]
+fn tools_cap_timestamps_at_source_date_epoch() -> Result<()> {
+    use std::process::{Command, Stdio};
+
+    let dir = std::path::Path::new("target/tools_epoch");
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join("in.txt"), "x")?;
+    std::fs::write(dir.join("script.sh"), "mkdir s\n")?;
+    let run = |bin: &str, epoch: &str, args: &[&str]| {
+        Command::new(bin)
+            .args(args)
+            .current_dir(dir)
+            .env("SOURCE_DATE_EPOCH", epoch)
+            .stdin(Stdio::null())
+            .output()
+            .map(|out| {
+                (
+                    out.status.code(),
+                    String::from_utf8_lossy(&out.stderr).into_owned(),
+                )
+            })
+    };
+    let ctl = |args: &[&str]| run(env!("CARGO_BIN_EXE_vfsctl"), "1700000000", args);
+
+    for image in ["a.vfs", "b.vfs"] {
+        assert_eq!(ctl(&["init", image])?.0, Some(0));
+        assert_eq!(ctl(&["mkdir", image, "d"])?.0, Some(0));
+        assert_eq!(ctl(&["put", image, "in.txt", "d/f"])?.0, Some(0));
+    }
+    // aceleasi comenzi sub acelasi epoch dau aceeasi imagine
+    assert_eq!(
+        std::fs::read(dir.join("a.vfs"))?,
+        std::fs::read(dir.join("b.vfs"))?
+    );
+    let shell = run(
+        env!("CARGO_BIN_EXE_vfs-shell"),
+        "1700000000",
+        &["a.vfs", "script.sh"],
+    )?;
+    assert_eq!(shell.0, Some(0), "{}", shell.1);
+
+    let epoch = Timestamp(1_700_000_000 * 1_000_000_000);
+    let vfs = Vfs::mount(dir.join("a.vfs"))?;
+    for path in ["d", "d/f", "s"] {
+        let m = vfs.metadata(path)?;
+        assert_eq!((m.created_at, m.modified_at), (epoch, epoch), "{path}");
+    }
+    drop(vfs);
+
+    // o valoare care nu e un numar opreste unealta inainte sa scrie ceva
+    let before = std::fs::read(dir.join("b.vfs"))?;
+    let (code, err) = run(
+        env!("CARGO_BIN_EXE_vfsctl"),
+        "soon",
+        &["mkdir", "b.vfs", "x"],
+    )?;
+    assert_eq!(code, Some(1));
+    assert!(err.contains("SOURCE_DATE_EPOCH"), "{err}");
+    let (code, _) = run(
+        env!("CARGO_BIN_EXE_vfs-shell"),
+        "soon",
+        &["b.vfs", "script.sh"],
+    )?;
+    assert_eq!(code, Some(1));
+    assert_eq!(std::fs::read(dir.join("b.vfs"))?, before);
+    Ok(())
+}
+
+#[test]
+fn vfs_shell_needs_create_for_a_new_image() -> Result<()> {
+    use std::process::{Command, Stdio};
+
+    let dir = std::path::Path::new("target/shell_create");
+    let _ = std::fs::remove_dir_all(dir);
+    std::fs::create_dir_all(dir)?;
+    let shell = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_vfs-shell"))
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .output()
+            .map(|out| out.status.code())
+    };
+
+    assert_eq!(shell(&["--help"])?, Some(0));
+    assert!(!dir.join("--help").exists());
+    assert_eq!(shell(&["-x", "-"])?, Some(2));
+    assert_eq!(shell(&["img.vfs", "-"])?, Some(1));
+    assert!(!dir.join("img.vfs").exists());
+    assert_eq!(shell(&["--create", "img.vfs", "-"])?, Some(0));
+    assert_eq!(shell(&["img.vfs", "-"])?, Some(0));
+    assert!(dir.join("img.vfs").exists());
+
+    // un script lipsa sau cu o linie esuata da 1
+    std::fs::write(dir.join("ok.sh"), "mkdir a\n")?;
+    std::fs::write(dir.join("bad.sh"), "mkdir b\ncat missing\n")?;
+    assert_eq!(shell(&["img.vfs", "missing.sh"])?, Some(1));
+    assert_eq!(shell(&["img.vfs", "ok.sh"])?, Some(0));
+    assert_eq!(shell(&["img.vfs", "bad.sh"])?, Some(1));
+    assert_eq!(shell(&["img.vfs", "ok.sh", "extra"])?, Some(2));
+    let vfs = Vfs::mount(dir.join("img.vfs"))?;
+    assert!(vfs.exists("a") && vfs.exists("b"));
+    Ok(())
+}
+
+#[test]
+fn legacy_images_mount_read_only_and_upgrade() -> Result<()> {
+    let big: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+    let read_only = || MountOptions {
+        read_only: true,
+        ..Default::default()
+    };
+
+    // scrisa de versiunea initiala: fara xattr-uri, permisiuni sau atime
+    let v1 = "target/legacy_v1.vfs";
+    std::fs::copy("tests/data/v1.vfs", v1)?;
+    let old = Vfs::mount_with(v1, read_only())?;
+    assert_eq!(read_all(&old, "docs/readme.txt")?, "hello from v1");
+    assert_eq!(read_all(&old, "notes.txt")?, "after the checkpoint");
+    assert!(!old.exists("docs/a.txt") && !old.exists("gone.txt"));
+    let meta = old.metadata("docs/sub")?;
+    assert_eq!((meta.mode, meta.uid, meta.gid), (DEFAULT_DIR_MODE, 0, 0));
+    assert_eq!(meta.accessed_at, meta.modified_at);
+    drop(old);
+    assert!(matches!(Vfs::mount(v1), Err(VfsError::ReadOnly(_))));
+
+    assert_eq!(Vfs::upgrade(v1, MountOptions::default())?, 1);
+    assert_eq!(Vfs::upgrade(v1, MountOptions::default())?, VERSION);
+    let v = Vfs::mount(v1)?;
+    let mut out = Vec::new();
+    v.open("docs/sub/b.bin")?.read_to_end(&mut out)?;
+    assert_eq!(out, big);
+    v.create("docs/new.txt")?
+        .write_all(b"written after upgrade")?;
+    drop(v);
+    let v = Vfs::mount(v1)?;
+    assert_eq!(read_all(&v, "docs/new.txt")?, "written after upgrade");
+    assert!(v.fsck()?.problems.is_empty());
+
+    // v6: xattr-uri, permisiuni, snapshot-uri, trash si dedup, dar extent-uri fara encoding
+    let v6 = "target/legacy_v6.vfs";
+    std::fs::copy("tests/data/v6.vfs", v6)?;
+    let old = Vfs::mount_with(v6, read_only())?;
+    assert_eq!(
+        old.get_xattr("docs/a.txt", "user.tag")?,
+        Some(b"blue".to_vec())
+    );
+    assert_eq!(old.metadata("docs/a.txt")?.mode, 0o600);
+    assert_eq!(old.trash_list()[0].original_path.as_str(), "/docs/c.bin");
+    let snap = Vfs::mount_snapshot(v6, "s1")?;
+    let mut out = Vec::new();
+    snap.open("docs/c.bin")?.read_to_end(&mut out)?;
+    assert_eq!(out, big);
+    drop((old, snap));
+
+    assert_eq!(Vfs::upgrade(v6, MountOptions::default())?, 6);
+    let v = Vfs::mount(v6)?;
+    assert_eq!(read_all(&v, "docs/a.txt")?, "hello from v6");
+    assert_eq!(read_all(&v, "notes.txt")?, "after the checkpoint");
+    assert_eq!(v.list_snapshots()[0].name, "s1");
+    assert_eq!(v.trash_list().len(), 1);
+    assert!(v.fsck()?.problems.is_empty());
+
+    // un format mai nou decat cel cunoscut nu se ghiceste
+    let mut future = std::fs::read("tests/data/v1.vfs")?;
+    future[8..12].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    std::fs::write("target/legacy_future.vfs", future)?;
+    assert!(matches!(
+        Vfs::mount("target/legacy_future.vfs"),
+        Err(VfsError::UnsupportedVersion(_))
+    ));
+    Ok(())
+}